@@ -1,4 +1,5 @@
 pub use axdisplay::DisplayInfo as AxDisplayInfo;
+pub use axdisplay::InputEvent as AxInputEvent;
 
 /// Gets the framebuffer information.
 pub fn ax_framebuffer_info() -> AxDisplayInfo {
@@ -9,3 +10,8 @@ pub fn ax_framebuffer_info() -> AxDisplayInfo {
 pub fn ax_framebuffer_flush() {
     axdisplay::framebuffer_flush()
 }
+
+/// Pops the earliest pending event of the input devices.
+pub fn ax_read_input_event() -> Option<AxInputEvent> {
+    axdisplay::read_input_event()
+}
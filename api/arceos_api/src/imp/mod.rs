@@ -38,5 +38,6 @@ pub use self::stdio::*;
 pub use self::task::*;
 
 pub use axhal::misc::terminate as ax_terminate;
+pub use axhal::random::fill_random as ax_fill_random;
 pub use axhal::time::{current_time as ax_current_time, TimeValue as AxTimeValue};
 pub use axio::PollState as AxPollState;
@@ -2,6 +2,7 @@ use crate::io::AxPollState;
 use axerrno::AxResult;
 use axnet::{UdpSocket, TcpSocket};
 use core::net::{IpAddr, SocketAddr};
+use core::time::Duration;
 
 /// A handle to a TCP socket.
 pub struct AxTcpSocketHandle(TcpSocket);
@@ -30,6 +31,48 @@ pub fn ax_tcp_set_nonblocking(socket: &AxTcpSocketHandle, nonblocking: bool) ->
     Ok(())
 }
 
+pub fn ax_tcp_set_nodelay(socket: &AxTcpSocketHandle, nodelay: bool) -> AxResult {
+    socket.0.set_nodelay(nodelay);
+    Ok(())
+}
+
+pub fn ax_tcp_nodelay(socket: &AxTcpSocketHandle) -> AxResult<bool> {
+    Ok(socket.0.nodelay())
+}
+
+pub fn ax_tcp_set_read_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_read_timeout(timeout)
+}
+
+pub fn ax_tcp_read_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.read_timeout())
+}
+
+pub fn ax_tcp_set_write_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_write_timeout(timeout)
+}
+
+pub fn ax_tcp_write_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.write_timeout())
+}
+
+pub fn ax_tcp_set_ttl(socket: &AxTcpSocketHandle, ttl: u8) -> AxResult {
+    socket.0.set_ttl(ttl)
+}
+
+pub fn ax_tcp_ttl(socket: &AxTcpSocketHandle) -> AxResult<u8> {
+    Ok(socket.0.ttl())
+}
+
+pub fn ax_tcp_set_linger(socket: &AxTcpSocketHandle, linger: Option<Duration>) -> AxResult {
+    socket.0.set_linger(linger);
+    Ok(())
+}
+
+pub fn ax_tcp_linger(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.linger())
+}
+
 pub fn ax_tcp_connect(socket: &AxTcpSocketHandle, addr: SocketAddr) -> AxResult {
     socket.0.connect(addr)
 }
@@ -85,6 +128,30 @@ pub fn ax_udp_set_nonblocking(socket: &AxUdpSocketHandle, nonblocking: bool) ->
     Ok(())
 }
 
+pub fn ax_udp_set_read_timeout(socket: &AxUdpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_read_timeout(timeout)
+}
+
+pub fn ax_udp_read_timeout(socket: &AxUdpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.read_timeout())
+}
+
+pub fn ax_udp_set_write_timeout(socket: &AxUdpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_write_timeout(timeout)
+}
+
+pub fn ax_udp_write_timeout(socket: &AxUdpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.write_timeout())
+}
+
+pub fn ax_udp_set_ttl(socket: &AxUdpSocketHandle, ttl: u8) -> AxResult {
+    socket.0.set_ttl(ttl)
+}
+
+pub fn ax_udp_ttl(socket: &AxUdpSocketHandle) -> AxResult<u8> {
+    Ok(socket.0.ttl())
+}
+
 pub fn ax_udp_bind(socket: &AxUdpSocketHandle, addr: SocketAddr) -> AxResult {
     socket.0.bind(addr)
 }
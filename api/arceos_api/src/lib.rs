@@ -34,6 +34,13 @@ pub mod sys {
     define_api! {
         /// Shutdown the whole system and all CPUs.
         pub fn ax_terminate() -> !;
+
+        /// Fills `buf` with random bytes from the hardware random number
+        /// generator, or from a clock-seeded generator if there is none.
+        ///
+        /// Returns `false` if the clock-seeded generator is used, whose bytes
+        /// are predictable.
+        pub fn ax_fill_random(buf: &mut [u8]) -> bool;
     }
 }
 
@@ -225,6 +232,7 @@ pub mod fs {
 pub mod net {
     use crate::{io::AxPollState, AxResult};
     use core::net::{IpAddr, SocketAddr};
+    use core::time::Duration;
 
     define_api_type! {
         @cfg "net";
@@ -245,6 +253,26 @@ pub mod net {
         pub fn ax_tcp_peer_addr(socket: &AxTcpSocketHandle) -> AxResult<SocketAddr>;
         /// Moves this TCP socket into or out of nonblocking mode.
         pub fn ax_tcp_set_nonblocking(socket: &AxTcpSocketHandle, nonblocking: bool) -> AxResult;
+        /// Disables or enables the Nagle's algorithm on the TCP socket.
+        pub fn ax_tcp_set_nodelay(socket: &AxTcpSocketHandle, nodelay: bool) -> AxResult;
+        /// Returns whether the Nagle's algorithm is disabled on the TCP socket.
+        pub fn ax_tcp_nodelay(socket: &AxTcpSocketHandle) -> AxResult<bool>;
+        /// Sets the timeout of the receive operations on the TCP socket.
+        pub fn ax_tcp_set_read_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult;
+        /// Returns the timeout of the receive operations on the TCP socket.
+        pub fn ax_tcp_read_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Sets the timeout of the send operations on the TCP socket.
+        pub fn ax_tcp_set_write_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult;
+        /// Returns the timeout of the send operations on the TCP socket.
+        pub fn ax_tcp_write_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Sets the time-to-live of the packets sent from the TCP socket.
+        pub fn ax_tcp_set_ttl(socket: &AxTcpSocketHandle, ttl: u8) -> AxResult;
+        /// Returns the time-to-live of the packets sent from the TCP socket.
+        pub fn ax_tcp_ttl(socket: &AxTcpSocketHandle) -> AxResult<u8>;
+        /// Sets the linger timeout of the TCP socket.
+        pub fn ax_tcp_set_linger(socket: &AxTcpSocketHandle, linger: Option<Duration>) -> AxResult;
+        /// Returns the linger timeout of the TCP socket.
+        pub fn ax_tcp_linger(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>>;
 
         /// Connects the TCP socket to the given address and port.
         pub fn ax_tcp_connect(handle: &AxTcpSocketHandle, addr: SocketAddr) -> AxResult;
@@ -278,6 +306,18 @@ pub mod net {
         pub fn ax_udp_peer_addr(socket: &AxUdpSocketHandle) -> AxResult<SocketAddr>;
         /// Moves this UDP socket into or out of nonblocking mode.
         pub fn ax_udp_set_nonblocking(socket: &AxUdpSocketHandle, nonblocking: bool) -> AxResult;
+        /// Sets the timeout of the receive operations on the UDP socket.
+        pub fn ax_udp_set_read_timeout(socket: &AxUdpSocketHandle, timeout: Option<Duration>) -> AxResult;
+        /// Returns the timeout of the receive operations on the UDP socket.
+        pub fn ax_udp_read_timeout(socket: &AxUdpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Sets the timeout of the send operations on the UDP socket.
+        pub fn ax_udp_set_write_timeout(socket: &AxUdpSocketHandle, timeout: Option<Duration>) -> AxResult;
+        /// Returns the timeout of the send operations on the UDP socket.
+        pub fn ax_udp_write_timeout(socket: &AxUdpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Sets the time-to-live of the packets sent from the UDP socket.
+        pub fn ax_udp_set_ttl(socket: &AxUdpSocketHandle, ttl: u8) -> AxResult;
+        /// Returns the time-to-live of the packets sent from the UDP socket.
+        pub fn ax_udp_ttl(socket: &AxUdpSocketHandle) -> AxResult<u8>;
 
         /// Binds the UDP socket to the given address and port.
         pub fn ax_udp_bind(socket: &AxUdpSocketHandle, addr: SocketAddr) -> AxResult;
@@ -306,7 +346,7 @@ pub mod net {
 
         // Miscellaneous
 
-        /// Resolves the host name to a list of IP addresses.
+        /// Resolves the host name to a list of IPv4 and IPv6 addresses.
         pub fn ax_dns_query(domain_name: &str) -> AxResult<alloc::vec::Vec<IpAddr>>;
         /// Poll the network stack.
         ///
@@ -321,6 +361,7 @@ pub mod display {
     define_api_type! {
         @cfg "display";
         pub type AxDisplayInfo;
+        pub type AxInputEvent;
     }
 
     define_api! {
@@ -329,6 +370,9 @@ pub mod display {
         pub fn ax_framebuffer_info() -> AxDisplayInfo;
         /// Flushes the framebuffer, i.e. show on the screen.
         pub fn ax_framebuffer_flush();
+        /// Pops the earliest pending event of the input devices (e.g.,
+        /// keyboards and mice), returns `None` if there is no event.
+        pub fn ax_read_input_event() -> Option<AxInputEvent>;
     }
 }
 
@@ -53,12 +53,18 @@ typedef struct {{
             "clockid_t",
             "rlimit",
             "aibuf",
+            "linger",
         ];
         let allow_vars = [
             "O_.*",
             "AF_.*",
             "SOCK_.*",
             "IPPROTO_.*",
+            "SOL_.*",
+            "SO_.*",
+            "IP_.*",
+            "IPV6_.*",
+            "TCP_.*",
             "FD_.*",
             "F_.*",
             "_SC_.*",
@@ -70,7 +70,8 @@ impl FileLike for File {
         })
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.inner.lock().set_nonblocking(nonblocking);
         Ok(())
     }
 }
@@ -111,7 +112,8 @@ pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) ->
     debug!("sys_open <= {:?} {:#o} {:#o}", filename, flags, mode);
     syscall_body!(sys_open, {
         let options = flags_to_options(flags, mode);
-        let file = axfs::fops::File::open(filename?, &options)?;
+        let mut file = axfs::fops::File::open(filename?, &options)?;
+        file.set_nonblocking(flags as u32 & ctypes::O_NONBLOCK != 0);
         File::new(file).add_to_fd_table()
     })
 }
@@ -1,25 +1,56 @@
 use alloc::{sync::Arc, vec, vec::Vec};
 use core::ffi::{c_char, c_int, c_void};
 use core::mem::size_of;
-use core::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use core::time::Duration;
 
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
-use axnet::{TcpSocket, UdpSocket};
+use axnet::{IcmpSocket, RawSocket, TcpSocket, UdpSocket};
 use axsync::Mutex;
 
-use super::fd_ops::FileLike;
+use super::fd_ops::{add_file_like, close_file_like, FileLike};
+use super::unix::{UnixAddr, UnixSocket, UnixSocketType};
 use crate::ctypes;
 use crate::utils::char_ptr_to_str;
 
+/// The TTL that `-1` resets `IP_TTL` to.
+const DEFAULT_TTL: u8 = 64;
+
 pub enum Socket {
     Udp(Mutex<UdpSocket>),
     Tcp(Mutex<TcpSocket>),
+    Icmp(Mutex<IcmpSocket>),
+    Raw(Mutex<RawSocket>),
+    Unix(UnixSocket),
+}
+
+/// The address of an IP or a Unix domain socket.
+#[derive(Debug)]
+enum SockAddr {
+    Inet(SocketAddr),
+    Unix(UnixAddr),
+}
+
+impl SockAddr {
+    fn inet(self) -> LinuxResult<SocketAddr> {
+        match self {
+            SockAddr::Inet(addr) => Ok(addr),
+            SockAddr::Unix(_) => Err(LinuxError::EAFNOSUPPORT),
+        }
+    }
+
+    fn unix(self) -> LinuxResult<UnixAddr> {
+        match self {
+            SockAddr::Unix(addr) => Ok(addr),
+            SockAddr::Inet(_) => Err(LinuxError::EAFNOSUPPORT),
+        }
+    }
 }
 
 impl Socket {
     fn add_to_fd_table(self) -> LinuxResult<c_int> {
-        super::fd_ops::add_file_like(Arc::new(self))
+        add_file_like(Arc::new(self))
     }
 
     fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
@@ -33,6 +64,8 @@ impl Socket {
         match self {
             Socket::Udp(udpsocket) => Ok(udpsocket.lock().send(buf)?),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().send(buf)?),
+            Socket::Icmp(_) | Socket::Raw(_) => Err(LinuxError::EDESTADDRREQ),
+            Socket::Unix(unixsocket) => unixsocket.send(buf),
         }
     }
 
@@ -40,6 +73,9 @@ impl Socket {
         match self {
             Socket::Udp(udpsocket) => Ok(udpsocket.lock().recv_from(buf).map(|e| e.0)?),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf)?),
+            Socket::Icmp(icmpsocket) => Ok(icmpsocket.lock().recv_from(buf).map(|e| e.0)?),
+            Socket::Raw(rawsocket) => Ok(rawsocket.lock().recv_from(buf).map(|e| e.0)?),
+            Socket::Unix(unixsocket) => unixsocket.recv_from(buf).map(|e| e.0),
         }
     }
 
@@ -47,67 +83,107 @@ impl Socket {
         match self {
             Socket::Udp(udpsocket) => Ok(udpsocket.lock().poll()?),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().poll()?),
+            Socket::Icmp(icmpsocket) => Ok(icmpsocket.lock().poll()?),
+            Socket::Raw(rawsocket) => Ok(rawsocket.lock().poll()?),
+            Socket::Unix(unixsocket) => unixsocket.poll(),
         }
     }
 
-    fn local_addr(&self) -> LinuxResult<SocketAddr> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().local_addr()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().local_addr()?),
-        }
+    fn local_addr(&self) -> LinuxResult<SockAddr> {
+        let addr = match self {
+            Socket::Udp(udpsocket) => udpsocket.lock().local_addr()?,
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().local_addr()?,
+            Socket::Icmp(icmpsocket) => icmpsocket.lock().local_addr()?,
+            Socket::Raw(_) => return Err(LinuxError::EOPNOTSUPP),
+            Socket::Unix(unixsocket) => return Ok(SockAddr::Unix(unixsocket.local_addr())),
+        };
+        Ok(SockAddr::Inet(addr))
     }
 
-    fn peer_addr(&self) -> LinuxResult<SocketAddr> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().peer_addr()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().peer_addr()?),
-        }
+    fn peer_addr(&self) -> LinuxResult<SockAddr> {
+        let addr = match self {
+            Socket::Udp(udpsocket) => udpsocket.lock().peer_addr()?,
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().peer_addr()?,
+            Socket::Icmp(_) | Socket::Raw(_) => return Err(LinuxError::ENOTCONN),
+            Socket::Unix(unixsocket) => return Ok(SockAddr::Unix(unixsocket.peer_addr()?)),
+        };
+        Ok(SockAddr::Inet(addr))
     }
 
-    fn bind(&self, addr: SocketAddr) -> LinuxResult {
+    fn bind(&self, addr: SockAddr) -> LinuxResult {
         match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr)?),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr.inet()?)?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr.inet()?)?),
+            Socket::Icmp(icmpsocket) => Ok(icmpsocket.lock().bind(addr.inet()?)?),
+            Socket::Raw(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Unix(unixsocket) => unixsocket.bind(addr.unix()?),
         }
     }
 
-    fn connect(&self, addr: SocketAddr) -> LinuxResult {
+    fn connect(&self, addr: SockAddr) -> LinuxResult {
         match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().connect(addr)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().connect(addr)?),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().connect(addr.inet()?)?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().connect(addr.inet()?)?),
+            Socket::Icmp(_) | Socket::Raw(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Unix(unixsocket) => unixsocket.connect(addr.unix()?),
         }
     }
 
-    fn sendto(&self, buf: &[u8], addr: SocketAddr) -> LinuxResult<usize> {
+    fn sendto(&self, buf: &[u8], addr: SockAddr) -> LinuxResult<usize> {
         match self {
             // diff: must bind before sendto
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send_to(buf, addr)?),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send_to(buf, addr.inet()?)?),
             Socket::Tcp(_) => Err(LinuxError::EISCONN),
+            Socket::Icmp(icmpsocket) => Ok(icmpsocket.lock().send_to(buf, addr.inet()?.ip())?),
+            Socket::Raw(rawsocket) => Ok(rawsocket.lock().send_to(buf, addr.inet()?.ip())?),
+            Socket::Unix(unixsocket) => unixsocket.send_to(buf, addr.unix()?),
         }
     }
 
-    fn recvfrom(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<SocketAddr>)> {
-        match self {
+    fn recvfrom(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<SockAddr>)> {
+        let (len, addr) = match self {
             // diff: must bind before recvfrom
-            Socket::Udp(udpsocket) => Ok(udpsocket
+            Socket::Udp(udpsocket) => udpsocket
                 .lock()
                 .recv_from(buf)
-                .map(|res| (res.0, Some(res.1)))?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf).map(|res| (res, None))?),
-        }
+                .map(|res| (res.0, Some(res.1)))?,
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().recv(buf).map(|res| (res, None))?,
+            Socket::Icmp(icmpsocket) => icmpsocket
+                .lock()
+                .recv_from(buf)
+                .map(|res| (res.0, Some(SocketAddr::new(res.1, 0))))?,
+            Socket::Raw(rawsocket) => rawsocket
+                .lock()
+                .recv_from(buf)
+                .map(|res| (res.0, Some(SocketAddr::new(res.1, 0))))?,
+            Socket::Unix(unixsocket) => {
+                let (len, addr) = unixsocket.recv_from(buf)?;
+                return Ok((len, addr.map(SockAddr::Unix)));
+            }
+        };
+        Ok((len, addr.map(SockAddr::Inet)))
     }
 
     fn listen(&self) -> LinuxResult {
         match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Udp(_) | Socket::Icmp(_) | Socket::Raw(_) => Err(LinuxError::EOPNOTSUPP),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen()?),
+            Socket::Unix(unixsocket) => unixsocket.listen(),
         }
     }
 
-    fn accept(&self) -> LinuxResult<TcpSocket> {
+    fn accept(&self) -> LinuxResult<(Socket, SockAddr)> {
         match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().accept()?),
+            Socket::Udp(_) | Socket::Icmp(_) | Socket::Raw(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Tcp(tcpsocket) => {
+                let new_socket = tcpsocket.lock().accept()?;
+                let addr = new_socket.peer_addr()?;
+                Ok((Socket::Tcp(Mutex::new(new_socket)), SockAddr::Inet(addr)))
+            }
+            Socket::Unix(unixsocket) => {
+                let (new_socket, addr) = unixsocket.accept()?;
+                Ok((Socket::Unix(new_socket), SockAddr::Unix(addr)))
+            }
         }
     }
 
@@ -126,6 +202,188 @@ impl Socket {
                 tcpsocket.shutdown()?;
                 Ok(())
             }
+            Socket::Icmp(_) | Socket::Raw(_) => Err(LinuxError::ENOTCONN),
+            Socket::Unix(unixsocket) => unixsocket.shutdown(),
+        }
+    }
+
+    unsafe fn setsockopt(
+        &self,
+        level: u32,
+        name: u32,
+        optval: *const c_void,
+        optlen: ctypes::socklen_t,
+    ) -> LinuxResult {
+        let int_val = || read_sockopt::<c_int>(optval, optlen);
+        let buf_size = || int_val().map(|size| size.max(0) as usize);
+        let flag = || int_val().map(|v| v != 0);
+        let timeout =
+            || read_sockopt::<ctypes::timeval>(optval, optlen).and_then(timeval_to_timeout);
+        let ttl = || -> LinuxResult<u8> {
+            match int_val()? {
+                -1 => Ok(DEFAULT_TTL),
+                ttl @ 1..=255 => Ok(ttl as u8),
+                _ => Err(LinuxError::EINVAL),
+            }
+        };
+
+        use ctypes::{IPPROTO_IP, IPPROTO_IPV6, IPPROTO_TCP, SOL_SOCKET};
+        match (self, level, name) {
+            (Socket::Udp(s), SOL_SOCKET, ctypes::SO_REUSEADDR) => {
+                s.lock().set_reuse_address(flag()?)
+            }
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_REUSEADDR) => {
+                s.lock().set_reuse_address(flag()?)
+            }
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_KEEPALIVE) => s.lock().set_keep_alive(flag()?),
+            (Socket::Udp(s), SOL_SOCKET, ctypes::SO_RCVBUF) => {
+                s.lock().set_recv_buffer_size(buf_size()?)
+            }
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_RCVBUF) => {
+                s.lock().set_recv_buffer_size(buf_size()?)
+            }
+            (Socket::Udp(s), SOL_SOCKET, ctypes::SO_SNDBUF) => {
+                s.lock().set_send_buffer_size(buf_size()?)
+            }
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_SNDBUF) => {
+                s.lock().set_send_buffer_size(buf_size()?)
+            }
+            (Socket::Udp(s), SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                s.lock().set_read_timeout(timeout()?)?
+            }
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                s.lock().set_read_timeout(timeout()?)?
+            }
+            (Socket::Udp(s), SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                s.lock().set_write_timeout(timeout()?)?
+            }
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                s.lock().set_write_timeout(timeout()?)?
+            }
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_LINGER) => {
+                let linger = read_sockopt::<ctypes::linger>(optval, optlen)?;
+                let timeout = Duration::from_secs(linger.l_linger.max(0) as u64);
+                s.lock()
+                    .set_linger((linger.l_onoff != 0).then_some(timeout));
+            }
+            (Socket::Tcp(s), IPPROTO_TCP, ctypes::TCP_NODELAY) => s.lock().set_nodelay(flag()?),
+            // smoltcp has only one interval, for both the idle time and the probes
+            (Socket::Tcp(s), IPPROTO_TCP, ctypes::TCP_KEEPIDLE | ctypes::TCP_KEEPINTVL) => {
+                let secs = int_val()?;
+                if secs <= 0 {
+                    return Err(LinuxError::EINVAL);
+                }
+                s.lock()
+                    .set_keep_alive_interval(Duration::from_secs(secs as u64))?;
+            }
+            (Socket::Udp(s), IPPROTO_IP, ctypes::IP_TTL)
+            | (Socket::Udp(s), IPPROTO_IPV6, ctypes::IPV6_UNICAST_HOPS) => {
+                s.lock().set_ttl(ttl()?)?
+            }
+            (Socket::Tcp(s), IPPROTO_IP, ctypes::IP_TTL)
+            | (Socket::Tcp(s), IPPROTO_IPV6, ctypes::IPV6_UNICAST_HOPS) => {
+                s.lock().set_ttl(ttl()?)?
+            }
+            (Socket::Icmp(s), SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                s.lock().set_read_timeout(timeout()?)?
+            }
+            (Socket::Raw(s), SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                s.lock().set_read_timeout(timeout()?)?
+            }
+            (Socket::Icmp(s), SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                s.lock().set_write_timeout(timeout()?)?
+            }
+            (Socket::Raw(s), SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                s.lock().set_write_timeout(timeout()?)?
+            }
+            (Socket::Icmp(s), IPPROTO_IP, ctypes::IP_TTL)
+            | (Socket::Icmp(s), IPPROTO_IPV6, ctypes::IPV6_UNICAST_HOPS) => {
+                s.lock().set_ttl(ttl()?)?
+            }
+            (Socket::Raw(s), IPPROTO_IP, ctypes::IP_TTL)
+            | (Socket::Raw(s), IPPROTO_IPV6, ctypes::IPV6_UNICAST_HOPS) => {
+                s.lock().set_ttl(ttl()?)?
+            }
+            _ => return Err(LinuxError::ENOPROTOOPT),
+        }
+        Ok(())
+    }
+
+    unsafe fn getsockopt(
+        &self,
+        level: u32,
+        name: u32,
+        optval: *mut c_void,
+        optlen: *mut ctypes::socklen_t,
+    ) -> LinuxResult {
+        let int_val = |val: c_int| write_sockopt(val, optval, optlen);
+        let flag = |val: bool| int_val(val as c_int);
+        let timeout = |val: Option<Duration>| {
+            write_sockopt::<ctypes::timeval>(val.unwrap_or_default().into(), optval, optlen)
+        };
+
+        use ctypes::{IPPROTO_IP, IPPROTO_IPV6, IPPROTO_TCP, SOL_SOCKET};
+        match (self, level, name) {
+            (Socket::Udp(_), SOL_SOCKET, ctypes::SO_TYPE) => int_val(ctypes::SOCK_DGRAM as _),
+            (Socket::Tcp(_), SOL_SOCKET, ctypes::SO_TYPE) => int_val(ctypes::SOCK_STREAM as _),
+            (Socket::Icmp(_), SOL_SOCKET, ctypes::SO_TYPE) => int_val(ctypes::SOCK_DGRAM as _),
+            (Socket::Raw(_), SOL_SOCKET, ctypes::SO_TYPE) => int_val(ctypes::SOCK_RAW as _),
+            (Socket::Unix(s), SOL_SOCKET, ctypes::SO_TYPE) => match s.socket_type() {
+                UnixSocketType::Stream => int_val(ctypes::SOCK_STREAM as _),
+                UnixSocketType::Datagram => int_val(ctypes::SOCK_DGRAM as _),
+            },
+            (Socket::Udp(s), SOL_SOCKET, ctypes::SO_REUSEADDR) => flag(s.lock().reuse_address()),
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_REUSEADDR) => flag(s.lock().reuse_address()),
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_KEEPALIVE) => flag(s.lock().keep_alive()),
+            (Socket::Udp(s), SOL_SOCKET, ctypes::SO_RCVBUF) => {
+                int_val(s.lock().recv_buffer_size() as _)
+            }
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_RCVBUF) => {
+                int_val(s.lock().recv_buffer_size() as _)
+            }
+            (Socket::Udp(s), SOL_SOCKET, ctypes::SO_SNDBUF) => {
+                int_val(s.lock().send_buffer_size() as _)
+            }
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_SNDBUF) => {
+                int_val(s.lock().send_buffer_size() as _)
+            }
+            (Socket::Udp(s), SOL_SOCKET, ctypes::SO_RCVTIMEO) => timeout(s.lock().read_timeout()),
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_RCVTIMEO) => timeout(s.lock().read_timeout()),
+            (Socket::Udp(s), SOL_SOCKET, ctypes::SO_SNDTIMEO) => timeout(s.lock().write_timeout()),
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_SNDTIMEO) => timeout(s.lock().write_timeout()),
+            (Socket::Tcp(s), SOL_SOCKET, ctypes::SO_LINGER) => {
+                let linger = s.lock().linger();
+                let val = ctypes::linger {
+                    l_onoff: linger.is_some() as c_int,
+                    l_linger: linger.map_or(0, |t| t.as_secs() as c_int),
+                };
+                write_sockopt(val, optval, optlen)
+            }
+            (Socket::Tcp(s), IPPROTO_TCP, ctypes::TCP_NODELAY) => flag(s.lock().nodelay()),
+            (Socket::Tcp(s), IPPROTO_TCP, ctypes::TCP_KEEPIDLE | ctypes::TCP_KEEPINTVL) => {
+                int_val(s.lock().keep_alive_interval().as_secs() as _)
+            }
+            (Socket::Udp(s), IPPROTO_IP, ctypes::IP_TTL)
+            | (Socket::Udp(s), IPPROTO_IPV6, ctypes::IPV6_UNICAST_HOPS) => {
+                int_val(s.lock().ttl() as _)
+            }
+            (Socket::Tcp(s), IPPROTO_IP, ctypes::IP_TTL)
+            | (Socket::Tcp(s), IPPROTO_IPV6, ctypes::IPV6_UNICAST_HOPS) => {
+                int_val(s.lock().ttl() as _)
+            }
+            (Socket::Icmp(s), SOL_SOCKET, ctypes::SO_RCVTIMEO) => timeout(s.lock().read_timeout()),
+            (Socket::Raw(s), SOL_SOCKET, ctypes::SO_RCVTIMEO) => timeout(s.lock().read_timeout()),
+            (Socket::Icmp(s), SOL_SOCKET, ctypes::SO_SNDTIMEO) => timeout(s.lock().write_timeout()),
+            (Socket::Raw(s), SOL_SOCKET, ctypes::SO_SNDTIMEO) => timeout(s.lock().write_timeout()),
+            (Socket::Icmp(s), IPPROTO_IP, ctypes::IP_TTL)
+            | (Socket::Icmp(s), IPPROTO_IPV6, ctypes::IPV6_UNICAST_HOPS) => {
+                int_val(s.lock().ttl() as _)
+            }
+            (Socket::Raw(s), IPPROTO_IP, ctypes::IP_TTL)
+            | (Socket::Raw(s), IPPROTO_IPV6, ctypes::IPV6_UNICAST_HOPS) => {
+                int_val(s.lock().ttl() as _)
+            }
+            _ => Err(LinuxError::ENOPROTOOPT),
         }
     }
 }
@@ -165,6 +423,9 @@ impl FileLike for Socket {
         match self {
             Socket::Udp(udpsocket) => udpsocket.lock().set_nonblocking(nonblock),
             Socket::Tcp(tcpsocket) => tcpsocket.lock().set_nonblocking(nonblock),
+            Socket::Icmp(icmpsocket) => icmpsocket.lock().set_nonblocking(nonblock),
+            Socket::Raw(rawsocket) => rawsocket.lock().set_nonblocking(nonblock),
+            Socket::Unix(unixsocket) => unixsocket.set_nonblocking(nonblock),
         }
         Ok(())
     }
@@ -194,34 +455,172 @@ impl From<ctypes::sockaddr_in> for SocketAddrV4 {
     }
 }
 
-fn into_sockaddr(addr: SocketAddr) -> (ctypes::sockaddr, ctypes::socklen_t) {
-    debug!("    Sockaddr: {}", addr);
+impl From<SocketAddrV6> for ctypes::sockaddr_in6 {
+    fn from(addr: SocketAddrV6) -> ctypes::sockaddr_in6 {
+        ctypes::sockaddr_in6 {
+            sin6_family: ctypes::AF_INET6 as u16,
+            sin6_port: addr.port().to_be(),
+            sin6_flowinfo: addr.flowinfo().to_be(),
+            sin6_addr: ctypes::in6_addr {
+                __in6_union: ctypes::in6_addr__bindgen_ty_1 {
+                    __s6_addr: addr.ip().octets(),
+                },
+            },
+            sin6_scope_id: addr.scope_id(),
+        }
+    }
+}
+
+impl From<ctypes::sockaddr_in6> for SocketAddrV6 {
+    fn from(addr: ctypes::sockaddr_in6) -> SocketAddrV6 {
+        SocketAddrV6::new(
+            Ipv6Addr::from(unsafe { addr.sin6_addr.__in6_union.__s6_addr }),
+            u16::from_be(addr.sin6_port),
+            u32::from_be(addr.sin6_flowinfo),
+            addr.sin6_scope_id,
+        )
+    }
+}
+
+/// Writes `addr` to the buffer `dst` of `*addrlen` bytes, and sets `*addrlen`
+/// to the size of the address.
+///
+/// As in Linux, the address is truncated if the buffer is too small.
+unsafe fn write_sockaddr(
+    addr: SockAddr,
+    dst: *mut ctypes::sockaddr,
+    addrlen: *mut ctypes::socklen_t,
+) {
+    unsafe fn copy_to<T>(
+        src: &T,
+        size: usize,
+        dst: *mut ctypes::sockaddr,
+        addrlen: *mut ctypes::socklen_t,
+    ) {
+        let len = size.min(*addrlen as usize);
+        core::ptr::copy_nonoverlapping(src as *const T as *const u8, dst as *mut u8, len);
+        *addrlen = size as _;
+    }
+
+    debug!("    Sockaddr: {:?}", addr);
     match addr {
-        SocketAddr::V4(addr) => (
-            unsafe { *(&ctypes::sockaddr_in::from(addr) as *const _ as *const ctypes::sockaddr) },
-            size_of::<ctypes::sockaddr>() as _,
-        ),
-        SocketAddr::V6(_) => panic!("IPv6 is not supported"),
+        SockAddr::Inet(SocketAddr::V4(addr)) => {
+            let size = size_of::<ctypes::sockaddr_in>();
+            copy_to(&ctypes::sockaddr_in::from(addr), size, dst, addrlen)
+        }
+        SockAddr::Inet(SocketAddr::V6(addr)) => {
+            let size = size_of::<ctypes::sockaddr_in6>();
+            copy_to(&ctypes::sockaddr_in6::from(addr), size, dst, addrlen)
+        }
+        SockAddr::Unix(addr) => {
+            let mut sun = ctypes::sockaddr_un {
+                sun_family: ctypes::AF_UNIX as _,
+                sun_path: [0; 108],
+            };
+            // an abstract name starts with a NUL byte, and a path ends with one
+            let (name, offset, nul) = match &addr {
+                UnixAddr::Unnamed => (&[][..], 0, 0),
+                UnixAddr::Path(path) => (path.as_bytes(), 0, 1),
+                UnixAddr::Abstract(name) => (&name[..], 1, 0),
+            };
+            for (dst, &src) in sun.sun_path[offset..].iter_mut().zip(name) {
+                *dst = src as _;
+            }
+            let path_len = (offset + name.len() + nul).min(sun.sun_path.len());
+            let size = size_of::<ctypes::sa_family_t>() + path_len;
+            copy_to(&sun, size, dst, addrlen)
+        }
+    }
+}
+
+/// Reads a socket option of type `T` from the buffer `optval` of `optlen`
+/// bytes.
+unsafe fn read_sockopt<T: Copy>(
+    optval: *const c_void,
+    optlen: ctypes::socklen_t,
+) -> LinuxResult<T> {
+    if optval.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    if (optlen as usize) < size_of::<T>() {
+        return Err(LinuxError::EINVAL);
     }
+    Ok((optval as *const T).read_unaligned())
+}
+
+/// Writes a socket option to the buffer `optval` of `*optlen` bytes, and sets
+/// `*optlen` to the number of bytes written.
+///
+/// As in Linux, the value is truncated if the buffer is too small.
+unsafe fn write_sockopt<T>(
+    val: T,
+    optval: *mut c_void,
+    optlen: *mut ctypes::socklen_t,
+) -> LinuxResult {
+    if optval.is_null() || optlen.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let len = size_of::<T>().min(*optlen as usize);
+    core::ptr::copy_nonoverlapping(&val as *const T as *const u8, optval as *mut u8, len);
+    *optlen = len as _;
+    Ok(())
+}
+
+/// Converts `SO_RCVTIMEO` or `SO_SNDTIMEO`, where zero means no timeout.
+fn timeval_to_timeout(tv: ctypes::timeval) -> LinuxResult<Option<Duration>> {
+    if tv.tv_sec < 0 || !(0..1_000_000).contains(&tv.tv_usec) {
+        return Err(LinuxError::EDOM);
+    }
+    let timeout = Duration::from(tv);
+    Ok((!timeout.is_zero()).then_some(timeout))
 }
 
 fn from_sockaddr(
     addr: *const ctypes::sockaddr,
     addrlen: ctypes::socklen_t,
-) -> LinuxResult<SocketAddr> {
+) -> LinuxResult<SockAddr> {
     if addr.is_null() {
         return Err(LinuxError::EFAULT);
     }
-    if addrlen != size_of::<ctypes::sockaddr>() as _ {
-        return Err(LinuxError::EINVAL);
-    }
-
-    let mid = unsafe { *(addr as *const ctypes::sockaddr_in) };
-    if mid.sin_family != ctypes::AF_INET as u16 {
+    if addrlen < size_of::<ctypes::sa_family_t>() as _ {
         return Err(LinuxError::EINVAL);
     }
 
-    let res = SocketAddr::V4(mid.into());
+    let check_len = |size: usize| match addrlen as usize >= size {
+        true => Ok(()),
+        false => Err(LinuxError::EINVAL),
+    };
+    let res = match unsafe { (*addr).sa_family } as u32 {
+        ctypes::AF_INET => {
+            check_len(size_of::<ctypes::sockaddr_in>())?;
+            let addr = unsafe { *(addr as *const ctypes::sockaddr_in) };
+            SockAddr::Inet(SocketAddr::V4(addr.into()))
+        }
+        ctypes::AF_INET6 => {
+            check_len(size_of::<ctypes::sockaddr_in6>())?;
+            let addr = unsafe { *(addr as *const ctypes::sockaddr_in6) };
+            SockAddr::Inet(SocketAddr::V6(addr.into()))
+        }
+        ctypes::AF_UNIX => {
+            let path_len = (addrlen as usize - size_of::<ctypes::sa_family_t>())
+                .min(size_of::<ctypes::sockaddr_un>() - size_of::<ctypes::sa_family_t>());
+            let sun = addr as *const ctypes::sockaddr_un;
+            let path = unsafe {
+                core::slice::from_raw_parts((*sun).sun_path.as_ptr() as *const u8, path_len)
+            };
+            SockAddr::Unix(match path {
+                [] => UnixAddr::Unnamed,
+                [0, name @ ..] => UnixAddr::Abstract(name.to_vec()),
+                _ => {
+                    let len = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+                    let path =
+                        core::str::from_utf8(&path[..len]).map_err(|_| LinuxError::EINVAL)?;
+                    UnixAddr::Path(path.into())
+                }
+            })
+        }
+        _ => return Err(LinuxError::EAFNOSUPPORT),
+    };
     debug!("    load sockaddr:{:#x} => {:?}", addr as usize, res);
     Ok(res)
 }
@@ -234,19 +633,75 @@ pub fn sys_socket(domain: c_int, socktype: c_int, protocol: c_int) -> c_int {
     let (domain, socktype, protocol) = (domain as u32, socktype as u32, protocol as u32);
     syscall_body!(sys_socket, {
         match (domain, socktype, protocol) {
-            (ctypes::AF_INET, ctypes::SOCK_STREAM, ctypes::IPPROTO_TCP)
-            | (ctypes::AF_INET, ctypes::SOCK_STREAM, 0) => {
+            // the sockets are dual-stack, an IPv4 address can be used for `AF_INET6`
+            (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_STREAM, ctypes::IPPROTO_TCP)
+            | (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_STREAM, 0) => {
                 Socket::Tcp(Mutex::new(TcpSocket::new())).add_to_fd_table()
             }
-            (ctypes::AF_INET, ctypes::SOCK_DGRAM, ctypes::IPPROTO_UDP)
-            | (ctypes::AF_INET, ctypes::SOCK_DGRAM, 0) => {
+            (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_DGRAM, ctypes::IPPROTO_UDP)
+            | (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_DGRAM, 0) => {
                 Socket::Udp(Mutex::new(UdpSocket::new())).add_to_fd_table()
             }
+            // ping sockets
+            (ctypes::AF_INET, ctypes::SOCK_DGRAM, ctypes::IPPROTO_ICMP)
+            | (ctypes::AF_INET6, ctypes::SOCK_DGRAM, ctypes::IPPROTO_ICMPV6) => {
+                Socket::Icmp(Mutex::new(IcmpSocket::new())).add_to_fd_table()
+            }
+            (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_RAW, 1..=255) => {
+                let ipv6 = domain == ctypes::AF_INET6;
+                Socket::Raw(Mutex::new(RawSocket::new(ipv6, protocol as u8)?)).add_to_fd_table()
+            }
+            (ctypes::AF_UNIX, _, 0) => {
+                let ty = unix_socket_type(socktype)?;
+                Socket::Unix(UnixSocket::new(ty)).add_to_fd_table()
+            }
             _ => Err(LinuxError::EINVAL),
         }
     })
 }
 
+/// Create a pair of connected Unix domain sockets.
+///
+/// Return 0 if success.
+pub fn sys_socketpair(domain: c_int, socktype: c_int, protocol: c_int, fds: &mut [c_int]) -> c_int {
+    debug!(
+        "sys_socketpair <= {} {} {} {:#x}",
+        domain,
+        socktype,
+        protocol,
+        fds.as_ptr() as usize
+    );
+    syscall_body!(sys_socketpair, {
+        if fds.len() != 2 {
+            return Err(LinuxError::EFAULT);
+        }
+        if domain as u32 != ctypes::AF_UNIX {
+            return Err(LinuxError::EAFNOSUPPORT);
+        }
+        if protocol != 0 {
+            return Err(LinuxError::EPROTONOSUPPORT);
+        }
+
+        let (a, b) = UnixSocket::new_pair(unix_socket_type(socktype as u32)?);
+        let fd_a = Socket::Unix(a).add_to_fd_table()?;
+        let fd_b = Socket::Unix(b).add_to_fd_table().inspect_err(|_| {
+            close_file_like(fd_a).ok();
+        })?;
+
+        fds[0] = fd_a;
+        fds[1] = fd_b;
+        Ok(0)
+    })
+}
+
+fn unix_socket_type(socktype: u32) -> LinuxResult<UnixSocketType> {
+    match socktype {
+        ctypes::SOCK_STREAM => Ok(UnixSocketType::Stream),
+        ctypes::SOCK_DGRAM => Ok(UnixSocketType::Datagram),
+        _ => Err(LinuxError::EPROTOTYPE),
+    }
+}
+
 /// Bind a address to a socket.
 ///
 /// Return 0 if success.
@@ -356,9 +811,7 @@ pub unsafe fn sys_recvfrom(
 
         let res = socket.recvfrom(buf)?;
         if let Some(addr) = res.1 {
-            unsafe {
-                (*socket_addr, *addrlen) = into_sockaddr(addr);
-            }
+            unsafe { write_sockaddr(addr, socket_addr, addrlen) };
         }
         Ok(res.0)
     })
@@ -417,12 +870,9 @@ pub unsafe fn sys_accept(
             return Err(LinuxError::EFAULT);
         }
         let socket = Socket::from_fd(socket_fd)?;
-        let new_socket = socket.accept()?;
-        let addr = new_socket.peer_addr()?;
-        let new_fd = Socket::add_to_fd_table(Socket::Tcp(Mutex::new(new_socket)))?;
-        unsafe {
-            (*socket_addr, *socket_len) = into_sockaddr(addr);
-        }
+        let (new_socket, addr) = socket.accept()?;
+        let new_fd = new_socket.add_to_fd_table()?;
+        unsafe { write_sockaddr(addr, socket_addr, socket_len) };
         Ok(new_fd)
     })
 }
@@ -443,7 +893,7 @@ pub fn sys_shutdown(
 
 /// Query addresses for a domain name.
 ///
-/// Only IPv4. Ports are always 0. Ignore servname and hint.
+/// Both IPv4 and IPv6 addresses are returned. Ports are always 0. Ignore servname and hint.
 /// Results' ai_flags and ai_canonname are 0 or NULL.
 ///
 /// Return address number if success.
@@ -502,7 +952,25 @@ pub unsafe fn sys_getaddrinfo(
                     lock: [0],
                     ref_: 0,
                 },
-                _ => panic!("IPv6 is not supported"),
+                IpAddr::V6(ip) => ctypes::aibuf {
+                    ai: ctypes::addrinfo {
+                        ai_family: ctypes::AF_INET6 as _,
+                        // TODO: This is a hard-code part, only return TCP parameters
+                        ai_socktype: ctypes::SOCK_STREAM as _,
+                        ai_protocol: ctypes::IPPROTO_TCP as _,
+                        ai_addrlen: size_of::<ctypes::sockaddr_in6>() as _,
+                        ai_addr: core::ptr::null_mut(),
+                        ai_canonname: core::ptr::null_mut(),
+                        ai_next: core::ptr::null_mut(),
+                        ai_flags: 0,
+                    },
+                    sa: ctypes::aibuf_sa {
+                        sin6: SocketAddrV6::new(ip, port, 0, 0).into(),
+                    },
+                    slot: i as i16,
+                    lock: [0],
+                    ref_: 0,
+                },
             };
             out.push(buf);
             out[i].ai.ai_addr =
@@ -549,9 +1017,7 @@ pub unsafe fn sys_getsockname(
         if unsafe { *addrlen } < size_of::<ctypes::sockaddr>() as u32 {
             return Err(LinuxError::EINVAL);
         }
-        unsafe {
-            (*addr, *addrlen) = into_sockaddr(Socket::from_fd(sock_fd)?.local_addr()?);
-        }
+        unsafe { write_sockaddr(Socket::from_fd(sock_fd)?.local_addr()?, addr, addrlen) };
         Ok(0)
     })
 }
@@ -573,9 +1039,49 @@ pub unsafe fn sys_getpeername(
         if unsafe { *addrlen } < size_of::<ctypes::sockaddr>() as u32 {
             return Err(LinuxError::EINVAL);
         }
-        unsafe {
-            (*addr, *addrlen) = into_sockaddr(Socket::from_fd(sock_fd)?.peer_addr()?);
-        }
+        unsafe { write_sockaddr(Socket::from_fd(sock_fd)?.peer_addr()?, addr, addrlen) };
+        Ok(0)
+    })
+}
+
+/// Set options on a socket.
+///
+/// Return 0 if success.
+pub unsafe fn sys_setsockopt(
+    socket_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *const c_void,
+    optlen: ctypes::socklen_t,
+) -> c_int {
+    debug!(
+        "sys_setsockopt <= {} {} {} {:#x} {}",
+        socket_fd, level, optname, optval as usize, optlen
+    );
+    syscall_body!(sys_setsockopt, {
+        let socket = Socket::from_fd(socket_fd)?;
+        unsafe { socket.setsockopt(level as u32, optname as u32, optval, optlen)? };
+        Ok(0)
+    })
+}
+
+/// Get options on a socket.
+///
+/// Return 0 if success.
+pub unsafe fn sys_getsockopt(
+    socket_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *mut c_void,
+    optlen: *mut ctypes::socklen_t,
+) -> c_int {
+    debug!(
+        "sys_getsockopt <= {} {} {} {:#x} {:#x}",
+        socket_fd, level, optname, optval as usize, optlen as usize
+    );
+    syscall_body!(sys_getsockopt, {
+        let socket = Socket::from_fd(socket_fd)?;
+        unsafe { socket.getsockopt(level as u32, optname as u32, optval, optlen)? };
         Ok(0)
     })
 }
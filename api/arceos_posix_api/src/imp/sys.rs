@@ -1,4 +1,5 @@
-use core::ffi::{c_int, c_long};
+use axerrno::LinuxError;
+use core::ffi::{c_int, c_long, c_uint, c_void};
 
 use crate::ctypes;
 
@@ -27,3 +28,22 @@ pub fn sys_sysconf(name: c_int) -> c_long {
         }
     })
 }
+
+/// Fill the buffer with random bytes.
+///
+/// The flags (`GRND_NONBLOCK` and `GRND_RANDOM`) are ignored, as the random
+/// numbers are always available.
+pub fn sys_getrandom(buf: *mut c_void, buflen: usize, flags: c_uint) -> ctypes::ssize_t {
+    debug!(
+        "sys_getrandom <= {:#x} {} {:#x}",
+        buf as usize, buflen, flags
+    );
+    syscall_body!(sys_getrandom, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, buflen) };
+        axhal::random::fill_random(dst);
+        Ok(buflen as ctypes::ssize_t)
+    })
+}
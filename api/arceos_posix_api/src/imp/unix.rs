@@ -0,0 +1,611 @@
+//! Unix domain sockets (`AF_UNIX`).
+//!
+//! The sockets live in the kernel only. A socket is bound to a filesystem path
+//! or to an abstract name (starting with a NUL byte), and the bound sockets
+//! are found by their names in a global table. With the `fs` feature, binding
+//! a path also creates a file there, which must be removed before the path can
+//! be bound again, as in Linux.
+//!
+//! A blocked task sleeps on the wait queue of the stream direction or the
+//! socket it waits for, and is woken by the peer that changes it. Without the
+//! `multitask` feature, it yields and retries instead.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::{Mutex, MutexGuard};
+
+/// The capacity of the buffer of each direction of a stream connection.
+const STREAM_BUF_LEN: usize = 64 * 1024;
+/// The largest datagram that can be sent.
+const MAX_DGRAM_LEN: usize = 64 * 1024;
+/// The number of datagrams a socket can queue before the senders block.
+const DGRAM_QUEUE_LEN: usize = 64;
+/// The number of pending connections a listening socket can hold.
+const LISTEN_BACKLOG: usize = 128;
+
+/// The bound sockets, indexed by their addresses.
+static BOUND_SOCKETS: Mutex<BTreeMap<UnixAddr, Weak<Inner>>> = Mutex::new(BTreeMap::new());
+
+/// The address of a Unix domain socket.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnixAddr {
+    /// Not bound.
+    Unnamed,
+    /// A filesystem path.
+    Path(String),
+    /// A name in the abstract namespace, without the leading NUL byte.
+    Abstract(Vec<u8>),
+}
+
+/// The type of a Unix domain socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnixSocketType {
+    Stream,
+    Datagram,
+}
+
+/// The tasks blocked on a socket or a stream direction.
+struct Waiters {
+    /// Incremented on every notification, so that a notification between
+    /// checking the state and sleeping is not lost.
+    #[cfg(feature = "multitask")]
+    seq: core::sync::atomic::AtomicUsize,
+    #[cfg(feature = "multitask")]
+    wq: axtask::WaitQueue,
+}
+
+/// One direction of a stream connection.
+struct StreamBuf {
+    data: VecDeque<u8>,
+    /// Either end has shut down or closed this direction.
+    closed: bool,
+}
+
+/// A [`StreamBuf`] shared by both ends, with the tasks waiting for data or
+/// space in it.
+struct Stream {
+    buf: Mutex<StreamBuf>,
+    waiters: Waiters,
+}
+
+struct Datagram {
+    data: Vec<u8>,
+    from: UnixAddr,
+}
+
+enum State {
+    Unconnected,
+    Listening(VecDeque<UnixSocket>),
+    /// A connected stream socket.
+    Connected {
+        rx: Arc<Stream>,
+        tx: Arc<Stream>,
+        peer_addr: UnixAddr,
+    },
+    /// A datagram socket with a default destination.
+    DgramConnected {
+        peer: Weak<Inner>,
+        peer_addr: UnixAddr,
+    },
+}
+
+struct Inner {
+    ty: UnixSocketType,
+    local_addr: Mutex<UnixAddr>,
+    /// The key in [`BOUND_SOCKETS`] if the socket is bound.
+    bound_key: Mutex<Option<UnixAddr>>,
+    state: Mutex<State>,
+    /// Received datagrams, only used by datagram sockets.
+    dgrams: Mutex<VecDeque<Datagram>>,
+    /// The tasks waiting for the pending connections or the datagrams, or for
+    /// space of them.
+    waiters: Waiters,
+}
+
+/// A Unix domain socket.
+pub struct UnixSocket {
+    inner: Arc<Inner>,
+    nonblock: AtomicBool,
+}
+
+#[cfg(feature = "multitask")]
+impl Waiters {
+    const fn new() -> Self {
+        Self {
+            seq: core::sync::atomic::AtomicUsize::new(0),
+            wq: axtask::WaitQueue::new(),
+        }
+    }
+
+    /// Returns the ticket to [`Waiters::wait`] with, which must be taken
+    /// before checking the state.
+    fn ticket(&self) -> usize {
+        self.seq.load(Ordering::Acquire)
+    }
+
+    /// Sleeps until a notification after the ticket is taken.
+    fn wait(&self, ticket: usize) {
+        self.wq
+            .wait_until(|| self.seq.load(Ordering::Acquire) != ticket);
+    }
+
+    /// Wakes all the waiting tasks.
+    fn notify(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_all(false);
+    }
+}
+
+#[cfg(not(feature = "multitask"))]
+impl Waiters {
+    const fn new() -> Self {
+        Self {}
+    }
+
+    fn ticket(&self) -> usize {
+        0
+    }
+
+    /// The only task yields, so the IRQs can be handled.
+    fn wait(&self, _ticket: usize) {
+        crate::sys_sched_yield();
+    }
+
+    fn notify(&self) {}
+}
+
+impl Stream {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            buf: Mutex::new(StreamBuf {
+                data: VecDeque::new(),
+                closed: false,
+            }),
+            waiters: Waiters::new(),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, StreamBuf> {
+        self.buf.lock()
+    }
+
+    /// Closes this direction and wakes both ends.
+    fn close(&self) {
+        self.lock().closed = true;
+        self.waiters.notify();
+    }
+}
+
+impl UnixAddr {
+    /// Returns the key of the address in [`BOUND_SOCKETS`], where the paths
+    /// are absolute.
+    fn key(&self) -> LinuxResult<UnixAddr> {
+        match self {
+            #[cfg(feature = "fs")]
+            UnixAddr::Path(path) => Ok(UnixAddr::Path(axfs::api::canonicalize(path)?)),
+            _ => Ok(self.clone()),
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let State::Connected { rx, tx, .. } = &*self.state.get_mut() {
+            rx.close();
+            tx.close();
+        }
+        if let Some(key) = self.bound_key.get_mut() {
+            let mut bound = BOUND_SOCKETS.lock();
+            // the entry of a dropped socket can't be upgraded
+            if bound.get(key).is_some_and(|s| s.strong_count() == 0) {
+                bound.remove(key);
+            }
+        }
+    }
+}
+
+impl UnixSocket {
+    /// Creates a new unbound Unix domain socket.
+    pub fn new(ty: UnixSocketType) -> Self {
+        Self::from_inner(Inner {
+            ty,
+            local_addr: Mutex::new(UnixAddr::Unnamed),
+            bound_key: Mutex::new(None),
+            state: Mutex::new(State::Unconnected),
+            dgrams: Mutex::new(VecDeque::new()),
+            waiters: Waiters::new(),
+        })
+    }
+
+    /// Creates a pair of connected sockets, like `socketpair()`.
+    pub fn new_pair(ty: UnixSocketType) -> (Self, Self) {
+        let a = Self::new(ty);
+        let b = Self::new(ty);
+        match ty {
+            UnixSocketType::Stream => {
+                let (a_to_b, b_to_a) = (Stream::new(), Stream::new());
+                *a.inner.state.lock() = State::Connected {
+                    rx: b_to_a.clone(),
+                    tx: a_to_b.clone(),
+                    peer_addr: UnixAddr::Unnamed,
+                };
+                *b.inner.state.lock() = State::Connected {
+                    rx: a_to_b,
+                    tx: b_to_a,
+                    peer_addr: UnixAddr::Unnamed,
+                };
+            }
+            UnixSocketType::Datagram => {
+                *a.inner.state.lock() = State::DgramConnected {
+                    peer: Arc::downgrade(&b.inner),
+                    peer_addr: UnixAddr::Unnamed,
+                };
+                *b.inner.state.lock() = State::DgramConnected {
+                    peer: Arc::downgrade(&a.inner),
+                    peer_addr: UnixAddr::Unnamed,
+                };
+            }
+        }
+        (a, b)
+    }
+
+    fn from_inner(inner: Inner) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            nonblock: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the type of the socket.
+    pub fn socket_type(&self) -> UnixSocketType {
+        self.inner.ty
+    }
+
+    /// Returns whether this socket is in nonblocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Moves this socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblock.store(nonblocking, Ordering::Release);
+    }
+
+    /// Returns the address the socket is bound to.
+    pub fn local_addr(&self) -> UnixAddr {
+        self.inner.local_addr.lock().clone()
+    }
+
+    /// Returns the address of the connected peer.
+    pub fn peer_addr(&self) -> LinuxResult<UnixAddr> {
+        match &*self.inner.state.lock() {
+            State::Connected { peer_addr, .. } | State::DgramConnected { peer_addr, .. } => {
+                Ok(peer_addr.clone())
+            }
+            _ => Err(LinuxError::ENOTCONN),
+        }
+    }
+
+    /// Binds the socket to a path or an abstract name.
+    pub fn bind(&self, addr: UnixAddr) -> LinuxResult {
+        let mut local_addr = self.inner.local_addr.lock();
+        if *local_addr != UnixAddr::Unnamed || addr == UnixAddr::Unnamed {
+            return Err(LinuxError::EINVAL);
+        }
+
+        let key = addr.key()?;
+        let mut bound = BOUND_SOCKETS.lock();
+        if bound.get(&key).is_some_and(|s| s.strong_count() > 0) {
+            return Err(LinuxError::EADDRINUSE);
+        }
+        #[cfg(feature = "fs")]
+        if let UnixAddr::Path(path) = &key {
+            // the socket file
+            axfs::api::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .map_err(|e| match e {
+                    axerrno::AxError::AlreadyExists => LinuxError::EADDRINUSE,
+                    e => e.into(),
+                })?;
+        }
+        bound.insert(key.clone(), Arc::downgrade(&self.inner));
+        *self.inner.bound_key.lock() = Some(key);
+        debug!("Unix socket: bound to {:?}", addr);
+        *local_addr = addr;
+        Ok(())
+    }
+
+    /// Starts listening for connections on a bound stream socket.
+    pub fn listen(&self) -> LinuxResult {
+        if self.inner.ty != UnixSocketType::Stream {
+            return Err(LinuxError::EOPNOTSUPP);
+        }
+        if *self.inner.local_addr.lock() == UnixAddr::Unnamed {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut state = self.inner.state.lock();
+        match &*state {
+            State::Unconnected => *state = State::Listening(VecDeque::new()),
+            State::Listening(_) => {}
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(())
+    }
+
+    /// Connects a stream socket to a listening socket, or sets the default
+    /// destination of a datagram socket.
+    pub fn connect(&self, addr: UnixAddr) -> LinuxResult {
+        let peer = lookup(&addr)?;
+        if peer.ty != self.inner.ty {
+            return Err(LinuxError::EPROTOTYPE);
+        }
+        if self.inner.ty == UnixSocketType::Datagram {
+            *self.inner.state.lock() = State::DgramConnected {
+                peer: Arc::downgrade(&peer),
+                peer_addr: addr,
+            };
+            return Ok(());
+        }
+
+        if !matches!(*self.inner.state.lock(), State::Unconnected) {
+            return Err(LinuxError::EISCONN);
+        }
+        if Arc::ptr_eq(&peer, &self.inner) {
+            return Err(LinuxError::ECONNREFUSED);
+        }
+        let (to_server, to_client) = (Stream::new(), Stream::new());
+        let mut server = Some(Self::from_inner(Inner {
+            ty: UnixSocketType::Stream,
+            local_addr: Mutex::new(addr.clone()),
+            bound_key: Mutex::new(None),
+            state: Mutex::new(State::Connected {
+                rx: to_server.clone(),
+                tx: to_client.clone(),
+                peer_addr: self.local_addr(),
+            }),
+            dgrams: Mutex::new(VecDeque::new()),
+            waiters: Waiters::new(),
+        }));
+        self.block_on(&peer.waiters, || match &mut *peer.state.lock() {
+            State::Listening(backlog) if backlog.len() < LISTEN_BACKLOG => {
+                backlog.push_back(server.take().unwrap());
+                peer.waiters.notify();
+                Ok(())
+            }
+            State::Listening(_) => Err(LinuxError::EAGAIN),
+            _ => Err(LinuxError::ECONNREFUSED),
+        })?;
+        *self.inner.state.lock() = State::Connected {
+            rx: to_client,
+            tx: to_server,
+            peer_addr: addr,
+        };
+        debug!("Unix socket: connected to {:?}", self.peer_addr());
+        Ok(())
+    }
+
+    /// Accepts a pending connection of a listening socket, returns the new
+    /// socket and the address of the peer.
+    pub fn accept(&self) -> LinuxResult<(UnixSocket, UnixAddr)> {
+        if self.inner.ty != UnixSocketType::Stream {
+            return Err(LinuxError::EOPNOTSUPP);
+        }
+        let waiters = &self.inner.waiters;
+        let socket = self.block_on(waiters, || match &mut *self.inner.state.lock() {
+            State::Listening(backlog) => {
+                let socket = backlog.pop_front().ok_or(LinuxError::EAGAIN)?;
+                waiters.notify();
+                Ok(socket)
+            }
+            _ => Err(LinuxError::EINVAL),
+        })?;
+        let peer_addr = socket.peer_addr()?;
+        Ok((socket, peer_addr))
+    }
+
+    /// Sends data to the connected peer.
+    pub fn send(&self, buf: &[u8]) -> LinuxResult<usize> {
+        let state = self.inner.state.lock();
+        match &*state {
+            State::Connected { tx, .. } => {
+                let tx = tx.clone();
+                drop(state);
+                self.send_stream(&tx, buf)
+            }
+            State::DgramConnected { peer, .. } => {
+                let peer = peer.upgrade().ok_or(LinuxError::ECONNREFUSED)?;
+                drop(state);
+                self.send_dgram(&peer, buf)
+            }
+            _ => Err(LinuxError::ENOTCONN),
+        }
+    }
+
+    /// Sends a datagram to the given address.
+    pub fn send_to(&self, buf: &[u8], addr: UnixAddr) -> LinuxResult<usize> {
+        if self.inner.ty == UnixSocketType::Stream {
+            return Err(LinuxError::EISCONN);
+        }
+        let peer = lookup(&addr)?;
+        if peer.ty != UnixSocketType::Datagram {
+            return Err(LinuxError::EPROTOTYPE);
+        }
+        self.send_dgram(&peer, buf)
+    }
+
+    /// Receives data on the socket, returns the number of bytes read and the
+    /// sender of a datagram.
+    pub fn recv_from(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<UnixAddr>)> {
+        match self.inner.ty {
+            UnixSocketType::Stream => {
+                let rx = match &*self.inner.state.lock() {
+                    State::Connected { rx, .. } => rx.clone(),
+                    _ => return Err(LinuxError::ENOTCONN),
+                };
+                self.block_on(&rx.waiters, || {
+                    let mut rx_buf = rx.lock();
+                    if rx_buf.data.is_empty() {
+                        return if rx_buf.closed {
+                            Ok((0, None))
+                        } else {
+                            Err(LinuxError::EAGAIN)
+                        };
+                    }
+                    let len = buf.len().min(rx_buf.data.len());
+                    for (dst, src) in buf.iter_mut().zip(rx_buf.data.drain(..len)) {
+                        *dst = src;
+                    }
+                    rx.waiters.notify();
+                    Ok((len, None))
+                })
+            }
+            UnixSocketType::Datagram => self.block_on(&self.inner.waiters, || {
+                let dgram = self
+                    .inner
+                    .dgrams
+                    .lock()
+                    .pop_front()
+                    .ok_or(LinuxError::EAGAIN)?;
+                self.inner.waiters.notify();
+                // the rest of a long datagram is discarded
+                let len = buf.len().min(dgram.data.len());
+                buf[..len].copy_from_slice(&dgram.data[..len]);
+                Ok((len, Some(dgram.from)))
+            }),
+        }
+    }
+
+    /// Shuts down both directions of a stream connection.
+    pub fn shutdown(&self) -> LinuxResult {
+        match &*self.inner.state.lock() {
+            State::Connected { rx, tx, .. } => {
+                rx.close();
+                tx.close();
+                Ok(())
+            }
+            _ => Err(LinuxError::ENOTCONN),
+        }
+    }
+
+    /// Whether the socket is readable or writable.
+    pub fn poll(&self) -> LinuxResult<PollState> {
+        let state = match &*self.inner.state.lock() {
+            State::Unconnected => PollState {
+                readable: false,
+                writable: self.inner.ty == UnixSocketType::Datagram,
+            },
+            State::Listening(backlog) => PollState {
+                readable: !backlog.is_empty(),
+                writable: false,
+            },
+            State::Connected { rx, tx, .. } => {
+                let (rx, tx) = (rx.lock(), tx.lock());
+                PollState {
+                    readable: !rx.data.is_empty() || rx.closed,
+                    writable: tx.data.len() < STREAM_BUF_LEN || tx.closed,
+                }
+            }
+            State::DgramConnected { peer, .. } => PollState {
+                readable: false,
+                writable: match peer.upgrade() {
+                    Some(peer) => peer.dgrams.lock().len() < DGRAM_QUEUE_LEN,
+                    None => true, // fails immediately
+                },
+            },
+        };
+        Ok(PollState {
+            readable: state.readable || !self.inner.dgrams.lock().is_empty(),
+            writable: state.writable,
+        })
+    }
+}
+
+/// Private methods
+impl UnixSocket {
+    fn send_stream(&self, tx: &Stream, buf: &[u8]) -> LinuxResult<usize> {
+        let mut written = 0;
+        loop {
+            let res = self.block_on(&tx.waiters, || {
+                let mut tx_buf = tx.lock();
+                if tx_buf.closed {
+                    return Err(LinuxError::EPIPE);
+                }
+                let len = (STREAM_BUF_LEN - tx_buf.data.len()).min(buf.len() - written);
+                if len == 0 {
+                    return Err(LinuxError::EAGAIN);
+                }
+                tx_buf.data.extend(&buf[written..written + len]);
+                tx.waiters.notify();
+                Ok(len)
+            });
+            match res {
+                Ok(len) => written += len,
+                // return what has been written
+                Err(_) if written > 0 => return Ok(written),
+                Err(e) => return Err(e),
+            }
+            if written == buf.len() || self.is_nonblocking() {
+                return Ok(written);
+            }
+        }
+    }
+
+    fn send_dgram(&self, peer: &Inner, buf: &[u8]) -> LinuxResult<usize> {
+        if buf.len() > MAX_DGRAM_LEN {
+            return Err(LinuxError::EMSGSIZE);
+        }
+        let from = self.local_addr();
+        self.block_on(&peer.waiters, || {
+            let mut dgrams = peer.dgrams.lock();
+            if dgrams.len() >= DGRAM_QUEUE_LEN {
+                return Err(LinuxError::EAGAIN);
+            }
+            dgrams.push_back(Datagram {
+                data: buf.to_vec(),
+                from: from.clone(),
+            });
+            peer.waiters.notify();
+            Ok(buf.len())
+        })
+    }
+
+    /// Retries `f` until it doesn't fail with `EAGAIN`, unless the socket is
+    /// nonblocking. It sleeps on `waiters` between the retries.
+    fn block_on<F, T>(&self, waiters: &Waiters, mut f: F) -> LinuxResult<T>
+    where
+        F: FnMut() -> LinuxResult<T>,
+    {
+        loop {
+            let ticket = waiters.ticket();
+            match f() {
+                Err(LinuxError::EAGAIN) if !self.is_nonblocking() => waiters.wait(ticket),
+                res => return res,
+            }
+        }
+    }
+}
+
+/// Finds the socket bound to `addr`.
+fn lookup(addr: &UnixAddr) -> LinuxResult<Arc<Inner>> {
+    if *addr == UnixAddr::Unnamed {
+        return Err(LinuxError::EINVAL);
+    }
+    let key = addr.key()?;
+    if let Some(socket) = BOUND_SOCKETS.lock().get(&key).and_then(Weak::upgrade) {
+        return Ok(socket);
+    }
+    match &key {
+        #[cfg(feature = "fs")]
+        UnixAddr::Path(path) if axfs::api::metadata(path).is_ok() => Err(LinuxError::ECONNREFUSED),
+        UnixAddr::Path(_) => Err(LinuxError::ENOENT),
+        _ => Err(LinuxError::ECONNREFUSED),
+    }
+}
@@ -14,10 +14,22 @@ use embedded_graphics::{
     primitives::{Circle, PrimitiveStyle, Rectangle, Triangle},
     text::{Alignment, Text},
 };
+use std::os::arceos::api::display as api;
 
 const INIT_X: i32 = 80;
 const INIT_Y: i32 = 400;
 const RECT_SIZE: u32 = 150;
+const STEP: i32 = 10;
+
+// Linux evdev codes reported by the input devices
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const KEY_UP: u16 = 103;
+const KEY_LEFT: u16 = 105;
+const KEY_RIGHT: u16 = 106;
+const KEY_DOWN: u16 = 108;
 
 pub struct DrawingBoard {
     disp: Display,
@@ -67,7 +79,7 @@ impl DrawingBoard {
     }
 }
 
-fn test_gpu() {
+fn test_gpu() -> DrawingBoard {
     let mut board = DrawingBoard::new();
     board.disp.clear(Rgb888::BLACK).unwrap();
     for _ in 0..5 {
@@ -75,12 +87,35 @@ fn test_gpu() {
         board.paint();
         board.disp.flush();
     }
+    board
 }
 
-#[cfg_attr(feature = "axstd", no_mangle)]
-fn main() -> ! {
-    test_gpu();
+/// Moves the drawing with the arrow keys or the mouse.
+fn test_input(mut board: DrawingBoard) -> ! {
     loop {
-        core::hint::spin_loop();
+        let Some(event) = api::ax_read_input_event() else {
+            core::hint::spin_loop();
+            continue;
+        };
+        let value = event.value as i32;
+        let delta = match (event.event_type, event.code) {
+            (EV_KEY, KEY_UP) if value != 0 => Point::new(0, -STEP),
+            (EV_KEY, KEY_DOWN) if value != 0 => Point::new(0, STEP),
+            (EV_KEY, KEY_LEFT) if value != 0 => Point::new(-STEP, 0),
+            (EV_KEY, KEY_RIGHT) if value != 0 => Point::new(STEP, 0),
+            (EV_REL, REL_X) => Point::new(value, 0),
+            (EV_REL, REL_Y) => Point::new(0, value),
+            _ => continue,
+        };
+        board.latest_pos += delta;
+        board.disp.clear(Rgb888::BLACK).unwrap();
+        board.paint();
+        board.disp.flush();
     }
 }
+
+#[cfg_attr(feature = "axstd", no_mangle)]
+fn main() -> ! {
+    let board = test_gpu();
+    test_input(board)
+}
@@ -8,10 +8,19 @@ extern crate axstd as std;
 use std::io::{self, prelude::*};
 use std::net::{TcpStream, ToSocketAddrs};
 
+#[cfg(feature = "https")]
+use std::net::tls::{self, TlsStream};
+
+const HOST: &str = "ident.me";
 #[cfg(feature = "dns")]
-const DEST: &str = "ident.me:80";
+const DEST_HOST: &str = HOST;
 #[cfg(not(feature = "dns"))]
-const DEST: &str = "49.12.234.183:80";
+const DEST_HOST: &str = "49.12.234.183";
+
+#[cfg(feature = "https")]
+const DEST_PORT: u16 = 443;
+#[cfg(not(feature = "https"))]
+const DEST_PORT: u16 = 80;
 
 const REQUEST: &str = "\
 GET / HTTP/1.1\r\n\
@@ -20,11 +29,15 @@ Accept: */*\r\n\
 \r\n";
 
 fn client() -> io::Result<()> {
-    for addr in DEST.to_socket_addrs()? {
-        println!("dest: {} ({})", DEST, addr);
+    for addr in (DEST_HOST, DEST_PORT).to_socket_addrs()? {
+        println!("dest: {}:{} ({})", DEST_HOST, DEST_PORT, addr);
     }
 
-    let mut stream = TcpStream::connect(DEST)?;
+    let stream = TcpStream::connect((DEST_HOST, DEST_PORT))?;
+    #[cfg(feature = "https")]
+    let mut stream = TlsStream::connect(stream, HOST, tls::client_config()?)?;
+    #[cfg(not(feature = "https"))]
+    let mut stream = stream;
     stream.write_all(REQUEST.as_bytes())?;
     let mut buf = [0; 2048];
     let n = stream.read(&mut buf)?;
@@ -0,0 +1,120 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate axstd as std;
+
+use core::time::Duration;
+use std::net::IpAddr;
+use std::thread;
+use std::time::Instant;
+
+use axnet::IcmpSocket;
+
+/// The addresses to ping, separated by commas, from the `PING_DEST`
+/// environment variable at compile time (e.g. `make A=apps/net/ping
+/// PING_DEST=10.0.2.2,::1 run`). By default, the loopback addresses and the
+/// gateway of the QEMU user network.
+const DESTS: &str = match option_env!("PING_DEST") {
+    Some(dests) => dests,
+    None => "127.0.0.1,::1,10.0.2.2",
+};
+const COUNT: u16 = 4;
+const PAYLOAD_LEN: usize = 56;
+const INTERVAL: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+const ICMPV4_ECHO_REQUEST: u8 = 8;
+const ICMPV4_ECHO_REPLY: u8 = 0;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+const ICMP_HEADER_LEN: usize = 8;
+
+/// Builds an echo request, the identifier and the checksum are filled by the
+/// socket.
+fn echo_request(dest: IpAddr, seq: u16) -> [u8; ICMP_HEADER_LEN + PAYLOAD_LEN] {
+    let mut packet = [0; ICMP_HEADER_LEN + PAYLOAD_LEN];
+    packet[0] = match dest {
+        IpAddr::V4(_) => ICMPV4_ECHO_REQUEST,
+        IpAddr::V6(_) => ICMPV6_ECHO_REQUEST,
+    };
+    packet[6..8].copy_from_slice(&seq.to_be_bytes());
+    for (i, b) in packet[ICMP_HEADER_LEN..].iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    packet
+}
+
+/// Waits for the echo reply of `seq`, and returns its length and source.
+fn recv_reply(socket: &IcmpSocket, seq: u16, buf: &mut [u8]) -> Option<(usize, IpAddr)> {
+    loop {
+        // a timeout fails with `WouldBlock`
+        let (len, from) = socket.recv_from(buf).ok()?;
+        if len < ICMP_HEADER_LEN || !matches!(buf[0], ICMPV4_ECHO_REPLY | ICMPV6_ECHO_REPLY) {
+            continue;
+        }
+        if u16::from_be_bytes([buf[6], buf[7]]) == seq {
+            return Some((len, from));
+        }
+    }
+}
+
+fn ping(dest: IpAddr) {
+    println!("PING {}: {} data bytes", dest, PAYLOAD_LEN);
+    let socket = IcmpSocket::new();
+    socket.set_read_timeout(Some(TIMEOUT)).unwrap();
+
+    let mut received = 0;
+    let mut rtts = Duration::ZERO;
+    let mut buf = [0; 1024];
+    for seq in 0..COUNT {
+        let start = Instant::now();
+        if let Err(e) = socket.send_to(&echo_request(dest, seq), dest) {
+            println!("ping: send to {} failed: {:?}", dest, e);
+            return;
+        }
+        match recv_reply(&socket, seq, &mut buf) {
+            Some((len, from)) => {
+                let rtt = start.elapsed();
+                println!(
+                    "{} bytes from {}: icmp_seq={} time={}.{:03} ms",
+                    len,
+                    from,
+                    seq,
+                    rtt.as_micros() / 1000,
+                    rtt.as_micros() % 1000,
+                );
+                received += 1;
+                rtts += rtt;
+            }
+            None => println!("Request timeout for icmp_seq {}", seq),
+        }
+        if seq + 1 < COUNT {
+            thread::sleep(INTERVAL);
+        }
+    }
+
+    println!("--- {} ping statistics ---", dest);
+    println!(
+        "{} packets transmitted, {} packets received, {}% packet loss",
+        COUNT,
+        received,
+        (COUNT - received) * 100 / COUNT,
+    );
+    if received > 0 {
+        let avg = rtts / received as u32;
+        println!(
+            "round-trip avg = {}.{:03} ms",
+            avg.as_micros() / 1000,
+            avg.as_micros() % 1000
+        );
+    }
+}
+
+#[no_mangle]
+fn main() {
+    println!("Hello, ping!");
+    for dest in DESTS.split(',').map(str::trim) {
+        ping(dest.parse().expect("invalid address in PING_DEST"));
+    }
+}
@@ -6,53 +6,94 @@ use buddy_system_allocator::Heap;
 use core::alloc::Layout;
 use core::ptr::NonNull;
 
+use crate::region::{RegionHeap, RegionList};
 use crate::{AllocError, AllocResult, BaseAllocator, ByteAllocator};
 
+struct BuddyHeap(Heap<32>);
+
+impl RegionHeap for BuddyHeap {
+    const ALIGN: usize = core::mem::size_of::<usize>();
+
+    unsafe fn new(start: usize, size: usize) -> Option<Self> {
+        let mut heap = Heap::<32>::new();
+        heap.init(start, size);
+        Some(Self(heap))
+    }
+
+    fn min_size(layout: Layout) -> usize {
+        // an unaligned region may not contain an aligned block of twice the size
+        2 * layout.size().max(layout.align()).next_power_of_two()
+    }
+
+    fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        self.0.alloc(layout).map_err(|_| AllocError::NoMemory)
+    }
+
+    fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
+        self.0.dealloc(pos, layout)
+    }
+
+    fn used_bytes(&self) -> usize {
+        self.0.stats_alloc_actual()
+    }
+}
+
 /// A byte-granularity memory allocator based on the [buddy_system_allocator].
 ///
+/// Each added memory region has its own buddy heap, so that blocks are never
+/// merged across regions, and a region can be removed by
+/// [`ByteAllocator::remove_memory`] once it is entirely free.
+///
 /// [buddy_system_allocator]: https://docs.rs/buddy_system_allocator/latest/buddy_system_allocator/
 pub struct BuddyByteAllocator {
-    inner: Heap<32>,
+    regions: RegionList<BuddyHeap>,
 }
 
 impl BuddyByteAllocator {
     /// Creates a new empty `BuddyByteAllocator`.
     pub const fn new() -> Self {
         Self {
-            inner: Heap::<32>::new(),
+            regions: RegionList::new(),
         }
     }
 }
 
 impl BaseAllocator for BuddyByteAllocator {
     fn init(&mut self, start: usize, size: usize) {
-        unsafe { self.inner.init(start, size) };
+        self.regions.add(start, size).unwrap();
     }
 
     fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
-        unsafe { self.inner.add_to_heap(start, start + size) };
-        Ok(())
+        self.regions.add(start, size)
     }
 }
 
 impl ByteAllocator for BuddyByteAllocator {
     fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
-        self.inner.alloc(layout).map_err(|_| AllocError::NoMemory)
+        self.regions.alloc(layout)
     }
 
     fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
-        self.inner.dealloc(pos, layout)
+        self.regions.dealloc(pos, layout)
+    }
+
+    fn remove_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        self.regions.remove(start, size)
+    }
+
+    fn min_region_size(&self, layout: Layout) -> usize {
+        RegionList::<BuddyHeap>::min_region_size(layout)
     }
 
     fn total_bytes(&self) -> usize {
-        self.inner.stats_total_bytes()
+        self.regions.total_bytes()
     }
 
     fn used_bytes(&self) -> usize {
-        self.inner.stats_alloc_actual()
+        self.regions.used_bytes()
     }
 
     fn available_bytes(&self) -> usize {
-        self.inner.stats_total_bytes() - self.inner.stats_alloc_actual()
+        self.regions.total_bytes() - self.regions.used_bytes()
     }
 }
@@ -0,0 +1,301 @@
+//! Buddy memory allocation in page-granularity, over multiple memory regions.
+
+use core::ptr::null_mut;
+
+use crate::{align_down, align_up, AllocError, AllocResult, BaseAllocator, PageAllocator};
+
+/// The maximum number of memory regions that can be managed.
+pub const MAX_REGIONS: usize = 16;
+
+/// Number of free lists, the largest block has `2^(MAX_ORDER - 1)` pages.
+const MAX_ORDER: usize = 32;
+/// Marks a page that is not the head of a free block.
+const NOT_FREE: u8 = u8::MAX;
+
+/// Free list node, stored in the first page of a free block.
+struct FreeNode {
+    prev: *mut FreeNode,
+    next: *mut FreeNode,
+}
+
+/// A contiguous memory region managed by the buddy system.
+///
+/// The beginning of the region holds one byte per page, which records the
+/// order of a free block if the page is its head, or [`NOT_FREE`] otherwise.
+/// Page frame numbers are absolute (`addr / PAGE_SIZE`), so a block of order
+/// `k` is always aligned to `2^k` pages.
+#[derive(Clone, Copy)]
+struct MemRegion {
+    start: usize,
+    end: usize,
+    base_pfn: usize,
+    end_pfn: usize,
+    orders: *mut u8,
+    free_lists: [*mut FreeNode; MAX_ORDER],
+    total_pages: usize,
+    used_pages: usize,
+}
+
+impl MemRegion {
+    /// Creates a region over `[start, start + size)`, or returns `None` if the
+    /// region is too small to hold any page after the metadata.
+    unsafe fn new(start: usize, size: usize, page_size: usize) -> Option<Self> {
+        let start_pfn = align_up(start, page_size) / page_size;
+        let end_pfn = align_down(start + size, page_size) / page_size;
+        if end_pfn <= start_pfn {
+            return None;
+        }
+        let meta_pages = (end_pfn - start_pfn).div_ceil(page_size);
+        let base_pfn = start_pfn + meta_pages;
+        if end_pfn <= base_pfn {
+            return None;
+        }
+
+        let orders = (start_pfn * page_size) as *mut u8;
+        core::ptr::write_bytes(orders, NOT_FREE, end_pfn - base_pfn);
+        let mut region = Self {
+            start,
+            end: start + size,
+            base_pfn,
+            end_pfn,
+            orders,
+            free_lists: [null_mut(); MAX_ORDER],
+            total_pages: end_pfn - base_pfn,
+            used_pages: 0,
+        };
+        region.free_range(base_pfn, end_pfn - base_pfn, page_size);
+        Some(region)
+    }
+
+    fn contains(&self, addr: usize) -> bool {
+        self.start <= addr && addr < self.end
+    }
+
+    unsafe fn order_of(&self, pfn: usize) -> u8 {
+        *self.orders.add(pfn - self.base_pfn)
+    }
+
+    unsafe fn set_order(&mut self, pfn: usize, order: u8) {
+        *self.orders.add(pfn - self.base_pfn) = order;
+    }
+
+    unsafe fn push(&mut self, pfn: usize, order: usize, page_size: usize) {
+        let node = (pfn * page_size) as *mut FreeNode;
+        let head = self.free_lists[order];
+        (*node).prev = null_mut();
+        (*node).next = head;
+        if !head.is_null() {
+            (*head).prev = node;
+        }
+        self.free_lists[order] = node;
+        self.set_order(pfn, order as u8);
+    }
+
+    unsafe fn remove(&mut self, pfn: usize, order: usize, page_size: usize) {
+        let node = (pfn * page_size) as *mut FreeNode;
+        let (prev, next) = ((*node).prev, (*node).next);
+        if prev.is_null() {
+            self.free_lists[order] = next;
+        } else {
+            (*prev).next = next;
+        }
+        if !next.is_null() {
+            (*next).prev = prev;
+        }
+        self.set_order(pfn, NOT_FREE);
+    }
+
+    /// Frees a block, and merges it with its buddies as much as possible.
+    unsafe fn free_block(&mut self, mut pfn: usize, mut order: usize, page_size: usize) {
+        while order + 1 < MAX_ORDER {
+            let buddy = pfn ^ (1 << order);
+            if buddy < self.base_pfn
+                || buddy + (1 << order) > self.end_pfn
+                || self.order_of(buddy) != order as u8
+            {
+                break;
+            }
+            self.remove(buddy, order, page_size);
+            pfn = pfn.min(buddy);
+            order += 1;
+        }
+        self.push(pfn, order, page_size);
+    }
+
+    /// Frees an arbitrary range of pages, by splitting it into aligned blocks.
+    unsafe fn free_range(&mut self, mut pfn: usize, mut count: usize, page_size: usize) {
+        while count > 0 {
+            let order = (pfn.trailing_zeros() as usize)
+                .min(usize::BITS as usize - 1 - count.leading_zeros() as usize)
+                .min(MAX_ORDER - 1);
+            self.free_block(pfn, order, page_size);
+            pfn += 1 << order;
+            count -= 1 << order;
+        }
+    }
+
+    unsafe fn alloc(
+        &mut self,
+        num_pages: usize,
+        align_pages: usize,
+        page_size: usize,
+    ) -> Option<usize> {
+        let order = num_pages
+            .max(align_pages)
+            .next_power_of_two()
+            .trailing_zeros() as usize;
+        let mut cur = (order..MAX_ORDER).find(|&o| !self.free_lists[o].is_null())?;
+        let pfn = self.free_lists[cur] as usize / page_size;
+        self.remove(pfn, cur, page_size);
+        // split the block, and put the upper halves back
+        while cur > order {
+            cur -= 1;
+            self.push(pfn + (1 << cur), cur, page_size);
+        }
+        // give back the unused tail
+        self.free_range(pfn + num_pages, (1 << order) - num_pages, page_size);
+        self.used_pages += num_pages;
+        Some(pfn * page_size)
+    }
+
+    unsafe fn dealloc(&mut self, pos: usize, num_pages: usize, page_size: usize) {
+        self.free_range(pos / page_size, num_pages, page_size);
+        self.used_pages -= num_pages;
+    }
+}
+
+/// A page-granularity memory allocator based on the buddy system.
+///
+/// Unlike [`BitmapPageAllocator`](crate::BitmapPageAllocator), it supports
+/// multiple discontiguous memory regions, each added by
+/// [`BaseAllocator::add_memory`]. Blocks are never merged across regions, and
+/// pages can be allocated from a specific region by
+/// [`BuddyPageAllocator::alloc_pages_in`].
+///
+/// The per-page metadata (one byte per page) is stored at the beginning of
+/// each region, and free blocks are linked through their first page.
+///
+/// The `PAGE_SIZE` must be a power of two.
+pub struct BuddyPageAllocator<const PAGE_SIZE: usize> {
+    regions: [Option<MemRegion>; MAX_REGIONS],
+    num_regions: usize,
+}
+
+unsafe impl<const PAGE_SIZE: usize> Send for BuddyPageAllocator<PAGE_SIZE> {}
+
+impl<const PAGE_SIZE: usize> BuddyPageAllocator<PAGE_SIZE> {
+    /// Creates a new empty `BuddyPageAllocator`.
+    pub const fn new() -> Self {
+        Self {
+            regions: [None; MAX_REGIONS],
+            num_regions: 0,
+        }
+    }
+
+    /// Returns the number of regions.
+    pub fn num_regions(&self) -> usize {
+        self.num_regions
+    }
+
+    /// Returns the memory range `(start, size)` of the given region.
+    pub fn region_range(&self, region: usize) -> Option<(usize, usize)> {
+        self.regions
+            .get(region)?
+            .map(|r| (r.start, r.end - r.start))
+    }
+
+    /// Returns the index of the region containing the address `addr`.
+    pub fn region_of(&self, addr: usize) -> Option<usize> {
+        self.regions().position(|r| r.contains(addr))
+    }
+
+    /// Allocates contiguous pages from the given region.
+    ///
+    /// The region index is the order in which the memory region was added,
+    /// starting from 0 for the region passed to [`BaseAllocator::init`].
+    pub fn alloc_pages_in(
+        &mut self,
+        region: usize,
+        num_pages: usize,
+        align_pow2: usize,
+    ) -> AllocResult<usize> {
+        let align_pages = Self::check_params(num_pages, align_pow2)?;
+        let region = self
+            .regions
+            .get_mut(region)
+            .and_then(|r| r.as_mut())
+            .ok_or(AllocError::InvalidParam)?;
+        unsafe { region.alloc(num_pages, align_pages, PAGE_SIZE) }.ok_or(AllocError::NoMemory)
+    }
+
+    fn check_params(num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        if num_pages == 0 || !align_pow2.is_power_of_two() || align_pow2 % PAGE_SIZE != 0 {
+            return Err(AllocError::InvalidParam);
+        }
+        if num_pages.max(align_pow2 / PAGE_SIZE) > 1 << (MAX_ORDER - 1) {
+            return Err(AllocError::NoMemory);
+        }
+        Ok(align_pow2 / PAGE_SIZE)
+    }
+
+    fn regions(&self) -> impl Iterator<Item = &MemRegion> {
+        self.regions[..self.num_regions].iter().flatten()
+    }
+}
+
+impl<const PAGE_SIZE: usize> BaseAllocator for BuddyPageAllocator<PAGE_SIZE> {
+    fn init(&mut self, start: usize, size: usize) {
+        assert!(PAGE_SIZE.is_power_of_two());
+        *self = Self::new();
+        self.add_memory(start, size).unwrap();
+    }
+
+    fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        let end = start.checked_add(size).ok_or(AllocError::InvalidParam)?;
+        if self.regions().any(|r| start < r.end && r.start < end) {
+            return Err(AllocError::MemoryOverlap);
+        }
+        if self.num_regions == MAX_REGIONS {
+            return Err(AllocError::NoMemory);
+        }
+        let region =
+            unsafe { MemRegion::new(start, size, PAGE_SIZE) }.ok_or(AllocError::InvalidParam)?;
+        self.regions[self.num_regions] = Some(region);
+        self.num_regions += 1;
+        Ok(())
+    }
+}
+
+impl<const PAGE_SIZE: usize> PageAllocator for BuddyPageAllocator<PAGE_SIZE> {
+    const PAGE_SIZE: usize = PAGE_SIZE;
+
+    fn alloc_pages(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        let align_pages = Self::check_params(num_pages, align_pow2)?;
+        self.regions[..self.num_regions]
+            .iter_mut()
+            .flatten()
+            .find_map(|r| unsafe { r.alloc(num_pages, align_pages, PAGE_SIZE) })
+            .ok_or(AllocError::NoMemory)
+    }
+
+    fn dealloc_pages(&mut self, pos: usize, num_pages: usize) {
+        let region = self.regions[..self.num_regions]
+            .iter_mut()
+            .flatten()
+            .find(|r| r.contains(pos))
+            .expect("dealloc_pages: address not in any region");
+        unsafe { region.dealloc(pos, num_pages, PAGE_SIZE) }
+    }
+
+    fn total_pages(&self) -> usize {
+        self.regions().map(|r| r.total_pages).sum()
+    }
+
+    fn used_pages(&self) -> usize {
+        self.regions().map(|r| r.used_pages).sum()
+    }
+
+    fn available_pages(&self) -> usize {
+        self.total_pages() - self.used_pages()
+    }
+}
@@ -5,7 +5,7 @@
 //! - [`ByteAllocator`]: Byte-granularity memory allocator. (e.g.,
 //!   [`BuddyByteAllocator`], [`SlabByteAllocator`])
 //! - [`PageAllocator`]: Page-granularity memory allocator. (e.g.,
-//!   [`BitmapPageAllocator`])
+//!   [`BitmapPageAllocator`], [`BuddyPageAllocator`])
 //! - [`IdAllocator`]: Used to allocate unique IDs.
 
 #![no_std]
@@ -17,6 +17,14 @@ mod bitmap;
 #[cfg(feature = "bitmap")]
 pub use bitmap::BitmapPageAllocator;
 
+#[cfg(feature = "buddy_page")]
+mod buddy_page;
+#[cfg(feature = "buddy_page")]
+pub use buddy_page::BuddyPageAllocator;
+
+#[cfg(any(feature = "buddy", feature = "slab", feature = "tlsf"))]
+mod region;
+
 #[cfg(feature = "buddy")]
 mod buddy;
 #[cfg(feature = "buddy")]
@@ -46,6 +54,8 @@ pub enum AllocError {
     NoMemory,
     /// Deallocate an unallocated memory region.
     NotAllocated,
+    /// Remove a memory region that still has allocated blocks.
+    MemoryInUse,
 }
 
 /// A [`Result`] type with [`AllocError`] as the error type.
@@ -68,6 +78,25 @@ pub trait ByteAllocator: BaseAllocator {
     /// Deallocate memory at the given position, size, and alignment.
     fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout);
 
+    /// Remove a memory region previously added by [`BaseAllocator::init`] or
+    /// [`BaseAllocator::add_memory`], so that it can be used by others.
+    ///
+    /// `start` and `size` must be the same as when the region was added. It
+    /// fails with [`AllocError::MemoryInUse`] if any block in the region is
+    /// still allocated. Allocators that cannot shrink always return
+    /// [`AllocError::InvalidParam`].
+    fn remove_memory(&mut self, _start: usize, _size: usize) -> AllocResult {
+        Err(AllocError::InvalidParam)
+    }
+
+    /// Returns the minimum size of a memory region to add by
+    /// [`BaseAllocator::add_memory`], so that an allocation of `layout` can
+    /// be served from it (e.g., with the control structure of the heap stored
+    /// in the region).
+    fn min_region_size(&self, layout: Layout) -> usize {
+        layout.size() + layout.align()
+    }
+
     /// Returns total memory size in bytes.
     fn total_bytes(&self) -> usize;
 
@@ -0,0 +1,208 @@
+//! Byte allocators that manage each memory region separately.
+//!
+//! Every region added to a [`RegionList`] gets its own heap instance, whose
+//! header is stored at the beginning of the region itself. Blocks never span
+//! two regions, so a region whose heap has no live allocation can be removed
+//! as a whole and returned to its owner.
+//!
+//! Besides the list in the order of addition, the regions are indexed by
+//! their addresses, so that the region of a deallocated block is found by a
+//! binary search.
+
+use core::alloc::Layout;
+use core::mem::{align_of, size_of};
+use core::ptr::NonNull;
+
+use crate::{align_up, AllocError, AllocResult};
+
+/// The maximum number of regions in a [`RegionList`].
+pub(crate) const MAX_REGIONS: usize = 64;
+
+/// A heap that manages exactly one contiguous memory region.
+pub(crate) trait RegionHeap: Sized {
+    /// The required alignment of the heap memory.
+    const ALIGN: usize;
+
+    /// Creates a heap that manages `[start, start + size)`.
+    ///
+    /// `start` is aligned to [`RegionHeap::ALIGN`]. Returns `None` if the
+    /// region is too small.
+    ///
+    /// # Safety
+    ///
+    /// The memory region must be valid and not used by others.
+    unsafe fn new(start: usize, size: usize) -> Option<Self>;
+
+    /// Returns the minimum size of a heap that can serve an allocation of
+    /// `layout` when it is empty.
+    fn min_size(layout: Layout) -> usize;
+
+    fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>>;
+
+    fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout);
+
+    fn used_bytes(&self) -> usize;
+}
+
+struct Region<H> {
+    start: usize,
+    size: usize,
+    next: Option<NonNull<Region<H>>>,
+    heap: H,
+}
+
+impl<H> Region<H> {
+    fn end(&self) -> usize {
+        self.start + self.size
+    }
+}
+
+/// A list of memory regions, each managed by its own [`RegionHeap`].
+///
+/// Allocations are served from the earliest added region that can satisfy
+/// them, so later regions are more likely to become free again.
+pub(crate) struct RegionList<H: RegionHeap> {
+    head: Option<NonNull<Region<H>>>,
+    /// The first `len` entries are the regions sorted by their addresses.
+    index: [NonNull<Region<H>>; MAX_REGIONS],
+    len: usize,
+    total_bytes: usize,
+    used_bytes: usize,
+}
+
+unsafe impl<H: RegionHeap + Send> Send for RegionList<H> {}
+
+impl<H: RegionHeap> RegionList<H> {
+    pub const fn new() -> Self {
+        Self {
+            head: None,
+            index: [NonNull::dangling(); MAX_REGIONS],
+            len: 0,
+            total_bytes: 0,
+            used_bytes: 0,
+        }
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    /// Returns the minimum size of a new region that can serve an allocation
+    /// of `layout`, including the header of the region.
+    pub fn min_region_size(layout: Layout) -> usize {
+        size_of::<Region<H>>() + align_of::<Region<H>>() + H::ALIGN + H::min_size(layout)
+    }
+
+    pub fn add(&mut self, start: usize, size: usize) -> AllocResult {
+        let end = start.checked_add(size).ok_or(AllocError::InvalidParam)?;
+        let pos = self
+            .sorted()
+            .partition_point(|r| unsafe { r.as_ref() }.start < start);
+        let overlaps_prev = pos > 0 && start < self.get(pos - 1).end();
+        let overlaps_next = pos < self.len && self.get(pos).start < end;
+        if overlaps_prev || overlaps_next {
+            return Err(AllocError::MemoryOverlap);
+        }
+        if self.len == MAX_REGIONS {
+            return Err(AllocError::NoMemory);
+        }
+        let hdr = align_up(start, align_of::<Region<H>>());
+        let heap_start = align_up(hdr + size_of::<Region<H>>(), H::ALIGN);
+        if heap_start >= end {
+            return Err(AllocError::InvalidParam);
+        }
+        let heap =
+            unsafe { H::new(heap_start, end - heap_start) }.ok_or(AllocError::InvalidParam)?;
+        let region = NonNull::new(hdr as *mut Region<H>).ok_or(AllocError::InvalidParam)?;
+        unsafe {
+            region.as_ptr().write(Region {
+                start,
+                size,
+                next: None,
+                heap,
+            })
+        };
+
+        // append to the tail to keep the allocation order
+        let mut link = &mut self.head;
+        while let Some(mut r) = *link {
+            link = unsafe { &mut r.as_mut().next };
+        }
+        *link = Some(region);
+        self.index.copy_within(pos..self.len, pos + 1);
+        self.index[pos] = region;
+        self.len += 1;
+        self.total_bytes += size;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, start: usize, size: usize) -> AllocResult {
+        let pos = self
+            .find(start)
+            .filter(|&i| self.get(i).start == start && self.get(i).size == size)
+            .ok_or(AllocError::NotAllocated)?;
+        let region = self.index[pos];
+        if unsafe { region.as_ref() }.heap.used_bytes() != 0 {
+            return Err(AllocError::MemoryInUse);
+        }
+
+        let mut link = &mut self.head;
+        while let Some(mut r) = *link {
+            if r == region {
+                *link = unsafe { r.as_ref() }.next;
+                break;
+            }
+            link = unsafe { &mut r.as_mut().next };
+        }
+        self.index.copy_within(pos + 1..self.len, pos);
+        self.len -= 1;
+        unsafe { region.as_ptr().drop_in_place() };
+        self.total_bytes -= size;
+        Ok(())
+    }
+
+    pub fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        let mut cur = self.head;
+        while let Some(mut r) = cur {
+            let region = unsafe { r.as_mut() };
+            if let Ok(ptr) = region.heap.alloc(layout) {
+                self.used_bytes += layout.size();
+                return Ok(ptr);
+            }
+            cur = region.next;
+        }
+        Err(AllocError::NoMemory)
+    }
+
+    pub fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
+        let addr = pos.as_ptr() as usize;
+        let Some(i) = self.find(addr) else {
+            panic!("dealloc: {:#x} does not belong to any region", addr);
+        };
+        let mut region = self.index[i];
+        unsafe { region.as_mut() }.heap.dealloc(pos, layout);
+        self.used_bytes -= layout.size();
+    }
+
+    /// Returns the position in the index of the region containing `addr`.
+    fn find(&self, addr: usize) -> Option<usize> {
+        let pos = self
+            .sorted()
+            .partition_point(|r| unsafe { r.as_ref() }.start <= addr);
+        (pos > 0 && addr < self.get(pos - 1).end()).then(|| pos - 1)
+    }
+
+    /// Returns the regions sorted by their addresses.
+    fn sorted(&self) -> &[NonNull<Region<H>>] {
+        &self.index[..self.len]
+    }
+
+    /// Returns the `i`-th region in the order of addresses.
+    fn get(&self, i: usize) -> &Region<H> {
+        unsafe { self.index[i].as_ref() }
+    }
+}
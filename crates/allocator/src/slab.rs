@@ -2,67 +2,122 @@
 //!
 //! TODO: comments
 
+use super::region::{RegionHeap, RegionList};
 use super::{AllocError, AllocResult, BaseAllocator, ByteAllocator};
 use core::alloc::Layout;
 use core::ptr::NonNull;
 use slab_allocator::Heap;
 
+const PAGE_SIZE: usize = 0x1000;
+
+struct SlabHeap {
+    inner: Heap,
+    used_bytes: usize,
+}
+
+impl RegionHeap for SlabHeap {
+    const ALIGN: usize = PAGE_SIZE;
+
+    unsafe fn new(start: usize, size: usize) -> Option<Self> {
+        let size = size & !(PAGE_SIZE - 1);
+        if size == 0 {
+            return None;
+        }
+        Some(Self {
+            inner: Heap::new(start, size),
+            used_bytes: 0,
+        })
+    }
+
+    fn min_size(layout: Layout) -> usize {
+        // Blocks up to a page are taken from a new slab of 64 blocks, larger
+        // ones are allocated by the buddy allocator directly. The heap loses
+        // at most a page for its size being truncated to pages.
+        let size = layout.size().max(layout.align());
+        let size = if size <= PAGE_SIZE {
+            size.next_power_of_two().max(64) * 64
+        } else {
+            size
+        };
+        2 * size.next_power_of_two() + PAGE_SIZE
+    }
+
+    fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        let addr = self
+            .inner
+            .allocate(layout)
+            .map_err(|_| AllocError::NoMemory)?;
+        self.used_bytes += layout.size();
+        Ok(unsafe { NonNull::new_unchecked(addr as *mut u8) })
+    }
+
+    fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
+        unsafe { self.inner.deallocate(pos.as_ptr() as usize, layout) }
+        self.used_bytes -= layout.size();
+    }
+
+    // Blocks cached by the slabs are not counted, they are released
+    // together with the region.
+    fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+}
+
 /// A byte-granularity memory allocator based on the [slab allocator].
 ///
+/// Each added memory region has its own slab heap, so that a region can be
+/// removed by [`ByteAllocator::remove_memory`] once it is entirely free.
+///
 /// [slab allocator]: ../slab_allocator/index.html
 pub struct SlabByteAllocator {
-    inner: Option<Heap>,
+    regions: RegionList<SlabHeap>,
 }
 
 impl SlabByteAllocator {
     /// Creates a new empty `SlabByteAllocator`.
     pub const fn new() -> Self {
-        Self { inner: None }
-    }
-
-    fn inner_mut(&mut self) -> &mut Heap {
-        self.inner.as_mut().unwrap()
-    }
-
-    fn inner(&self) -> &Heap {
-        self.inner.as_ref().unwrap()
+        Self {
+            regions: RegionList::new(),
+        }
     }
 }
 
 impl BaseAllocator for SlabByteAllocator {
     fn init(&mut self, start: usize, size: usize) {
-        self.inner = unsafe { Some(Heap::new(start, size)) };
+        self.regions.add(start, size).unwrap();
     }
 
     fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
-        unsafe {
-            self.inner_mut().add_memory(start, size);
-        }
-        Ok(())
+        self.regions.add(start, size)
     }
 }
 
 impl ByteAllocator for SlabByteAllocator {
     fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
-        self.inner_mut()
-            .allocate(layout)
-            .map(|addr| unsafe { NonNull::new_unchecked(addr as *mut u8) })
-            .map_err(|_| AllocError::NoMemory)
+        self.regions.alloc(layout)
     }
 
     fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
-        unsafe { self.inner_mut().deallocate(pos.as_ptr() as usize, layout) }
+        self.regions.dealloc(pos, layout)
+    }
+
+    fn remove_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        self.regions.remove(start, size)
+    }
+
+    fn min_region_size(&self, layout: Layout) -> usize {
+        RegionList::<SlabHeap>::min_region_size(layout)
     }
 
     fn total_bytes(&self) -> usize {
-        self.inner().total_bytes()
+        self.regions.total_bytes()
     }
 
     fn used_bytes(&self) -> usize {
-        self.inner().used_bytes()
+        self.regions.used_bytes()
     }
 
     fn available_bytes(&self) -> usize {
-        self.inner().available_bytes()
+        self.regions.total_bytes() - self.regions.used_bytes()
     }
 }
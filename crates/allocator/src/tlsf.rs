@@ -2,76 +2,116 @@
 //!
 //! This module wraps the implementation provided by the [rlsf] crate.
 
+use super::region::{RegionHeap, RegionList};
 use super::{AllocError, AllocResult, BaseAllocator, ByteAllocator};
 use core::alloc::Layout;
 use core::ptr::NonNull;
-use rlsf::Tlsf;
+use rlsf::{Tlsf, GRANULARITY};
+
+const FLLEN: usize = 28;
+const SLLEN: usize = 32;
+
+struct TlsfHeap {
+    inner: Tlsf<'static, u32, u32, FLLEN, SLLEN>, // max pool size: 32 * 2^28 = 8G
+    used_bytes: usize,
+}
+
+impl RegionHeap for TlsfHeap {
+    const ALIGN: usize = 16;
+
+    unsafe fn new(start: usize, size: usize) -> Option<Self> {
+        let mut inner = Tlsf::new();
+        let pool = core::slice::from_raw_parts_mut(start as *mut u8, size);
+        inner.insert_free_block_ptr(NonNull::new(pool).unwrap())?;
+        Some(Self {
+            inner,
+            used_bytes: 0,
+        })
+    }
+
+    fn min_size(layout: Layout) -> usize {
+        // The searched free block is rounded up to the next size class, which
+        // is at most 1/SLLEN larger. The pool also loses the alignment of its
+        // start and the sentinel block at its end.
+        let size = layout.size() + layout.align() + GRANULARITY;
+        size + size / SLLEN + GRANULARITY * 2
+    }
+
+    fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        let ptr = self.inner.allocate(layout).ok_or(AllocError::NoMemory)?;
+        self.used_bytes += layout.size();
+        Ok(ptr)
+    }
+
+    fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
+        unsafe { self.inner.deallocate(pos, layout.align()) }
+        self.used_bytes -= layout.size();
+    }
+
+    fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+}
 
 /// A TLSF (Two-Level Segregated Fit) memory allocator.
 ///
-/// It's just a wrapper structure of [`rlsf::Tlsf`], with `FLLEN` and `SLLEN`
-/// fixed to 28 and 32.
+/// Each added memory region is managed by its own [`rlsf::Tlsf`] (with
+/// `FLLEN` and `SLLEN` fixed to 28 and 32), whose control structure is
+/// stored at the beginning of the region. So a region can be removed by
+/// [`ByteAllocator::remove_memory`] once it is entirely free, and the region
+/// of a freed block is found by its address.
+///
+/// The control structure takes about 7 KiB of each region, see
+/// [`ByteAllocator::min_region_size`].
 pub struct TlsfByteAllocator {
-    inner: Tlsf<'static, u32, u32, 28, 32>, // max pool size: 32 * 2^28 = 8G
-    total_bytes: usize,
-    used_bytes: usize,
+    regions: RegionList<TlsfHeap>,
 }
 
 impl TlsfByteAllocator {
     /// Creates a new empty [`TlsfByteAllocator`].
     pub const fn new() -> Self {
         Self {
-            inner: Tlsf::new(),
-            total_bytes: 0,
-            used_bytes: 0,
+            regions: RegionList::new(),
         }
     }
 }
 
 impl BaseAllocator for TlsfByteAllocator {
     fn init(&mut self, start: usize, size: usize) {
-        unsafe {
-            let pool = core::slice::from_raw_parts_mut(start as *mut u8, size);
-            self.inner
-                .insert_free_block_ptr(NonNull::new(pool).unwrap())
-                .unwrap();
-        }
-        self.total_bytes = size;
+        self.regions.add(start, size).unwrap();
     }
 
     fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
-        unsafe {
-            let pool = core::slice::from_raw_parts_mut(start as *mut u8, size);
-            self.inner
-                .insert_free_block_ptr(NonNull::new(pool).unwrap())
-                .ok_or(AllocError::InvalidParam)?;
-        }
-        self.total_bytes += size;
-        Ok(())
+        self.regions.add(start, size)
     }
 }
 
 impl ByteAllocator for TlsfByteAllocator {
     fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
-        let ptr = self.inner.allocate(layout).ok_or(AllocError::NoMemory)?;
-        self.used_bytes += layout.size();
-        Ok(ptr)
+        self.regions.alloc(layout)
     }
 
     fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
-        unsafe { self.inner.deallocate(pos, layout.align()) }
-        self.used_bytes -= layout.size();
+        self.regions.dealloc(pos, layout)
+    }
+
+    fn remove_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        self.regions.remove(start, size)
+    }
+
+    fn min_region_size(&self, layout: Layout) -> usize {
+        RegionList::<TlsfHeap>::min_region_size(layout)
     }
 
     fn total_bytes(&self) -> usize {
-        self.total_bytes
+        self.regions.total_bytes()
     }
 
     fn used_bytes(&self) -> usize {
-        self.used_bytes
+        self.regions.used_bytes()
     }
 
     fn available_bytes(&self) -> usize {
-        self.total_bytes - self.used_bytes
+        self.regions.total_bytes() - self.regions.used_bytes()
     }
 }
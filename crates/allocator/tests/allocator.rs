@@ -5,7 +5,10 @@ use std::alloc::{Allocator, Layout};
 use std::collections::BTreeMap;
 use std::io::Write;
 
-use allocator::{AllocatorRc, BuddyByteAllocator, SlabByteAllocator, TlsfByteAllocator};
+use allocator::{
+    AllocError, AllocatorRc, BaseAllocator, BuddyByteAllocator, ByteAllocator, SlabByteAllocator,
+    TlsfByteAllocator,
+};
 use rand::{prelude::SliceRandom, Rng};
 
 const POOL_SIZE: usize = 1024 * 1024 * 128;
@@ -141,3 +144,91 @@ fn tlsf_alloc() {
         test_btree_map(50_000, &alloc);
     })
 }
+
+fn test_remove_memory(mut alloc: impl ByteAllocator, pool: &mut [u8]) {
+    let half = pool.len() / 2;
+    let base = pool.as_mut_ptr() as usize;
+    let region2 = base + half;
+    alloc.init(base, half);
+    alloc.add_memory(region2, half).unwrap();
+
+    // fill the first region until allocations go to the second one
+    let layout = Layout::from_size_align(1 << 20, 8).unwrap();
+    let mut blocks = vec![];
+    loop {
+        let ptr = alloc.alloc(layout).unwrap();
+        blocks.push(ptr);
+        if ptr.as_ptr() as usize >= region2 {
+            break;
+        }
+    }
+    assert!(matches!(
+        alloc.remove_memory(region2, half),
+        Err(AllocError::MemoryInUse)
+    ));
+
+    let ptr = blocks.pop().unwrap();
+    alloc.dealloc(ptr, layout);
+    alloc.remove_memory(region2, half).unwrap();
+    assert_eq!(alloc.total_bytes(), half);
+    if let Ok(ptr) = alloc.alloc(layout) {
+        assert!((ptr.as_ptr() as usize) < region2);
+        blocks.push(ptr);
+    }
+
+    for ptr in blocks {
+        alloc.dealloc(ptr, layout);
+    }
+    assert_eq!(alloc.used_bytes(), 0);
+    assert!(matches!(
+        alloc.remove_memory(region2, half),
+        Err(AllocError::NotAllocated)
+    ));
+}
+
+#[test]
+fn remove_memory() {
+    run_test(|pool| test_remove_memory(BuddyByteAllocator::new(), pool));
+    run_test(|pool| test_remove_memory(SlabByteAllocator::new(), pool));
+    run_test(|pool| test_remove_memory(TlsfByteAllocator::new(), pool));
+}
+
+fn test_min_region_size(mut alloc: impl ByteAllocator, pool: &mut [u8]) {
+    let layout = Layout::from_size_align(1 << 20, 4096).unwrap();
+    let size = alloc.min_region_size(layout);
+    assert!(size > layout.size() && size <= pool.len() / 4);
+
+    // regions added in descending order of addresses, each can serve the
+    // allocation by itself
+    let base = pool.as_mut_ptr() as usize + 1;
+    let starts = [base + 2 * size, base + size, base];
+    alloc.init(starts[0], size);
+    for &start in &starts[1..] {
+        alloc.add_memory(start, size).unwrap();
+    }
+    assert!(matches!(
+        alloc.add_memory(base + size / 2, size),
+        Err(AllocError::MemoryOverlap)
+    ));
+
+    let blocks = starts.map(|_| alloc.alloc(layout).unwrap());
+    for &start in &starts {
+        assert!(blocks
+            .iter()
+            .any(|&ptr| (start..start + size).contains(&(ptr.as_ptr() as usize))));
+    }
+    for ptr in blocks {
+        alloc.dealloc(ptr, layout);
+    }
+    for start in starts {
+        alloc.remove_memory(start, size).unwrap();
+    }
+    assert_eq!(alloc.total_bytes(), 0);
+}
+
+#[test]
+fn min_region_size() {
+    run_test(|pool| test_min_region_size(BuddyByteAllocator::new(), pool));
+    run_test(|pool| test_min_region_size(SlabByteAllocator::new(), pool));
+    run_test(|pool| test_min_region_size(TlsfByteAllocator::new(), pool));
+}
@@ -0,0 +1,95 @@
+use std::alloc::Layout;
+
+use allocator::{AllocError, BaseAllocator, BuddyPageAllocator, PageAllocator};
+use rand::Rng;
+
+const PAGE_SIZE: usize = 0x1000;
+
+/// Allocates a page-aligned pool with `num_pages` pages.
+fn alloc_pool(num_pages: usize) -> (usize, usize) {
+    let layout = Layout::from_size_align(num_pages * PAGE_SIZE, PAGE_SIZE).unwrap();
+    let ptr = unsafe { std::alloc::alloc(layout) };
+    assert!(!ptr.is_null());
+    (ptr as usize, layout.size())
+}
+
+fn free_pool((start, size): (usize, usize)) {
+    let layout = Layout::from_size_align(size, PAGE_SIZE).unwrap();
+    unsafe { std::alloc::dealloc(start as *mut u8, layout) };
+}
+
+#[test]
+fn buddy_page_alloc() {
+    let pool = alloc_pool(4096);
+    let mut alloc = BuddyPageAllocator::<PAGE_SIZE>::new();
+    alloc.init(pool.0, pool.1);
+    // one page is used for the per-page metadata
+    let total = alloc.total_pages();
+    assert_eq!(total, 4095);
+
+    let mut rng = rand::thread_rng();
+    let mut blocks = Vec::new();
+    for _ in 0..1000 {
+        let num_pages = rng.gen_range(1..8);
+        let align = PAGE_SIZE << rng.gen_range(0..4);
+        let pos = alloc.alloc_pages(num_pages, align).unwrap();
+        assert_eq!(pos % align, 0);
+        assert!(pool.0 <= pos && pos + num_pages * PAGE_SIZE <= pool.0 + pool.1);
+        unsafe { core::ptr::write_bytes(pos as *mut u8, 0xaa, num_pages * PAGE_SIZE) };
+        blocks.push((pos, num_pages));
+        if rng.gen_bool(0.3) {
+            let (pos, num_pages) = blocks.swap_remove(rng.gen_range(0..blocks.len()));
+            alloc.dealloc_pages(pos, num_pages);
+        }
+    }
+    let used: usize = blocks.iter().map(|b| b.1).sum();
+    assert_eq!(alloc.used_pages(), used);
+    for (pos, num_pages) in blocks {
+        alloc.dealloc_pages(pos, num_pages);
+    }
+    assert_eq!(alloc.used_pages(), 0);
+
+    // all blocks have been merged back
+    let pos = alloc.alloc_pages(2048, 2048 * PAGE_SIZE).unwrap();
+    alloc.dealloc_pages(pos, 2048);
+    free_pool(pool);
+}
+
+#[test]
+fn buddy_page_alloc_regions() {
+    let pools = [alloc_pool(64), alloc_pool(1024)];
+    let mut alloc = BuddyPageAllocator::<PAGE_SIZE>::new();
+    alloc.init(pools[0].0, pools[0].1);
+    alloc.add_memory(pools[1].0, pools[1].1).unwrap();
+    assert!(matches!(
+        alloc.add_memory(pools[1].0 + PAGE_SIZE, PAGE_SIZE),
+        Err(AllocError::MemoryOverlap)
+    ));
+    assert_eq!(alloc.num_regions(), 2);
+    assert_eq!(alloc.region_range(1), Some(pools[1]));
+    assert_eq!(alloc.total_pages(), 63 + 1023);
+
+    let pos = alloc.alloc_pages_in(1, 4, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.region_of(pos), Some(1));
+    let pos2 = alloc.alloc_pages_in(0, 4, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.region_of(pos2), Some(0));
+    assert!(matches!(
+        alloc.alloc_pages_in(0, 128, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    ));
+    assert!(matches!(
+        alloc.alloc_pages_in(2, 1, PAGE_SIZE),
+        Err(AllocError::InvalidParam)
+    ));
+
+    // falls back to the second region
+    let pos3 = alloc.alloc_pages(128, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.region_of(pos3), Some(1));
+    assert_eq!(alloc.used_pages(), 136);
+
+    alloc.dealloc_pages(pos, 4);
+    alloc.dealloc_pages(pos2, 4);
+    alloc.dealloc_pages(pos3, 128);
+    assert_eq!(alloc.used_pages(), 0);
+    pools.into_iter().for_each(free_pool);
+}
@@ -19,6 +19,12 @@ register_structs! {
         /// Flag Register.
         (0x18 => fr: ReadOnly<u32>),
         (0x1c => _reserved1),
+        /// Integer Baud Rate Register.
+        (0x24 => ibrd: ReadWrite<u32>),
+        /// Fractional Baud Rate Register.
+        (0x28 => fbrd: ReadWrite<u32>),
+        /// Line Control Register.
+        (0x2c => lcr_h: ReadWrite<u32>),
         /// Control register.
         (0x30 => cr: ReadWrite<u32>),
         /// Interrupt FIFO Level Select Register.
@@ -94,6 +100,33 @@ impl Pl011Uart {
         }
     }
 
+    /// Return true if the transmit FIFO is full, i.e. [`Self::putchar`] would
+    /// wait.
+    pub fn is_transmit_full(&self) -> bool {
+        self.regs().fr.get() & (1 << 5) != 0
+    }
+
+    /// Return true if the receive FIFO has data.
+    pub fn is_receive_ready(&self) -> bool {
+        self.regs().fr.get() & (1 << 4) == 0
+    }
+
+    /// Sets the baud rate divisor and the line control (`UARTLCR_H`), the
+    /// UART is disabled while they are updated.
+    ///
+    /// The divisor is in units of 1/64, i.e. `clock_hz * 4 / baud_rate`.
+    pub fn set_line_control(&mut self, divisor: u32, lcr_h: u32) {
+        // wait for the transmission to finish, bit 3 is BUSY
+        while self.regs().fr.get() & (1 << 3) != 0 {}
+        let cr = self.regs().cr.get();
+        self.regs().cr.set(cr & !1);
+        self.regs().ibrd.set(divisor >> 6);
+        self.regs().fbrd.set(divisor & 0x3f);
+        // writing LCR_H latches IBRD and FBRD
+        self.regs().lcr_h.set(lcr_h);
+        self.regs().cr.set(cr);
+    }
+
     /// Return true if pl011 has received an interrupt
     pub fn is_receive_interrupt(&self) -> bool {
         let pending = self.regs().mis.get();
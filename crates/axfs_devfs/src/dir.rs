@@ -37,6 +37,11 @@ impl DirNode {
     pub fn add(&self, name: &'static str, node: VfsNodeRef) {
         self.children.write().insert(name, node);
     }
+
+    /// Remove a node from this directory, returns the removed node.
+    pub fn remove_node(&self, name: &str) -> Option<VfsNodeRef> {
+        self.children.write().remove(name)
+    }
 }
 
 impl VfsNodeOps for DirNode {
@@ -47,6 +47,14 @@ impl DeviceFileSystem {
     pub fn add(&self, name: &'static str, node: VfsNodeRef) {
         self.root.add(name, node);
     }
+
+    /// Remove a node from the root directory, returns the removed node.
+    ///
+    /// The opened files of the node are still valid, as they hold references
+    /// to it.
+    pub fn remove_node(&self, name: &str) -> Option<VfsNodeRef> {
+        self.root.remove_node(name)
+    }
 }
 
 impl VfsOps for DeviceFileSystem {
@@ -113,3 +113,28 @@ fn test_devfs() {
     test_devfs_ops(&devfs).unwrap();
     test_get_parent(&devfs).unwrap();
 }
+
+#[test]
+fn test_remove_node() {
+    let devfs = DeviceFileSystem::new();
+    devfs.add("null", Arc::new(NullDev));
+    let dir_foo = devfs.mkdir("foo");
+    dir_foo.add("zero", Arc::new(ZeroDev));
+
+    let root = devfs.root_dir();
+    let node = root.clone().lookup("foo/zero").unwrap();
+    assert!(dir_foo.remove_node("zero").is_some());
+    assert_eq!(
+        root.clone().lookup("foo/zero").err(),
+        Some(VfsError::NotFound)
+    );
+    assert!(dir_foo.remove_node("zero").is_none());
+    // the removed node is still usable by its holders
+    let mut buf = [1; 4];
+    assert_eq!(node.read_at(0, &mut buf).unwrap(), 4);
+    assert_eq!(buf, [0; 4]);
+
+    assert!(devfs.remove_node("null").is_some());
+    assert_eq!(root.clone().lookup("null").err(), Some(VfsError::NotFound));
+    assert!(root.lookup("foo").is_ok());
+}
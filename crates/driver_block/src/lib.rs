@@ -10,9 +10,70 @@ pub mod ramdisk;
 #[cfg(feature = "bcm2835-sdhci")]
 pub mod bcm2835sdhci;
 
+#[cfg(feature = "nvme")]
+pub mod nvme;
+
 #[doc(no_inline)]
 pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
 
+/// The ID of a submitted block request.
+pub type RequestId = usize;
+
+/// The operation of a block request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockOp {
+    /// Reads blocks into the buffer.
+    Read,
+    /// Writes blocks from the buffer.
+    Write,
+    /// Writes all pending data to the storage.
+    Flush,
+}
+
+/// A segment of the scatter-gather buffer of a block request.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockSegment {
+    /// The start of the segment.
+    pub ptr: *mut u8,
+    /// The length of the segment in bytes, which must be a multiple of the
+    /// block size.
+    pub len: usize,
+}
+
+impl BlockSegment {
+    /// Creates a segment that covers the whole buffer.
+    pub fn new(buf: &mut [u8]) -> Self {
+        Self {
+            ptr: buf.as_mut_ptr(),
+            len: buf.len(),
+        }
+    }
+
+    /// Creates a segment that covers the whole buffer, which may only be read
+    /// from (i.e. by [`BlockOp::Write`]).
+    pub fn new_readonly(buf: &[u8]) -> Self {
+        Self {
+            ptr: buf.as_ptr() as *mut u8,
+            len: buf.len(),
+        }
+    }
+}
+
+/// A block request, which may be in flight with others.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockRequest<'a> {
+    /// The operation.
+    pub op: BlockOp,
+    /// The first block to transfer, ignored by [`BlockOp::Flush`].
+    pub block_id: u64,
+    /// The buffer segments, which are transferred to or from contiguous
+    /// blocks in order.
+    ///
+    /// Only the buffers must stay valid until the request is completed, the
+    /// slice itself is not used after submission.
+    pub segments: &'a [BlockSegment],
+}
+
 /// Operations that require a block storage device driver to implement.
 pub trait BlockDriverOps: BaseDriverOps {
     /// The number of blocks in this storage device.
@@ -36,4 +97,82 @@ pub trait BlockDriverOps: BaseDriverOps {
 
     /// Flushes the device to write all pending data to the storage.
     fn flush(&mut self) -> DevResult;
+
+    /// Acknowledges the pending interrupt of the device, returns whether the
+    /// device has raised one.
+    fn ack_interrupt(&mut self) -> bool {
+        false
+    }
+
+    /// The maximum number of requests in flight.
+    ///
+    /// It's 1 for the drivers that only support synchronous I/O.
+    fn queue_depth(&self) -> usize {
+        1
+    }
+
+    /// Submits a request without waiting for it, returns its ID to be passed
+    /// to [`BlockDriverOps::poll_request`].
+    ///
+    /// Returns [`DevError::Again`] if the queue is full, then some in-flight
+    /// requests must be completed before trying again.
+    ///
+    /// The default implementation completes the request synchronously by
+    /// [`read_block`], [`write_block`] and [`flush`], errors are returned
+    /// here instead of by [`poll_request`].
+    ///
+    /// # Safety
+    ///
+    /// The segments must be valid for the operation, and must not be accessed
+    /// until the request is completed.
+    ///
+    /// [`read_block`]: BlockDriverOps::read_block
+    /// [`write_block`]: BlockDriverOps::write_block
+    /// [`flush`]: BlockDriverOps::flush
+    /// [`poll_request`]: BlockDriverOps::poll_request
+    unsafe fn submit_request(&mut self, req: &BlockRequest) -> DevResult<RequestId> {
+        let mut block_id = req.block_id;
+        for seg in req.segments {
+            match req.op {
+                BlockOp::Read => {
+                    let buf = core::slice::from_raw_parts_mut(seg.ptr, seg.len);
+                    self.read_block(block_id, buf)?
+                }
+                BlockOp::Write => {
+                    // the segment may be made from a shared slice
+                    let buf = core::slice::from_raw_parts(seg.ptr, seg.len);
+                    self.write_block(block_id, buf)?
+                }
+                BlockOp::Flush => break,
+            }
+            block_id += (seg.len / self.block_size()) as u64;
+        }
+        if req.op == BlockOp::Flush {
+            self.flush()?;
+        }
+        Ok(0)
+    }
+
+    /// Checks whether the submitted request has completed, returns
+    /// `Ok(true)` and forgets the request if it has completed successfully,
+    /// or `Ok(false)` if it's still in flight.
+    fn poll_request(&mut self, _id: RequestId) -> DevResult<bool> {
+        Ok(true)
+    }
+}
+
+/// Submits a request and waits for its completion by polling.
+///
+/// # Safety
+///
+/// The segments must be valid for the operation.
+pub unsafe fn execute_request<D: BlockDriverOps + ?Sized>(
+    dev: &mut D,
+    req: &BlockRequest,
+) -> DevResult {
+    let id = dev.submit_request(req)?;
+    while !dev.poll_request(id)? {
+        core::hint::spin_loop();
+    }
+    Ok(())
 }
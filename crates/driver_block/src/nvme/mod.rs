@@ -0,0 +1,423 @@
+//! NVM Express (NVMe) driver for PCIe SSDs.
+//!
+//! The driver sets up the admin queue and several I/O queue pairs, and uses
+//! the first active namespace of the controller. Requests are polled, and a
+//! request larger than one transfer is split into chunks that are submitted to
+//! all I/O queues at once.
+//!
+//! Data is transferred through a DMA buffer of each I/O queue, so the buffers
+//! passed to [`BlockDriverOps`] have no alignment requirements.
+
+extern crate alloc;
+
+mod queue;
+
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use crate::BlockDriverOps;
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+use self::queue::{Command, Dma, Queue};
+
+/// Physical address for DMA.
+pub type PhysAddr = usize;
+
+/// The memory page size used by the controller.
+pub const PAGE_SIZE: usize = 0x1000;
+
+/// The platform functions used by the NVMe driver.
+///
+/// # Safety
+///
+/// The allocated DMA memory must be physically contiguous, and accessible by
+/// the device at the returned physical address.
+pub unsafe trait NvmeHal {
+    /// Allocates `pages` contiguous physical pages for DMA, returns the
+    /// physical address and the virtual address of the first page, or a zero
+    /// physical address if it fails.
+    fn dma_alloc(pages: usize) -> (PhysAddr, NonNull<u8>);
+
+    /// Deallocates the DMA memory allocated by [`NvmeHal::dma_alloc`].
+    ///
+    /// # Safety
+    ///
+    /// The memory must not be accessed by the device anymore.
+    unsafe fn dma_dealloc(paddr: PhysAddr, vaddr: NonNull<u8>, pages: usize) -> i32;
+
+    /// Waits for the given duration.
+    fn busy_wait(duration: Duration);
+}
+
+// controller registers
+const REG_CAP: usize = 0x00;
+const REG_INTMS: usize = 0x0c;
+const REG_CC: usize = 0x14;
+const REG_CSTS: usize = 0x1c;
+const REG_AQA: usize = 0x24;
+const REG_ASQ: usize = 0x28;
+const REG_ACQ: usize = 0x30;
+
+const CC_EN: u32 = 1 << 0;
+const CC_IOSQES: u32 = 6 << 16; // 64-byte submission queue entries
+const CC_IOCQES: u32 = 4 << 20; // 16-byte completion queue entries
+const CSTS_RDY: u32 = 1 << 0;
+const CSTS_CFS: u32 = 1 << 1;
+
+// admin commands
+const ADMIN_CREATE_IO_SQ: u8 = 0x01;
+const ADMIN_CREATE_IO_CQ: u8 = 0x05;
+const ADMIN_IDENTIFY: u8 = 0x06;
+const ADMIN_SET_FEATURES: u8 = 0x09;
+
+const IDENTIFY_NAMESPACE: u32 = 0x00;
+const IDENTIFY_CONTROLLER: u32 = 0x01;
+const IDENTIFY_ACTIVE_NAMESPACES: u32 = 0x02;
+const FEATURE_NUMBER_OF_QUEUES: u32 = 0x07;
+
+// NVM commands
+const NVM_FLUSH: u8 = 0x00;
+const NVM_WRITE: u8 = 0x01;
+const NVM_READ: u8 = 0x02;
+
+const ADMIN_QUEUE_SIZE: u16 = 32;
+const IO_QUEUE_SIZE: u16 = 64;
+
+/// The number of pages of the DMA buffer of each I/O queue, which is the
+/// maximum size of one transfer (unless the controller limits it further).
+const MAX_TRANSFER_PAGES: usize = 32;
+
+/// An I/O queue pair with its DMA buffer.
+///
+/// The buffer is followed by a page of the PRP (physical region page) list
+/// that points to the buffer pages except the first one.
+struct IoQueue<H: NvmeHal> {
+    queue: Queue<H>,
+    buf: Dma<H>,
+}
+
+impl<H: NvmeHal> IoQueue<H> {
+    fn new(queue: Queue<H>) -> DevResult<Self> {
+        let buf = Dma::<H>::new(MAX_TRANSFER_PAGES + 1)?;
+        let base = buf.paddr();
+        let prp_list = unsafe { buf.as_ptr().add(MAX_TRANSFER_PAGES * PAGE_SIZE) as *mut u64 };
+        for i in 0..MAX_TRANSFER_PAGES - 1 {
+            let entry = (base + (i + 1) * PAGE_SIZE) as u64;
+            unsafe { prp_list.add(i).write(entry.to_le()) };
+        }
+        Ok(Self { queue, buf })
+    }
+
+    /// Submits a read or write of `len` bytes from the buffer, starting at
+    /// the logical block `lba`.
+    fn submit_rw(&mut self, opcode: u8, nsid: u32, lba: u64, len: usize, block_size: usize) {
+        let base = self.buf.paddr();
+        let mut cmd = Command::new(opcode);
+        cmd.nsid = nsid;
+        cmd.prp1 = base as u64;
+        cmd.prp2 = match len.div_ceil(PAGE_SIZE) {
+            0 | 1 => 0,
+            2 => (base + PAGE_SIZE) as u64,
+            _ => (base + MAX_TRANSFER_PAGES * PAGE_SIZE) as u64,
+        };
+        cmd.cdw10 = lba as u32;
+        cmd.cdw11 = (lba >> 32) as u32;
+        cmd.cdw12 = (len / block_size - 1) as u32;
+        self.queue.submit(cmd);
+    }
+}
+
+/// The NVMe controller driver, which accesses one namespace.
+pub struct NvmeDev<H: NvmeHal> {
+    regs: usize,
+    timeout: Duration,
+    admin: Queue<H>,
+    io_queues: Vec<IoQueue<H>>,
+    nsid: u32,
+    num_blocks: u64,
+    block_size: usize,
+    max_transfer: usize,
+}
+
+unsafe impl<H: NvmeHal> Send for NvmeDev<H> {}
+unsafe impl<H: NvmeHal> Sync for NvmeDev<H> {}
+
+impl<H: NvmeHal> NvmeDev<H> {
+    /// Initializes the controller whose registers (BAR 0) are mapped at
+    /// `regs`, with at most `max_io_queues` I/O queue pairs.
+    pub fn try_new(regs: usize, max_io_queues: u16) -> DevResult<Self> {
+        let cap = unsafe { ((regs + REG_CAP) as *const u64).read_volatile() };
+        let max_queue_entries = (cap & 0xffff) as u16 + 1;
+        let timeout = Duration::from_millis(((cap >> 24) & 0xff).max(1) * 500);
+        let doorbell_stride = 4 << ((cap >> 32) & 0xf);
+        let nvm_command_set = cap & (1 << 37) != 0;
+        let min_page_size = PAGE_SIZE << ((cap >> 48) & 0xf);
+        if !nvm_command_set || min_page_size != PAGE_SIZE {
+            log::warn!("NVMe: unsupported controller, CAP = {:#x}", cap);
+            return Err(DevError::Unsupported);
+        }
+
+        let admin_size = ADMIN_QUEUE_SIZE.min(max_queue_entries);
+        let mut dev = Self {
+            regs,
+            timeout,
+            admin: Queue::new(0, admin_size, regs, doorbell_stride, timeout)?,
+            io_queues: Vec::new(),
+            nsid: 0,
+            num_blocks: 0,
+            block_size: 0,
+            max_transfer: MAX_TRANSFER_PAGES * PAGE_SIZE,
+        };
+
+        // reset the controller and set up the admin queue
+        dev.disable()?;
+        let aqa = (admin_size as u32 - 1) | ((admin_size as u32 - 1) << 16);
+        dev.write_reg(REG_AQA, aqa);
+        dev.write_reg64(REG_ASQ, dev.admin.sq_paddr() as u64);
+        dev.write_reg64(REG_ACQ, dev.admin.cq_paddr() as u64);
+        // the completions are polled
+        dev.write_reg(REG_INTMS, u32::MAX);
+        dev.write_reg(REG_CC, CC_EN | CC_IOSQES | CC_IOCQES);
+        dev.wait_ready(true)?;
+
+        dev.identify()?;
+        let io_queue_size = IO_QUEUE_SIZE.min(max_queue_entries);
+        for id in 1..=dev.negotiate_io_queues(max_io_queues)? {
+            let queue = Queue::new(id, io_queue_size, regs, doorbell_stride, timeout)?;
+            dev.create_io_queue(&queue)?;
+            dev.io_queues.push(IoQueue::new(queue)?);
+        }
+        log::info!(
+            "NVMe: namespace {}, {} blocks of {} bytes, {} I/O queues",
+            dev.nsid,
+            dev.num_blocks,
+            dev.block_size,
+            dev.io_queues.len()
+        );
+        Ok(dev)
+    }
+
+    /// The ID of the namespace in use.
+    pub const fn namespace_id(&self) -> u32 {
+        self.nsid
+    }
+
+    /// The number of I/O queue pairs.
+    pub fn num_io_queues(&self) -> usize {
+        self.io_queues.len()
+    }
+
+    fn read_reg(&self, offset: usize) -> u32 {
+        unsafe { ((self.regs + offset) as *const u32).read_volatile() }
+    }
+
+    fn write_reg(&self, offset: usize, value: u32) {
+        unsafe { ((self.regs + offset) as *mut u32).write_volatile(value) }
+    }
+
+    fn write_reg64(&self, offset: usize, value: u64) {
+        self.write_reg(offset, value as u32);
+        self.write_reg(offset + 4, (value >> 32) as u32);
+    }
+
+    /// Waits until `CSTS.RDY` becomes `ready`, or the timeout in `CAP.TO`
+    /// elapses.
+    fn wait_ready(&self, ready: bool) -> DevResult {
+        const STEP: Duration = Duration::from_millis(1);
+        let mut waited = Duration::ZERO;
+        loop {
+            let csts = self.read_reg(REG_CSTS);
+            if csts & CSTS_CFS != 0 && ready {
+                log::warn!("NVMe: controller fatal status");
+                return Err(DevError::Io);
+            }
+            if (csts & CSTS_RDY != 0) == ready {
+                return Ok(());
+            }
+            if waited >= self.timeout {
+                log::warn!("NVMe: controller is not ready in {:?}", self.timeout);
+                return Err(DevError::ResourceBusy);
+            }
+            H::busy_wait(STEP);
+            waited += STEP;
+        }
+    }
+
+    fn disable(&self) -> DevResult {
+        self.write_reg(REG_CC, self.read_reg(REG_CC) & !CC_EN);
+        self.wait_ready(false)
+    }
+
+    /// Executes an identify command, returns the 4 KiB data structure.
+    fn identify_data(&mut self, cns: u32, nsid: u32) -> DevResult<Dma<H>> {
+        let data = Dma::<H>::new(1)?;
+        let mut cmd = Command::new(ADMIN_IDENTIFY);
+        cmd.nsid = nsid;
+        cmd.prp1 = data.paddr() as u64;
+        cmd.cdw10 = cns;
+        self.admin.execute(cmd)?;
+        Ok(data)
+    }
+
+    /// Finds the first active namespace and reads its size and format, and
+    /// the transfer limit of the controller.
+    fn identify(&mut self) -> DevResult {
+        let ctrl = self.identify_data(IDENTIFY_CONTROLLER, 0)?;
+        let mdts = ctrl.as_slice()[77];
+        if mdts != 0 {
+            self.max_transfer = self.max_transfer.min(PAGE_SIZE << mdts);
+        }
+
+        let ns_list = self.identify_data(IDENTIFY_ACTIVE_NAMESPACES, 0)?;
+        let nsid = u32::from_le_bytes(ns_list.as_slice()[..4].try_into().unwrap());
+        if nsid == 0 {
+            log::warn!("NVMe: no active namespace");
+            return Err(DevError::Unsupported);
+        }
+
+        let ns = self.identify_data(IDENTIFY_NAMESPACE, nsid)?;
+        let ns = ns.as_slice();
+        let num_blocks = u64::from_le_bytes(ns[0..8].try_into().unwrap());
+        let format = (ns[26] & 0xf) as usize;
+        let lba_format = &ns[128 + format * 4..128 + format * 4 + 4];
+        let metadata_size = u16::from_le_bytes([lba_format[0], lba_format[1]]);
+        let block_size = 1usize << lba_format[2];
+        if metadata_size != 0 || !(512..=PAGE_SIZE).contains(&block_size) {
+            log::warn!(
+                "NVMe: unsupported LBA format, {} + {} bytes",
+                block_size,
+                metadata_size
+            );
+            return Err(DevError::Unsupported);
+        }
+
+        self.nsid = nsid;
+        self.num_blocks = num_blocks;
+        self.block_size = block_size;
+        Ok(())
+    }
+
+    /// Requests `count` I/O queue pairs, returns the number allocated by the
+    /// controller, which may be less.
+    fn negotiate_io_queues(&mut self, count: u16) -> DevResult<u16> {
+        let count = count.max(1);
+        let mut cmd = Command::new(ADMIN_SET_FEATURES);
+        cmd.cdw10 = FEATURE_NUMBER_OF_QUEUES;
+        cmd.cdw11 = (count as u32 - 1) | ((count as u32 - 1) << 16);
+        let result = self.admin.execute(cmd)?;
+        let num_sq = (result & 0xffff) as u16 + 1;
+        let num_cq = (result >> 16) as u16 + 1;
+        Ok(count.min(num_sq).min(num_cq))
+    }
+
+    fn create_io_queue(&mut self, queue: &Queue<H>) -> DevResult {
+        let qid_size = queue.id() as u32 | ((queue.size() as u32 - 1) << 16);
+
+        let mut cmd = Command::new(ADMIN_CREATE_IO_CQ);
+        cmd.prp1 = queue.cq_paddr() as u64;
+        cmd.cdw10 = qid_size;
+        cmd.cdw11 = 1; // physically contiguous, interrupts disabled
+        self.admin.execute(cmd)?;
+
+        let mut cmd = Command::new(ADMIN_CREATE_IO_SQ);
+        cmd.prp1 = queue.sq_paddr() as u64;
+        cmd.cdw10 = qid_size;
+        cmd.cdw11 = 1 | ((queue.id() as u32) << 16); // physically contiguous, the CQ
+        self.admin.execute(cmd)?;
+        Ok(())
+    }
+
+    fn check_request(&self, block_id: u64, len: usize) -> DevResult {
+        let blocks = (len / self.block_size) as u64;
+        if len % self.block_size != 0 || block_id.saturating_add(blocks) > self.num_blocks {
+            return Err(DevError::InvalidParam);
+        }
+        Ok(())
+    }
+}
+
+impl<H: NvmeHal> Drop for NvmeDev<H> {
+    fn drop(&mut self) {
+        // stop the DMA before the queues are freed
+        if self.disable().is_err() {
+            log::warn!("NVMe: failed to disable the controller");
+        }
+    }
+}
+
+impl<H: NvmeHal> const BaseDriverOps for NvmeDev<H> {
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Block
+    }
+
+    fn device_name(&self) -> &str {
+        "nvme"
+    }
+}
+
+impl<H: NvmeHal> BlockDriverOps for NvmeDev<H> {
+    #[inline]
+    fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    #[inline]
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        self.check_request(block_id, buf.len())?;
+        let (nsid, block_size, chunk) = (self.nsid, self.block_size, self.max_transfer);
+        let mut lba = block_id;
+        for batch in buf.chunks_mut(chunk * self.io_queues.len()) {
+            let mut next = lba;
+            for (q, part) in self.io_queues.iter_mut().zip(batch.chunks(chunk)) {
+                q.submit_rw(NVM_READ, nsid, next, part.len(), block_size);
+                next += (part.len() / block_size) as u64;
+            }
+            // wait for all submitted commands even if one fails
+            let mut res = Ok(());
+            for (q, part) in self.io_queues.iter_mut().zip(batch.chunks_mut(chunk)) {
+                match q.queue.wait() {
+                    Ok(_) => part.copy_from_slice(&q.buf.as_slice()[..part.len()]),
+                    Err(e) => res = Err(e),
+                }
+            }
+            res?;
+            lba = next;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        self.check_request(block_id, buf.len())?;
+        let (nsid, block_size, chunk) = (self.nsid, self.block_size, self.max_transfer);
+        let mut lba = block_id;
+        for batch in buf.chunks(chunk * self.io_queues.len()) {
+            let mut next = lba;
+            for (q, part) in self.io_queues.iter_mut().zip(batch.chunks(chunk)) {
+                q.buf.as_mut_slice()[..part.len()].copy_from_slice(part);
+                q.submit_rw(NVM_WRITE, nsid, next, part.len(), block_size);
+                next += (part.len() / block_size) as u64;
+            }
+            let mut res = Ok(());
+            for q in self.io_queues.iter_mut().take(batch.len().div_ceil(chunk)) {
+                if let Err(e) = q.queue.wait() {
+                    res = Err(e);
+                }
+            }
+            res?;
+            lba = next;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> DevResult {
+        let mut cmd = Command::new(NVM_FLUSH);
+        cmd.nsid = self.nsid;
+        self.io_queues[0].queue.execute(cmd)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+//! Submission/completion queue pairs and DMA memory.
+
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use core::sync::atomic::{fence, Ordering};
+use core::time::Duration;
+
+use driver_common::{DevError, DevResult};
+
+use super::{NvmeHal, PhysAddr, PAGE_SIZE};
+
+/// The size of a submission queue entry in bytes (2^6).
+pub const SQ_ENTRY_SIZE: usize = 64;
+/// The size of a completion queue entry in bytes (2^4).
+pub const CQ_ENTRY_SIZE: usize = 16;
+
+/// Physically contiguous memory for DMA, zeroed on allocation.
+pub struct Dma<H: NvmeHal> {
+    paddr: PhysAddr,
+    vaddr: NonNull<u8>,
+    pages: usize,
+    _hal: PhantomData<H>,
+}
+
+impl<H: NvmeHal> Dma<H> {
+    pub fn new(pages: usize) -> DevResult<Self> {
+        let (paddr, vaddr) = H::dma_alloc(pages);
+        if paddr == 0 {
+            return Err(DevError::NoMemory);
+        }
+        unsafe { vaddr.as_ptr().write_bytes(0, pages * PAGE_SIZE) };
+        Ok(Self {
+            paddr,
+            vaddr,
+            pages,
+            _hal: PhantomData,
+        })
+    }
+
+    pub const fn paddr(&self) -> PhysAddr {
+        self.paddr
+    }
+
+    pub const fn as_ptr(&self) -> *mut u8 {
+        self.vaddr.as_ptr()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.as_ptr(), self.pages * PAGE_SIZE) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.as_ptr(), self.pages * PAGE_SIZE) }
+    }
+}
+
+impl<H: NvmeHal> Drop for Dma<H> {
+    fn drop(&mut self) {
+        unsafe { H::dma_dealloc(self.paddr, self.vaddr, self.pages) };
+    }
+}
+
+/// A submission queue entry (command).
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Command {
+    /// Opcode (bits 7:0) and command identifier (bits 31:16).
+    pub cdw0: u32,
+    pub nsid: u32,
+    pub cdw2: u32,
+    pub cdw3: u32,
+    pub mptr: u64,
+    pub prp1: u64,
+    pub prp2: u64,
+    pub cdw10: u32,
+    pub cdw11: u32,
+    pub cdw12: u32,
+    pub cdw13: u32,
+    pub cdw14: u32,
+    pub cdw15: u32,
+}
+
+impl Command {
+    pub fn new(opcode: u8) -> Self {
+        Self {
+            cdw0: opcode as u32,
+            ..Default::default()
+        }
+    }
+}
+
+/// A completion queue entry.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Completion {
+    result: u32,
+    _reserved: u32,
+    sq_head: u16,
+    sq_id: u16,
+    cid: u16,
+    /// Phase tag (bit 0) and status field (bits 15:1).
+    status: u16,
+}
+
+/// A submission queue and its completion queue, which have the same ID and
+/// size.
+///
+/// Commands are completed one at a time: [`Queue::submit`] must be followed by
+/// [`Queue::wait`] before the next command is submitted.
+pub struct Queue<H: NvmeHal> {
+    id: u16,
+    size: u16,
+    timeout: Duration,
+    sq: Dma<H>,
+    cq: Dma<H>,
+    sq_tail: u16,
+    cq_head: u16,
+    phase: bool,
+    next_cid: u16,
+    sq_doorbell: *mut u32,
+    cq_doorbell: *mut u32,
+}
+
+impl<H: NvmeHal> Queue<H> {
+    /// Allocates a queue pair with `size` entries, the doorbells are computed
+    /// from the controller register base and the doorbell stride. A command
+    /// fails if it is not completed in `timeout`.
+    pub fn new(
+        id: u16,
+        size: u16,
+        regs: usize,
+        doorbell_stride: usize,
+        timeout: Duration,
+    ) -> DevResult<Self> {
+        let pages = |entry_size: usize| (size as usize * entry_size).div_ceil(PAGE_SIZE);
+        let doorbell = |idx: usize| (regs + 0x1000 + idx * doorbell_stride) as *mut u32;
+        Ok(Self {
+            id,
+            size,
+            timeout,
+            sq: Dma::new(pages(SQ_ENTRY_SIZE))?,
+            cq: Dma::new(pages(CQ_ENTRY_SIZE))?,
+            sq_tail: 0,
+            cq_head: 0,
+            phase: true,
+            next_cid: 0,
+            sq_doorbell: doorbell(2 * id as usize),
+            cq_doorbell: doorbell(2 * id as usize + 1),
+        })
+    }
+
+    pub const fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub const fn size(&self) -> u16 {
+        self.size
+    }
+
+    pub const fn sq_paddr(&self) -> PhysAddr {
+        self.sq.paddr()
+    }
+
+    pub const fn cq_paddr(&self) -> PhysAddr {
+        self.cq.paddr()
+    }
+
+    /// Puts the command into the submission queue and rings the doorbell.
+    pub fn submit(&mut self, mut cmd: Command) {
+        cmd.cdw0 = (cmd.cdw0 & 0xffff) | ((self.next_cid as u32) << 16);
+        self.next_cid = self.next_cid.wrapping_add(1);
+        unsafe {
+            let entry = self.sq.as_ptr() as *mut Command;
+            entry.add(self.sq_tail as usize).write_volatile(cmd);
+        }
+        self.sq_tail = (self.sq_tail + 1) % self.size;
+        fence(Ordering::SeqCst);
+        unsafe { self.sq_doorbell.write_volatile(self.sq_tail as u32) };
+    }
+
+    /// Waits for the completion of the last submitted command, returns the
+    /// command specific result (DW0).
+    ///
+    /// Fails with [`DevError::Io`] if the command is not completed in the
+    /// timeout, its completion is skipped if it arrives later.
+    pub fn wait(&mut self) -> DevResult<u32> {
+        const STEP: Duration = Duration::from_micros(1);
+        let cid = self.next_cid.wrapping_sub(1);
+        let mut waited = Duration::ZERO;
+        let completion = loop {
+            match self.pop_completion() {
+                Some(completion) if completion.cid == cid => break completion,
+                // the late completion of a command that timed out
+                Some(_) => continue,
+                None => {}
+            }
+            if waited >= self.timeout {
+                log::warn!(
+                    "NVMe command {} on queue {} timed out in {:?}",
+                    cid,
+                    self.id,
+                    self.timeout
+                );
+                return Err(DevError::Io);
+            }
+            H::busy_wait(STEP);
+            waited += STEP;
+        };
+
+        // status code type (bits 11:9) and status code (bits 8:1)
+        let status = completion.status >> 1;
+        if status != 0 {
+            log::warn!(
+                "NVMe command {} on queue {} failed: SCT {:#x}, SC {:#x}",
+                completion.cid,
+                self.id,
+                (status >> 8) & 0x7,
+                status & 0xff
+            );
+            return Err(DevError::Io);
+        }
+        Ok(completion.result)
+    }
+
+    /// Takes the next entry out of the completion queue, returns `None` if the
+    /// controller has not posted it yet.
+    fn pop_completion(&mut self) -> Option<Completion> {
+        let entry = unsafe { (self.cq.as_ptr() as *const Completion).add(self.cq_head as usize) };
+        let completion = unsafe { entry.read_volatile() };
+        if (completion.status & 1 == 1) != self.phase {
+            return None;
+        }
+        fence(Ordering::SeqCst);
+
+        self.cq_head += 1;
+        if self.cq_head == self.size {
+            self.cq_head = 0;
+            self.phase = !self.phase;
+        }
+        unsafe { self.cq_doorbell.write_volatile(self.cq_head as u32) };
+        Some(completion)
+    }
+
+    /// Submits the command and waits for its completion.
+    pub fn execute(&mut self, cmd: Command) -> DevResult<u32> {
+        self.submit(cmd);
+        self.wait()
+    }
+}
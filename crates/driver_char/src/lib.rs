@@ -0,0 +1,93 @@
+//! Common traits and types for character device drivers (i.e. serial ports).
+
+#![no_std]
+#![feature(doc_auto_cfg)]
+
+#[cfg(feature = "uart16550")]
+pub mod uart16550;
+
+#[cfg(feature = "pl011")]
+pub mod pl011;
+
+#[doc(no_inline)]
+pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+/// The parity bit of a serial line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    /// No parity bit.
+    None,
+    /// The number of 1 bits (including the parity bit) is odd.
+    Odd,
+    /// The number of 1 bits (including the parity bit) is even.
+    Even,
+}
+
+/// The line settings of a serial port, like the `c_cflag` and the speed of
+/// `termios`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialConfig {
+    /// The baud rate in bits per second.
+    pub baud_rate: u32,
+    /// The number of data bits, 5 to 8.
+    pub data_bits: u8,
+    /// The parity bit.
+    pub parity: Parity,
+    /// The number of stop bits, 1 or 2.
+    pub stop_bits: u8,
+}
+
+impl Default for SerialConfig {
+    /// 115200 baud, 8 data bits, no parity and 1 stop bit (8N1).
+    fn default() -> Self {
+        Self {
+            baud_rate: 115200,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: 1,
+        }
+    }
+}
+
+impl SerialConfig {
+    /// Whether the settings are valid.
+    pub fn is_valid(&self) -> bool {
+        self.baud_rate > 0 && (5..=8).contains(&self.data_bits) && (1..=2).contains(&self.stop_bits)
+    }
+}
+
+/// Whether a character device can be read or written without waiting.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CharPollState {
+    /// Some input is available.
+    pub readable: bool,
+    /// Some output can be accepted.
+    pub writable: bool,
+}
+
+/// Operations that require a character device driver to implement.
+///
+/// The operations never wait, they return [`DevError::Again`] if no byte can
+/// be read or written.
+pub trait CharDriverOps: BaseDriverOps {
+    /// Reads the available input into `buf`, returns the number of bytes
+    /// read.
+    fn read(&mut self, buf: &mut [u8]) -> DevResult<usize>;
+
+    /// Writes as many bytes of `buf` as the device accepts, returns the
+    /// number of bytes written.
+    fn write(&mut self, buf: &[u8]) -> DevResult<usize>;
+
+    /// Returns whether the device can be read or written.
+    fn poll(&mut self) -> CharPollState;
+
+    /// The current line settings.
+    fn config(&self) -> SerialConfig {
+        SerialConfig::default()
+    }
+
+    /// Changes the line settings.
+    fn set_config(&mut self, _config: &SerialConfig) -> DevResult {
+        Err(DevError::Unsupported)
+    }
+}
@@ -0,0 +1,118 @@
+//! Driver for the ARM PL011 UART.
+
+use arm_pl011::pl011::Pl011Uart;
+
+use crate::{BaseDriverOps, CharDriverOps, CharPollState, DevError, DevResult, DeviceType};
+use crate::{Parity, SerialConfig};
+
+// Fields of the line control register (`UARTLCR_H`).
+const LCR_H_PEN: u32 = 1 << 1;
+const LCR_H_EPS: u32 = 1 << 2;
+const LCR_H_STP2: u32 = 1 << 3;
+const LCR_H_FEN: u32 = 1 << 4;
+const LCR_H_WLEN_SHIFT: u32 = 5;
+
+/// The PL011 UART driver.
+pub struct Pl011 {
+    inner: Pl011Uart,
+    clock_hz: u32,
+    config: SerialConfig,
+}
+
+impl Pl011 {
+    /// Creates a driver of the PL011 UART whose registers are mapped at
+    /// `base`, and whose input clock is `clock_hz`. The UART is not touched
+    /// until [`Self::init`].
+    ///
+    /// # Safety
+    ///
+    /// `base` must be the mapped registers of a PL011 UART, which is not used
+    /// by others (e.g., the console).
+    pub unsafe fn new(base: *mut u8, clock_hz: u32) -> Self {
+        Self {
+            inner: Pl011Uart::new(base),
+            clock_hz,
+            config: SerialConfig::default(),
+        }
+    }
+
+    /// Initializes the UART with the default settings (8N1 at 115200 baud).
+    pub fn init(&mut self) -> DevResult {
+        self.inner.init();
+        self.set_config(&SerialConfig::default())
+    }
+}
+
+impl BaseDriverOps for Pl011 {
+    fn device_name(&self) -> &str {
+        "pl011"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Char
+    }
+}
+
+impl CharDriverOps for Pl011 {
+    fn read(&mut self, buf: &mut [u8]) -> DevResult<usize> {
+        let mut len = 0;
+        while len < buf.len() {
+            match self.inner.getchar() {
+                Some(b) => buf[len] = b,
+                None => break,
+            }
+            len += 1;
+        }
+        match len {
+            0 if !buf.is_empty() => Err(DevError::Again),
+            len => Ok(len),
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> DevResult<usize> {
+        let mut len = 0;
+        while len < buf.len() && !self.inner.is_transmit_full() {
+            self.inner.putchar(buf[len]);
+            len += 1;
+        }
+        match len {
+            0 if !buf.is_empty() => Err(DevError::Again),
+            len => Ok(len),
+        }
+    }
+
+    fn poll(&mut self) -> CharPollState {
+        CharPollState {
+            readable: self.inner.is_receive_ready(),
+            writable: !self.inner.is_transmit_full(),
+        }
+    }
+
+    fn config(&self) -> SerialConfig {
+        self.config
+    }
+
+    fn set_config(&mut self, config: &SerialConfig) -> DevResult {
+        if !config.is_valid() {
+            return Err(DevError::InvalidParam);
+        }
+        // in units of 1/64, the integer part is 16 bits
+        let divisor = self.clock_hz as u64 * 4 / config.baud_rate as u64;
+        if !(1 << 6..1 << 22).contains(&divisor) {
+            return Err(DevError::InvalidParam);
+        }
+        let divisor = divisor as u32;
+        let mut lcr_h = LCR_H_FEN | ((config.data_bits - 5) as u32) << LCR_H_WLEN_SHIFT;
+        if config.stop_bits == 2 {
+            lcr_h |= LCR_H_STP2;
+        }
+        match config.parity {
+            Parity::None => {}
+            Parity::Odd => lcr_h |= LCR_H_PEN,
+            Parity::Even => lcr_h |= LCR_H_PEN | LCR_H_EPS,
+        }
+        self.inner.set_line_control(divisor, lcr_h);
+        self.config = *config;
+        Ok(())
+    }
+}
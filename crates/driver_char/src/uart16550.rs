@@ -0,0 +1,233 @@
+//! Driver for the 16550 UART and the compatible ones (e.g., `ns16550a` in
+//! QEMU and `snps,dw-apb-uart`).
+
+use crate::{BaseDriverOps, CharDriverOps, CharPollState, DevError, DevResult, DeviceType};
+use crate::{Parity, SerialConfig};
+
+// Register indexes, the DLAB bit of LCR selects DLL/DLM at 0 and 1.
+const RBR_THR_DLL: usize = 0;
+const IER_DLM: usize = 1;
+const FCR: usize = 2;
+const LCR: usize = 3;
+const MCR: usize = 4;
+const LSR: usize = 5;
+const SCR: usize = 7;
+
+const LCR_STOP_BITS_2: u8 = 1 << 2;
+const LCR_PARITY_ENABLE: u8 = 1 << 3;
+const LCR_PARITY_EVEN: u8 = 1 << 4;
+const LCR_DLAB: u8 = 1 << 7;
+const LSR_DATA_READY: u8 = 1 << 0;
+const LSR_THR_EMPTY: u8 = 1 << 5;
+/// Enable the FIFOs and clear them.
+const FCR_ENABLE_CLEAR: u8 = 0b111;
+/// Data terminal ready, request to send and the auxiliary output 2 (which
+/// gates the interrupt line on PCs).
+const MCR_DTR_RTS_OUT2: u8 = 0b1011;
+/// The size of the transmit FIFO.
+const TX_FIFO_SIZE: usize = 16;
+
+/// How the registers of a 16550 UART are accessed.
+#[derive(Debug, Clone, Copy)]
+pub enum Uart16550Io {
+    /// Memory-mapped registers, the register `i` is at `base + (i <<
+    /// reg_shift)` and is accessed with `reg_io_width` (1 or 4) bytes.
+    Mmio {
+        /// The mapped address of the registers.
+        base: usize,
+        /// The log2 of the distance between registers.
+        reg_shift: u8,
+        /// The access width in bytes.
+        reg_io_width: u8,
+    },
+    /// I/O ports starting from the given port.
+    #[cfg(target_arch = "x86_64")]
+    Port(u16),
+}
+
+/// The 16550 UART driver.
+pub struct Uart16550 {
+    io: Uart16550Io,
+    clock_hz: u32,
+    config: SerialConfig,
+}
+
+impl Uart16550Io {
+    fn read(&self, reg: usize) -> u8 {
+        match *self {
+            Self::Mmio {
+                base,
+                reg_shift,
+                reg_io_width,
+            } => {
+                let addr = base + (reg << reg_shift);
+                unsafe {
+                    match reg_io_width {
+                        4 => (addr as *const u32).read_volatile() as u8,
+                        _ => (addr as *const u8).read_volatile(),
+                    }
+                }
+            }
+            #[cfg(target_arch = "x86_64")]
+            Self::Port(port) => {
+                let value: u8;
+                unsafe {
+                    core::arch::asm!(
+                        "in al, dx",
+                        out("al") value,
+                        in("dx") port + reg as u16,
+                        options(nomem, nostack, preserves_flags),
+                    )
+                };
+                value
+            }
+        }
+    }
+
+    fn write(&self, reg: usize, value: u8) {
+        match *self {
+            Self::Mmio {
+                base,
+                reg_shift,
+                reg_io_width,
+            } => {
+                let addr = base + (reg << reg_shift);
+                unsafe {
+                    match reg_io_width {
+                        4 => (addr as *mut u32).write_volatile(value as u32),
+                        _ => (addr as *mut u8).write_volatile(value),
+                    }
+                }
+            }
+            #[cfg(target_arch = "x86_64")]
+            Self::Port(port) => unsafe {
+                core::arch::asm!(
+                    "out dx, al",
+                    in("dx") port + reg as u16,
+                    in("al") value,
+                    options(nomem, nostack, preserves_flags),
+                )
+            },
+        }
+    }
+}
+
+impl Uart16550 {
+    /// Creates a driver of the UART accessed by `io`, whose input clock is
+    /// `clock_hz`. The UART is not touched until [`Self::init`].
+    ///
+    /// # Safety
+    ///
+    /// `io` must be the registers of a 16550 compatible UART, which are not
+    /// used by others (e.g., the console).
+    pub unsafe fn new(io: Uart16550Io, clock_hz: u32) -> Self {
+        Self {
+            io,
+            clock_hz,
+            config: SerialConfig::default(),
+        }
+    }
+
+    /// Whether there is a UART, by checking that the scratch register keeps
+    /// the value written.
+    pub fn is_present(&self) -> bool {
+        [0x55, 0xaa].iter().all(|&v| {
+            self.io.write(SCR, v);
+            self.io.read(SCR) == v
+        })
+    }
+
+    /// Initializes the UART with the default settings (8N1 at 115200 baud),
+    /// the interrupts are disabled.
+    pub fn init(&mut self) -> DevResult {
+        self.io.write(IER_DLM, 0);
+        self.set_config(&SerialConfig::default())?;
+        self.io.write(FCR, FCR_ENABLE_CLEAR);
+        self.io.write(MCR, MCR_DTR_RTS_OUT2);
+        Ok(())
+    }
+
+    fn line_status(&self) -> u8 {
+        self.io.read(LSR)
+    }
+}
+
+impl BaseDriverOps for Uart16550 {
+    fn device_name(&self) -> &str {
+        "uart16550"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Char
+    }
+}
+
+impl CharDriverOps for Uart16550 {
+    fn read(&mut self, buf: &mut [u8]) -> DevResult<usize> {
+        let mut len = 0;
+        while len < buf.len() && self.line_status() & LSR_DATA_READY != 0 {
+            buf[len] = self.io.read(RBR_THR_DLL);
+            len += 1;
+        }
+        match len {
+            0 if !buf.is_empty() => Err(DevError::Again),
+            len => Ok(len),
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> DevResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.line_status() & LSR_THR_EMPTY == 0 {
+            return Err(DevError::Again);
+        }
+        // the whole FIFO is empty
+        let len = buf.len().min(TX_FIFO_SIZE);
+        for &b in &buf[..len] {
+            self.io.write(RBR_THR_DLL, b);
+        }
+        Ok(len)
+    }
+
+    fn poll(&mut self) -> CharPollState {
+        let lsr = self.line_status();
+        CharPollState {
+            readable: lsr & LSR_DATA_READY != 0,
+            writable: lsr & LSR_THR_EMPTY != 0,
+        }
+    }
+
+    fn config(&self) -> SerialConfig {
+        self.config
+    }
+
+    fn set_config(&mut self, config: &SerialConfig) -> DevResult {
+        if !config.is_valid() {
+            return Err(DevError::InvalidParam);
+        }
+        let divisor = config
+            .baud_rate
+            .checked_mul(16)
+            .map(|rate| self.clock_hz / rate)
+            .ok_or(DevError::InvalidParam)?;
+        if divisor == 0 || divisor > u16::MAX as u32 {
+            return Err(DevError::InvalidParam);
+        }
+        let mut lcr = config.data_bits - 5;
+        if config.stop_bits == 2 {
+            lcr |= LCR_STOP_BITS_2;
+        }
+        match config.parity {
+            Parity::None => {}
+            Parity::Odd => lcr |= LCR_PARITY_ENABLE,
+            Parity::Even => lcr |= LCR_PARITY_ENABLE | LCR_PARITY_EVEN,
+        }
+        self.io.write(LCR, LCR_DLAB);
+        self.io.write(RBR_THR_DLL, divisor as u8);
+        self.io.write(IER_DLM, (divisor >> 8) as u8);
+        self.io.write(LCR, lcr);
+        self.config = *config;
+        Ok(())
+    }
+}
@@ -7,11 +7,23 @@
 //! - [`driver_block`][2]: Common traits for block storage drivers.
 //! - [`driver_display`][3]: Common traits and types for graphics display drivers.
 //! - [`driver_net`][4]: Common traits and types for network (NIC) drivers.
+//! - [`driver_char`][5]: Common traits and types for character device drivers.
+//! - [`driver_rng`][6]: Common traits for hardware random number generators.
+//! - [`driver_input`][7]: Common traits and types for input device drivers.
+//! - [`driver_gpio`][8]: Common traits and types for GPIO controller drivers.
+//! - [`driver_i2c`][9]: Common traits for I2C controller drivers.
+//! - [`driver_spi`][10]: Common traits and types for SPI controller drivers.
 //!
 //! [1]: https://github.com/rcore-os/arceos
 //! [2]: ../driver_block/index.html
 //! [3]: ../driver_display/index.html
 //! [4]: ../driver_net/index.html
+//! [5]: ../driver_char/index.html
+//! [6]: ../driver_rng/index.html
+//! [7]: ../driver_input/index.html
+//! [8]: ../driver_gpio/index.html
+//! [9]: ../driver_i2c/index.html
+//! [10]: ../driver_spi/index.html
 
 #![no_std]
 #![feature(const_trait_impl)]
@@ -27,6 +39,16 @@ pub enum DeviceType {
     Net,
     /// Graphic display device (e.g., GPU)
     Display,
+    /// Random number generator (e.g., entropy source).
+    Rng,
+    /// Input device (e.g., keyboard, mouse).
+    Input,
+    /// General-purpose I/O controller.
+    Gpio,
+    /// I2C bus controller.
+    I2c,
+    /// SPI bus controller.
+    Spi,
 }
 
 /// The error type for device operation failures.
@@ -61,4 +83,57 @@ pub trait BaseDriverOps: Send + Sync {
 
     /// The type of the device.
     fn device_type(&self) -> DeviceType;
+
+    /// The IRQ number that the device raises, or `None` if the device can
+    /// only be polled.
+    fn irq_num(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Access to the 32-bit registers of a memory-mapped device.
+///
+/// Drivers that are generic over it can be tested on the host with a mock
+/// implementation that emulates the device.
+pub trait RegisterAccess: Send + Sync {
+    /// Reads the register at `offset` bytes from the base.
+    fn read_reg(&self, offset: usize) -> u32;
+
+    /// Writes `value` to the register at `offset` bytes from the base.
+    fn write_reg(&self, offset: usize, value: u32);
+}
+
+/// The registers of a memory-mapped device, accessed by volatile loads and
+/// stores.
+#[derive(Debug, Clone, Copy)]
+pub struct MmioRegisters {
+    base: usize,
+}
+
+impl MmioRegisters {
+    /// Creates an accessor of the registers mapped at `base`.
+    ///
+    /// # Safety
+    ///
+    /// `base` must be the mapped registers of the device, and be valid as
+    /// long as the accessor is used.
+    pub unsafe fn new(base: *mut u8) -> Self {
+        Self {
+            base: base as usize,
+        }
+    }
+}
+
+impl RegisterAccess for MmioRegisters {
+    #[inline]
+    fn read_reg(&self, offset: usize) -> u32 {
+        // Safe because `base` is mapped, as required by `new`.
+        unsafe { ((self.base + offset) as *const u32).read_volatile() }
+    }
+
+    #[inline]
+    fn write_reg(&self, offset: usize, value: u32) {
+        // Safe because `base` is mapped, as required by `new`.
+        unsafe { ((self.base + offset) as *mut u32).write_volatile(value) }
+    }
 }
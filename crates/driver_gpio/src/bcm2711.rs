@@ -0,0 +1,146 @@
+//! Driver for the GPIO controller of the BCM2711 (Raspberry Pi 4).
+
+use driver_common::{MmioRegisters, RegisterAccess};
+
+use crate::{BaseDriverOps, DevError, DevResult, DeviceType, GpioDriverOps, PinMode, Pull};
+
+/// The number of pins of the controller.
+pub const NUM_PINS: usize = 58;
+
+// Registers, see the BCM2711 ARM Peripherals datasheet.
+const GPFSEL0: usize = 0x00;
+const GPSET0: usize = 0x1c;
+const GPCLR0: usize = 0x28;
+const GPLEV0: usize = 0x34;
+const GPIO_PUP_PDN_CNTRL_REG0: usize = 0xe4;
+
+// Values of the function select fields (3 bits for each pin).
+const FSEL_INPUT: u32 = 0b000;
+const FSEL_OUTPUT: u32 = 0b001;
+/// The values of the alternate functions 0 to 5.
+const FSEL_ALT: [u32; 6] = [0b100, 0b101, 0b110, 0b111, 0b011, 0b010];
+
+// Values of the pull resistor fields (2 bits for each pin).
+const PULL_NONE: u32 = 0b00;
+const PULL_UP: u32 = 0b01;
+const PULL_DOWN: u32 = 0b10;
+
+/// The BCM2711 GPIO controller driver.
+pub struct Bcm2711Gpio<R: RegisterAccess = MmioRegisters> {
+    regs: R,
+}
+
+impl Bcm2711Gpio {
+    /// Creates a driver of the controller whose registers are mapped at
+    /// `base`.
+    ///
+    /// # Safety
+    ///
+    /// `base` must be the mapped registers of the BCM2711 GPIO controller.
+    pub unsafe fn new(base: *mut u8) -> Self {
+        Self::with_registers(MmioRegisters::new(base))
+    }
+}
+
+impl<R: RegisterAccess> Bcm2711Gpio<R> {
+    /// Creates a driver that accesses the registers by `regs`.
+    pub const fn with_registers(regs: R) -> Self {
+        Self { regs }
+    }
+
+    /// Changes the function of the pin by the raw value of its function
+    /// select field, as in the `brcm,function` property of device trees.
+    pub fn set_function(&mut self, pin: usize, fsel: u32) -> DevResult {
+        check_pin(pin)?;
+        if fsel > 0b111 {
+            return Err(DevError::InvalidParam);
+        }
+        self.modify(GPFSEL0 + 4 * (pin / 10), 3 * (pin % 10), 3, fsel);
+        Ok(())
+    }
+
+    /// Replaces the `width` bits at `shift` of the register with `value`.
+    fn modify(&self, offset: usize, shift: usize, width: usize, value: u32) {
+        let mask = ((1 << width) - 1) << shift;
+        let old = self.regs.read_reg(offset);
+        self.regs
+            .write_reg(offset, (old & !mask) | (value << shift));
+    }
+}
+
+impl<R: RegisterAccess> BaseDriverOps for Bcm2711Gpio<R> {
+    fn device_name(&self) -> &str {
+        "bcm2711-gpio"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Gpio
+    }
+}
+
+impl<R: RegisterAccess> GpioDriverOps for Bcm2711Gpio<R> {
+    fn num_pins(&self) -> usize {
+        NUM_PINS
+    }
+
+    fn mode(&self, pin: usize) -> DevResult<PinMode> {
+        check_pin(pin)?;
+        let fsel = (self.regs.read_reg(GPFSEL0 + 4 * (pin / 10)) >> (3 * (pin % 10))) & 0b111;
+        Ok(match fsel {
+            FSEL_INPUT => PinMode::Input,
+            FSEL_OUTPUT => PinMode::Output,
+            _ => {
+                let alt = FSEL_ALT.iter().position(|&v| v == fsel).unwrap();
+                PinMode::Alt(alt as u8)
+            }
+        })
+    }
+
+    fn set_mode(&mut self, pin: usize, mode: PinMode) -> DevResult {
+        let fsel = match mode {
+            PinMode::Input => FSEL_INPUT,
+            PinMode::Output => FSEL_OUTPUT,
+            PinMode::Alt(alt) => *FSEL_ALT.get(alt as usize).ok_or(DevError::InvalidParam)?,
+        };
+        self.set_function(pin, fsel)
+    }
+
+    fn set_pull(&mut self, pin: usize, pull: Pull) -> DevResult {
+        check_pin(pin)?;
+        let value = match pull {
+            Pull::None => PULL_NONE,
+            Pull::Up => PULL_UP,
+            Pull::Down => PULL_DOWN,
+        };
+        self.modify(
+            GPIO_PUP_PDN_CNTRL_REG0 + 4 * (pin / 16),
+            2 * (pin % 16),
+            2,
+            value,
+        );
+        Ok(())
+    }
+
+    fn read(&self, pin: usize) -> DevResult<bool> {
+        check_pin(pin)?;
+        let level = self.regs.read_reg(GPLEV0 + 4 * (pin / 32));
+        Ok(level & (1 << (pin % 32)) != 0)
+    }
+
+    fn write(&mut self, pin: usize, value: bool) -> DevResult {
+        check_pin(pin)?;
+        // writing 0 bits to the set and clear registers has no effect
+        let offset = if value { GPSET0 } else { GPCLR0 };
+        self.regs
+            .write_reg(offset + 4 * (pin / 32), 1 << (pin % 32));
+        Ok(())
+    }
+}
+
+fn check_pin(pin: usize) -> DevResult {
+    if pin < NUM_PINS {
+        Ok(())
+    } else {
+        Err(DevError::InvalidParam)
+    }
+}
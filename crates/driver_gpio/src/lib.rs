@@ -0,0 +1,65 @@
+//! Common traits and types for GPIO controller drivers.
+//!
+//! The drivers of memory-mapped controllers access the registers through
+//! [`RegisterAccess`](driver_common::RegisterAccess), so they are tested on
+//! the host with mock controllers (`cargo test -p driver_gpio`).
+
+#![cfg_attr(not(test), no_std)]
+#![feature(doc_auto_cfg)]
+
+#[cfg(feature = "bcm2711")]
+pub mod bcm2711;
+
+#[cfg(all(test, feature = "bcm2711"))]
+mod tests;
+
+#[doc(no_inline)]
+pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+/// The function of a GPIO pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinMode {
+    /// The pin is an input.
+    Input,
+    /// The pin is an output.
+    Output,
+    /// The pin is connected to a peripheral (e.g., an I2C or SPI controller),
+    /// the alternate functions are numbered as in the datasheet.
+    Alt(u8),
+}
+
+/// The internal pull resistor of a GPIO pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pull {
+    /// No pull resistor, the pin floats.
+    None,
+    /// Pulled up to the supply voltage.
+    Up,
+    /// Pulled down to the ground.
+    Down,
+}
+
+/// Operations that require a GPIO controller driver to implement.
+///
+/// The pins are numbered from 0 to [`GpioDriverOps::num_pins`] - 1, the
+/// operations on other pins fail with [`DevError::InvalidParam`].
+pub trait GpioDriverOps: BaseDriverOps {
+    /// The number of pins of the controller.
+    fn num_pins(&self) -> usize;
+
+    /// Returns the function of the pin.
+    fn mode(&self, pin: usize) -> DevResult<PinMode>;
+
+    /// Changes the function of the pin.
+    fn set_mode(&mut self, pin: usize, mode: PinMode) -> DevResult;
+
+    /// Changes the pull resistor of the pin.
+    fn set_pull(&mut self, pin: usize, pull: Pull) -> DevResult;
+
+    /// Reads the level of the pin, `true` for high, whatever its function is.
+    fn read(&self, pin: usize) -> DevResult<bool>;
+
+    /// Drives the pin high (`true`) or low. It takes effect when the pin is
+    /// an output.
+    fn write(&mut self, pin: usize, value: bool) -> DevResult;
+}
@@ -0,0 +1,114 @@
+use std::sync::{Arc, Mutex};
+
+use driver_common::RegisterAccess;
+
+use crate::bcm2711::{Bcm2711Gpio, NUM_PINS};
+use crate::{DevError, GpioDriverOps, PinMode, Pull};
+
+const GPSET0: usize = 0x1c;
+const GPCLR0: usize = 0x28;
+const GPLEV0: usize = 0x34;
+
+/// A mock BCM2711 GPIO controller, whose set and clear registers change the
+/// levels of the pins.
+#[derive(Clone)]
+struct MockGpio(Arc<Mutex<Vec<u32>>>);
+
+impl MockGpio {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(vec![0; 64])))
+    }
+
+    fn reg(&self, offset: usize) -> u32 {
+        self.0.lock().unwrap()[offset / 4]
+    }
+
+    /// Drives the pin from outside, like a button.
+    fn drive(&self, pin: usize, value: bool) {
+        let mut regs = self.0.lock().unwrap();
+        let level = &mut regs[(GPLEV0 + 4 * (pin / 32)) / 4];
+        if value {
+            *level |= 1 << (pin % 32);
+        } else {
+            *level &= !(1 << (pin % 32));
+        }
+    }
+}
+
+impl RegisterAccess for MockGpio {
+    fn read_reg(&self, offset: usize) -> u32 {
+        match offset {
+            GPSET0 | 0x20 | GPCLR0 | 0x2c => 0, // write-only
+            _ => self.reg(offset),
+        }
+    }
+
+    fn write_reg(&self, offset: usize, value: u32) {
+        let mut regs = self.0.lock().unwrap();
+        match offset {
+            GPSET0 | 0x20 => regs[(GPLEV0 + offset - GPSET0) / 4] |= value,
+            GPCLR0 | 0x2c => regs[(GPLEV0 + offset - GPCLR0) / 4] &= !value,
+            _ => regs[offset / 4] = value,
+        }
+    }
+}
+
+#[test]
+fn test_mode() {
+    let mock = MockGpio::new();
+    let mut gpio = Bcm2711Gpio::with_registers(mock.clone());
+    assert_eq!(gpio.num_pins(), NUM_PINS);
+    assert_eq!(gpio.mode(17).unwrap(), PinMode::Input);
+
+    gpio.set_mode(17, PinMode::Output).unwrap();
+    gpio.set_mode(2, PinMode::Alt(0)).unwrap();
+    gpio.set_mode(3, PinMode::Alt(5)).unwrap();
+    assert_eq!(gpio.mode(17).unwrap(), PinMode::Output);
+    assert_eq!(gpio.mode(2).unwrap(), PinMode::Alt(0));
+    assert_eq!(gpio.mode(3).unwrap(), PinMode::Alt(5));
+    // GPFSEL1 bits 23:21, GPFSEL0 bits 8:6 and 11:9
+    assert_eq!(mock.reg(0x04), 0b001 << 21);
+    assert_eq!(mock.reg(0x00), 0b100 << 6 | 0b010 << 9);
+
+    gpio.set_mode(17, PinMode::Input).unwrap();
+    assert_eq!(mock.reg(0x04), 0);
+    assert!(matches!(
+        gpio.set_mode(4, PinMode::Alt(6)),
+        Err(DevError::InvalidParam)
+    ));
+    assert!(matches!(
+        gpio.set_mode(NUM_PINS, PinMode::Output),
+        Err(DevError::InvalidParam)
+    ));
+}
+
+#[test]
+fn test_pull() {
+    let mock = MockGpio::new();
+    let mut gpio = Bcm2711Gpio::with_registers(mock.clone());
+    gpio.set_pull(0, Pull::Up).unwrap();
+    gpio.set_pull(15, Pull::Down).unwrap();
+    gpio.set_pull(57, Pull::Up).unwrap();
+    assert_eq!(mock.reg(0xe4), 0b01 | 0b10 << 30);
+    assert_eq!(mock.reg(0xf0), 0b01 << 18);
+
+    gpio.set_pull(15, Pull::None).unwrap();
+    assert_eq!(mock.reg(0xe4), 0b01);
+}
+
+#[test]
+fn test_read_write() {
+    let mock = MockGpio::new();
+    let mut gpio = Bcm2711Gpio::with_registers(mock.clone());
+    gpio.set_mode(40, PinMode::Output).unwrap();
+    gpio.write(40, true).unwrap();
+    assert!(gpio.read(40).unwrap());
+    assert_eq!(mock.reg(GPLEV0 + 4), 1 << 8);
+    gpio.write(40, false).unwrap();
+    assert!(!gpio.read(40).unwrap());
+
+    mock.drive(5, true);
+    assert!(gpio.read(5).unwrap());
+    assert!(!gpio.read(6).unwrap());
+    assert!(matches!(gpio.read(NUM_PINS), Err(DevError::InvalidParam)));
+}
@@ -0,0 +1,228 @@
+//! Driver for the I2C controllers (Broadcom Serial Controllers, BSC) of the
+//! BCM2711 (Raspberry Pi 4).
+//!
+//! The transfers are polled, so the controller's interrupts are not used.
+
+use driver_common::{MmioRegisters, RegisterAccess};
+
+use crate::{BaseDriverOps, DevError, DevResult, DeviceType, I2cDriverOps, MAX_ADDR};
+
+// Registers, see the BCM2711 ARM Peripherals datasheet.
+const REG_C: usize = 0x00;
+const REG_S: usize = 0x04;
+const REG_DLEN: usize = 0x08;
+const REG_A: usize = 0x0c;
+const REG_FIFO: usize = 0x10;
+const REG_DIV: usize = 0x14;
+const REG_DEL: usize = 0x18;
+
+// Fields of the control register.
+const C_I2CEN: u32 = 1 << 15;
+const C_ST: u32 = 1 << 7;
+const C_CLEAR: u32 = 1 << 4;
+const C_READ: u32 = 1 << 0;
+
+// Fields of the status register, `CLKT`, `ERR` and `DONE` are cleared by
+// writing 1.
+const S_CLKT: u32 = 1 << 9;
+const S_ERR: u32 = 1 << 8;
+const S_RXD: u32 = 1 << 5;
+const S_TXD: u32 = 1 << 4;
+const S_DONE: u32 = 1 << 1;
+const S_TA: u32 = 1 << 0;
+
+/// The depth of the FIFO, in bytes.
+const FIFO_DEPTH: usize = 16;
+/// The largest data length of a transfer.
+const MAX_DLEN: usize = 0xffff;
+/// The largest clock divider.
+const MAX_DIV: u32 = 0xfffe;
+/// How many times the status is polled before a transfer is given up, in case
+/// the controller hangs.
+const POLL_LIMIT: usize = 10_000_000;
+
+/// The BCM2711 I2C controller driver.
+pub struct Bcm2711I2c<R: RegisterAccess = MmioRegisters> {
+    regs: R,
+    clock_hz: u32,
+    bus_speed: u32,
+}
+
+impl Bcm2711I2c {
+    /// Creates a driver of the controller whose registers are mapped at
+    /// `base`, and whose input clock (the core clock) is `clock_hz`. The
+    /// controller is not touched until [`Self::init`].
+    ///
+    /// # Safety
+    ///
+    /// `base` must be the mapped registers of a BCM2711 I2C controller, which
+    /// is not used by others.
+    pub unsafe fn new(base: *mut u8, clock_hz: u32) -> Self {
+        Self::with_registers(MmioRegisters::new(base), clock_hz)
+    }
+}
+
+impl<R: RegisterAccess> Bcm2711I2c<R> {
+    /// Creates a driver that accesses the registers by `regs`.
+    pub const fn with_registers(regs: R, clock_hz: u32) -> Self {
+        Self {
+            regs,
+            clock_hz,
+            bus_speed: 0,
+        }
+    }
+
+    /// Resets the controller, and sets the bus speed to `bus_speed` Hz.
+    pub fn init(&mut self, bus_speed: u32) -> DevResult {
+        self.regs.write_reg(REG_C, C_CLEAR);
+        self.regs.write_reg(REG_S, S_CLKT | S_ERR | S_DONE);
+        self.set_bus_speed(bus_speed)
+    }
+
+    /// Clears the FIFO and the status, and starts a transfer of `len` bytes.
+    fn start(&self, addr: u8, len: usize, read: bool) {
+        self.regs.write_reg(REG_S, S_CLKT | S_ERR | S_DONE);
+        self.regs.write_reg(REG_A, addr as u32);
+        self.regs.write_reg(REG_DLEN, len as u32);
+        let read = if read { C_READ } else { 0 };
+        self.regs.write_reg(REG_C, C_I2CEN | C_ST | read);
+    }
+
+    /// Waits until the transfer is done, and reads the received bytes into
+    /// `buf`.
+    fn receive(&self, buf: &mut [u8]) -> DevResult {
+        let mut len = 0;
+        for _ in 0..POLL_LIMIT {
+            let status = self.regs.read_reg(REG_S);
+            while len < buf.len() && self.regs.read_reg(REG_S) & S_RXD != 0 {
+                buf[len] = self.regs.read_reg(REG_FIFO) as u8;
+                len += 1;
+            }
+            if status & S_DONE != 0 {
+                return self.finish(status, len == buf.len());
+            }
+        }
+        self.finish(0, false)
+    }
+
+    /// Waits until the transfer is done, and writes the bytes of `buf` that
+    /// are not in the FIFO yet.
+    fn send(&self, buf: &[u8]) -> DevResult {
+        let mut len = 0;
+        for _ in 0..POLL_LIMIT {
+            let status = self.regs.read_reg(REG_S);
+            while len < buf.len() && self.regs.read_reg(REG_S) & S_TXD != 0 {
+                self.regs.write_reg(REG_FIFO, buf[len] as u32);
+                len += 1;
+            }
+            if status & S_DONE != 0 {
+                return self.finish(status, len == buf.len());
+            }
+        }
+        self.finish(0, false)
+    }
+
+    /// Stops the controller after a transfer, which succeeded if it's done
+    /// with all the bytes and without errors.
+    fn finish(&self, status: u32, complete: bool) -> DevResult {
+        self.regs.write_reg(REG_C, C_CLEAR);
+        self.regs.write_reg(REG_S, S_CLKT | S_ERR | S_DONE);
+        if status & S_DONE == 0 || status & (S_ERR | S_CLKT) != 0 || !complete {
+            return Err(DevError::Io);
+        }
+        Ok(())
+    }
+}
+
+impl<R: RegisterAccess> BaseDriverOps for Bcm2711I2c<R> {
+    fn device_name(&self) -> &str {
+        "bcm2711-i2c"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::I2c
+    }
+}
+
+impl<R: RegisterAccess> I2cDriverOps for Bcm2711I2c<R> {
+    fn bus_speed(&self) -> u32 {
+        self.bus_speed
+    }
+
+    fn set_bus_speed(&mut self, hz: u32) -> DevResult {
+        if hz == 0 {
+            return Err(DevError::InvalidParam);
+        }
+        // the divider is even, and the bus speed is no faster than requested
+        let div = self.clock_hz.div_ceil(hz).next_multiple_of(2);
+        if !(2..=MAX_DIV).contains(&div) {
+            return Err(DevError::InvalidParam);
+        }
+        // delay the data changes after the falling and the rising edges of
+        // SCL, as Linux does
+        let fedl = (div / 16).max(1);
+        let redl = (div / 4).max(1);
+        self.regs.write_reg(REG_DIV, div);
+        self.regs.write_reg(REG_DEL, fedl << 16 | redl);
+        self.bus_speed = self.clock_hz / div;
+        Ok(())
+    }
+
+    fn read(&mut self, addr: u8, buf: &mut [u8]) -> DevResult {
+        check_transfer(addr, buf.len())?;
+        self.regs.write_reg(REG_C, C_CLEAR);
+        self.start(addr, buf.len(), true);
+        self.receive(buf)
+    }
+
+    fn write(&mut self, addr: u8, buf: &[u8]) -> DevResult {
+        check_transfer(addr, buf.len())?;
+        self.regs.write_reg(REG_C, C_CLEAR);
+        self.start(addr, buf.len(), false);
+        self.send(buf)
+    }
+
+    fn write_read(&mut self, addr: u8, wbuf: &[u8], rbuf: &mut [u8]) -> DevResult {
+        check_transfer(addr, rbuf.len())?;
+        // the bytes to write must fit in the FIFO, as the read is set up
+        // before the write ends
+        if wbuf.is_empty() || wbuf.len() > FIFO_DEPTH {
+            return Err(DevError::InvalidParam);
+        }
+        self.regs.write_reg(REG_C, C_CLEAR);
+        for &b in wbuf {
+            self.regs.write_reg(REG_FIFO, b as u32);
+        }
+        self.start(addr, wbuf.len(), false);
+
+        // Once the write is active, starting a read makes the controller send
+        // a repeated start instead of a stop after the write. If the write is
+        // already done, the read follows a stop.
+        let mut status = 0;
+        for _ in 0..POLL_LIMIT {
+            status = self.regs.read_reg(REG_S);
+            if status & (S_TA | S_DONE) != 0 {
+                break;
+            }
+        }
+        if status & S_DONE != 0 {
+            if status & (S_ERR | S_CLKT) != 0 {
+                return self.finish(status, false);
+            }
+            self.regs.write_reg(REG_S, S_DONE);
+        } else if status & S_TA == 0 {
+            return self.finish(status, false);
+        }
+        self.regs.write_reg(REG_DLEN, rbuf.len() as u32);
+        self.regs.write_reg(REG_C, C_I2CEN | C_ST | C_READ);
+        self.receive(rbuf)
+    }
+}
+
+fn check_transfer(addr: u8, len: usize) -> DevResult {
+    if addr > MAX_ADDR || !(1..=MAX_DLEN).contains(&len) {
+        Err(DevError::InvalidParam)
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+//! Common traits for I2C controller drivers.
+//!
+//! The drivers of memory-mapped controllers access the registers through
+//! [`RegisterAccess`](driver_common::RegisterAccess), so they are tested on
+//! the host with mock controllers (`cargo test -p driver_i2c`).
+
+#![cfg_attr(not(test), no_std)]
+#![feature(doc_auto_cfg)]
+
+#[cfg(feature = "bcm2711")]
+pub mod bcm2711;
+
+#[cfg(all(test, feature = "bcm2711"))]
+mod tests;
+
+#[doc(no_inline)]
+pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+/// The largest 7-bit target address.
+pub const MAX_ADDR: u8 = 0x7f;
+
+/// The standard-mode bus speed (100 kHz).
+pub const STANDARD_SPEED: u32 = 100_000;
+
+/// Operations that require an I2C controller driver to implement.
+///
+/// The controller is the only master of the bus, and the targets are
+/// addressed by 7-bit addresses (up to [`MAX_ADDR`]). The transfers wait
+/// until they complete, and fail with [`DevError::Io`] if the target doesn't
+/// acknowledge.
+pub trait I2cDriverOps: BaseDriverOps {
+    /// The clock rate of the bus, in Hz.
+    fn bus_speed(&self) -> u32;
+
+    /// Changes the clock rate of the bus, in Hz.
+    fn set_bus_speed(&mut self, hz: u32) -> DevResult;
+
+    /// Reads `buf.len()` bytes from the target.
+    fn read(&mut self, addr: u8, buf: &mut [u8]) -> DevResult;
+
+    /// Writes `buf` to the target.
+    fn write(&mut self, addr: u8, buf: &[u8]) -> DevResult;
+
+    /// Writes `wbuf` to the target, then reads `rbuf.len()` bytes from it
+    /// after a repeated start condition, e.g., to read the registers of a
+    /// sensor.
+    fn write_read(&mut self, addr: u8, wbuf: &[u8], rbuf: &mut [u8]) -> DevResult;
+}
@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use driver_common::RegisterAccess;
+
+use crate::bcm2711::Bcm2711I2c;
+use crate::{DevError, I2cDriverOps};
+
+const EEPROM_ADDR: u8 = 0x50;
+
+/// An EEPROM-like target, the first byte written sets the address of the
+/// following reads and writes.
+struct Eeprom {
+    mem: [u8; 256],
+    ptr: Option<u8>,
+}
+
+impl Eeprom {
+    fn write(&mut self, byte: u8) {
+        match self.ptr {
+            None => self.ptr = Some(byte),
+            Some(ptr) => {
+                self.mem[ptr as usize] = byte;
+                self.ptr = Some(ptr.wrapping_add(1));
+            }
+        }
+    }
+
+    fn read(&mut self) -> u8 {
+        let ptr = self.ptr.unwrap_or(0);
+        self.ptr = Some(ptr.wrapping_add(1));
+        self.mem[ptr as usize]
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    c: u32,
+    s: u32,
+    dlen: u32,
+    a: u32,
+    div: u32,
+    del: u32,
+    fifo: VecDeque<u8>,
+    /// The bytes left in the active transfer.
+    remaining: u32,
+    eeprom: Option<Eeprom>,
+}
+
+/// A mock BCM2711 I2C controller with an EEPROM at [`EEPROM_ADDR`]. The
+/// transfers complete as soon as the FIFO allows.
+#[derive(Clone)]
+struct MockBsc(Arc<Mutex<MockState>>);
+
+impl MockBsc {
+    fn new() -> Self {
+        let state = MockState {
+            eeprom: Some(Eeprom {
+                mem: [0; 256],
+                ptr: None,
+            }),
+            ..Default::default()
+        };
+        Self(Arc::new(Mutex::new(state)))
+    }
+
+    fn with_eeprom<T>(&self, f: impl FnOnce(&mut Eeprom) -> T) -> T {
+        f(self.0.lock().unwrap().eeprom.as_mut().unwrap())
+    }
+}
+
+impl MockState {
+    fn start(&mut self) {
+        if self.a != EEPROM_ADDR as u32 {
+            // not acknowledged
+            self.s |= 1 << 8 | 1 << 1;
+            return;
+        }
+        let eeprom = self.eeprom.as_mut().unwrap();
+        if self.c & 1 == 0 {
+            // a new write sets the address again
+            eeprom.ptr = None;
+        }
+        self.remaining = self.dlen;
+        self.s |= 1;
+        self.step();
+    }
+
+    /// Moves the bytes between the FIFO and the target.
+    fn step(&mut self) {
+        if self.s & 1 == 0 {
+            return;
+        }
+        let eeprom = self.eeprom.as_mut().unwrap();
+        if self.c & 1 != 0 {
+            while self.remaining > 0 && self.fifo.len() < 16 {
+                self.fifo.push_back(eeprom.read());
+                self.remaining -= 1;
+            }
+        } else {
+            while self.remaining > 0 {
+                let Some(byte) = self.fifo.pop_front() else {
+                    break;
+                };
+                eeprom.write(byte);
+                self.remaining -= 1;
+            }
+        }
+        if self.remaining == 0 {
+            self.s = (self.s & !1) | 1 << 1;
+        }
+    }
+
+    fn status(&self) -> u32 {
+        let mut s = self.s;
+        if !self.fifo.is_empty() {
+            s |= 1 << 5; // RXD
+        }
+        if self.fifo.len() < 16 {
+            s |= 1 << 4; // TXD
+        }
+        s
+    }
+}
+
+impl RegisterAccess for MockBsc {
+    fn read_reg(&self, offset: usize) -> u32 {
+        let mut state = self.0.lock().unwrap();
+        match offset {
+            0x00 => state.c,
+            0x04 => state.status(),
+            0x08 => state.dlen,
+            0x0c => state.a,
+            0x10 => {
+                let byte = state.fifo.pop_front().unwrap_or(0);
+                state.step();
+                byte as u32
+            }
+            0x14 => state.div,
+            0x18 => state.del,
+            _ => 0,
+        }
+    }
+
+    fn write_reg(&self, offset: usize, value: u32) {
+        let mut state = self.0.lock().unwrap();
+        match offset {
+            0x00 => {
+                if value & 0b11 << 4 != 0 {
+                    state.fifo.clear();
+                }
+                state.c = value & !(1 << 7 | 0b11 << 4);
+                if value & (1 << 15 | 1 << 7) == 1 << 15 | 1 << 7 {
+                    state.start();
+                }
+            }
+            0x04 => state.s &= !(value & (1 << 9 | 1 << 8 | 1 << 1)),
+            0x08 => state.dlen = value,
+            0x0c => state.a = value,
+            0x10 => {
+                state.fifo.push_back(value as u8);
+                state.step();
+            }
+            0x14 => state.div = value,
+            0x18 => state.del = value,
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_bus_speed() {
+    let mock = MockBsc::new();
+    let mut i2c = Bcm2711I2c::with_registers(mock.clone(), 500_000_000);
+    i2c.init(100_000).unwrap();
+    assert_eq!(mock.read_reg(0x14), 5000);
+    assert_eq!(i2c.bus_speed(), 100_000);
+
+    // rounded up to an even divider, so no faster than requested
+    i2c.set_bus_speed(400_000).unwrap();
+    assert_eq!(mock.read_reg(0x14), 1250);
+    i2c.set_bus_speed(3_000_000).unwrap();
+    assert_eq!(mock.read_reg(0x14), 168);
+    assert!(i2c.bus_speed() <= 3_000_000);
+
+    assert!(matches!(i2c.set_bus_speed(0), Err(DevError::InvalidParam)));
+    assert!(matches!(
+        i2c.set_bus_speed(1000),
+        Err(DevError::InvalidParam)
+    ));
+}
+
+#[test]
+fn test_write_read() {
+    let mock = MockBsc::new();
+    let mut i2c = Bcm2711I2c::with_registers(mock.clone(), 500_000_000);
+    i2c.init(100_000).unwrap();
+
+    // more than the FIFO holds
+    let data: Vec<u8> = (0..40).collect();
+    let mut wbuf = vec![0x10];
+    wbuf.extend_from_slice(&data);
+    i2c.write(EEPROM_ADDR, &wbuf).unwrap();
+    mock.with_eeprom(|e| assert_eq!(&e.mem[0x10..0x38], &data[..]));
+
+    let mut rbuf = [0; 40];
+    i2c.write_read(EEPROM_ADDR, &[0x10], &mut rbuf).unwrap();
+    assert_eq!(&rbuf[..], &data[..]);
+
+    // continues from where the last read stops
+    mock.with_eeprom(|e| e.mem[0x38] = 0xaa);
+    let mut rbuf = [0; 1];
+    i2c.read(EEPROM_ADDR, &mut rbuf).unwrap();
+    assert_eq!(rbuf, [0xaa]);
+}
+
+#[test]
+fn test_errors() {
+    let mock = MockBsc::new();
+    let mut i2c = Bcm2711I2c::with_registers(mock, 500_000_000);
+    i2c.init(100_000).unwrap();
+
+    let mut rbuf = [0; 4];
+    assert!(matches!(i2c.write(0x51, &[0]), Err(DevError::Io)));
+    assert!(matches!(i2c.read(0x51, &mut rbuf), Err(DevError::Io)));
+    assert!(matches!(
+        i2c.write_read(0x51, &[0], &mut rbuf),
+        Err(DevError::Io)
+    ));
+    // the controller recovers after the errors
+    i2c.write_read(EEPROM_ADDR, &[0], &mut rbuf).unwrap();
+
+    assert!(matches!(i2c.write(0x80, &[0]), Err(DevError::InvalidParam)));
+    assert!(matches!(
+        i2c.write(EEPROM_ADDR, &[]),
+        Err(DevError::InvalidParam)
+    ));
+    assert!(matches!(
+        i2c.write_read(EEPROM_ADDR, &[0; 17], &mut rbuf),
+        Err(DevError::InvalidParam)
+    ));
+}
@@ -0,0 +1,37 @@
+//! Common traits and types for input device drivers (i.e. keyboards, mice).
+
+#![no_std]
+
+#[doc(no_inline)]
+pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+/// Event types, the same as the `EV_*` constants of Linux evdev.
+pub mod event_type {
+    /// Marks the end of a group of events.
+    pub const SYN: u16 = 0x00;
+    /// Key or button state changes.
+    pub const KEY: u16 = 0x01;
+    /// Relative axis changes (e.g., mouse movements).
+    pub const REL: u16 = 0x02;
+    /// Absolute axis changes (e.g., touch screen coordinates).
+    pub const ABS: u16 = 0x03;
+}
+
+/// An input event, in the format of Linux evdev.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    /// The event type, one of the constants in [`event_type`].
+    pub event_type: u16,
+    /// The key code or the axis, depending on the event type.
+    pub code: u16,
+    /// The key state (0 for released, 1 for pressed, 2 for repeated), or the
+    /// axis value.
+    pub value: u32,
+}
+
+/// Operations that require an input device driver to implement.
+pub trait InputDriverOps: BaseDriverOps {
+    /// Pops the earliest pending event, or returns [`DevError::Again`] if
+    /// there is none.
+    fn read_event(&mut self) -> DevResult<InputEvent>;
+}
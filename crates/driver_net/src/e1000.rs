@@ -0,0 +1,501 @@
+//! Driver for the Intel 8254x/82574 gigabit ethernet controllers (e1000 and
+//! e1000e), as emulated by QEMU, VirtualBox and VMware.
+//!
+//! The driver uses one receive and one transmit ring of legacy descriptors.
+//! The packet buffers are allocated from a [`NetBufPool`] and are handed to
+//! the NIC directly, so no data is copied.
+
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use core::sync::atomic::{fence, Ordering};
+use core::time::Duration;
+
+use alloc::sync::Arc;
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+use crate::{EthernetAddress, NetBuf, NetBufBox, NetBufPool, NetBufPtr, NetDriverOps};
+
+extern crate alloc;
+
+/// Physical address for DMA.
+pub type PhysAddr = usize;
+
+/// The memory page size used for the descriptor rings.
+pub const PAGE_SIZE: usize = 0x1000;
+
+/// The PCI vendor ID of Intel.
+pub const INTEL_VEND: u16 = 0x8086;
+
+/// The PCI device IDs of the supported controllers.
+pub const E1000_DEVICE_IDS: &[u16] = &[
+    0x100e, // 82540EM, the default NIC of QEMU
+    0x100f, // 82545EM, the default NIC of VMware
+    0x10d3, // 82574L, QEMU `e1000e`
+];
+
+/// The platform functions used by the e1000 driver.
+///
+/// # Safety
+///
+/// The allocated DMA memory must be physically contiguous, and accessible by
+/// the device at the returned physical address. So must be the memory of the
+/// [`NetBufPool`] translated by [`E1000Hal::virt_to_phys`].
+pub unsafe trait E1000Hal {
+    /// Allocates `pages` contiguous physical pages for DMA, returns the
+    /// physical address and the virtual address of the first page, or a zero
+    /// physical address if it fails.
+    fn dma_alloc(pages: usize) -> (PhysAddr, NonNull<u8>);
+
+    /// Deallocates the DMA memory allocated by [`E1000Hal::dma_alloc`].
+    ///
+    /// # Safety
+    ///
+    /// The memory must not be accessed by the device anymore.
+    unsafe fn dma_dealloc(paddr: PhysAddr, vaddr: NonNull<u8>, pages: usize) -> i32;
+
+    /// Translates the virtual address of a packet buffer to the physical
+    /// address seen by the device.
+    fn virt_to_phys(vaddr: usize) -> PhysAddr;
+
+    /// Waits for the given duration.
+    fn busy_wait(duration: Duration);
+}
+
+// registers
+const REG_CTRL: usize = 0x0000;
+const REG_CTRL_EXT: usize = 0x0018;
+const REG_ICR: usize = 0x00c0;
+const REG_IMS: usize = 0x00d0;
+const REG_IMC: usize = 0x00d8;
+const REG_IVAR: usize = 0x00e4; // 82574 only
+const REG_RCTL: usize = 0x0100;
+const REG_TCTL: usize = 0x0400;
+const REG_TIPG: usize = 0x0410;
+const REG_RDBAL: usize = 0x2800;
+const REG_RDBAH: usize = 0x2804;
+const REG_RDLEN: usize = 0x2808;
+const REG_RDH: usize = 0x2810;
+const REG_RDT: usize = 0x2818;
+const REG_TDBAL: usize = 0x3800;
+const REG_TDBAH: usize = 0x3804;
+const REG_TDLEN: usize = 0x3808;
+const REG_TDH: usize = 0x3810;
+const REG_TDT: usize = 0x3818;
+const REG_MTA: usize = 0x5200;
+const REG_RAL0: usize = 0x5400;
+const REG_RAH0: usize = 0x5404;
+
+const MTA_ENTRIES: usize = 128;
+
+const CTRL_LRST: u32 = 1 << 3;
+const CTRL_ASDE: u32 = 1 << 5;
+const CTRL_SLU: u32 = 1 << 6;
+const CTRL_ILOS: u32 = 1 << 7;
+const CTRL_RST: u32 = 1 << 26;
+const CTRL_VME: u32 = 1 << 30;
+const CTRL_PHY_RST: u32 = 1 << 31;
+
+const CTRL_EXT_PBA_CLR: u32 = 1 << 31;
+
+const RAH_AV: u32 = 1 << 31;
+
+const RCTL_EN: u32 = 1 << 1;
+const RCTL_MPE: u32 = 1 << 4;
+const RCTL_BAM: u32 = 1 << 15;
+const RCTL_BSIZE_2048: u32 = 0 << 16;
+const RCTL_SECRC: u32 = 1 << 26;
+
+const TCTL_EN: u32 = 1 << 1;
+const TCTL_PSP: u32 = 1 << 3;
+const TCTL_CT: u32 = 0x0f << 4;
+const TCTL_COLD: u32 = 0x40 << 12; // full duplex
+/// The recommended inter packet gap of the IEEE 802.3 standard.
+const TIPG_DEFAULT: u32 = 10 | (8 << 10) | (6 << 20);
+
+const ICR_TXDW: u32 = 1 << 0;
+const ICR_LSC: u32 = 1 << 2;
+const ICR_RXDMT0: u32 = 1 << 4;
+const ICR_RXO: u32 = 1 << 6;
+const ICR_RXT0: u32 = 1 << 7;
+
+const IVAR_VALID: u32 = 1 << 3;
+const IVAR_RX0_SHIFT: u32 = 0;
+const IVAR_TX0_SHIFT: u32 = 8;
+const IVAR_OTHER_SHIFT: u32 = 16;
+/// Interrupt on every transmit descriptor write-back.
+const IVAR_TX_INT_EVERY_WB: u32 = 1 << 31;
+
+const DESC_STATUS_DD: u8 = 1 << 0;
+const DESC_STATUS_EOP: u8 = 1 << 1;
+const TX_CMD_EOP: u8 = 1 << 0;
+const TX_CMD_IFCS: u8 = 1 << 1;
+const TX_CMD_RS: u8 = 1 << 3;
+
+/// The size of the receive buffers configured in `RCTL`, which is also the
+/// length of the buffers in the pool.
+const RX_BUFFER_SIZE: usize = 2048;
+
+/// A legacy receive descriptor.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct RxDesc {
+    addr: u64,
+    len: u16,
+    checksum: u16,
+    status: u8,
+    errors: u8,
+    special: u16,
+}
+
+/// A legacy transmit descriptor.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct TxDesc {
+    addr: u64,
+    len: u16,
+    cso: u8,
+    cmd: u8,
+    status: u8,
+    css: u8,
+    special: u16,
+}
+
+/// The e1000 NIC device driver.
+///
+/// `QS` is the size of both descriptor rings, it must be a multiple of 8
+/// between 8 and 4096.
+///
+/// The receive descriptors from `rx_next` (inclusive) to `rx_tail`
+/// (exclusive) are owned by the NIC, and so are the transmit descriptors from
+/// `tx_clean` to `tx_tail`. One descriptor of each ring is always left unused
+/// to tell a full ring from an empty one.
+pub struct E1000Nic<H: E1000Hal, const QS: usize> {
+    regs: usize,
+    mac: [u8; 6],
+    dma_paddr: PhysAddr,
+    dma_vaddr: NonNull<u8>,
+    rx_buffers: [Option<NetBufBox>; QS],
+    tx_buffers: [Option<NetBufBox>; QS],
+    rx_next: usize,
+    rx_tail: usize,
+    tx_clean: usize,
+    tx_tail: usize,
+    buf_pool: Arc<NetBufPool>,
+    irq_num: Option<usize>,
+    _hal: PhantomData<H>,
+}
+
+unsafe impl<H: E1000Hal, const QS: usize> Send for E1000Nic<H, QS> {}
+unsafe impl<H: E1000Hal, const QS: usize> Sync for E1000Nic<H, QS> {}
+
+impl<H: E1000Hal, const QS: usize> E1000Nic<H, QS> {
+    const RING_SIZE: usize = QS * core::mem::size_of::<RxDesc>();
+    const DMA_PAGES: usize = (2 * Self::RING_SIZE).div_ceil(PAGE_SIZE);
+
+    /// Creates a new driver instance and initializes the NIC, or returns an
+    /// error if any step fails.
+    ///
+    /// `regs` is the virtual address of the memory mapped registers (BAR 0).
+    pub fn init(regs: usize) -> DevResult<Self> {
+        if QS < 8 || QS > 4096 || QS % 8 != 0 {
+            return Err(DevError::InvalidParam);
+        }
+        let buf_pool = NetBufPool::new(2 * QS, RX_BUFFER_SIZE)?;
+        let (dma_paddr, dma_vaddr) = H::dma_alloc(Self::DMA_PAGES);
+        if dma_paddr == 0 {
+            return Err(DevError::NoMemory);
+        }
+        let dma_size = Self::DMA_PAGES * PAGE_SIZE;
+        unsafe { dma_vaddr.as_ptr().write_bytes(0, dma_size) };
+
+        const NONE_BUF: Option<NetBufBox> = None;
+        let mut nic = Self {
+            regs,
+            mac: [0; 6],
+            dma_paddr,
+            dma_vaddr,
+            rx_buffers: [NONE_BUF; QS],
+            tx_buffers: [NONE_BUF; QS],
+            rx_next: 0,
+            rx_tail: 0,
+            tx_clean: 0,
+            tx_tail: 0,
+            buf_pool,
+            irq_num: None,
+            _hal: PhantomData,
+        };
+        nic.reset()?;
+        nic.init_rx()?;
+        nic.init_tx();
+        Ok(nic)
+    }
+
+    /// Routes the receive, transmit and link status interrupts to `irq_num`,
+    /// and enables them.
+    ///
+    /// The interrupt (INTx or MSI) must be already configured in the PCI
+    /// configuration space to raise `irq_num`.
+    pub fn enable_interrupts(&mut self, irq_num: usize) {
+        self.write_reg(REG_ICR, u32::MAX);
+        self.write_reg(
+            REG_IMS,
+            ICR_TXDW | ICR_LSC | ICR_RXDMT0 | ICR_RXO | ICR_RXT0,
+        );
+        self.irq_num = Some(irq_num);
+    }
+
+    /// Maps all the interrupt causes to the MSI-X vector 0, and enables them
+    /// as [`E1000Nic::enable_interrupts`] does.
+    ///
+    /// Only the 82574 supports MSI-X. The entry 0 of its MSI-X table must be
+    /// already configured to raise `irq_num`.
+    pub fn enable_msix_interrupts(&mut self, irq_num: usize) {
+        let vector0 = IVAR_VALID; // the vector number is 0
+        self.write_reg(
+            REG_IVAR,
+            vector0 << IVAR_RX0_SHIFT
+                | vector0 << IVAR_TX0_SHIFT
+                | vector0 << IVAR_OTHER_SHIFT
+                | IVAR_TX_INT_EVERY_WB,
+        );
+        // clear the pending bits on reading ICR in the MSI-X mode
+        self.write_reg(REG_CTRL_EXT, self.read_reg(REG_CTRL_EXT) | CTRL_EXT_PBA_CLR);
+        self.enable_interrupts(irq_num);
+    }
+
+    fn read_reg(&self, offset: usize) -> u32 {
+        // Safe because `regs` is the mapped BAR 0 of the NIC.
+        unsafe { ((self.regs + offset) as *const u32).read_volatile() }
+    }
+
+    fn write_reg(&self, offset: usize, value: u32) {
+        // Safe because `regs` is the mapped BAR 0 of the NIC.
+        unsafe { ((self.regs + offset) as *mut u32).write_volatile(value) }
+    }
+
+    fn rx_desc(&self, idx: usize) -> *mut RxDesc {
+        unsafe { (self.dma_vaddr.as_ptr() as *mut RxDesc).add(idx) }
+    }
+
+    fn tx_desc(&self, idx: usize) -> *mut TxDesc {
+        let ring = unsafe { self.dma_vaddr.as_ptr().add(Self::RING_SIZE) as *mut TxDesc };
+        unsafe { ring.add(idx) }
+    }
+
+    /// Resets the controller, sets the link up and reads the MAC address.
+    fn reset(&mut self) -> DevResult {
+        self.write_reg(REG_IMC, u32::MAX);
+        self.write_reg(REG_CTRL, self.read_reg(REG_CTRL) | CTRL_RST);
+        H::busy_wait(Duration::from_millis(1));
+        let mut timeout = 100;
+        while self.read_reg(REG_CTRL) & CTRL_RST != 0 {
+            if timeout == 0 {
+                log::warn!("e1000: timed out waiting for the reset");
+                return Err(DevError::BadState);
+            }
+            timeout -= 1;
+            H::busy_wait(Duration::from_millis(1));
+        }
+        // interrupts are enabled again by the reset
+        self.write_reg(REG_IMC, u32::MAX);
+        self.read_reg(REG_ICR);
+
+        let ctrl = self.read_reg(REG_CTRL) & !(CTRL_LRST | CTRL_ILOS | CTRL_VME | CTRL_PHY_RST);
+        self.write_reg(REG_CTRL, ctrl | CTRL_SLU | CTRL_ASDE);
+
+        // The first receive address is loaded from the EEPROM on reset.
+        let ral = self.read_reg(REG_RAL0);
+        let rah = self.read_reg(REG_RAH0);
+        if rah & RAH_AV == 0 {
+            log::warn!("e1000: no valid MAC address");
+            return Err(DevError::BadState);
+        }
+        self.mac[..4].copy_from_slice(&ral.to_le_bytes());
+        self.mac[4..].copy_from_slice(&rah.to_le_bytes()[..2]);
+
+        // All multicast frames are accepted by `RCTL.MPE` (e.g., for IPv6
+        // neighbor discovery), the filter table is not used.
+        for i in 0..MTA_ENTRIES {
+            self.write_reg(REG_MTA + 4 * i, 0);
+        }
+        Ok(())
+    }
+
+    /// Fills the receive ring with buffers and enables the receiver.
+    fn init_rx(&mut self) -> DevResult {
+        for _ in 0..QS - 1 {
+            let rx_buf = self.buf_pool.alloc_boxed().ok_or(DevError::NoMemory)?;
+            self.post_rx_buffer(rx_buf);
+        }
+        self.write_reg(REG_RDBAL, self.dma_paddr as u32);
+        self.write_reg(REG_RDBAH, (self.dma_paddr as u64 >> 32) as u32);
+        self.write_reg(REG_RDLEN, Self::RING_SIZE as u32);
+        self.write_reg(REG_RDH, 0);
+        self.write_reg(REG_RDT, self.rx_tail as u32);
+        self.write_reg(
+            REG_RCTL,
+            RCTL_EN | RCTL_MPE | RCTL_BAM | RCTL_BSIZE_2048 | RCTL_SECRC,
+        );
+        Ok(())
+    }
+
+    /// Enables the transmitter with an empty transmit ring.
+    fn init_tx(&mut self) {
+        let ring_paddr = (self.dma_paddr + Self::RING_SIZE) as u64;
+        self.write_reg(REG_TDBAL, ring_paddr as u32);
+        self.write_reg(REG_TDBAH, (ring_paddr >> 32) as u32);
+        self.write_reg(REG_TDLEN, Self::RING_SIZE as u32);
+        self.write_reg(REG_TDH, 0);
+        self.write_reg(REG_TDT, 0);
+        self.write_reg(REG_TIPG, TIPG_DEFAULT);
+        self.write_reg(REG_TCTL, TCTL_EN | TCTL_PSP | TCTL_CT | TCTL_COLD);
+    }
+
+    /// Puts the buffer into the descriptor at `rx_tail`, without notifying
+    /// the NIC.
+    fn post_rx_buffer(&mut self, mut rx_buf: NetBufBox) {
+        let idx = self.rx_tail;
+        let desc = RxDesc {
+            addr: H::virt_to_phys(rx_buf.raw_buf_mut().as_mut_ptr() as usize) as u64,
+            ..Default::default()
+        };
+        unsafe { self.rx_desc(idx).write_volatile(desc) };
+        self.rx_buffers[idx] = Some(rx_buf);
+        self.rx_tail = (idx + 1) % QS;
+    }
+}
+
+impl<H: E1000Hal, const QS: usize> Drop for E1000Nic<H, QS> {
+    fn drop(&mut self) {
+        // Stop the NIC before giving its rings back.
+        self.write_reg(REG_IMC, u32::MAX);
+        self.write_reg(REG_RCTL, 0);
+        self.write_reg(REG_TCTL, 0);
+        self.write_reg(REG_CTRL, self.read_reg(REG_CTRL) | CTRL_RST);
+        H::busy_wait(Duration::from_millis(1));
+        unsafe { H::dma_dealloc(self.dma_paddr, self.dma_vaddr, Self::DMA_PAGES) };
+    }
+}
+
+impl<H: E1000Hal, const QS: usize> BaseDriverOps for E1000Nic<H, QS> {
+    fn device_name(&self) -> &str {
+        "e1000"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Net
+    }
+
+    fn irq_num(&self) -> Option<usize> {
+        self.irq_num
+    }
+}
+
+impl<H: E1000Hal, const QS: usize> NetDriverOps for E1000Nic<H, QS> {
+    fn mac_address(&self) -> EthernetAddress {
+        EthernetAddress(self.mac)
+    }
+
+    fn can_transmit(&self) -> bool {
+        (self.tx_tail + 1) % QS != self.tx_clean
+    }
+
+    fn can_receive(&self) -> bool {
+        let desc = unsafe { self.rx_desc(self.rx_next).read_volatile() };
+        self.rx_next != self.rx_tail && desc.status & DESC_STATUS_DD != 0
+    }
+
+    fn rx_queue_size(&self) -> usize {
+        QS
+    }
+
+    fn tx_queue_size(&self) -> usize {
+        QS
+    }
+
+    fn recycle_rx_buffer(&mut self, rx_buf: NetBufPtr) -> DevResult {
+        let rx_buf = unsafe { NetBuf::from_buf_ptr(rx_buf) };
+        // The descriptor at `rx_tail` is expected to be empty since its
+        // buffer was taken away at `Self::receive()`.
+        if self.rx_buffers[self.rx_tail].is_some() {
+            return Err(DevError::BadState);
+        }
+        self.post_rx_buffer(rx_buf);
+        fence(Ordering::SeqCst);
+        self.write_reg(REG_RDT, self.rx_tail as u32);
+        Ok(())
+    }
+
+    fn recycle_tx_buffers(&mut self) -> DevResult {
+        while self.tx_clean != self.tx_tail {
+            let desc = unsafe { self.tx_desc(self.tx_clean).read_volatile() };
+            if desc.status & DESC_STATUS_DD == 0 {
+                break;
+            }
+            // Recycle the buffer into the pool.
+            self.tx_buffers[self.tx_clean]
+                .take()
+                .ok_or(DevError::BadState)?;
+            self.tx_clean = (self.tx_clean + 1) % QS;
+        }
+        Ok(())
+    }
+
+    fn transmit(&mut self, tx_buf: NetBufPtr) -> DevResult {
+        let tx_buf = unsafe { NetBuf::from_buf_ptr(tx_buf) };
+        if !self.can_transmit() {
+            return Err(DevError::Again);
+        }
+        let idx = self.tx_tail;
+        let desc = TxDesc {
+            addr: H::virt_to_phys(tx_buf.packet().as_ptr() as usize) as u64,
+            len: tx_buf.packet().len() as u16,
+            cmd: TX_CMD_EOP | TX_CMD_IFCS | TX_CMD_RS,
+            ..Default::default()
+        };
+        unsafe { self.tx_desc(idx).write_volatile(desc) };
+        self.tx_buffers[idx] = Some(tx_buf);
+        self.tx_tail = (idx + 1) % QS;
+        fence(Ordering::SeqCst);
+        self.write_reg(REG_TDT, self.tx_tail as u32);
+        Ok(())
+    }
+
+    fn receive(&mut self) -> DevResult<NetBufPtr> {
+        while self.can_receive() {
+            let idx = self.rx_next;
+            fence(Ordering::SeqCst);
+            let desc = unsafe { self.rx_desc(idx).read_volatile() };
+            let mut rx_buf = self.rx_buffers[idx].take().ok_or(DevError::BadState)?;
+            self.rx_next = (idx + 1) % QS;
+
+            // Frames never span several buffers as long packets are
+            // disabled, drop the broken ones.
+            if desc.errors != 0 || desc.status & DESC_STATUS_EOP == 0 {
+                log::warn!("e1000: dropped a broken frame, errors {:#x}", desc.errors);
+                self.recycle_rx_buffer(rx_buf.into_buf_ptr())?;
+                continue;
+            }
+            rx_buf.set_packet_len(desc.len as usize);
+            return Ok(rx_buf.into_buf_ptr());
+        }
+        Err(DevError::Again)
+    }
+
+    fn alloc_tx_buffer(&mut self, size: usize) -> DevResult<NetBufPtr> {
+        let mut tx_buf = self.buf_pool.alloc_boxed().ok_or(DevError::NoMemory)?;
+        if size > tx_buf.capacity() {
+            return Err(DevError::InvalidParam);
+        }
+        tx_buf.set_packet_len(size);
+        Ok(tx_buf.into_buf_ptr())
+    }
+
+    fn ack_interrupt(&mut self) -> bool {
+        if self.irq_num.is_none() {
+            return false;
+        }
+        // reading the cause register clears it
+        self.read_reg(REG_ICR) != 0
+    }
+}
@@ -1,27 +1,92 @@
 use core::convert::From;
 use core::{mem::ManuallyDrop, ptr::NonNull};
 
-use alloc::{collections::VecDeque, sync::Arc};
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
 use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
 use ixgbe_driver::{IxgbeDevice, IxgbeError, IxgbeNetBuf, MemPool, NicDevice};
 pub use ixgbe_driver::{IxgbeHal, PhysAddr, INTEL_82599, INTEL_VEND};
 
-use crate::{EthernetAddress, NetBufPtr, NetDriverOps};
+use crate::{EthernetAddress, NetBufPtr, NetDriverOps, RssConfig, RSS_TABLE_LEN, STANDARD_MTU};
 
 extern crate alloc;
 
 const RECV_BATCH_SIZE: usize = 64;
 const RX_BUFFER_SIZE: usize = 1024;
+/// The minimum number of the packet buffers, there are at least twice as many
+/// as the descriptors.
 const MEM_POOL: usize = 4096;
+/// The minimum size of the packet buffers, which is also the size of the
+/// receive buffers configured by `ixgbe_driver`.
 const MEM_POOL_ENTRY_SIZE: usize = 2048;
 
+/// Length of the ethernet header and the frame check sequence.
+const ETHERNET_OVERHEAD: usize = 14 + 4;
+/// The largest frame the NIC receives with jumbo frames enabled.
+const MAX_JUMBO_FRAME_SIZE: usize = 9728;
+/// The largest MTU supported by [`IxgbeNic::init_with_mtu`].
+pub const MAX_MTU: usize = MAX_JUMBO_FRAME_SIZE - ETHERNET_OVERHEAD;
+/// The smallest MTU allowed for IPv4.
+const MIN_MTU: usize = 68;
+
+// Registers and fields used to enable jumbo frames, see the 82599 datasheet.
+const IXGBE_RXCTRL: usize = 0x03000;
+const IXGBE_RXCTRL_RXEN: u32 = 1 << 0;
+const IXGBE_HLREG0: usize = 0x04240;
+const IXGBE_HLREG0_JUMBOEN: u32 = 1 << 2;
+const IXGBE_MAXFRS: usize = 0x04268;
+const IXGBE_MAXFRS_MFS_SHIFT: u32 = 16;
+const fn ixgbe_srrctl(queue: u16) -> usize {
+    0x01014 + 0x40 * queue as usize
+}
+const IXGBE_SRRCTL_BSIZEPKT_MASK: u32 = 0x1f;
+/// Unit of the packet buffer size in `SRRCTL`.
+const IXGBE_SRRCTL_BSIZEPKT_UNIT: usize = 1024;
+
+// Registers and fields used to enable MSI-X interrupts.
+const IXGBE_EICR: usize = 0x00800;
+const IXGBE_EIMS: usize = 0x00880;
+const IXGBE_EIMC: usize = 0x00888;
+const IXGBE_GPIE: usize = 0x00898;
+const IXGBE_GPIE_MSIX_MODE: u32 = 1 << 4;
+const IXGBE_GPIE_PBA_SUPPORT: u32 = 1 << 31;
+const fn ixgbe_ivar(index: u16) -> usize {
+    0x00900 + 4 * index as usize
+}
+const IXGBE_IVAR_ALLOC_VAL: u32 = 1 << 7;
+/// All the causes of the extended interrupt registers in MSI-X mode.
+const IXGBE_EIMS_ALL: u32 = 0x7fff_ffff;
+
+// Registers and fields used to configure RSS.
+const IXGBE_MRQC: usize = 0x05818;
+const IXGBE_MRQC_RSSEN: u32 = 1 << 0;
+const IXGBE_MRQC_RSS_FIELD_IPV4_TCP: u32 = 1 << 16;
+const IXGBE_MRQC_RSS_FIELD_IPV4: u32 = 1 << 17;
+const IXGBE_MRQC_RSS_FIELD_IPV6: u32 = 1 << 20;
+const IXGBE_MRQC_RSS_FIELD_IPV6_TCP: u32 = 1 << 21;
+const IXGBE_MRQC_RSS_FIELD_IPV4_UDP: u32 = 1 << 22;
+const IXGBE_MRQC_RSS_FIELD_IPV6_UDP: u32 = 1 << 23;
+const fn ixgbe_reta(index: usize) -> usize {
+    0x05c00 + 4 * index
+}
+const fn ixgbe_rssrk(index: usize) -> usize {
+    0x05c80 + 4 * index
+}
+/// The largest number of queues that RSS spreads the packets over.
+pub const MAX_RSS_QUEUES: u16 = 16;
+
 /// The ixgbe NIC device driver.
 ///
-/// `QS` is the ixgbe queue size, `QN` is the ixgbe queue num.
+/// `QS` is the ixgbe queue size, `QN` is the ixgbe queue num. If there are
+/// more than one queues, the received packets are spread over the first
+/// [`MAX_RSS_QUEUES`] of them by RSS.
 pub struct IxgbeNic<H: IxgbeHal, const QS: usize, const QN: u16> {
     inner: IxgbeDevice<H, QS>,
     mem_pool: Arc<MemPool>,
-    rx_buffer_queue: VecDeque<NetBufPtr>,
+    /// The received packets of each queue that are not returned yet.
+    rx_buffer_queues: Vec<VecDeque<NetBufPtr>>,
+    mtu: usize,
+    base: usize,
+    irq_num: Option<usize>,
 }
 
 unsafe impl<H: IxgbeHal, const QS: usize, const QN: u16> Sync for IxgbeNic<H, QS, QN> {}
@@ -31,19 +96,78 @@ impl<H: IxgbeHal, const QS: usize, const QN: u16> IxgbeNic<H, QS, QN> {
     /// Creates a net ixgbe NIC instance and initialize, or returns a error if
     /// any step fails.
     pub fn init(base: usize, len: usize) -> DevResult<Self> {
-        let mem_pool = MemPool::allocate::<H>(MEM_POOL, MEM_POOL_ENTRY_SIZE)
+        Self::init_with_mtu(base, len, STANDARD_MTU)
+    }
+
+    /// Creates a net ixgbe NIC instance with the given MTU and initialize, or
+    /// returns a error if any step fails.
+    ///
+    /// Jumbo frames are enabled if `mtu` is larger than [`STANDARD_MTU`], it
+    /// can be up to [`MAX_MTU`].
+    pub fn init_with_mtu(base: usize, len: usize, mtu: usize) -> DevResult<Self> {
+        if !(MIN_MTU..=MAX_MTU).contains(&mtu) {
+            return Err(DevError::InvalidParam);
+        }
+        let max_frame_size = mtu + ETHERNET_OVERHEAD;
+        let entry_size = max_frame_size
+            .next_multiple_of(IXGBE_SRRCTL_BSIZEPKT_UNIT)
+            .max(MEM_POOL_ENTRY_SIZE);
+
+        let mem_pool = MemPool::allocate::<H>(MEM_POOL.max(2 * QS * QN as usize), entry_size)
             .map_err(|_| DevError::NoMemory)?;
         let inner = IxgbeDevice::<H, QS>::init(base, len, QN, QN, &mem_pool).map_err(|err| {
             log::error!("Failed to initialize ixgbe device: {:?}", err);
             DevError::BadState
         })?;
+        if mtu > STANDARD_MTU {
+            // Safe because `base` is the mapped BAR0 of the initialized device.
+            unsafe { enable_jumbo_frames(base, QN, max_frame_size, entry_size) };
+            log::info!("ixgbe: jumbo frames enabled, MTU {}", mtu);
+        }
 
-        let rx_buffer_queue = VecDeque::with_capacity(RX_BUFFER_SIZE);
-        Ok(Self {
+        let rx_buffer_queues = (0..QN)
+            .map(|_| VecDeque::with_capacity(RX_BUFFER_SIZE))
+            .collect();
+        let mut nic = Self {
             inner,
             mem_pool,
-            rx_buffer_queue,
-        })
+            rx_buffer_queues,
+            mtu,
+            base,
+            irq_num: None,
+        };
+        if QN > 1 {
+            let num_queues = QN.min(MAX_RSS_QUEUES) as usize;
+            nic.set_rss(&RssConfig::new(num_queues))?;
+            log::info!("ixgbe: RSS enabled on {} queues", num_queues);
+        }
+        Ok(nic)
+    }
+
+    /// Routes the interrupts of all queues to the MSI-X vector 0, which
+    /// raises `irq_num`, and enables them.
+    ///
+    /// MSI-X must be enabled in the PCI configuration space, with the entry 0
+    /// of the table programmed to raise `irq_num`.
+    pub fn enable_interrupts(&mut self, irq_num: usize) {
+        let reg = |offset: usize| (self.base + offset) as *mut u32;
+        // Safe because `base` is the mapped BAR0 of the initialized device.
+        unsafe {
+            reg(IXGBE_EIMC).write_volatile(IXGBE_EIMS_ALL);
+            let gpie = reg(IXGBE_GPIE).read_volatile();
+            reg(IXGBE_GPIE).write_volatile(gpie | IXGBE_GPIE_MSIX_MODE | IXGBE_GPIE_PBA_SUPPORT);
+            // each IVAR register maps the RX and TX causes of two queues
+            let cause = IXGBE_IVAR_ALLOC_VAL; // vector 0
+            for queue in 0..QN {
+                let ivar = reg(ixgbe_ivar(queue / 2));
+                let shift = 16 * (queue % 2) as u32;
+                let value = ivar.read_volatile() & !(0xffff << shift);
+                ivar.write_volatile(value | (cause | cause << 8) << shift);
+            }
+            reg(IXGBE_EICR).write_volatile(IXGBE_EIMS_ALL);
+            reg(IXGBE_EIMS).write_volatile(IXGBE_EIMS_ALL);
+        }
+        self.irq_num = Some(irq_num);
     }
 }
 
@@ -55,6 +179,10 @@ impl<H: IxgbeHal, const QS: usize, const QN: u16> BaseDriverOps for IxgbeNic<H,
     fn device_type(&self) -> DeviceType {
         DeviceType::Net
     }
+
+    fn irq_num(&self) -> Option<usize> {
+        self.irq_num
+    }
 }
 
 impl<H: IxgbeHal, const QS: usize, const QN: u16> NetDriverOps for IxgbeNic<H, QS, QN> {
@@ -62,6 +190,10 @@ impl<H: IxgbeHal, const QS: usize, const QN: u16> NetDriverOps for IxgbeNic<H, Q
         EthernetAddress(self.inner.get_mac_addr())
     }
 
+    fn mtu(&self) -> usize {
+        self.mtu
+    }
+
     fn rx_queue_size(&self) -> usize {
         QS
     }
@@ -71,46 +203,98 @@ impl<H: IxgbeHal, const QS: usize, const QN: u16> NetDriverOps for IxgbeNic<H, Q
     }
 
     fn can_receive(&self) -> bool {
-        !self.rx_buffer_queue.is_empty() || self.inner.can_receive(0).unwrap()
+        self.can_receive_on(0)
     }
 
     fn can_transmit(&self) -> bool {
-        // Default implementation is return true forever.
-        self.inner.can_send(0).unwrap()
+        self.can_transmit_on(0)
     }
 
     fn recycle_rx_buffer(&mut self, rx_buf: NetBufPtr) -> DevResult {
+        self.recycle_rx_buffer_on(0, rx_buf)
+    }
+
+    fn recycle_tx_buffers(&mut self) -> DevResult {
+        self.recycle_tx_buffers_on(0)
+    }
+
+    fn receive(&mut self) -> DevResult<NetBufPtr> {
+        self.receive_on(0)
+    }
+
+    fn transmit(&mut self, tx_buf: NetBufPtr) -> DevResult {
+        self.transmit_on(0, tx_buf)
+    }
+
+    fn alloc_tx_buffer(&mut self, size: usize) -> DevResult<NetBufPtr> {
+        let tx_buf = IxgbeNetBuf::alloc(&self.mem_pool, size).map_err(|_| DevError::NoMemory)?;
+        Ok(NetBufPtr::from(tx_buf))
+    }
+
+    fn ack_interrupt(&mut self) -> bool {
+        if self.irq_num.is_none() {
+            return false;
+        }
+        let eicr = (self.base + IXGBE_EICR) as *mut u32;
+        // Safe because `base` is the mapped BAR0 of the initialized device.
+        unsafe {
+            let causes = eicr.read_volatile();
+            eicr.write_volatile(causes);
+            causes != 0
+        }
+    }
+
+    fn num_queues(&self) -> usize {
+        QN as usize
+    }
+
+    fn can_receive_on(&self, queue: usize) -> bool {
+        queue < QN as usize
+            && (!self.rx_buffer_queues[queue].is_empty()
+                || self.inner.can_receive(queue as u16).unwrap())
+    }
+
+    fn can_transmit_on(&self, queue: usize) -> bool {
+        // Default implementation is return true forever.
+        queue < QN as usize && self.inner.can_send(queue as u16).unwrap()
+    }
+
+    fn recycle_rx_buffer_on(&mut self, _queue: usize, rx_buf: NetBufPtr) -> DevResult {
+        // The buffers of all queues come from the same pool.
         let rx_buf = ixgbe_ptr_to_buf(rx_buf, &self.mem_pool)?;
         drop(rx_buf);
         Ok(())
     }
 
-    fn recycle_tx_buffers(&mut self) -> DevResult {
+    fn recycle_tx_buffers_on(&mut self, queue: usize) -> DevResult {
+        let queue = queue_id::<QN>(queue)?;
         self.inner
-            .recycle_tx_buffers(0)
+            .recycle_tx_buffers(queue)
             .map_err(|_| DevError::BadState)?;
         Ok(())
     }
 
-    fn receive(&mut self) -> DevResult<NetBufPtr> {
-        if !self.can_receive() {
+    fn receive_on(&mut self, queue: usize) -> DevResult<NetBufPtr> {
+        let id = queue_id::<QN>(queue)?;
+        if !self.can_receive_on(queue) {
             return Err(DevError::Again);
         }
-        if !self.rx_buffer_queue.is_empty() {
+        let rx_buffer_queue = &mut self.rx_buffer_queues[queue];
+        if !rx_buffer_queue.is_empty() {
             // RX buffer have received packets.
-            Ok(self.rx_buffer_queue.pop_front().unwrap())
+            Ok(rx_buffer_queue.pop_front().unwrap())
         } else {
             // RX queue is empty, receive from ixgbe NIC.
-            match self.inner.receive_packets(0, RECV_BATCH_SIZE, |rx_buf| {
+            match self.inner.receive_packets(id, RECV_BATCH_SIZE, |rx_buf| {
                 let rx_buf = NetBufPtr::from(rx_buf);
-                self.rx_buffer_queue.push_back(rx_buf);
+                rx_buffer_queue.push_back(rx_buf);
             }) {
                 Ok(recv_nums) => {
                     if recv_nums == 0 {
                         // No packet is received, it is impossible things.
                         panic!("Error: No receive packets.")
                     } else {
-                        Ok(self.rx_buffer_queue.pop_front().unwrap())
+                        Ok(rx_buffer_queue.pop_front().unwrap())
                     }
                 }
                 Err(e) => match e {
@@ -121,9 +305,10 @@ impl<H: IxgbeHal, const QS: usize, const QN: u16> NetDriverOps for IxgbeNic<H, Q
         }
     }
 
-    fn transmit(&mut self, tx_buf: NetBufPtr) -> DevResult {
+    fn transmit_on(&mut self, queue: usize, tx_buf: NetBufPtr) -> DevResult {
+        let queue = queue_id::<QN>(queue)?;
         let tx_buf = ixgbe_ptr_to_buf(tx_buf, &self.mem_pool)?;
-        match self.inner.send(0, tx_buf) {
+        match self.inner.send(queue, tx_buf) {
             Ok(_) => Ok(()),
             Err(err) => match err {
                 IxgbeError::QueueFull => Err(DevError::Again),
@@ -132,9 +317,40 @@ impl<H: IxgbeHal, const QS: usize, const QN: u16> NetDriverOps for IxgbeNic<H, Q
         }
     }
 
-    fn alloc_tx_buffer(&mut self, size: usize) -> DevResult<NetBufPtr> {
-        let tx_buf = IxgbeNetBuf::alloc(&self.mem_pool, size).map_err(|_| DevError::NoMemory)?;
-        Ok(NetBufPtr::from(tx_buf))
+    fn set_rss(&mut self, config: &RssConfig) -> DevResult {
+        if config.num_queues() > QN.min(MAX_RSS_QUEUES) as usize {
+            return Err(DevError::InvalidParam);
+        }
+        let hash_types = &config.hash_types;
+        let fields = [
+            (hash_types.ipv4, IXGBE_MRQC_RSS_FIELD_IPV4),
+            (hash_types.tcp_ipv4, IXGBE_MRQC_RSS_FIELD_IPV4_TCP),
+            (hash_types.udp_ipv4, IXGBE_MRQC_RSS_FIELD_IPV4_UDP),
+            (hash_types.ipv6, IXGBE_MRQC_RSS_FIELD_IPV6),
+            (hash_types.tcp_ipv6, IXGBE_MRQC_RSS_FIELD_IPV6_TCP),
+            (hash_types.udp_ipv6, IXGBE_MRQC_RSS_FIELD_IPV6_UDP),
+        ];
+        let mrqc = fields
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .fold(IXGBE_MRQC_RSSEN, |mrqc, (_, field)| mrqc | field);
+
+        let reg = |offset: usize| (self.base + offset) as *mut u32;
+        // Safe because `base` is the mapped BAR0 of the initialized device.
+        unsafe {
+            // the first byte of the key is the lowest byte of `RSSRK[0]`
+            for (i, key) in config.key.chunks_exact(4).enumerate() {
+                let key = u32::from_le_bytes(key.try_into().unwrap());
+                reg(ixgbe_rssrk(i)).write_volatile(key);
+            }
+            // each `RETA` register holds 4 entries of the indirection table
+            for i in 0..RSS_TABLE_LEN / 4 {
+                let entries: [u8; 4] = config.table[4 * i..4 * i + 4].try_into().unwrap();
+                reg(ixgbe_reta(i)).write_volatile(u32::from_le_bytes(entries));
+            }
+            reg(IXGBE_MRQC).write_volatile(mrqc);
+        }
+        Ok(())
     }
 }
 
@@ -153,6 +369,42 @@ impl From<IxgbeNetBuf> for NetBufPtr {
     }
 }
 
+/// Lets the NIC accept frames of up to `max_frame_size` bytes into receive
+/// buffers of `rx_buf_size` bytes, on the first `num_queues` receive queues.
+///
+/// The receiver is disabled while the registers are updated.
+unsafe fn enable_jumbo_frames(
+    base: usize,
+    num_queues: u16,
+    max_frame_size: usize,
+    rx_buf_size: usize,
+) {
+    let reg = |offset: usize| (base + offset) as *mut u32;
+    let rxctrl = reg(IXGBE_RXCTRL).read_volatile();
+    reg(IXGBE_RXCTRL).write_volatile(rxctrl & !IXGBE_RXCTRL_RXEN);
+
+    let hlreg0 = reg(IXGBE_HLREG0).read_volatile();
+    reg(IXGBE_HLREG0).write_volatile(hlreg0 | IXGBE_HLREG0_JUMBOEN);
+    reg(IXGBE_MAXFRS).write_volatile((max_frame_size as u32) << IXGBE_MAXFRS_MFS_SHIFT);
+    let bsize = (rx_buf_size / IXGBE_SRRCTL_BSIZEPKT_UNIT) as u32;
+    for queue in 0..num_queues {
+        let srrctl = reg(ixgbe_srrctl(queue)).read_volatile();
+        reg(ixgbe_srrctl(queue)).write_volatile((srrctl & !IXGBE_SRRCTL_BSIZEPKT_MASK) | bsize);
+    }
+
+    reg(IXGBE_RXCTRL).write_volatile(rxctrl);
+}
+
+/// Converts the index of a queue to the queue ID of `ixgbe_driver`, or
+/// returns an error if there is no such queue.
+fn queue_id<const QN: u16>(queue: usize) -> DevResult<u16> {
+    if queue < QN as usize {
+        Ok(queue as u16)
+    } else {
+        Err(DevError::InvalidParam)
+    }
+}
+
 // Converts a `NetBufPtr` to `IxgbeNetBuf`.
 fn ixgbe_ptr_to_buf(ptr: NetBufPtr, pool: &Arc<MemPool>) -> DevResult<IxgbeNetBuf> {
     IxgbeNetBuf::construct(ptr.raw_ptr.as_ptr() as usize, pool, ptr.len)
@@ -5,10 +5,13 @@
 #![feature(const_slice_from_raw_parts_mut)]
 #![feature(box_into_inner)]
 
+#[cfg(feature = "e1000")]
+pub mod e1000;
 #[cfg(feature = "ixgbe")]
 /// ixgbe NIC device driver.
 pub mod ixgbe;
 mod net_buf;
+mod rss;
 
 use core::ptr::NonNull;
 
@@ -16,11 +19,20 @@ use core::ptr::NonNull;
 pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
 
 pub use self::net_buf::{NetBuf, NetBufBox, NetBufPool};
+pub use self::rss::{RssConfig, RssHashTypes, DEFAULT_RSS_KEY, RSS_KEY_LEN, RSS_TABLE_LEN};
 
 /// The ethernet address of the NIC (MAC address).
 pub struct EthernetAddress(pub [u8; 6]);
 
+/// The MTU of standard (non-jumbo) ethernet frames.
+pub const STANDARD_MTU: usize = 1500;
+
 /// Operations that require a network device (NIC) driver to implement.
+///
+/// A NIC may have several pairs of receive and transmit queues, see
+/// [`NetDriverOps::num_queues`]. The methods without a queue index operate on
+/// the queue 0, and the methods with one (`*_on`) operate on the given queue.
+/// A driver with multiple queues overrides both.
 pub trait NetDriverOps: BaseDriverOps {
     /// The ethernet address of the NIC.
     fn mac_address(&self) -> EthernetAddress;
@@ -31,6 +43,13 @@ pub trait NetDriverOps: BaseDriverOps {
     /// Whether can receive packets.
     fn can_receive(&self) -> bool;
 
+    /// The maximum transmission unit (MTU) of the NIC, i.e. the size of the
+    /// largest IP packet that can be sent or received, without the ethernet
+    /// header.
+    fn mtu(&self) -> usize {
+        STANDARD_MTU
+    }
+
     /// Size of the receive queue.
     fn rx_queue_size(&self) -> usize;
 
@@ -64,6 +83,73 @@ pub trait NetDriverOps: BaseDriverOps {
     /// Allocate a memory buffer of a specified size for network transmission,
     /// returns [`DevResult`]
     fn alloc_tx_buffer(&mut self, size: usize) -> DevResult<NetBufPtr>;
+
+    /// Acknowledges the pending interrupt of the device, returns whether the
+    /// device has raised one.
+    fn ack_interrupt(&mut self) -> bool {
+        false
+    }
+
+    /// The number of receive and transmit queue pairs.
+    fn num_queues(&self) -> usize {
+        1
+    }
+
+    /// Whether can transmit packets on the given queue.
+    fn can_transmit_on(&self, queue: usize) -> bool {
+        queue == 0 && self.can_transmit()
+    }
+
+    /// Whether can receive packets from the given queue.
+    fn can_receive_on(&self, queue: usize) -> bool {
+        queue == 0 && self.can_receive()
+    }
+
+    /// Gives back the `rx_buf` to the given receive queue, which must be the
+    /// one it was received from.
+    fn recycle_rx_buffer_on(&mut self, queue: usize, rx_buf: NetBufPtr) -> DevResult {
+        check_queue(self, queue)?;
+        self.recycle_rx_buffer(rx_buf)
+    }
+
+    /// Poll the given transmit queue and gives back the buffers for previous
+    /// transmiting.
+    fn recycle_tx_buffers_on(&mut self, queue: usize) -> DevResult {
+        check_queue(self, queue)?;
+        self.recycle_tx_buffers()
+    }
+
+    /// Transmits a packet in the buffer on the given queue, without blocking.
+    fn transmit_on(&mut self, queue: usize, tx_buf: NetBufPtr) -> DevResult {
+        check_queue(self, queue)?;
+        self.transmit(tx_buf)
+    }
+
+    /// Receives a packet from the given queue, like
+    /// [`NetDriverOps::receive`].
+    fn receive_on(&mut self, queue: usize) -> DevResult<NetBufPtr> {
+        check_queue(self, queue)?;
+        self.receive()
+    }
+
+    /// Configures receive-side scaling, which spreads the received packets
+    /// over the receive queues.
+    ///
+    /// Returns [`DevError::Unsupported`] if the NIC has no such feature, or
+    /// [`DevError::InvalidParam`] if the indirection table refers to a queue
+    /// that doesn't exist.
+    fn set_rss(&mut self, _config: &RssConfig) -> DevResult {
+        Err(DevError::Unsupported)
+    }
+}
+
+/// Returns an error if `queue` is not a queue of `dev`.
+fn check_queue<D: NetDriverOps + ?Sized>(dev: &D, queue: usize) -> DevResult {
+    if queue < dev.num_queues() {
+        Ok(())
+    } else {
+        Err(DevError::InvalidParam)
+    }
 }
 
 /// A raw buffer struct for network device.
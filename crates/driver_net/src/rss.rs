@@ -0,0 +1,93 @@
+//! Receive-side scaling (RSS), which spreads the received packets over the
+//! receive queues by a hash of their headers.
+
+/// Length of the RSS hash key, in bytes.
+pub const RSS_KEY_LEN: usize = 40;
+
+/// Number of entries in the RSS indirection table.
+pub const RSS_TABLE_LEN: usize = 128;
+
+/// The hash key used by most NICs by default, from the Microsoft RSS
+/// specification.
+pub const DEFAULT_RSS_KEY: [u8; RSS_KEY_LEN] = [
+    0x6d, 0x5a, 0x56, 0xda, 0x25, 0x5b, 0x0e, 0xc2, 0x41, 0x67, 0x25, 0x3d, 0x43, 0xa3, 0x8f, 0xb0,
+    0xd0, 0xca, 0x2b, 0xcb, 0xae, 0x7b, 0x30, 0xb4, 0x77, 0xcb, 0x2d, 0xa3, 0x80, 0x30, 0xf2, 0x0c,
+    0x6a, 0x42, 0xb7, 0x3b, 0xbe, 0xac, 0x01, 0xfa,
+];
+
+/// The packet headers that are hashed to choose the receive queue.
+///
+/// Packets of other types, or of the disabled types, go to the queue 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RssHashTypes {
+    /// Hash the source and destination addresses of IPv4 packets.
+    pub ipv4: bool,
+    /// Hash the addresses and the ports of TCP over IPv4.
+    pub tcp_ipv4: bool,
+    /// Hash the addresses and the ports of UDP over IPv4.
+    pub udp_ipv4: bool,
+    /// Hash the source and destination addresses of IPv6 packets.
+    pub ipv6: bool,
+    /// Hash the addresses and the ports of TCP over IPv6.
+    pub tcp_ipv6: bool,
+    /// Hash the addresses and the ports of UDP over IPv6.
+    pub udp_ipv6: bool,
+}
+
+impl RssHashTypes {
+    /// Hashes all the supported headers.
+    pub const ALL: Self = Self {
+        ipv4: true,
+        tcp_ipv4: true,
+        udp_ipv4: true,
+        ipv6: true,
+        tcp_ipv6: true,
+        udp_ipv6: true,
+    };
+}
+
+/// The configuration of receive-side scaling.
+///
+/// The NIC computes the Toeplitz hash of the headers with [`key`], and the
+/// low 7 bits of the hash select the entry in [`table`], which is the index
+/// of the receive queue.
+///
+/// [`key`]: RssConfig::key
+/// [`table`]: RssConfig::table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RssConfig {
+    /// The hash key.
+    pub key: [u8; RSS_KEY_LEN],
+    /// The packet headers to hash.
+    pub hash_types: RssHashTypes,
+    /// The indirection table, maps the hash to the receive queue.
+    pub table: [u8; RSS_TABLE_LEN],
+}
+
+impl RssConfig {
+    /// Creates a configuration that spreads the packets of all the supported
+    /// types evenly over `num_queues` receive queues, with the
+    /// [`DEFAULT_RSS_KEY`].
+    pub fn new(num_queues: usize) -> Self {
+        let num_queues = num_queues.clamp(1, u8::MAX as usize + 1);
+        let mut table = [0; RSS_TABLE_LEN];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = (i % num_queues) as u8;
+        }
+        Self {
+            key: DEFAULT_RSS_KEY,
+            hash_types: RssHashTypes::ALL,
+            table,
+        }
+    }
+
+    /// Returns the receive queue of the packets with the given hash.
+    pub fn queue_of(&self, hash: u32) -> usize {
+        self.table[hash as usize % RSS_TABLE_LEN] as usize
+    }
+
+    /// Returns the number of receive queues used by the indirection table.
+    pub fn num_queues(&self) -> usize {
+        self.table.iter().max().map_or(0, |&max| max as usize + 1)
+    }
+}
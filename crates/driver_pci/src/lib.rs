@@ -1,18 +1,117 @@
 //! Structures and functions for PCI bus operations.
 //!
-//! Currently, it just re-exports structures from the crate [virtio-drivers][1]
-//! and its module [`virtio_drivers::transport::pci::bus`][2].
+//! Currently, it re-exports structures from the crate [virtio-drivers][1]
+//! and its module [`virtio_drivers::transport::pci::bus`][2], and provides
+//! MSI/MSI-X support in the [`msi`] module.
 //!
 //! [1]: https://docs.rs/virtio-drivers/latest/virtio_drivers/
 //! [2]: https://docs.rs/virtio-drivers/latest/virtio_drivers/transport/pci/bus/index.html
 
 #![no_std]
 
+pub mod msi;
+
 pub use virtio_drivers::transport::pci::bus::{BarInfo, Cam, HeaderType, MemoryBarType, PciError};
 pub use virtio_drivers::transport::pci::bus::{
     CapabilityInfo, Command, DeviceFunction, DeviceFunctionInfo, PciRoot, Status,
 };
 
+/// The offset of the capabilities pointer in the configuration space.
+const CAPABILITIES_POINTER: u8 = 0x34;
+/// The bit in the status register that indicates a capability list.
+const STATUS_CAPABILITIES_LIST: u16 = 1 << 4;
+
+/// Direct access to the configuration space of PCI devices, for the
+/// registers that [`PciRoot`] does not expose (e.g., capabilities).
+#[derive(Clone, Copy)]
+pub struct ConfigSpace {
+    mmio_base: *mut u32,
+    cam: Cam,
+}
+
+unsafe impl Send for ConfigSpace {}
+unsafe impl Sync for ConfigSpace {}
+
+impl ConfigSpace {
+    /// Creates an accessor of the configuration space at `mmio_base`.
+    ///
+    /// # Safety
+    ///
+    /// `mmio_base` must be the mapped address of the configuration access
+    /// mechanism `cam`, the same as given to [`PciRoot::new`].
+    pub const unsafe fn new(mmio_base: *mut u8, cam: Cam) -> Self {
+        Self {
+            mmio_base: mmio_base as *mut u32,
+            cam,
+        }
+    }
+
+    fn register(&self, bdf: DeviceFunction, offset: u8) -> *mut u32 {
+        let bdf_offset = match self.cam {
+            Cam::MmioCam => {
+                (bdf.bus as usize) << 16
+                    | (bdf.device as usize) << 11
+                    | (bdf.function as usize) << 8
+            }
+            Cam::Ecam => {
+                (bdf.bus as usize) << 20
+                    | (bdf.device as usize) << 15
+                    | (bdf.function as usize) << 12
+            }
+        };
+        // the registers are 32-bit aligned
+        let index = (bdf_offset | (offset & !3) as usize) / 4;
+        unsafe { self.mmio_base.add(index) }
+    }
+
+    /// Reads the 32-bit register at `offset`, which is rounded down to a
+    /// multiple of 4.
+    pub fn read_u32(&self, bdf: DeviceFunction, offset: u8) -> u32 {
+        unsafe { self.register(bdf, offset).read_volatile() }
+    }
+
+    /// Writes the 32-bit register at `offset`, which is rounded down to a
+    /// multiple of 4.
+    pub fn write_u32(&self, bdf: DeviceFunction, offset: u8, value: u32) {
+        unsafe { self.register(bdf, offset).write_volatile(value) }
+    }
+
+    /// Reads the 16-bit register at `offset`, which is rounded down to a
+    /// multiple of 2.
+    pub fn read_u16(&self, bdf: DeviceFunction, offset: u8) -> u16 {
+        (self.read_u32(bdf, offset) >> ((offset & 2) * 8)) as u16
+    }
+
+    /// Writes the 16-bit register at `offset`, which is rounded down to a
+    /// multiple of 2. The other half of the 32-bit register is kept.
+    pub fn write_u16(&self, bdf: DeviceFunction, offset: u8, value: u16) {
+        let shift = (offset & 2) * 8;
+        let word = self.read_u32(bdf, offset) & !(0xffff << shift);
+        self.write_u32(bdf, offset, word | (value as u32) << shift);
+    }
+
+    /// Iterates over the capabilities of the device, as `(offset, id)`.
+    pub fn capabilities(&self, bdf: DeviceFunction) -> impl Iterator<Item = (u8, u8)> + '_ {
+        let status = (self.read_u32(bdf, 0x04) >> 16) as u16;
+        let mut next = if status & STATUS_CAPABILITIES_LIST != 0 {
+            self.read_u32(bdf, CAPABILITIES_POINTER) as u8 & !3
+        } else {
+            0
+        };
+        // at most 48 capabilities fit in the configuration space
+        core::iter::from_fn(move || {
+            if next == 0 {
+                return None;
+            }
+            let header = self.read_u32(bdf, next);
+            let cap = (next, header as u8);
+            next = (header >> 8) as u8 & !3;
+            Some(cap)
+        })
+        .take(48)
+    }
+}
+
 /// Used to allocate MMIO regions for PCI BARs.
 pub struct PciRangeAllocator {
     _start: u64,
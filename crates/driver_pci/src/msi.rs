@@ -0,0 +1,176 @@
+//! Message signaled interrupts (MSI and MSI-X).
+//!
+//! A device that supports MSI or MSI-X raises an interrupt by writing a
+//! message (the data) to an address, both are provided by the interrupt
+//! controller of the platform. The [`MsiCapability`] and [`MsixCapability`]
+//! are found in the capability list of the device, and are used to program
+//! the messages and enable the interrupts.
+
+use crate::{ConfigSpace, DeviceFunction};
+
+/// The capability ID of MSI.
+pub const PCI_CAP_ID_MSI: u8 = 0x05;
+/// The capability ID of MSI-X.
+pub const PCI_CAP_ID_MSIX: u8 = 0x11;
+
+// Fields of the MSI message control register.
+const MSI_CTRL_ENABLE: u16 = 1 << 0;
+const MSI_CTRL_MULTI_MSG_CAPABLE_SHIFT: u16 = 1;
+const MSI_CTRL_MULTI_MSG_ENABLE_MASK: u16 = 0b111 << 4;
+const MSI_CTRL_64BIT: u16 = 1 << 7;
+const MSI_CTRL_PER_VECTOR_MASK: u16 = 1 << 8;
+
+// Fields of the MSI-X message control register.
+const MSIX_CTRL_TABLE_SIZE_MASK: u16 = 0x7ff;
+const MSIX_CTRL_FUNCTION_MASK: u16 = 1 << 14;
+const MSIX_CTRL_ENABLE: u16 = 1 << 15;
+
+/// The size in bytes of an entry of the MSI-X table.
+const MSIX_ENTRY_SIZE: usize = 16;
+/// The mask bit in the vector control word of an MSI-X table entry.
+const MSIX_ENTRY_CTRL_MASKED: u32 = 1 << 0;
+
+/// An interrupt message, which is written to `address` by the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsiMessage {
+    /// The address that the device writes to.
+    pub address: u64,
+    /// The data that the device writes.
+    pub data: u32,
+}
+
+/// The MSI capability of a device.
+#[derive(Debug, Clone, Copy)]
+pub struct MsiCapability {
+    offset: u8,
+    control: u16,
+}
+
+/// The MSI-X capability of a device.
+#[derive(Debug, Clone, Copy)]
+pub struct MsixCapability {
+    offset: u8,
+    control: u16,
+    table: u32,
+    pba: u32,
+}
+
+/// Returns the offset and the message control register of the capability
+/// `id`.
+fn find_capability(config: &ConfigSpace, bdf: DeviceFunction, id: u8) -> Option<(u8, u16)> {
+    config
+        .capabilities(bdf)
+        .find(|&(_, cap_id)| cap_id == id)
+        .map(|(offset, _)| (offset, config.read_u16(bdf, offset + 2)))
+}
+
+impl MsiCapability {
+    /// Finds the MSI capability of the device.
+    pub fn find(config: &ConfigSpace, bdf: DeviceFunction) -> Option<Self> {
+        let (offset, control) = find_capability(config, bdf, PCI_CAP_ID_MSI)?;
+        Some(Self { offset, control })
+    }
+
+    /// Whether the message address is 64-bit.
+    pub fn is_64bit(&self) -> bool {
+        self.control & MSI_CTRL_64BIT != 0
+    }
+
+    /// The maximum number of vectors the device requests.
+    pub fn max_vectors(&self) -> usize {
+        1 << ((self.control >> MSI_CTRL_MULTI_MSG_CAPABLE_SHIFT) & 0b111)
+    }
+
+    /// Programs `msg` and enables MSI with a single vector. It fails if the
+    /// device only supports 32-bit addresses and the address of `msg` is
+    /// above 4 GiB.
+    pub fn enable(&self, config: &ConfigSpace, bdf: DeviceFunction, msg: MsiMessage) -> bool {
+        if !self.is_64bit() && msg.address > u32::MAX as u64 {
+            return false;
+        }
+        let offset = self.offset;
+        config.write_u32(bdf, offset + 4, msg.address as u32);
+        let data_offset = if self.is_64bit() {
+            config.write_u32(bdf, offset + 8, (msg.address >> 32) as u32);
+            offset + 12
+        } else {
+            offset + 8
+        };
+        config.write_u16(bdf, data_offset, msg.data as u16);
+        if self.control & MSI_CTRL_PER_VECTOR_MASK != 0 {
+            // unmask all vectors, the mask bits follow the data
+            config.write_u32(bdf, data_offset + 4, 0);
+        }
+        let control = self.control & !MSI_CTRL_MULTI_MSG_ENABLE_MASK;
+        config.write_u16(bdf, offset + 2, control | MSI_CTRL_ENABLE);
+        true
+    }
+
+    /// Disables MSI.
+    pub fn disable(&self, config: &ConfigSpace, bdf: DeviceFunction) {
+        config.write_u16(bdf, self.offset + 2, self.control & !MSI_CTRL_ENABLE);
+    }
+}
+
+impl MsixCapability {
+    /// Finds the MSI-X capability of the device.
+    pub fn find(config: &ConfigSpace, bdf: DeviceFunction) -> Option<Self> {
+        let (offset, control) = find_capability(config, bdf, PCI_CAP_ID_MSIX)?;
+        Some(Self {
+            offset,
+            control,
+            table: config.read_u32(bdf, offset + 4),
+            pba: config.read_u32(bdf, offset + 8),
+        })
+    }
+
+    /// The number of entries in the MSI-X table.
+    pub fn table_size(&self) -> usize {
+        (self.control & MSIX_CTRL_TABLE_SIZE_MASK) as usize + 1
+    }
+
+    /// The BAR that contains the MSI-X table, and the offset of the table in
+    /// the BAR.
+    pub fn table_bar(&self) -> (u8, u32) {
+        ((self.table & 0b111) as u8, self.table & !0b111)
+    }
+
+    /// The BAR that contains the pending bit array, and the offset of the
+    /// array in the BAR.
+    pub fn pba_bar(&self) -> (u8, u32) {
+        ((self.pba & 0b111) as u8, self.pba & !0b111)
+    }
+
+    /// Programs the entry `index` of the MSI-X table at `table_vaddr`, and
+    /// unmasks it.
+    ///
+    /// # Safety
+    ///
+    /// `table_vaddr` must be the mapped address of the MSI-X table, i.e. the
+    /// mapped address of the BAR plus the offset, see [`Self::table_bar`].
+    pub unsafe fn set_entry(&self, table_vaddr: *mut u8, index: usize, msg: MsiMessage) -> bool {
+        if index >= self.table_size() {
+            return false;
+        }
+        let entry = table_vaddr.add(index * MSIX_ENTRY_SIZE) as *mut u32;
+        let ctrl = entry.add(3).read_volatile();
+        entry.add(3).write_volatile(ctrl | MSIX_ENTRY_CTRL_MASKED);
+        entry.write_volatile(msg.address as u32);
+        entry.add(1).write_volatile((msg.address >> 32) as u32);
+        entry.add(2).write_volatile(msg.data);
+        entry.add(3).write_volatile(ctrl & !MSIX_ENTRY_CTRL_MASKED);
+        true
+    }
+
+    /// Enables MSI-X. The entries that are not set by [`Self::set_entry`]
+    /// stay masked.
+    pub fn enable(&self, config: &ConfigSpace, bdf: DeviceFunction) {
+        let control = self.control & !MSIX_CTRL_FUNCTION_MASK;
+        config.write_u16(bdf, self.offset + 2, control | MSIX_CTRL_ENABLE);
+    }
+
+    /// Disables MSI-X.
+    pub fn disable(&self, config: &ConfigSpace, bdf: DeviceFunction) {
+        config.write_u16(bdf, self.offset + 2, self.control & !MSIX_CTRL_ENABLE);
+    }
+}
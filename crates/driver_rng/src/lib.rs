@@ -0,0 +1,16 @@
+//! Common traits for hardware random number generator drivers.
+
+#![no_std]
+
+#[doc(no_inline)]
+pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+/// Operations that require a random number generator driver to implement.
+pub trait RngDriverOps: BaseDriverOps {
+    /// Fills `buf` with random bytes from the device, returns the number of
+    /// bytes filled.
+    ///
+    /// The device may return fewer bytes than requested if it has not
+    /// gathered enough entropy.
+    fn request_entropy(&mut self, buf: &mut [u8]) -> DevResult<usize>;
+}
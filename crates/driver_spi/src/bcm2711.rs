@@ -0,0 +1,161 @@
+//! Driver for the SPI0 controller of the BCM2711 (Raspberry Pi 4).
+//!
+//! The transfers are polled, so the controller's interrupts and DMA are not
+//! used.
+
+use driver_common::{MmioRegisters, RegisterAccess};
+
+use crate::{BaseDriverOps, DevError, DevResult, DeviceType, SpiConfig, SpiDriverOps, SpiMode};
+
+/// The number of chip select lines of the controller.
+pub const NUM_CHIP_SELECTS: usize = 2;
+
+// Registers, see the BCM2711 ARM Peripherals datasheet.
+const REG_CS: usize = 0x00;
+const REG_FIFO: usize = 0x04;
+const REG_CLK: usize = 0x08;
+
+// Fields of the control and status register.
+const CS_CPHA: u32 = 1 << 2;
+const CS_CPOL: u32 = 1 << 3;
+const CS_CLEAR: u32 = 0b11 << 4;
+const CS_TA: u32 = 1 << 7;
+const CS_DONE: u32 = 1 << 16;
+const CS_RXD: u32 = 1 << 17;
+const CS_TXD: u32 = 1 << 18;
+
+/// The largest clock divider.
+const MAX_DIV: u32 = 0xfffe;
+/// How many times the status is polled before a transfer is given up, in case
+/// the controller hangs.
+const POLL_LIMIT: usize = 10_000_000;
+
+/// The BCM2711 SPI0 controller driver.
+pub struct Bcm2711Spi<R: RegisterAccess = MmioRegisters> {
+    regs: R,
+    clock_hz: u32,
+    config: SpiConfig,
+}
+
+impl Bcm2711Spi {
+    /// Creates a driver of the controller whose registers are mapped at
+    /// `base`, and whose input clock (the core clock) is `clock_hz`. The
+    /// controller is not touched until [`Self::init`].
+    ///
+    /// # Safety
+    ///
+    /// `base` must be the mapped registers of the BCM2711 SPI0 controller,
+    /// which is not used by others.
+    pub unsafe fn new(base: *mut u8, clock_hz: u32) -> Self {
+        Self::with_registers(MmioRegisters::new(base), clock_hz)
+    }
+}
+
+impl<R: RegisterAccess> Bcm2711Spi<R> {
+    /// Creates a driver that accesses the registers by `regs`.
+    pub const fn with_registers(regs: R, clock_hz: u32) -> Self {
+        Self {
+            regs,
+            clock_hz,
+            config: SpiConfig {
+                speed_hz: 0,
+                mode: SpiMode::MODE0,
+            },
+        }
+    }
+
+    /// Resets the controller, and configures the bus.
+    pub fn init(&mut self, config: SpiConfig) -> DevResult {
+        self.regs.write_reg(REG_CS, CS_CLEAR);
+        self.set_config(config)
+    }
+
+    /// Stops the transfer, which succeeded if it's done with all the bytes.
+    fn finish(&self, complete: bool) -> DevResult {
+        self.regs.write_reg(REG_CS, CS_CLEAR);
+        if complete {
+            Ok(())
+        } else {
+            Err(DevError::Io)
+        }
+    }
+}
+
+impl<R: RegisterAccess> BaseDriverOps for Bcm2711Spi<R> {
+    fn device_name(&self) -> &str {
+        "bcm2711-spi"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Spi
+    }
+}
+
+impl<R: RegisterAccess> SpiDriverOps for Bcm2711Spi<R> {
+    fn num_chip_selects(&self) -> usize {
+        NUM_CHIP_SELECTS
+    }
+
+    fn config(&self) -> SpiConfig {
+        self.config
+    }
+
+    fn set_config(&mut self, config: SpiConfig) -> DevResult {
+        if config.speed_hz == 0 {
+            return Err(DevError::InvalidParam);
+        }
+        // the divider is even, and the clock rate is no faster than requested
+        let div = self.clock_hz.div_ceil(config.speed_hz).next_multiple_of(2);
+        if !(2..=MAX_DIV).contains(&div) {
+            return Err(DevError::InvalidParam);
+        }
+        self.regs.write_reg(REG_CLK, div);
+        self.config = SpiConfig {
+            speed_hz: self.clock_hz / div,
+            mode: config.mode,
+        };
+        Ok(())
+    }
+
+    fn transfer(&mut self, cs: usize, write: &[u8], read: &mut [u8]) -> DevResult {
+        if cs >= NUM_CHIP_SELECTS {
+            return Err(DevError::InvalidParam);
+        }
+        let len = write.len().max(read.len());
+        if len == 0 {
+            return Ok(());
+        }
+        let mut ctrl = cs as u32;
+        if self.config.mode.cpol {
+            ctrl |= CS_CPOL;
+        }
+        if self.config.mode.cpha {
+            ctrl |= CS_CPHA;
+        }
+        self.regs.write_reg(REG_CS, ctrl | CS_CLEAR);
+        self.regs.write_reg(REG_CS, ctrl | CS_TA);
+
+        // every byte sent brings a byte in, so the transfer is done once all
+        // the bytes are received
+        let (mut sent, mut received) = (0, 0);
+        for _ in 0..POLL_LIMIT {
+            let status = self.regs.read_reg(REG_CS);
+            if status & CS_TXD != 0 && sent < len {
+                self.regs
+                    .write_reg(REG_FIFO, write.get(sent).copied().unwrap_or(0) as u32);
+                sent += 1;
+            }
+            if status & CS_RXD != 0 && received < len {
+                let byte = self.regs.read_reg(REG_FIFO) as u8;
+                if let Some(b) = read.get_mut(received) {
+                    *b = byte;
+                }
+                received += 1;
+            }
+            if received == len && status & CS_DONE != 0 {
+                return self.finish(true);
+            }
+        }
+        self.finish(false)
+    }
+}
@@ -0,0 +1,75 @@
+//! Common traits and types for SPI controller drivers.
+//!
+//! The drivers of memory-mapped controllers access the registers through
+//! [`RegisterAccess`](driver_common::RegisterAccess), so they are tested on
+//! the host with mock controllers (`cargo test -p driver_spi`).
+
+#![cfg_attr(not(test), no_std)]
+#![feature(doc_auto_cfg)]
+
+#[cfg(feature = "bcm2711")]
+pub mod bcm2711;
+
+#[cfg(all(test, feature = "bcm2711"))]
+mod tests;
+
+#[doc(no_inline)]
+pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+/// The clock polarity and phase of an SPI bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpiMode {
+    /// The clock idles high (CPOL).
+    pub cpol: bool,
+    /// The data are sampled on the second edge of the clock (CPHA).
+    pub cpha: bool,
+}
+
+impl SpiMode {
+    /// Mode 0: CPOL = 0, CPHA = 0.
+    pub const MODE0: Self = Self::new(false, false);
+    /// Mode 1: CPOL = 0, CPHA = 1.
+    pub const MODE1: Self = Self::new(false, true);
+    /// Mode 2: CPOL = 1, CPHA = 0.
+    pub const MODE2: Self = Self::new(true, false);
+    /// Mode 3: CPOL = 1, CPHA = 1.
+    pub const MODE3: Self = Self::new(true, true);
+
+    const fn new(cpol: bool, cpha: bool) -> Self {
+        Self { cpol, cpha }
+    }
+}
+
+/// The configuration of an SPI bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpiConfig {
+    /// The clock rate, in Hz.
+    pub speed_hz: u32,
+    /// The clock polarity and phase.
+    pub mode: SpiMode,
+}
+
+/// Operations that require an SPI controller driver to implement.
+///
+/// The controller is the master of the bus, the targets are selected by
+/// their chip select lines, numbered from 0 to
+/// [`SpiDriverOps::num_chip_selects`] - 1.
+pub trait SpiDriverOps: BaseDriverOps {
+    /// The number of chip select lines of the controller.
+    fn num_chip_selects(&self) -> usize;
+
+    /// The current configuration of the bus.
+    fn config(&self) -> SpiConfig;
+
+    /// Changes the configuration of the bus. The clock rate is rounded down
+    /// to one that the controller supports, see [`SpiDriverOps::config`] for
+    /// the result.
+    fn set_config(&mut self, config: SpiConfig) -> DevResult;
+
+    /// Selects the target by the chip select line `cs`, and exchanges
+    /// `max(write.len(), read.len())` bytes in full duplex.
+    ///
+    /// The bytes past `write` are sent as zeros, and the received bytes past
+    /// `read` are dropped.
+    fn transfer(&mut self, cs: usize, write: &[u8], read: &mut [u8]) -> DevResult;
+}
@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use driver_common::RegisterAccess;
+
+use crate::bcm2711::{Bcm2711Spi, NUM_CHIP_SELECTS};
+use crate::{DevError, SpiConfig, SpiDriverOps, SpiMode};
+
+#[derive(Default)]
+struct MockState {
+    cs: u32,
+    clk: u32,
+    rx: VecDeque<u8>,
+    /// The bytes sent, with the control bits (chip select and mode) at the
+    /// time.
+    sent: Vec<(u32, u8)>,
+}
+
+/// A mock BCM2711 SPI0 controller whose MISO is wired to MOSI, so the bytes
+/// sent come back one for one.
+#[derive(Clone, Default)]
+struct MockSpi(Arc<Mutex<MockState>>);
+
+impl MockSpi {
+    fn sent(&self) -> Vec<(u32, u8)> {
+        core::mem::take(&mut self.0.lock().unwrap().sent)
+    }
+}
+
+impl RegisterAccess for MockSpi {
+    fn read_reg(&self, offset: usize) -> u32 {
+        let mut state = self.0.lock().unwrap();
+        match offset {
+            0x00 => {
+                let mut cs = state.cs;
+                if cs & 1 << 7 != 0 {
+                    // the bytes are sent as soon as they are written
+                    cs |= 1 << 16 | 1 << 18;
+                }
+                if !state.rx.is_empty() {
+                    cs |= 1 << 17;
+                }
+                cs
+            }
+            0x04 => state.rx.pop_front().unwrap_or(0) as u32,
+            0x08 => state.clk,
+            _ => 0,
+        }
+    }
+
+    fn write_reg(&self, offset: usize, value: u32) {
+        let mut state = self.0.lock().unwrap();
+        match offset {
+            0x00 => {
+                if value & 0b11 << 4 != 0 {
+                    state.rx.clear();
+                }
+                state.cs = value & !(0b11 << 4);
+            }
+            0x04 => {
+                assert!(state.cs & 1 << 7 != 0, "written while not active");
+                let ctrl = state.cs & 0b1111;
+                state.sent.push((ctrl, value as u8));
+                state.rx.push_back(value as u8);
+            }
+            0x08 => state.clk = value,
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_config() {
+    let mock = MockSpi::default();
+    let mut spi = Bcm2711Spi::with_registers(mock.clone(), 500_000_000);
+    spi.init(SpiConfig {
+        speed_hz: 1_000_000,
+        mode: SpiMode::MODE0,
+    })
+    .unwrap();
+    assert_eq!(mock.read_reg(0x08), 500);
+    assert_eq!(spi.config().speed_hz, 1_000_000);
+
+    // rounded up to an even divider, so no faster than requested
+    spi.set_config(SpiConfig {
+        speed_hz: 3_000_000,
+        mode: SpiMode::MODE3,
+    })
+    .unwrap();
+    assert_eq!(mock.read_reg(0x08), 168);
+    assert_eq!(spi.config().speed_hz, 500_000_000 / 168);
+    assert_eq!(spi.config().mode, SpiMode::MODE3);
+
+    let slow = SpiConfig {
+        speed_hz: 1000,
+        mode: SpiMode::MODE0,
+    };
+    assert!(matches!(spi.set_config(slow), Err(DevError::InvalidParam)));
+}
+
+#[test]
+fn test_transfer() {
+    let mock = MockSpi::default();
+    let mut spi = Bcm2711Spi::with_registers(mock.clone(), 500_000_000);
+    spi.init(SpiConfig {
+        speed_hz: 1_000_000,
+        mode: SpiMode::MODE2,
+    })
+    .unwrap();
+    assert_eq!(spi.num_chip_selects(), NUM_CHIP_SELECTS);
+
+    // more than the FIFO holds
+    let data: Vec<u8> = (0..100).collect();
+    let mut rbuf = [0; 100];
+    spi.transfer(1, &data, &mut rbuf).unwrap();
+    assert_eq!(&rbuf[..], &data[..]);
+    let sent = mock.sent();
+    assert_eq!(sent.len(), 100);
+    // chip select 1, CPOL = 1
+    assert!(sent.iter().all(|&(ctrl, _)| ctrl == 1 | 1 << 3));
+
+    // padded with zeros, and the extra bytes dropped
+    let mut rbuf = [0xff; 4];
+    spi.transfer(0, &[1, 2], &mut rbuf).unwrap();
+    assert_eq!(rbuf, [1, 2, 0, 0]);
+    let mut rbuf = [0; 1];
+    spi.transfer(0, &[3, 4, 5], &mut rbuf).unwrap();
+    assert_eq!(rbuf, [3]);
+    let sent: Vec<u8> = mock.sent().into_iter().map(|(_, b)| b).collect();
+    assert_eq!(sent, [1, 2, 0, 0, 3, 4, 5]);
+
+    assert!(matches!(
+        spi.transfer(NUM_CHIP_SELECTS, &[0], &mut []),
+        Err(DevError::InvalidParam)
+    ));
+}
@@ -1,11 +1,44 @@
 use crate::as_dev_err;
-use driver_block::BlockDriverOps;
-use driver_common::{BaseDriverOps, DevResult, DeviceType};
-use virtio_drivers::{device::blk::VirtIOBlk as InnerDev, transport::Transport, Hal};
+use alloc::{boxed::Box, collections::BTreeMap};
+use driver_block::{BlockDriverOps, BlockOp, BlockRequest, BlockSegment, RequestId};
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+use virtio_drivers::device::blk::{BlkReq, BlkResp, VirtIOBlk as InnerDev, SECTOR_SIZE};
+use virtio_drivers::{transport::Transport, Hal};
+
+extern crate alloc;
+
+/// The number of descriptors used by a virtio request (header, data and
+/// status).
+const DESCS_PER_REQUEST: usize = 3;
+
+/// A virtio request in flight, i.e. one segment of a [`BlockRequest`].
+///
+/// It's boxed as the device accesses the header and the status by their
+/// addresses until the request is completed.
+struct Inflight {
+    req: BlkReq,
+    resp: BlkResp,
+    seg: BlockSegment,
+    write: bool,
+    parent: RequestId,
+}
+
+/// A submitted [`BlockRequest`] that has not been polled to completion.
+struct Pending {
+    remaining: usize,
+    result: DevResult,
+}
 
 /// The VirtIO block device driver.
+///
+/// Each segment of a submitted request becomes a virtio request, so up to
+/// [`BlockDriverOps::queue_depth`] segments can be in flight at the same time.
 pub struct VirtIoBlkDev<H: Hal, T: Transport> {
     inner: InnerDev<H, T>,
+    irq_num: Option<usize>,
+    inflight: BTreeMap<u16, Box<Inflight>>,
+    pending: BTreeMap<RequestId, Pending>,
+    next_id: RequestId,
 }
 
 unsafe impl<H: Hal, T: Transport> Send for VirtIoBlkDev<H, T> {}
@@ -14,11 +47,76 @@ unsafe impl<H: Hal, T: Transport> Sync for VirtIoBlkDev<H, T> {}
 impl<H: Hal, T: Transport> VirtIoBlkDev<H, T> {
     /// Creates a new driver instance and initializes the device, or returns
     /// an error if any step fails.
-    pub fn try_new(transport: T) -> DevResult<Self> {
+    ///
+    /// `irq_num` is the IRQ of the transport, if it's known.
+    pub fn try_new(transport: T, irq_num: Option<usize>) -> DevResult<Self> {
         Ok(Self {
             inner: InnerDev::new(transport).map_err(as_dev_err)?,
+            irq_num,
+            inflight: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            next_id: 0,
         })
     }
+
+    fn alloc_id(&mut self) -> RequestId {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        id
+    }
+
+    /// Submits one segment as a virtio request, and keeps it until completion.
+    unsafe fn submit_segment(&mut self, block_id: u64, mut inflight: Box<Inflight>) -> DevResult {
+        let Inflight {
+            req,
+            resp,
+            seg,
+            write,
+            ..
+        } = &mut *inflight;
+        let token = if *write {
+            let buf = core::slice::from_raw_parts(seg.ptr, seg.len);
+            self.inner.write_block_nb(block_id as _, req, buf, resp)
+        } else {
+            let buf = core::slice::from_raw_parts_mut(seg.ptr, seg.len);
+            self.inner.read_block_nb(block_id as _, req, buf, resp)
+        }
+        .map_err(as_dev_err)?;
+        self.inflight.insert(token, inflight);
+        Ok(())
+    }
+
+    /// Completes all the virtio requests that the device has finished, and
+    /// records their results in the requests they belong to.
+    fn reap_used(&mut self) {
+        while let Some(token) = self.inner.peek_used() {
+            let Some(mut inflight) = self.inflight.remove(&token) else {
+                break;
+            };
+            let Inflight {
+                req,
+                resp,
+                seg,
+                write,
+                parent,
+            } = &mut *inflight;
+            let res = unsafe {
+                if *write {
+                    let buf = core::slice::from_raw_parts(seg.ptr, seg.len);
+                    self.inner.complete_write_block(token, req, buf, resp)
+                } else {
+                    let buf = core::slice::from_raw_parts_mut(seg.ptr, seg.len);
+                    self.inner.complete_read_block(token, req, buf, resp)
+                }
+            };
+            if let Some(pending) = self.pending.get_mut(&*parent) {
+                pending.remaining -= 1;
+                if let (Err(e), Ok(())) = (res, &pending.result) {
+                    pending.result = Err(as_dev_err(e));
+                }
+            }
+        }
+    }
 }
 
 impl<H: Hal, T: Transport> const BaseDriverOps for VirtIoBlkDev<H, T> {
@@ -29,6 +127,10 @@ impl<H: Hal, T: Transport> const BaseDriverOps for VirtIoBlkDev<H, T> {
     fn device_type(&self) -> DeviceType {
         DeviceType::Block
     }
+
+    fn irq_num(&self) -> Option<usize> {
+        self.irq_num
+    }
 }
 
 impl<H: Hal, T: Transport> BlockDriverOps for VirtIoBlkDev<H, T> {
@@ -39,22 +141,109 @@ impl<H: Hal, T: Transport> BlockDriverOps for VirtIoBlkDev<H, T> {
 
     #[inline]
     fn block_size(&self) -> usize {
-        virtio_drivers::device::blk::SECTOR_SIZE
+        SECTOR_SIZE
     }
 
     fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
-        self.inner
-            .read_block(block_id as _, buf)
-            .map_err(as_dev_err)
+        let segments = [BlockSegment::new(buf)];
+        let req = BlockRequest {
+            op: BlockOp::Read,
+            block_id,
+            segments: &segments,
+        };
+        unsafe { driver_block::execute_request(self, &req) }
     }
 
     fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
-        self.inner
-            .write_block(block_id as _, buf)
-            .map_err(as_dev_err)
+        let segments = [BlockSegment::new_readonly(buf)];
+        let req = BlockRequest {
+            op: BlockOp::Write,
+            block_id,
+            segments: &segments,
+        };
+        unsafe { driver_block::execute_request(self, &req) }
     }
 
     fn flush(&mut self) -> DevResult {
         Ok(())
     }
+
+    #[inline]
+    fn ack_interrupt(&mut self) -> bool {
+        self.inner.ack_interrupt()
+    }
+
+    fn queue_depth(&self) -> usize {
+        self.inner.virt_queue_size() as usize / DESCS_PER_REQUEST
+    }
+
+    unsafe fn submit_request(&mut self, req: &BlockRequest) -> DevResult<RequestId> {
+        let write = match req.op {
+            BlockOp::Read => false,
+            BlockOp::Write => true,
+            // Writes are completed by the device before being reported as
+            // used, so there is nothing to flush.
+            BlockOp::Flush => {
+                let id = self.alloc_id();
+                let pending = Pending {
+                    remaining: 0,
+                    result: Ok(()),
+                };
+                self.pending.insert(id, pending);
+                return Ok(id);
+            }
+        };
+        if req.segments.len() > self.queue_depth() {
+            return Err(DevError::InvalidParam);
+        }
+        if req
+            .segments
+            .iter()
+            .any(|seg| seg.len == 0 || seg.len % SECTOR_SIZE != 0)
+        {
+            return Err(DevError::InvalidParam);
+        }
+        if self.inflight.len() + req.segments.len() > self.queue_depth() {
+            self.reap_used();
+            if self.inflight.len() + req.segments.len() > self.queue_depth() {
+                return Err(DevError::Again);
+            }
+        }
+
+        let id = self.alloc_id();
+        let mut pending = Pending {
+            remaining: 0,
+            result: Ok(()),
+        };
+        let mut block_id = req.block_id;
+        for &seg in req.segments {
+            let inflight = Box::new(Inflight {
+                req: BlkReq::default(),
+                resp: BlkResp::default(),
+                seg,
+                write,
+                parent: id,
+            });
+            // The segments already submitted must still be completed, so the
+            // error is reported by `poll_request`.
+            if let Err(e) = self.submit_segment(block_id, inflight) {
+                pending.result = Err(e);
+                break;
+            }
+            pending.remaining += 1;
+            block_id += (seg.len / SECTOR_SIZE) as u64;
+        }
+        self.pending.insert(id, pending);
+        Ok(id)
+    }
+
+    fn poll_request(&mut self, id: RequestId) -> DevResult<bool> {
+        self.reap_used();
+        let pending = self.pending.get(&id).ok_or(DevError::InvalidParam)?;
+        if pending.remaining > 0 {
+            return Ok(false);
+        }
+        let pending = self.pending.remove(&id).unwrap();
+        pending.result.map(|_| true)
+    }
 }
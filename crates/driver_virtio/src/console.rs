@@ -0,0 +1,66 @@
+use crate::as_dev_err;
+use driver_char::{CharDriverOps, CharPollState};
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+use virtio_drivers::{device::console::VirtIOConsole as InnerDev, transport::Transport, Hal};
+
+/// The VirtIO console device driver.
+pub struct VirtIoConsoleDev<H: Hal, T: Transport> {
+    inner: InnerDev<H, T>,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIoConsoleDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIoConsoleDev<H, T> {}
+
+impl<H: Hal, T: Transport> VirtIoConsoleDev<H, T> {
+    /// Creates a new driver instance and initializes the device, or returns
+    /// an error if any step fails.
+    pub fn try_new(transport: T) -> DevResult<Self> {
+        Ok(Self {
+            inner: InnerDev::new(transport).map_err(as_dev_err)?,
+        })
+    }
+}
+
+impl<H: Hal, T: Transport> const BaseDriverOps for VirtIoConsoleDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-console"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Char
+    }
+}
+
+impl<H: Hal, T: Transport> CharDriverOps for VirtIoConsoleDev<H, T> {
+    fn read(&mut self, buf: &mut [u8]) -> DevResult<usize> {
+        let mut count = 0;
+        while count < buf.len() {
+            match self.inner.recv(true).map_err(as_dev_err)? {
+                Some(b) => buf[count] = b,
+                None => break,
+            }
+            count += 1;
+        }
+        if count == 0 && !buf.is_empty() {
+            Err(DevError::Again)
+        } else {
+            Ok(count)
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> DevResult<usize> {
+        // The transmit queue is drained synchronously by `send`, so the device
+        // always accepts the whole buffer.
+        for &b in buf {
+            self.inner.send(b).map_err(as_dev_err)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn poll(&mut self) -> CharPollState {
+        CharPollState {
+            readable: matches!(self.inner.recv(false), Ok(Some(_))),
+            writable: true,
+        }
+    }
+}
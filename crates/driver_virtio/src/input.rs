@@ -0,0 +1,43 @@
+use crate::as_dev_err;
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+use driver_input::{InputDriverOps, InputEvent};
+use virtio_drivers::{device::input::VirtIOInput as InnerDev, transport::Transport, Hal};
+
+/// The VirtIO input device driver (i.e. keyboards, mice and tablets).
+pub struct VirtIoInputDev<H: Hal, T: Transport> {
+    inner: InnerDev<H, T>,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIoInputDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIoInputDev<H, T> {}
+
+impl<H: Hal, T: Transport> VirtIoInputDev<H, T> {
+    /// Creates a new driver instance and initializes the device, or returns
+    /// an error if any step fails.
+    pub fn try_new(transport: T) -> DevResult<Self> {
+        Ok(Self {
+            inner: InnerDev::new(transport).map_err(as_dev_err)?,
+        })
+    }
+}
+
+impl<H: Hal, T: Transport> const BaseDriverOps for VirtIoInputDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-input"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Input
+    }
+}
+
+impl<H: Hal, T: Transport> InputDriverOps for VirtIoInputDev<H, T> {
+    fn read_event(&mut self) -> DevResult<InputEvent> {
+        let e = self.inner.pop_pending_event().ok_or(DevError::Again)?;
+        Ok(InputEvent {
+            event_type: e.event_type,
+            code: e.code,
+            value: e.value,
+        })
+    }
+}
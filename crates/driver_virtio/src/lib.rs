@@ -16,17 +16,29 @@
 
 #[cfg(feature = "block")]
 mod blk;
+#[cfg(feature = "console")]
+mod console;
 #[cfg(feature = "gpu")]
 mod gpu;
+#[cfg(feature = "input")]
+mod input;
 #[cfg(feature = "net")]
 mod net;
+#[cfg(feature = "rng")]
+mod rng;
 
 #[cfg(feature = "block")]
 pub use self::blk::VirtIoBlkDev;
+#[cfg(feature = "console")]
+pub use self::console::VirtIoConsoleDev;
 #[cfg(feature = "gpu")]
 pub use self::gpu::VirtIoGpuDev;
+#[cfg(feature = "input")]
+pub use self::input::VirtIoInputDev;
 #[cfg(feature = "net")]
 pub use self::net::VirtIoNetDev;
+#[cfg(feature = "rng")]
+pub use self::rng::VirtIoRngDev;
 
 pub use virtio_drivers::transport::pci::bus as pci;
 pub use virtio_drivers::transport::{mmio::MmioTransport, pci::PciTransport, Transport};
@@ -75,6 +87,9 @@ const fn as_dev_type(t: VirtIoDevType) -> Option<DeviceType> {
         Block => Some(DeviceType::Block),
         Network => Some(DeviceType::Net),
         GPU => Some(DeviceType::Display),
+        Console => Some(DeviceType::Char),
+        EntropySource => Some(DeviceType::Rng),
+        Input => Some(DeviceType::Input),
         _ => None,
     }
 }
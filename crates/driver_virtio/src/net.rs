@@ -10,13 +10,15 @@ const NET_BUF_LEN: usize = 1526;
 
 /// The VirtIO network device driver.
 ///
-/// `QS` is the VirtIO queue size.
+/// `QS` is the VirtIO queue size. Only one pair of receive and transmit queues
+/// is used, as multiqueue is not negotiated by `virtio-drivers`.
 pub struct VirtIoNetDev<H: Hal, T: Transport, const QS: usize> {
     rx_buffers: [Option<NetBufBox>; QS],
     tx_buffers: [Option<NetBufBox>; QS],
     free_tx_bufs: Vec<NetBufBox>,
     buf_pool: Arc<NetBufPool>,
     inner: InnerDev<H, T, QS>,
+    irq_num: Option<usize>,
 }
 
 unsafe impl<H: Hal, T: Transport, const QS: usize> Send for VirtIoNetDev<H, T, QS> {}
@@ -25,7 +27,9 @@ unsafe impl<H: Hal, T: Transport, const QS: usize> Sync for VirtIoNetDev<H, T, Q
 impl<H: Hal, T: Transport, const QS: usize> VirtIoNetDev<H, T, QS> {
     /// Creates a new driver instance and initializes the device, or returns
     /// an error if any step fails.
-    pub fn try_new(transport: T) -> DevResult<Self> {
+    ///
+    /// `irq_num` is the IRQ of the transport, if it's known.
+    pub fn try_new(transport: T, irq_num: Option<usize>) -> DevResult<Self> {
         // 0. Create a new driver instance.
         const NONE_BUF: Option<NetBufBox> = None;
         let inner = InnerDev::new(transport).map_err(as_dev_err)?;
@@ -40,6 +44,7 @@ impl<H: Hal, T: Transport, const QS: usize> VirtIoNetDev<H, T, QS> {
             tx_buffers,
             free_tx_bufs,
             buf_pool,
+            irq_num,
         };
 
         // 1. Fill all rx buffers.
@@ -80,6 +85,10 @@ impl<H: Hal, T: Transport, const QS: usize> const BaseDriverOps for VirtIoNetDev
     fn device_type(&self) -> DeviceType {
         DeviceType::Net
     }
+
+    fn irq_num(&self) -> Option<usize> {
+        self.irq_num
+    }
 }
 
 impl<H: Hal, T: Transport, const QS: usize> NetDriverOps for VirtIoNetDev<H, T, QS> {
@@ -190,4 +199,9 @@ impl<H: Hal, T: Transport, const QS: usize> NetDriverOps for VirtIoNetDev<H, T,
         // 2. Return the buffer.
         Ok(net_buf.into_buf_ptr())
     }
+
+    #[inline]
+    fn ack_interrupt(&mut self) -> bool {
+        self.inner.ack_interrupt()
+    }
 }
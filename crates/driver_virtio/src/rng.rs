@@ -0,0 +1,185 @@
+use core::marker::PhantomData;
+use core::ptr::{addr_of, addr_of_mut, NonNull};
+use core::sync::atomic::{fence, Ordering};
+
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+use driver_rng::RngDriverOps;
+use virtio_drivers::transport::{DeviceStatus, Transport};
+use virtio_drivers::{BufferDirection, Hal, PhysAddr};
+
+const PAGE_SIZE: usize = 0x1000;
+const QUEUE_SIZE: usize = 4;
+const QUEUE_IDX: u16 = 0;
+
+const VIRTIO_F_VERSION_1: u64 = 1 << 32;
+const VIRTQ_DESC_F_WRITE: u16 = 2;
+
+#[repr(C)]
+struct Descriptor {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+#[repr(C)]
+struct AvailRing {
+    flags: u16,
+    idx: u16,
+    ring: [u16; QUEUE_SIZE],
+    used_event: u16,
+}
+
+#[repr(C)]
+struct UsedElem {
+    id: u32,
+    len: u32,
+}
+
+#[repr(C)]
+struct UsedRing {
+    flags: u16,
+    idx: u16,
+    ring: [UsedElem; QUEUE_SIZE],
+    avail_event: u16,
+}
+
+/// The VirtIO entropy device driver.
+///
+/// The device has a single request queue, which is small enough to be set up
+/// here directly: the first DMA page holds the descriptor table and the
+/// available ring, the second one the used ring (the legacy layout), and the
+/// third one the buffer that the device fills with random bytes. Only one
+/// request is in flight at a time.
+pub struct VirtIoRngDev<H: Hal, T: Transport> {
+    transport: T,
+    dma_paddr: PhysAddr,
+    dma_vaddr: NonNull<u8>,
+    avail_idx: u16,
+    _hal: PhantomData<H>,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIoRngDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIoRngDev<H, T> {}
+
+impl<H: Hal, T: Transport> VirtIoRngDev<H, T> {
+    const DMA_PAGES: usize = 3;
+
+    /// Creates a new driver instance and initializes the device, or returns
+    /// an error if any step fails.
+    pub fn try_new(mut transport: T) -> DevResult<Self> {
+        transport.set_status(DeviceStatus::empty());
+        transport.set_status(DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER);
+        let features = transport.read_device_features() & VIRTIO_F_VERSION_1;
+        transport.write_driver_features(features);
+        transport.set_status(
+            DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER | DeviceStatus::FEATURES_OK,
+        );
+        if !transport.get_status().contains(DeviceStatus::FEATURES_OK) {
+            transport.set_status(DeviceStatus::FAILED);
+            return Err(DevError::Unsupported);
+        }
+        transport.set_guest_page_size(PAGE_SIZE as u32);
+
+        let (dma_paddr, dma_vaddr) = H::dma_alloc(Self::DMA_PAGES, BufferDirection::Both);
+        if dma_paddr == 0 {
+            transport.set_status(DeviceStatus::FAILED);
+            return Err(DevError::NoMemory);
+        }
+        let dma_size = Self::DMA_PAGES * PAGE_SIZE;
+        unsafe { dma_vaddr.as_ptr().write_bytes(0, dma_size) };
+        transport.queue_set(
+            QUEUE_IDX,
+            QUEUE_SIZE as u32,
+            dma_paddr,
+            dma_paddr + core::mem::size_of::<[Descriptor; QUEUE_SIZE]>(),
+            dma_paddr + PAGE_SIZE,
+        );
+        transport.set_status(
+            DeviceStatus::ACKNOWLEDGE
+                | DeviceStatus::DRIVER
+                | DeviceStatus::FEATURES_OK
+                | DeviceStatus::DRIVER_OK,
+        );
+
+        Ok(Self {
+            transport,
+            dma_paddr,
+            dma_vaddr,
+            avail_idx: 0,
+            _hal: PhantomData,
+        })
+    }
+
+    fn desc(&self) -> *mut Descriptor {
+        self.dma_vaddr.as_ptr() as *mut Descriptor
+    }
+
+    fn avail(&self) -> *mut AvailRing {
+        let offset = core::mem::size_of::<[Descriptor; QUEUE_SIZE]>();
+        unsafe { self.dma_vaddr.as_ptr().add(offset) as *mut AvailRing }
+    }
+
+    fn used(&self) -> *const UsedRing {
+        unsafe { self.dma_vaddr.as_ptr().add(PAGE_SIZE) as *const UsedRing }
+    }
+
+    fn data(&self) -> *const u8 {
+        unsafe { self.dma_vaddr.as_ptr().add(2 * PAGE_SIZE) }
+    }
+}
+
+impl<H: Hal, T: Transport> Drop for VirtIoRngDev<H, T> {
+    fn drop(&mut self) {
+        // Stop the device before giving its queue memory back.
+        self.transport.set_status(DeviceStatus::empty());
+        unsafe { H::dma_dealloc(self.dma_paddr, self.dma_vaddr, Self::DMA_PAGES) };
+    }
+}
+
+impl<H: Hal, T: Transport> const BaseDriverOps for VirtIoRngDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-rng"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Rng
+    }
+}
+
+impl<H: Hal, T: Transport> RngDriverOps for VirtIoRngDev<H, T> {
+    fn request_entropy(&mut self, buf: &mut [u8]) -> DevResult<usize> {
+        let len = buf.len().min(PAGE_SIZE);
+        if len == 0 {
+            return Ok(0);
+        }
+        let slot = self.avail_idx as usize % QUEUE_SIZE;
+        unsafe {
+            self.desc().write_volatile(Descriptor {
+                addr: (self.dma_paddr + 2 * PAGE_SIZE) as u64,
+                len: len as u32,
+                flags: VIRTQ_DESC_F_WRITE,
+                next: 0,
+            });
+            let avail = self.avail();
+            addr_of_mut!((*avail).ring[slot]).write_volatile(0);
+            fence(Ordering::SeqCst);
+            self.avail_idx = self.avail_idx.wrapping_add(1);
+            addr_of_mut!((*avail).idx).write_volatile(self.avail_idx);
+            fence(Ordering::SeqCst);
+        }
+        self.transport.notify(QUEUE_IDX);
+
+        // Requests complete in order, so the used index catches up with the
+        // available index once the device has filled the buffer.
+        let used = self.used();
+        while unsafe { addr_of!((*used).idx).read_volatile() } != self.avail_idx {
+            core::hint::spin_loop();
+        }
+        fence(Ordering::SeqCst);
+        let filled = unsafe { addr_of!((*used).ring[slot].len).read_volatile() } as usize;
+        let filled = filled.min(len);
+        unsafe { core::ptr::copy_nonoverlapping(self.data(), buf.as_mut_ptr(), filled) };
+        Ok(filled)
+    }
+}
@@ -0,0 +1,891 @@
+//! A zero-allocation parser of the flattened device tree (FDT), also known as
+//! the device tree blob (DTB).
+//!
+//! It reads the blob in place, and provides iteration over the nodes and
+//! properties, lookup by path or by `compatible` string, and decoding of the
+//! common properties (`reg`, `interrupts`, ...). The [devicetree
+//! specification] describes the format.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use fdt_parser::Fdt;
+//!
+//! # let dtb_ptr = core::ptr::null();
+//! let fdt = unsafe { Fdt::from_ptr(dtb_ptr) }.unwrap();
+//! for region in fdt.memory_regions() {
+//!     println!("memory: {:#x}..{:#x}", region.address, region.address + region.size.unwrap_or(0));
+//! }
+//! for node in fdt.find_compatible(&["virtio,mmio"]) {
+//!     let reg = node.reg().unwrap().next().unwrap();
+//!     println!("{}: {:#x?}", node.name(), reg);
+//! }
+//! println!("{} CPUs", fdt.cpu_count());
+//! ```
+//!
+//! [devicetree specification]: https://www.devicetree.org/specifications/
+
+#![cfg_attr(not(test), no_std)]
+
+use core::fmt;
+
+/// The magic number at the beginning of a device tree blob.
+pub const FDT_MAGIC: u32 = 0xd00d_feed;
+
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+const HEADER_SIZE: usize = 40;
+/// The oldest version that has the fields we read.
+const LAST_COMP_VERSION: u32 = 16;
+/// The maximum depth of nodes whose cells are tracked.
+const MAX_DEPTH: usize = 16;
+
+/// The errors of parsing a device tree blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FdtError {
+    /// The pointer to the blob is null or unaligned.
+    BadPtr,
+    /// The blob does not start with [`FDT_MAGIC`].
+    BadMagic,
+    /// The blob is of an incompatible version.
+    BadVersion,
+    /// The blob is shorter than the sizes in its header.
+    Truncated,
+    /// The blocks in the header are out of the blob.
+    BadLayout,
+}
+
+impl fmt::Display for FdtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Self::BadPtr => "bad pointer",
+            Self::BadMagic => "bad magic number",
+            Self::BadVersion => "incompatible version",
+            Self::Truncated => "truncated blob",
+            Self::BadLayout => "bad block layout",
+        };
+        f.write_str(msg)
+    }
+}
+
+/// Reads a big-endian `u32` at `offset`.
+fn be32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// Reads a NUL-terminated string at the beginning of `data`.
+fn c_str(data: &[u8]) -> Option<&str> {
+    let len = data.iter().position(|&b| b == 0)?;
+    core::str::from_utf8(&data[..len]).ok()
+}
+
+const fn align4(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+/// A parsed flattened device tree.
+#[derive(Clone, Copy)]
+pub struct Fdt<'a> {
+    data: &'a [u8],
+    structs: &'a [u8],
+    strings: &'a [u8],
+    boot_cpuid: u32,
+}
+
+impl<'a> Fdt<'a> {
+    /// Parses the device tree blob in `data`.
+    pub fn new(data: &'a [u8]) -> Result<Self, FdtError> {
+        let header = |idx: usize| be32(data, idx * 4).ok_or(FdtError::Truncated);
+        if header(0)? != FDT_MAGIC {
+            return Err(FdtError::BadMagic);
+        }
+        if data.len() < HEADER_SIZE {
+            return Err(FdtError::Truncated);
+        }
+        let total_size = header(1)? as usize;
+        if data.len() < total_size {
+            return Err(FdtError::Truncated);
+        }
+        if header(6)? > LAST_COMP_VERSION || header(5)? < LAST_COMP_VERSION {
+            return Err(FdtError::BadVersion);
+        }
+        let data = &data[..total_size];
+        let block = |offset: u32, size: u32| {
+            let start = offset as usize;
+            let end = start
+                .checked_add(size as usize)
+                .ok_or(FdtError::BadLayout)?;
+            data.get(start..end).ok_or(FdtError::BadLayout)
+        };
+        Ok(Self {
+            data,
+            structs: block(header(2)?, header(9)?)?,
+            strings: block(header(3)?, header(8)?)?,
+            boot_cpuid: header(7)?,
+        })
+    }
+
+    /// Parses the device tree blob at `ptr`, whose size is read from its
+    /// header.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a readable device tree blob that lives for `'a`.
+    pub unsafe fn from_ptr(ptr: *const u8) -> Result<Self, FdtError> {
+        if ptr.is_null() || ptr as usize & 3 != 0 {
+            return Err(FdtError::BadPtr);
+        }
+        let header = core::slice::from_raw_parts(ptr, 8);
+        if be32(header, 0) != Some(FDT_MAGIC) {
+            return Err(FdtError::BadMagic);
+        }
+        let total_size = be32(header, 4).unwrap() as usize;
+        Self::new(core::slice::from_raw_parts(ptr, total_size))
+    }
+
+    /// The size in bytes of the whole blob.
+    pub fn total_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The physical ID of the boot CPU.
+    pub fn boot_cpuid(&self) -> u32 {
+        self.boot_cpuid
+    }
+
+    /// The entries of the memory reservation block, as `(address, size)`
+    /// pairs.
+    pub fn reserved_regions(&self) -> impl Iterator<Item = (u64, u64)> + 'a {
+        let data = self.data;
+        let offset = be32(data, 16).unwrap_or(0) as usize;
+        let read = move |idx: usize| {
+            let at = offset + idx * 8;
+            Some(((be32(data, at)? as u64) << 32) | be32(data, at + 4)? as u64)
+        };
+        (0..)
+            .map(move |i| Some((read(i * 2)?, read(i * 2 + 1)?)))
+            .map_while(|entry| entry.filter(|&(addr, size)| addr != 0 || size != 0))
+    }
+
+    /// The root node.
+    pub fn root(&self) -> Option<Node<'a>> {
+        self.all_nodes().next()
+    }
+
+    /// Iterates over all nodes in depth-first order, starting from the root.
+    pub fn all_nodes(&self) -> NodeIter<'a> {
+        NodeIter {
+            fdt: *self,
+            offset: 0,
+            depth: 0,
+            cells: [Cells::DEFAULT; MAX_DEPTH],
+        }
+    }
+
+    /// Finds the node at `path`, such as `/cpus` or `/soc/serial@10000000`.
+    ///
+    /// The unit address can be omitted in the components of the path, then
+    /// the first node with the name matches.
+    pub fn find_node(&self, path: &str) -> Option<Node<'a>> {
+        let path = path.strip_prefix('/')?;
+        let mut components = path.split('/').filter(|c| !c.is_empty());
+        let mut target = components.next();
+        // the depth of the last matched node
+        let mut matched = 0;
+        for node in self.all_nodes() {
+            let Some(component) = target else {
+                return Some(node); // the root
+            };
+            if node.depth == 0 {
+                continue;
+            }
+            if node.depth <= matched {
+                // left the subtree of the last matched node
+                return None;
+            }
+            if node.depth == matched + 1 && node.name_matches(component) {
+                matched += 1;
+                target = components.next();
+                if target.is_none() {
+                    return Some(node);
+                }
+            }
+        }
+        None
+    }
+
+    /// Iterates over the nodes that are compatible with any of `compatible`,
+    /// and whose `status` is okay.
+    pub fn find_compatible<'b>(
+        &self,
+        compatible: &'b [&'b str],
+    ) -> impl Iterator<Item = Node<'a>> + 'b
+    where
+        'a: 'b,
+    {
+        self.all_nodes().filter(move |node| {
+            node.is_available() && compatible.iter().any(|c| node.is_compatible(c))
+        })
+    }
+
+    /// Finds the node whose `phandle` is `phandle`.
+    pub fn find_phandle(&self, phandle: u32) -> Option<Node<'a>> {
+        self.all_nodes().find(|node| {
+            node.property("phandle")
+                .or_else(|| node.property("linux,phandle"))
+                .and_then(|prop| prop.as_u32())
+                == Some(phandle)
+        })
+    }
+
+    /// The `/chosen` node.
+    pub fn chosen(&self) -> Option<Node<'a>> {
+        self.find_node("/chosen")
+    }
+
+    /// The physical memory regions of all `memory` nodes.
+    pub fn memory_regions(&self) -> impl Iterator<Item = RegEntry> + 'a {
+        self.all_nodes()
+            .filter(|node| node.depth == 1 && node.is_memory() && node.is_available())
+            .filter_map(|node| node.reg())
+            .flatten()
+    }
+
+    /// The regions of the available children of the `/reserved-memory` node.
+    ///
+    /// Only the statically placed ones (with `reg`) are included, the ones
+    /// with just `size` are allocated by the OS from the free memory.
+    pub fn reserved_memory(&self) -> impl Iterator<Item = RegEntry> + 'a {
+        let mut in_reserved = false;
+        self.all_nodes()
+            .filter(move |node| {
+                if node.depth == 1 {
+                    in_reserved = node.name_matches("reserved-memory");
+                }
+                in_reserved && node.depth == 2 && node.is_available()
+            })
+            .filter_map(|node| node.reg())
+            .flatten()
+    }
+
+    /// Iterates over the available CPU nodes under `/cpus`.
+    pub fn cpus(&self) -> impl Iterator<Item = Node<'a>> + 'a {
+        let mut in_cpus = false;
+        self.all_nodes().filter(move |node| {
+            match node.depth {
+                1 => in_cpus = node.name == "cpus",
+                2 if in_cpus => {
+                    return node.property("device_type").and_then(|p| p.as_str()) == Some("cpu")
+                        && node.is_available()
+                }
+                _ => {}
+            }
+            false
+        })
+    }
+
+    /// The number of available CPUs.
+    pub fn cpu_count(&self) -> usize {
+        self.cpus().count()
+    }
+}
+
+impl fmt::Debug for Fdt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Fdt")
+            .field("total_size", &self.total_size())
+            .field("boot_cpuid", &self.boot_cpuid)
+            .finish()
+    }
+}
+
+/// The `#address-cells` and `#size-cells` that a node declares for its
+/// children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cells {
+    /// The number of `u32` cells of an address.
+    pub address: u32,
+    /// The number of `u32` cells of a size.
+    pub size: u32,
+}
+
+impl Cells {
+    /// The default when the properties are absent.
+    pub const DEFAULT: Self = Self {
+        address: 2,
+        size: 1,
+    };
+}
+
+/// An iterator over the nodes of a device tree, see [`Fdt::all_nodes`].
+pub struct NodeIter<'a> {
+    fdt: Fdt<'a>,
+    offset: usize,
+    depth: usize,
+    /// The cells declared by the current ancestors, indexed by depth.
+    cells: [Cells; MAX_DEPTH],
+}
+
+impl<'a> Iterator for NodeIter<'a> {
+    type Item = Node<'a>;
+
+    fn next(&mut self) -> Option<Node<'a>> {
+        let structs = self.fdt.structs;
+        loop {
+            let token = be32(structs, self.offset)?;
+            self.offset += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    let name = c_str(structs.get(self.offset..)?)?;
+                    self.offset += align4(name.len() + 1);
+                    let parent_cells = match self.depth {
+                        0 => Cells::DEFAULT,
+                        d => self.cells[(d - 1).min(MAX_DEPTH - 1)],
+                    };
+                    let node = Node {
+                        fdt: self.fdt,
+                        name,
+                        props_offset: self.offset,
+                        depth: self.depth,
+                        parent_cells,
+                    };
+                    if self.depth < MAX_DEPTH {
+                        self.cells[self.depth] = node.cells();
+                    }
+                    self.depth += 1;
+                    return Some(node);
+                }
+                FDT_END_NODE => self.depth = self.depth.checked_sub(1)?,
+                FDT_PROP => {
+                    let len = be32(structs, self.offset)? as usize;
+                    self.offset += 8 + align4(len);
+                }
+                FDT_NOP => {}
+                FDT_END => return None,
+                _ => return None, // malformed
+            }
+        }
+    }
+}
+
+/// A node of a device tree.
+#[derive(Clone, Copy)]
+pub struct Node<'a> {
+    fdt: Fdt<'a>,
+    name: &'a str,
+    /// The offset of the first token after the name in the structure block.
+    props_offset: usize,
+    depth: usize,
+    parent_cells: Cells,
+}
+
+impl<'a> Node<'a> {
+    /// The name of the node, including the unit address (e.g.
+    /// `memory@80000000`). It's empty for the root.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// The depth of the node, which is 0 for the root.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Whether the name is `name`, or `name` has no unit address and matches
+    /// the name without the unit address.
+    fn name_matches(&self, name: &str) -> bool {
+        self.name == name || (!name.contains('@') && self.name.split('@').next() == Some(name))
+    }
+
+    /// Iterates over the properties of the node.
+    pub fn properties(&self) -> impl Iterator<Item = Property<'a>> + 'a {
+        let Fdt {
+            structs, strings, ..
+        } = self.fdt;
+        let mut offset = self.props_offset;
+        core::iter::from_fn(move || loop {
+            match be32(structs, offset)? {
+                FDT_PROP => {
+                    let len = be32(structs, offset + 4)? as usize;
+                    let name_offset = be32(structs, offset + 8)? as usize;
+                    let value = structs.get(offset + 12..offset + 12 + len)?;
+                    offset += 12 + align4(len);
+                    return Some(Property {
+                        name: c_str(strings.get(name_offset..)?)?,
+                        value,
+                    });
+                }
+                FDT_NOP => offset += 4,
+                _ => return None, // the first child or the end of the node
+            }
+        })
+    }
+
+    /// Finds the property named `name`.
+    pub fn property(&self, name: &str) -> Option<Property<'a>> {
+        self.properties().find(|prop| prop.name == name)
+    }
+
+    /// Iterates over the strings in the `compatible` property.
+    pub fn compatible(&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.property("compatible")
+            .into_iter()
+            .flat_map(|prop| prop.as_str_list())
+    }
+
+    /// Whether `compatible` is one of the strings in the `compatible`
+    /// property.
+    pub fn is_compatible(&self, compatible: &str) -> bool {
+        self.compatible().any(|c| c == compatible)
+    }
+
+    /// Whether the `status` is absent, `okay` or `ok`.
+    pub fn is_available(&self) -> bool {
+        match self.property("status").and_then(|p| p.as_str()) {
+            None | Some("okay") | Some("ok") => true,
+            Some(_) => false,
+        }
+    }
+
+    fn is_memory(&self) -> bool {
+        self.property("device_type").and_then(|p| p.as_str()) == Some("memory")
+    }
+
+    /// The cells this node declares for its children.
+    pub fn cells(&self) -> Cells {
+        let cells = |name| self.property(name).and_then(|p| p.as_u32());
+        Cells {
+            address: cells("#address-cells").unwrap_or(Cells::DEFAULT.address),
+            size: cells("#size-cells").unwrap_or(Cells::DEFAULT.size),
+        }
+    }
+
+    /// Decodes the `reg` property with the cells of the parent.
+    ///
+    /// The addresses are in the address space of the parent bus, `ranges`
+    /// are not translated.
+    pub fn reg(&self) -> Option<impl Iterator<Item = RegEntry> + 'a> {
+        let Cells { address, size } = self.parent_cells;
+        let (address, size) = (address as usize, size as usize);
+        let value = self.property("reg")?.value;
+        let entry_len = (address + size) * 4;
+        if entry_len == 0 || address > 2 || size > 2 {
+            return None;
+        }
+        Some(value.chunks_exact(entry_len).map(move |entry| {
+            let (addr, len) = entry.split_at(address * 4);
+            RegEntry {
+                address: cells_to_u64(addr),
+                size: (size != 0).then(|| cells_to_u64(len)),
+            }
+        }))
+    }
+
+    /// The raw cells of the `interrupts` property, whose meaning depends on
+    /// the interrupt controller.
+    pub fn interrupts(&self) -> Option<impl Iterator<Item = u32> + 'a> {
+        self.property("interrupts").map(|prop| prop.as_u32_cells())
+    }
+}
+
+impl fmt::Debug for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("name", &self.name)
+            .field("depth", &self.depth)
+            .finish()
+    }
+}
+
+/// Joins big-endian `u32` cells into a `u64`.
+fn cells_to_u64(cells: &[u8]) -> u64 {
+    cells.chunks_exact(4).fold(0, |acc, c| {
+        (acc << 32) | u32::from_be_bytes(c.try_into().unwrap()) as u64
+    })
+}
+
+/// An entry of the `reg` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegEntry {
+    /// The start address.
+    pub address: u64,
+    /// The size, or `None` if `#size-cells` of the parent is 0.
+    pub size: Option<u64>,
+}
+
+/// A property of a node.
+#[derive(Debug, Clone, Copy)]
+pub struct Property<'a> {
+    /// The name of the property.
+    pub name: &'a str,
+    /// The raw value of the property.
+    pub value: &'a [u8],
+}
+
+impl<'a> Property<'a> {
+    /// The value as a single `u32` cell.
+    pub fn as_u32(&self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.value.try_into().ok()?))
+    }
+
+    /// The value as a `u64` of one or two cells.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.value.len() {
+            4 | 8 => Some(cells_to_u64(self.value)),
+            _ => None,
+        }
+    }
+
+    /// The value as a NUL-terminated string.
+    pub fn as_str(&self) -> Option<&'a str> {
+        c_str(self.value)
+    }
+
+    /// The value as a list of NUL-terminated strings.
+    pub fn as_str_list(&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.value
+            .split(|&b| b == 0)
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| core::str::from_utf8(s).ok())
+    }
+
+    /// The value as `u32` cells.
+    pub fn as_u32_cells(&self) -> impl Iterator<Item = u32> + 'a {
+        self.value
+            .chunks_exact(4)
+            .map(|c| u32::from_be_bytes(c.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a device tree blob in memory.
+    #[derive(Default)]
+    struct Builder {
+        structs: Vec<u8>,
+        strings: Vec<u8>,
+    }
+
+    impl Builder {
+        fn token(&mut self, token: u32) -> &mut Self {
+            self.structs.extend_from_slice(&token.to_be_bytes());
+            self
+        }
+
+        fn pad(&mut self) {
+            self.structs.resize(align4(self.structs.len()), 0);
+        }
+
+        fn begin(&mut self, name: &str) -> &mut Self {
+            self.token(FDT_BEGIN_NODE);
+            self.structs.extend_from_slice(name.as_bytes());
+            self.structs.push(0);
+            self.pad();
+            self
+        }
+
+        fn end(&mut self) -> &mut Self {
+            self.token(FDT_END_NODE)
+        }
+
+        fn prop(&mut self, name: &str, value: &[u8]) -> &mut Self {
+            let name_offset = self.strings.len() as u32;
+            self.strings.extend_from_slice(name.as_bytes());
+            self.strings.push(0);
+            self.token(FDT_PROP)
+                .token(value.len() as u32)
+                .token(name_offset);
+            self.structs.extend_from_slice(value);
+            self.pad();
+            self
+        }
+
+        fn prop_cells(&mut self, name: &str, cells: &[u32]) -> &mut Self {
+            let value: Vec<u8> = cells.iter().flat_map(|c| c.to_be_bytes()).collect();
+            self.prop(name, &value)
+        }
+
+        fn prop_str(&mut self, name: &str, value: &str) -> &mut Self {
+            self.prop(name, format!("{}\0", value).as_bytes())
+        }
+
+        fn build(&mut self, reserved: &[(u64, u64)]) -> Vec<u8> {
+            self.token(FDT_END);
+            let rsv_offset = HEADER_SIZE;
+            let rsv_len = (reserved.len() + 1) * 16;
+            let struct_offset = rsv_offset + rsv_len;
+            let strings_offset = struct_offset + self.structs.len();
+            let total_size = strings_offset + self.strings.len();
+            let header = [
+                FDT_MAGIC,
+                total_size as u32,
+                struct_offset as u32,
+                strings_offset as u32,
+                rsv_offset as u32,
+                17, // version
+                16, // last compatible version
+                0,  // boot CPU
+                self.strings.len() as u32,
+                self.structs.len() as u32,
+            ];
+            let mut blob: Vec<u8> = header.iter().flat_map(|h| h.to_be_bytes()).collect();
+            for &(addr, size) in reserved.iter().chain([(0, 0)].iter()) {
+                blob.extend_from_slice(&addr.to_be_bytes());
+                blob.extend_from_slice(&size.to_be_bytes());
+            }
+            blob.extend_from_slice(&self.structs);
+            blob.extend_from_slice(&self.strings);
+            blob
+        }
+    }
+
+    /// A blob like the one of the QEMU RISC-V virt machine.
+    fn qemu_virt() -> Vec<u8> {
+        let mut b = Builder::default();
+        b.begin("")
+            .prop_cells("#address-cells", &[2])
+            .prop_cells("#size-cells", &[2])
+            .prop_str("compatible", "riscv-virtio")
+            .begin("chosen")
+            .prop_str("stdout-path", "/soc/serial@10000000")
+            .end()
+            .begin("memory@80000000")
+            .prop_str("device_type", "memory")
+            .prop_cells("reg", &[0, 0x8000_0000, 0, 0x800_0000])
+            .end()
+            .begin("cpus")
+            .prop_cells("#address-cells", &[1])
+            .prop_cells("#size-cells", &[0]);
+        for (i, status) in ["okay", "okay", "disabled"].iter().enumerate() {
+            b.begin(&format!("cpu@{}", i))
+                .prop_str("device_type", "cpu")
+                .prop_cells("reg", &[i as u32])
+                .prop_str("status", status)
+                .begin("interrupt-controller")
+                .prop_str("compatible", "riscv,cpu-intc")
+                .end()
+                .end();
+        }
+        b.begin("cpu-map").end().end();
+        b.begin("soc")
+            .prop_cells("#address-cells", &[2])
+            .prop_cells("#size-cells", &[2])
+            .prop("ranges", &[])
+            .begin("plic@c000000")
+            .prop_cells("phandle", &[3])
+            .prop_cells("#interrupt-cells", &[1])
+            .end();
+        for i in 0..2u32 {
+            b.begin(&format!("virtio_mmio@{:x}", 0x1000_1000 + i * 0x1000))
+                .prop_cells("interrupts", &[1 + i])
+                .prop_cells("interrupt-parent", &[3])
+                .prop_cells("reg", &[0, 0x1000_1000 + i * 0x1000, 0, 0x1000])
+                .prop_str("compatible", "virtio,mmio")
+                .end();
+        }
+        b.begin("serial@10000000")
+            .prop_cells("reg", &[0, 0x1000_0000, 0, 0x100])
+            .prop("compatible", b"ns16550a\0generic-uart\0")
+            .end()
+            .end() // soc
+            .end() // root
+            .build(&[(0x8000_0000, 0x20_0000)])
+    }
+
+    #[test]
+    fn test_header() {
+        let blob = qemu_virt();
+        let fdt = Fdt::new(&blob).unwrap();
+        assert_eq!(fdt.total_size(), blob.len());
+        assert_eq!(fdt.boot_cpuid(), 0);
+        assert_eq!(
+            fdt.reserved_regions().collect::<Vec<_>>(),
+            [(0x8000_0000, 0x20_0000)]
+        );
+
+        let mut bad = blob.clone();
+        bad[0] = 0;
+        assert_eq!(Fdt::new(&bad).unwrap_err(), FdtError::BadMagic);
+        assert_eq!(
+            Fdt::new(&blob[..blob.len() - 1]).unwrap_err(),
+            FdtError::Truncated
+        );
+        let mut bad = blob.clone();
+        bad[27] = 17; // last compatible version
+        assert_eq!(Fdt::new(&bad).unwrap_err(), FdtError::BadVersion);
+        assert_eq!(
+            unsafe { Fdt::from_ptr(core::ptr::null()) }.unwrap_err(),
+            FdtError::BadPtr
+        );
+        let fdt = unsafe { Fdt::from_ptr(blob.as_ptr()) }.unwrap();
+        assert_eq!(fdt.total_size(), blob.len());
+    }
+
+    #[test]
+    fn test_nodes() {
+        let blob = qemu_virt();
+        let fdt = Fdt::new(&blob).unwrap();
+        let root = fdt.root().unwrap();
+        assert_eq!(root.name(), "");
+        assert_eq!(root.depth(), 0);
+        assert!(root.is_compatible("riscv-virtio"));
+        assert_eq!(fdt.all_nodes().count(), 16);
+
+        let names: Vec<_> = fdt
+            .all_nodes()
+            .filter(|n| n.depth() == 1)
+            .map(|n| n.name())
+            .collect();
+        assert_eq!(names, ["chosen", "memory@80000000", "cpus", "soc"]);
+
+        let chosen = fdt.chosen().unwrap();
+        assert_eq!(
+            chosen.property("stdout-path").unwrap().as_str(),
+            Some("/soc/serial@10000000")
+        );
+        assert_eq!(fdt.find_node("/").unwrap().name(), "");
+        assert_eq!(fdt.find_node("/memory").unwrap().name(), "memory@80000000");
+        assert_eq!(fdt.find_node("/cpus/cpu@1").unwrap().name(), "cpu@1");
+        assert_eq!(
+            fdt.find_node("/cpus/cpu@1/interrupt-controller")
+                .unwrap()
+                .depth(),
+            3
+        );
+        assert!(fdt.find_node("/cpus/cpu@3").is_none());
+        assert!(fdt.find_node("/serial").is_none());
+        assert!(fdt.find_node("cpus").is_none());
+
+        let serial = fdt.find_node("/soc/serial").unwrap();
+        assert_eq!(
+            serial.compatible().collect::<Vec<_>>(),
+            ["ns16550a", "generic-uart"]
+        );
+        assert!(serial.is_compatible("generic-uart"));
+        assert!(!serial.is_compatible("generic"));
+        assert_eq!(fdt.find_phandle(3).unwrap().name(), "plic@c000000");
+        assert!(fdt.find_phandle(4).is_none());
+    }
+
+    #[test]
+    fn test_reg() {
+        let blob = qemu_virt();
+        let fdt = Fdt::new(&blob).unwrap();
+        assert_eq!(
+            fdt.memory_regions().collect::<Vec<_>>(),
+            [RegEntry {
+                address: 0x8000_0000,
+                size: Some(0x800_0000),
+            }]
+        );
+
+        let virtio: Vec<_> = fdt.find_compatible(&["virtio,mmio"]).collect();
+        assert_eq!(virtio.len(), 2);
+        let reg = virtio[1].reg().unwrap().collect::<Vec<_>>();
+        assert_eq!(
+            reg,
+            [RegEntry {
+                address: 0x1000_2000,
+                size: Some(0x1000),
+            }]
+        );
+        assert_eq!(virtio[1].interrupts().unwrap().collect::<Vec<_>>(), [2]);
+
+        let cpu = fdt.find_node("/cpus/cpu@2").unwrap();
+        assert_eq!(
+            cpu.reg().unwrap().collect::<Vec<_>>(),
+            [RegEntry {
+                address: 2,
+                size: None,
+            }]
+        );
+        assert_eq!(cpu.cells(), Cells::DEFAULT);
+        assert_eq!(
+            fdt.find_node("/cpus").unwrap().cells(),
+            Cells {
+                address: 1,
+                size: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_reserved_memory() {
+        let mut b = Builder::default();
+        b.begin("")
+            .prop_cells("#address-cells", &[1])
+            .prop_cells("#size-cells", &[1])
+            .begin("reserved-memory")
+            .prop_cells("#address-cells", &[2])
+            .prop_cells("#size-cells", &[1])
+            .prop("ranges", &[])
+            .begin("fw@1000000")
+            .prop_cells("reg", &[0, 0x100_0000, 0x2_0000, 1, 0, 0x1000])
+            .prop("no-map", &[])
+            .end()
+            .begin("cma")
+            .prop_cells("size", &[0x400_0000])
+            .end()
+            .begin("off@2000000")
+            .prop_cells("reg", &[0, 0x200_0000, 0x1000])
+            .prop_str("status", "disabled")
+            .end()
+            .end()
+            .begin("soc")
+            .begin("dev@3000000")
+            .prop_cells("reg", &[0x300_0000, 0x1000])
+            .end()
+            .end()
+            .end();
+        let blob = b.build(&[]);
+        let fdt = Fdt::new(&blob).unwrap();
+        assert_eq!(
+            fdt.reserved_memory().collect::<Vec<_>>(),
+            [
+                RegEntry {
+                    address: 0x100_0000,
+                    size: Some(0x2_0000),
+                },
+                RegEntry {
+                    address: 1 << 32,
+                    size: Some(0x1000),
+                },
+            ]
+        );
+        assert_eq!(fdt.reserved_regions().count(), 0);
+        assert_eq!(Fdt::new(&qemu_virt()).unwrap().reserved_memory().count(), 0);
+    }
+
+    #[test]
+    fn test_cpus() {
+        let blob = qemu_virt();
+        let fdt = Fdt::new(&blob).unwrap();
+        let cpus: Vec<_> = fdt.cpus().map(|n| n.name()).collect();
+        assert_eq!(cpus, ["cpu@0", "cpu@1"]);
+        assert_eq!(fdt.cpu_count(), 2);
+    }
+
+    #[test]
+    fn test_property() {
+        let prop = Property {
+            name: "test",
+            value: &[0, 0, 0, 1, 0, 0, 0, 2],
+        };
+        assert_eq!(prop.as_u32(), None);
+        assert_eq!(prop.as_u64(), Some(0x1_0000_0002));
+        assert_eq!(prop.as_u32_cells().collect::<Vec<_>>(), [1, 2]);
+        let prop = Property {
+            name: "test",
+            value: b"a\0bc\0",
+        };
+        assert_eq!(prop.as_str(), Some("a"));
+        assert_eq!(prop.as_str_list().collect::<Vec<_>>(), ["a", "bc"]);
+    }
+}
@@ -22,7 +22,6 @@ mod slab;
 use slab::Slab;
 
 const SET_SIZE: usize = 64;
-const MIN_HEAP_SIZE: usize = 0x8000;
 
 enum HeapAllocator {
     Slab64Bytes,
@@ -61,13 +60,10 @@ impl Heap {
             heap_start_addr % 4096 == 0,
             "Start address should be page aligned"
         );
+        assert!(heap_size > 0, "Heap size should be greater than zero");
         assert!(
-            heap_size >= MIN_HEAP_SIZE,
-            "Heap size should be greater or equal to minimum heap size"
-        );
-        assert!(
-            heap_size % MIN_HEAP_SIZE == 0,
-            "Heap size should be a multiple of minimum heap size"
+            heap_size % 4096 == 0,
+            "Heap size should be a multiple of page size"
         );
         Heap {
             slab_64_bytes: Slab::<64>::new(0, 0),
@@ -0,0 +1,280 @@
+//! Heap debugging: redzones, poisoning, double-free and leak detection.
+//!
+//! Every allocation is extended with a header and two redzones:
+//!
+//! ```text
+//! | header | front redzone | user data (size) | rear redzone |
+//! ^ raw                   ^ ptr
+//! ```
+//!
+//! The header records the layout and the caller addresses, and links all live
+//! allocations together so that they can be dumped by
+//! [`dump_allocations`](crate::dump_allocations). The redzones are filled with
+//! a pattern and checked when the memory is freed. Newly allocated memory is
+//! filled with [`POISON_ALLOC`] and freed memory with [`POISON_FREE`], so that
+//! the use of uninitialized or freed memory is easy to spot.
+//!
+//! The caller addresses are found by walking the frame pointers, which needs
+//! `-C force-frame-pointers=yes` (and `-fno-omit-frame-pointer` for C code).
+//! Code without frame pointers may still be on the call chain, so the walk
+//! never reads outside the stack of the current task, which is given by
+//! [`AllocDebugIf`].
+
+use core::alloc::Layout;
+use core::mem::size_of;
+use core::ops::Range;
+use core::ptr::{null_mut, NonNull};
+
+use allocator::{AllocError, AllocResult};
+use spinlock::SpinNoIrq;
+
+use crate::GlobalAllocator;
+
+/// Size of each redzone.
+const REDZONE_SIZE: usize = 16;
+/// Number of return addresses recorded for each allocation.
+const MAX_CALLERS: usize = 6;
+
+/// The pattern of redzones.
+const REDZONE_BYTE: u8 = 0xcc;
+/// The pattern of newly allocated memory.
+pub const POISON_ALLOC: u8 = 0x5a;
+/// The pattern of freed memory.
+pub const POISON_FREE: u8 = 0x6b;
+
+const MAGIC_ALLOC: usize = 0xa110_ca7e_d0d0_cafe_u64 as usize;
+const MAGIC_FREE: usize = 0xf7ee_d0d0_dead_beef_u64 as usize;
+
+struct Header {
+    prev: *mut Header,
+    next: *mut Header,
+    size: usize,
+    align: usize,
+    callers: [usize; MAX_CALLERS],
+    // placed last, as the byte allocator may reuse the beginning of a freed
+    // block for its own metadata
+    magic: usize,
+}
+
+/// All live allocations.
+struct AllocList {
+    head: *mut Header,
+    count: usize,
+    bytes: usize,
+}
+
+unsafe impl Send for AllocList {}
+
+static LIVE_ALLOCS: SpinNoIrq<AllocList> = SpinNoIrq::new(AllocList {
+    head: null_mut(),
+    count: 0,
+    bytes: 0,
+});
+
+/// Returns the offset of the user data from the start of the raw block.
+const fn front_size(align: usize) -> usize {
+    let size = size_of::<Header>() + REDZONE_SIZE;
+    (size + align - 1) & !(align - 1)
+}
+
+/// Returns the layout of the raw block that holds `layout`.
+fn raw_layout(layout: Layout) -> AllocResult<Layout> {
+    let align = layout.align().max(REDZONE_SIZE);
+    let size = front_size(align)
+        .checked_add(layout.size())
+        .and_then(|s| s.checked_add(REDZONE_SIZE))
+        .ok_or(AllocError::InvalidParam)?;
+    Layout::from_size_align(size, align).map_err(|_| AllocError::InvalidParam)
+}
+
+/// Extern interfaces that must be implemented in other crates.
+#[crate_interface::def_interface]
+pub trait AllocDebugIf {
+    /// Gets the stack range of the current task.
+    ///
+    /// Returns [`None`] if it is unknown (e.g., on the boot stack), in which
+    /// case `TASK_STACK_SIZE` bytes above the stack pointer are assumed.
+    fn current_stack_range() -> Option<Range<usize>>;
+}
+
+fn front_redzone(hdr: *mut Header, align: usize) -> &'static mut [u8] {
+    let start = hdr as usize + size_of::<Header>();
+    let end = hdr as usize + front_size(align);
+    unsafe { core::slice::from_raw_parts_mut(start as *mut u8, end - start) }
+}
+
+fn rear_redzone(ptr: usize, size: usize) -> &'static mut [u8] {
+    unsafe { core::slice::from_raw_parts_mut((ptr + size) as *mut u8, REDZONE_SIZE) }
+}
+
+/// Walks the frame pointers, and returns the first `MAX_CALLERS` return
+/// addresses.
+///
+/// The first few addresses are inside the allocator itself (e.g.,
+/// `__rust_alloc`), the rest point to the real caller.
+#[inline(always)]
+fn callers() -> [usize; MAX_CALLERS] {
+    let mut callers = [0; MAX_CALLERS];
+    let (mut fp, sp): (usize, usize);
+    unsafe {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "x86_64")] {
+                core::arch::asm!("mov {}, rbp", "mov {}, rsp", out(reg) fp, out(reg) sp);
+            } else if #[cfg(target_arch = "aarch64")] {
+                core::arch::asm!("mov {}, x29", "mov {}, sp", out(reg) fp, out(reg) sp);
+            } else if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
+                core::arch::asm!("mv {}, s0", "mv {}, sp", out(reg) fp, out(reg) sp);
+            } else {
+                (fp, sp) = (0, 0);
+            }
+        }
+    }
+
+    let stack = crate_interface::call_interface!(AllocDebugIf::current_stack_range)
+        .unwrap_or(sp..sp.saturating_add(axconfig::TASK_STACK_SIZE));
+    // a frame record holds the previous frame pointer and the return address
+    const RECORD_SIZE: usize = 2 * size_of::<usize>();
+    let mut i = 0;
+    while i < MAX_CALLERS && fp % size_of::<usize>() == 0 {
+        // on RISC-V, `fp` points to the end of the frame record
+        #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+        let record = fp.wrapping_sub(RECORD_SIZE);
+        #[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+        let record = fp;
+        if record < stack.start || record > stack.end.saturating_sub(RECORD_SIZE) {
+            break;
+        }
+        let (next_fp, ra) =
+            unsafe { (*(record as *const usize), *(record as *const usize).add(1)) };
+        if ra == 0 {
+            break;
+        }
+        callers[i] = ra;
+        i += 1;
+        // the stack grows downwards
+        if next_fp <= fp {
+            break;
+        }
+        fp = next_fp;
+    }
+    callers
+}
+
+fn report(kind: &str, ptr: usize, hdr: &Header) {
+    error!(
+        "{} at {:#x}: size={}, align={}, allocated by {:#x?}",
+        kind, ptr, hdr.size, hdr.align, hdr.callers
+    );
+}
+
+#[inline(always)]
+pub(crate) fn alloc(ga: &GlobalAllocator, layout: Layout) -> AllocResult<NonNull<u8>> {
+    let callers = callers();
+    let raw = ga.balloc_alloc(raw_layout(layout)?)?;
+    let align = layout.align().max(REDZONE_SIZE);
+    let hdr = raw.as_ptr() as *mut Header;
+    let ptr = raw.as_ptr() as usize + front_size(align);
+
+    front_redzone(hdr, align).fill(REDZONE_BYTE);
+    rear_redzone(ptr, layout.size()).fill(REDZONE_BYTE);
+    unsafe { core::ptr::write_bytes(ptr as *mut u8, POISON_ALLOC, layout.size()) };
+
+    let mut list = LIVE_ALLOCS.lock();
+    unsafe {
+        hdr.write(Header {
+            prev: null_mut(),
+            next: list.head,
+            size: layout.size(),
+            align: layout.align(),
+            callers,
+            magic: MAGIC_ALLOC,
+        });
+        if !list.head.is_null() {
+            (*list.head).prev = hdr;
+        }
+    }
+    list.head = hdr;
+    list.count += 1;
+    list.bytes += layout.size();
+    Ok(unsafe { NonNull::new_unchecked(ptr as *mut u8) })
+}
+
+/// Checks and frees the allocation at `pos`.
+///
+/// Returns [`AllocError::NotAllocated`] on double-free or invalid-free, in
+/// which case nothing is freed.
+pub(crate) fn dealloc(ga: &GlobalAllocator, pos: NonNull<u8>, layout: Layout) -> AllocResult {
+    let ptr = pos.as_ptr() as usize;
+    let align = layout.align().max(REDZONE_SIZE);
+    let Some(raw) = ptr.checked_sub(front_size(align)) else {
+        error!("invalid free at {:#x}", ptr);
+        return Err(AllocError::NotAllocated);
+    };
+    let hdr = raw as *mut Header;
+
+    let mut list = LIVE_ALLOCS.lock();
+    let h = unsafe { &mut *hdr };
+    match h.magic {
+        MAGIC_ALLOC => {}
+        MAGIC_FREE => {
+            report("double free", ptr, h);
+            return Err(AllocError::NotAllocated);
+        }
+        _ => {
+            error!("invalid free at {:#x}: {:?}", ptr, layout);
+            return Err(AllocError::NotAllocated);
+        }
+    }
+    if h.size != layout.size() || h.align != layout.align() {
+        report("invalid free", ptr, h);
+        error!("  freed with {:?}", layout);
+        return Err(AllocError::NotAllocated);
+    }
+    if front_redzone(hdr, align).iter().any(|&b| b != REDZONE_BYTE) {
+        report("heap buffer underflow", ptr, h);
+        panic!("heap corruption detected at {:#x}", ptr);
+    }
+    if rear_redzone(ptr, h.size).iter().any(|&b| b != REDZONE_BYTE) {
+        report("heap buffer overflow", ptr, h);
+        panic!("heap corruption detected at {:#x}", ptr);
+    }
+
+    // unlink from the live list
+    unsafe {
+        if h.prev.is_null() {
+            list.head = h.next;
+        } else {
+            (*h.prev).next = h.next;
+        }
+        if !h.next.is_null() {
+            (*h.next).prev = h.prev;
+        }
+    }
+    list.count -= 1;
+    list.bytes -= h.size;
+    h.magic = MAGIC_FREE;
+    drop(list);
+
+    unsafe { core::ptr::write_bytes(ptr as *mut u8, POISON_FREE, layout.size()) };
+    let raw = unsafe { NonNull::new_unchecked(raw as *mut u8) };
+    ga.balloc_dealloc(raw, raw_layout(layout)?);
+    Ok(())
+}
+
+pub(crate) fn dump_allocations() {
+    let list = LIVE_ALLOCS.lock();
+    warn!(
+        "{} outstanding allocations, {} bytes in total:",
+        list.count, list.bytes
+    );
+    let mut cur = list.head;
+    while !cur.is_null() {
+        let h = unsafe { &*cur };
+        let ptr = cur as usize + front_size(h.align.max(REDZONE_SIZE));
+        warn!(
+            "  {:#x}: size={}, align={}, allocated by {:#x?}",
+            ptr, h.size, h.align, h.callers
+        );
+        cur = h.next;
+    }
+}
@@ -4,24 +4,51 @@
 //! [`core::alloc::GlobalAlloc`]. A static global variable of type
 //! [`GlobalAllocator`] is defined with the `#[global_allocator]` attribute, to
 //! be registered as the standard library’s default allocator.
+//!
+//! Memory can also be allocated through named [`AllocZone`]s, which have their
+//! own limits and statistics. Each zone implements the [`Allocator`] trait.
+//!
+//! With the `debug` feature, every allocation is surrounded by redzones and
+//! tracked with its caller addresses. Freed memory is poisoned, double-free
+//! and invalid-free are detected, and the outstanding allocations can be
+//! dumped by [`dump_allocations`]. `AllocDebugIf` must be implemented by
+//! other crates in this case.
+//!
+//! [`Allocator`]: core::alloc::Allocator
 
 #![no_std]
+#![feature(allocator_api)]
 
 #[macro_use]
 extern crate log;
 extern crate alloc;
 
 mod page;
+mod zone;
+
+#[cfg(feature = "debug")]
+mod debug;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "debug")]
+pub use debug::AllocDebugIf;
 
-use allocator::{AllocResult, BaseAllocator, BitmapPageAllocator, ByteAllocator, PageAllocator};
+use allocator::{AllocResult, BaseAllocator, BuddyPageAllocator, ByteAllocator, PageAllocator};
 use core::alloc::{GlobalAlloc, Layout};
 use core::ptr::NonNull;
 use spinlock::SpinNoIrq;
 
 const PAGE_SIZE: usize = 0x1000;
 const MIN_HEAP_SIZE: usize = 0x8000; // 32 K
+const MAX_GROWN_REGIONS: usize = 64;
 
 pub use page::GlobalPage;
+pub use zone::{
+    find_zone, register_zone, zone_stats, AllocZone, ZoneReservation, ZoneStats, FS_ZONE,
+    MAX_ZONES, NET_ZONE,
+};
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "slab")] {
@@ -40,13 +67,21 @@ cfg_if::cfg_if! {
 /// there is no memory, asks the page allocator for more memory and adds it to
 /// the byte allocator.
 ///
+/// The regions taken from the page allocator are recorded. When a page
+/// allocation fails, the ones that became entirely free are removed from the
+/// byte allocator and given back to the page allocator (see
+/// [`GlobalAllocator::reclaim_heap`]).
+///
 /// Currently, [`TlsfByteAllocator`] is used as the byte allocator, while
-/// [`BitmapPageAllocator`] is used as the page allocator.
+/// [`BuddyPageAllocator`] is used as the page allocator. The page allocator
+/// manages multiple discontiguous memory regions, pages can be allocated from
+/// a specific one by [`GlobalAllocator::alloc_pages_in`].
 ///
 /// [`TlsfByteAllocator`]: allocator::TlsfByteAllocator
 pub struct GlobalAllocator {
     balloc: SpinNoIrq<DefaultByteAllocator>,
-    palloc: SpinNoIrq<BitmapPageAllocator<PAGE_SIZE>>,
+    palloc: SpinNoIrq<BuddyPageAllocator<PAGE_SIZE>>,
+    grown_regions: SpinNoIrq<[Option<(usize, usize)>; MAX_GROWN_REGIONS]>,
 }
 
 impl GlobalAllocator {
@@ -54,7 +89,8 @@ impl GlobalAllocator {
     pub const fn new() -> Self {
         Self {
             balloc: SpinNoIrq::new(DefaultByteAllocator::new()),
-            palloc: SpinNoIrq::new(BitmapPageAllocator::new()),
+            palloc: SpinNoIrq::new(BuddyPageAllocator::new()),
+            grown_regions: SpinNoIrq::new([None; MAX_GROWN_REGIONS]),
         }
     }
 
@@ -88,9 +124,10 @@ impl GlobalAllocator {
 
     /// Add the given region to the allocator.
     ///
-    /// It will add the whole region to the byte allocator.
+    /// It will add the whole region to the page allocator as a new page
+    /// region, the byte allocator grows into it on demand.
     pub fn add_memory(&self, start_vaddr: usize, size: usize) -> AllocResult {
-        self.balloc.lock().add_memory(start_vaddr, size)
+        self.palloc.lock().add_memory(start_vaddr, size)
     }
 
     /// Allocate arbitrary number of bytes. Returns the left bound of the
@@ -103,6 +140,16 @@ impl GlobalAllocator {
     /// `align_pow2` must be a power of 2, and the returned region bound will be
     ///  aligned to it.
     pub fn alloc(&self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "debug")] {
+                debug::alloc(self, layout)
+            } else {
+                self.balloc_alloc(layout)
+            }
+        }
+    }
+
+    fn balloc_alloc(&self, layout: Layout) -> AllocResult<NonNull<u8>> {
         // simple two-level allocator: if no heap memory, allocate from the page allocator.
         let mut balloc = self.balloc.lock();
         loop {
@@ -110,17 +157,37 @@ impl GlobalAllocator {
                 return Ok(ptr);
             } else {
                 let old_size = balloc.total_bytes();
+                // each region holds its own heap header
                 let expand_size = old_size
-                    .max(layout.size())
+                    .max(balloc.min_region_size(layout))
                     .next_power_of_two()
                     .max(PAGE_SIZE);
-                let heap_ptr = self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
+                // Do not reclaim the heap here, as we are holding its lock.
+                let heap_ptr = self
+                    .palloc
+                    .lock()
+                    .alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
                 debug!(
                     "expand heap memory: [{:#x}, {:#x})",
                     heap_ptr,
                     heap_ptr + expand_size
                 );
-                balloc.add_memory(heap_ptr, expand_size)?;
+                if let Err(e) = balloc.add_memory(heap_ptr, expand_size) {
+                    self.palloc
+                        .lock()
+                        .dealloc_pages(heap_ptr, expand_size / PAGE_SIZE);
+                    return Err(e);
+                }
+                // If there is no free slot, the region just cannot be reclaimed.
+                match self.grown_regions.lock().iter_mut().find(|r| r.is_none()) {
+                    Some(slot) => *slot = Some((heap_ptr, expand_size)),
+                    None => warn!(
+                        "heap region [{:#x}, {:#x}) cannot be reclaimed: all {} slots are used",
+                        heap_ptr,
+                        heap_ptr + expand_size,
+                        MAX_GROWN_REGIONS
+                    ),
+                }
             }
         }
     }
@@ -131,8 +198,38 @@ impl GlobalAllocator {
     /// the same as the one used in [`alloc`]. Otherwise, the behavior is
     /// undefined.
     ///
+    /// With the `debug` feature, it panics if `pos` is not allocated (e.g.,
+    /// double-free), see [`try_dealloc`].
+    ///
     /// [`alloc`]: GlobalAllocator::alloc
+    /// [`try_dealloc`]: GlobalAllocator::try_dealloc
     pub fn dealloc(&self, pos: NonNull<u8>, layout: Layout) {
+        if let Err(e) = self.try_dealloc(pos, layout) {
+            panic!("dealloc {:#x} failed: {:?}", pos.as_ptr() as usize, e);
+        }
+    }
+
+    /// Gives back the allocated region to the byte allocator, and returns
+    /// whether it succeeds.
+    ///
+    /// With the `debug` feature, it returns [`AllocError::NotAllocated`] if
+    /// `pos` is not allocated or has been freed, or `layout` does not match the
+    /// one used in [`alloc`]. Otherwise, such misuse is undefined behavior.
+    ///
+    /// [`alloc`]: GlobalAllocator::alloc
+    /// [`AllocError::NotAllocated`]: allocator::AllocError::NotAllocated
+    pub fn try_dealloc(&self, pos: NonNull<u8>, layout: Layout) -> AllocResult {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "debug")] {
+                debug::dealloc(self, pos, layout)
+            } else {
+                self.balloc_dealloc(pos, layout);
+                Ok(())
+            }
+        }
+    }
+
+    fn balloc_dealloc(&self, pos: NonNull<u8>, layout: Layout) {
         self.balloc.lock().dealloc(pos, layout)
     }
 
@@ -140,10 +237,89 @@ impl GlobalAllocator {
     ///
     /// It allocates `num_pages` pages from the page allocator.
     ///
+    /// If there is not enough free pages, it tries to reclaim the free heap
+    /// memory by [`reclaim_heap`] and retries once.
+    ///
     /// `align_pow2` must be a power of 2, and the returned region bound will be
     /// aligned to it.
+    ///
+    /// [`reclaim_heap`]: GlobalAllocator::reclaim_heap
     pub fn alloc_pages(&self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
-        self.palloc.lock().alloc_pages(num_pages, align_pow2)
+        let res = self.palloc.lock().alloc_pages(num_pages, align_pow2);
+        if res.is_err() && self.reclaim_heap() > 0 {
+            return self.palloc.lock().alloc_pages(num_pages, align_pow2);
+        }
+        res
+    }
+
+    /// Allocates contiguous pages from the page region containing `vaddr`.
+    ///
+    /// Each memory region passed to [`init`] or [`add_memory`] is a page
+    /// region, so pages can be allocated from a given memory region by any
+    /// address in it (e.g., its start address). Returns
+    /// [`AllocError::InvalidParam`] if no page region contains `vaddr`.
+    ///
+    /// [`init`]: GlobalAllocator::init
+    /// [`add_memory`]: GlobalAllocator::add_memory
+    /// [`AllocError::InvalidParam`]: allocator::AllocError::InvalidParam
+    pub fn alloc_pages_in(
+        &self,
+        vaddr: usize,
+        num_pages: usize,
+        align_pow2: usize,
+    ) -> AllocResult<usize> {
+        let region = self
+            .page_region_of(vaddr)
+            .ok_or(allocator::AllocError::InvalidParam)?;
+        let res = self
+            .palloc
+            .lock()
+            .alloc_pages_in(region, num_pages, align_pow2);
+        if res.is_err() && self.reclaim_heap() > 0 {
+            return self
+                .palloc
+                .lock()
+                .alloc_pages_in(region, num_pages, align_pow2);
+        }
+        res
+    }
+
+    /// Returns the number of page regions.
+    pub fn num_page_regions(&self) -> usize {
+        self.palloc.lock().num_regions()
+    }
+
+    /// Returns the index of the page region containing `vaddr`.
+    pub fn page_region_of(&self, vaddr: usize) -> Option<usize> {
+        self.palloc.lock().region_of(vaddr)
+    }
+
+    /// Gives back the heap regions that are entirely free to the page
+    /// allocator.
+    ///
+    /// Only the regions that the byte allocator once took from the page
+    /// allocator are considered. Returns the number of reclaimed pages.
+    pub fn reclaim_heap(&self) -> usize {
+        let mut reclaimed = [None; MAX_GROWN_REGIONS];
+        {
+            let mut balloc = self.balloc.lock();
+            let mut grown_regions = self.grown_regions.lock();
+            for (slot, out) in grown_regions.iter_mut().zip(reclaimed.iter_mut()) {
+                if let Some((start, size)) = *slot {
+                    if balloc.remove_memory(start, size).is_ok() {
+                        *out = slot.take();
+                    }
+                }
+            }
+        }
+
+        let mut num_pages = 0;
+        for (start, size) in reclaimed.into_iter().flatten() {
+            debug!("reclaim heap memory: [{:#x}, {:#x})", start, start + size);
+            self.palloc.lock().dealloc_pages(start, size / PAGE_SIZE);
+            num_pages += size / PAGE_SIZE;
+        }
+        num_pages
     }
 
     /// Gives back the allocated pages starts from `pos` to the page allocator.
@@ -200,6 +376,14 @@ pub fn global_allocator() -> &'static GlobalAllocator {
     &GLOBAL_ALLOCATOR
 }
 
+/// Prints all outstanding allocations with their sizes and callers.
+///
+/// It is only available with the `debug` feature, otherwise it does nothing.
+pub fn dump_allocations() {
+    #[cfg(feature = "debug")]
+    debug::dump_allocations();
+}
+
 /// Initializes the global allocator with the given memory region.
 ///
 /// Note that the memory region bounds are just numbers, and the allocator
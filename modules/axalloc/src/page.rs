@@ -100,9 +100,10 @@ impl Drop for GlobalPage {
 
 const fn alloc_err_to_ax_err(e: AllocError) -> AxError {
     match e {
-        AllocError::InvalidParam | AllocError::MemoryOverlap | AllocError::NotAllocated => {
-            AxError::InvalidInput
-        }
+        AllocError::InvalidParam
+        | AllocError::MemoryOverlap
+        | AllocError::NotAllocated
+        | AllocError::MemoryInUse => AxError::InvalidInput,
         AllocError::NoMemory => AxError::NoMemory,
     }
 }
@@ -0,0 +1,155 @@
+extern crate std;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use std::sync::Once;
+
+use allocator::AllocError;
+
+use crate::{find_zone, global_add_memory, global_allocator, global_init};
+use crate::{register_zone, zone_stats, AllocZone, PAGE_SIZE};
+
+const HEAP_SIZE: usize = 16 * 1024 * 1024;
+
+/// Initializes the global allocator with memory from the host allocator, the
+/// zones allocate from it.
+fn init_heap() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let layout = Layout::from_size_align(HEAP_SIZE, PAGE_SIZE).unwrap();
+        let heap = unsafe { std::alloc::alloc(layout) };
+        assert!(!heap.is_null());
+        global_init(heap as usize, HEAP_SIZE);
+    });
+}
+
+#[test]
+fn test_alloc_pages_in() {
+    const REGION_SIZE: usize = 64 * PAGE_SIZE;
+    init_heap();
+
+    let layout = Layout::from_size_align(REGION_SIZE, PAGE_SIZE).unwrap();
+    let region = unsafe { std::alloc::alloc(layout) } as usize;
+    global_add_memory(region, REGION_SIZE).unwrap();
+
+    // any address in the region selects it
+    let pos = global_allocator()
+        .alloc_pages_in(region + REGION_SIZE - 1, 4, PAGE_SIZE)
+        .unwrap();
+    assert!((region..region + REGION_SIZE).contains(&pos));
+    global_allocator().dealloc_pages(pos, 4);
+    assert!(matches!(
+        global_allocator().alloc_pages_in(PAGE_SIZE, 1, PAGE_SIZE),
+        Err(AllocError::InvalidParam)
+    ));
+}
+
+#[test]
+fn test_zone_limit() {
+    static ZONE: AllocZone = AllocZone::new("test-limit", 4 * PAGE_SIZE);
+    init_heap();
+
+    let a = ZONE.alloc_pages(3, PAGE_SIZE).unwrap();
+    assert_eq!(ZONE.used_bytes(), 3 * PAGE_SIZE);
+    assert!(matches!(
+        ZONE.alloc_pages(2, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    ));
+    let b = ZONE.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(ZONE.used_bytes(), 4 * PAGE_SIZE);
+
+    let stats = ZONE.stats();
+    assert_eq!(stats.name, "test-limit");
+    assert_eq!(stats.limit, 4 * PAGE_SIZE);
+    assert_eq!(stats.num_allocs, 2);
+    assert_eq!(stats.num_failures, 1);
+
+    ZONE.dealloc_pages(a, 3);
+    ZONE.dealloc_pages(b, 1);
+    let stats = ZONE.stats();
+    assert_eq!(stats.used_bytes, 0);
+    assert_eq!(stats.peak_bytes, 4 * PAGE_SIZE);
+    assert_eq!(stats.num_allocs, 0);
+
+    // lowering the limit only affects further allocations
+    let a = ZONE.alloc_pages(2, PAGE_SIZE).unwrap();
+    ZONE.set_limit(PAGE_SIZE);
+    assert!(matches!(
+        ZONE.alloc_pages(1, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    ));
+    ZONE.dealloc_pages(a, 2);
+    ZONE.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(ZONE.stats().num_failures, 2);
+}
+
+#[test]
+fn test_zone_allocator() {
+    static ZONE: AllocZone = AllocZone::new("test-allocator", 1024);
+    init_heap();
+
+    let mut v: Vec<u8, _> = Vec::with_capacity_in(1000, &ZONE);
+    v.extend_from_slice(&[1; 1000]);
+    assert_eq!(ZONE.used_bytes(), 1000);
+    assert!(Box::try_new_in([0u8; 100], &ZONE).is_err());
+    assert!(v.try_reserve(1000).is_err());
+    assert_eq!(ZONE.stats().num_failures, 2);
+
+    // zero-sized allocations are not charged
+    let empty: Vec<u8, _> = Vec::with_capacity_in(0, &ZONE);
+    assert_eq!(ZONE.stats().num_allocs, 1);
+
+    drop(v);
+    drop(empty);
+    assert_eq!(ZONE.used_bytes(), 0);
+    let b = Box::try_new_in([0u8; 1024], &ZONE).unwrap();
+    assert_eq!(ZONE.stats().peak_bytes, 1024);
+    drop(b);
+    assert_eq!(ZONE.stats().num_allocs, 0);
+}
+
+#[test]
+fn test_zone_reservation() {
+    static ZONE: AllocZone = AllocZone::new("test-reservation", 100);
+
+    let r1 = ZONE.reserve(60).unwrap();
+    assert_eq!(r1.size(), 60);
+    assert_eq!(r1.zone().name(), "test-reservation");
+    assert!(ZONE.reserve(41).is_err());
+    let r2 = ZONE.reserve(40).unwrap();
+    assert_eq!(ZONE.used_bytes(), 100);
+    assert_eq!(ZONE.stats().num_allocs, 2);
+
+    drop(r1);
+    assert_eq!(ZONE.used_bytes(), 40);
+    drop(r2);
+    let stats = ZONE.stats();
+    assert_eq!(stats.used_bytes, 0);
+    assert_eq!(stats.peak_bytes, 100);
+    assert_eq!(stats.num_failures, 1);
+}
+
+#[test]
+fn test_register_zone() {
+    static ZONE: AllocZone = AllocZone::new("test-register", 0);
+    static DUP: AllocZone = AllocZone::new("net", 0);
+
+    let mut names = Vec::new();
+    zone_stats(|s| names.push(s.name));
+    assert!(names.contains(&"net"));
+    assert!(names.contains(&"fs"));
+    assert!(find_zone("net").unwrap().limit() < usize::MAX);
+
+    register_zone(&ZONE).unwrap();
+    assert!(core::ptr::eq(find_zone("test-register").unwrap(), &ZONE));
+    assert!(matches!(
+        register_zone(&ZONE),
+        Err(AllocError::MemoryOverlap)
+    ));
+    assert!(matches!(
+        register_zone(&DUP),
+        Err(AllocError::MemoryOverlap)
+    ));
+    assert!(find_zone("test-unknown").is_none());
+}
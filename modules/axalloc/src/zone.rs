@@ -0,0 +1,264 @@
+//! Named allocation zones with per-zone limits and statistics.
+//!
+//! A zone does not own memory itself, it allocates from the global allocator
+//! and keeps track of how many bytes are in use. Allocations beyond the zone
+//! limit fail, so one subsystem exhausting its budget does not starve the
+//! others.
+//!
+//! Memory that must come from another allocator (e.g. a `Vec` handed over to
+//! a library) can still be accounted for by a [`ZoneReservation`].
+
+use core::alloc::{AllocError, Allocator, Layout};
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use allocator::AllocResult;
+use spinlock::SpinNoIrq;
+
+use crate::{global_allocator, PAGE_SIZE};
+
+/// The maximum number of zones that can be registered.
+pub const MAX_ZONES: usize = 16;
+
+/// Zone for network buffers, limited to [`axconfig::NET_ZONE_LIMIT`] bytes.
+pub static NET_ZONE: AllocZone = AllocZone::new("net", axconfig::NET_ZONE_LIMIT);
+/// Zone for filesystem caches, limited to [`axconfig::FS_ZONE_LIMIT`] bytes.
+pub static FS_ZONE: AllocZone = AllocZone::new("fs", axconfig::FS_ZONE_LIMIT);
+
+static ZONES: SpinNoIrq<[Option<&'static AllocZone>; MAX_ZONES]> = SpinNoIrq::new({
+    let mut zones = [None; MAX_ZONES];
+    zones[0] = Some(&NET_ZONE);
+    zones[1] = Some(&FS_ZONE);
+    zones
+});
+
+/// A snapshot of the statistics of an [`AllocZone`].
+#[derive(Debug, Clone, Copy)]
+pub struct ZoneStats {
+    /// The zone name.
+    pub name: &'static str,
+    /// The maximum number of bytes the zone may hold.
+    pub limit: usize,
+    /// The number of bytes currently allocated.
+    pub used_bytes: usize,
+    /// The highest value of `used_bytes` ever reached.
+    pub peak_bytes: usize,
+    /// The number of live allocations.
+    pub num_allocs: usize,
+    /// The number of allocations that failed.
+    pub num_failures: usize,
+}
+
+/// A named allocation zone.
+///
+/// It implements [`Allocator`], so collections can be created in a zone with
+/// e.g. `Vec::new_in(&NET_ZONE)`.
+pub struct AllocZone {
+    name: &'static str,
+    limit: AtomicUsize,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+    num_allocs: AtomicUsize,
+    num_failures: AtomicUsize,
+}
+
+impl AllocZone {
+    /// Creates a new zone that may hold at most `limit` bytes.
+    pub const fn new(name: &'static str, limit: usize) -> Self {
+        Self {
+            name,
+            limit: AtomicUsize::new(limit),
+            used: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            num_allocs: AtomicUsize::new(0),
+            num_failures: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the zone name.
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the maximum number of bytes the zone may hold.
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    /// Sets the maximum number of bytes the zone may hold.
+    ///
+    /// Lowering the limit below the current usage does not free anything,
+    /// but makes further allocations fail until enough memory is returned.
+    pub fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes currently allocated in the zone.
+    pub fn used_bytes(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Returns a snapshot of the zone statistics.
+    pub fn stats(&self) -> ZoneStats {
+        ZoneStats {
+            name: self.name,
+            limit: self.limit(),
+            used_bytes: self.used_bytes(),
+            peak_bytes: self.peak.load(Ordering::Relaxed),
+            num_allocs: self.num_allocs.load(Ordering::Relaxed),
+            num_failures: self.num_failures.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Charges `size` bytes allocated elsewhere to this zone, until the
+    /// returned [`ZoneReservation`] is dropped.
+    ///
+    /// It fails like an allocation if the zone would exceed its limit.
+    pub fn reserve(&'static self, size: usize) -> AllocResult<ZoneReservation> {
+        self.charge(size)?;
+        Ok(ZoneReservation { zone: self, size })
+    }
+
+    /// Allocates contiguous pages in this zone.
+    ///
+    /// See [`GlobalAllocator::alloc_pages`](crate::GlobalAllocator::alloc_pages).
+    pub fn alloc_pages(&self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        let size = num_pages * PAGE_SIZE;
+        self.charge(size)?;
+        global_allocator()
+            .alloc_pages(num_pages, align_pow2)
+            .map_err(|e| {
+                self.uncharge_failed(size);
+                e
+            })
+    }
+
+    /// Gives back the pages allocated by [`AllocZone::alloc_pages`].
+    pub fn dealloc_pages(&self, pos: usize, num_pages: usize) {
+        global_allocator().dealloc_pages(pos, num_pages);
+        self.uncharge(num_pages * PAGE_SIZE);
+    }
+
+    fn charge(&self, size: usize) -> AllocResult {
+        let limit = self.limit();
+        let res = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |used| {
+                used.checked_add(size).filter(|&new| new <= limit)
+            });
+        match res {
+            Ok(old) => {
+                self.peak.fetch_max(old + size, Ordering::Relaxed);
+                self.num_allocs.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(_) => {
+                warn!(
+                    "allocation zone {:?} exceeds its limit: {} + {} > {}",
+                    self.name,
+                    self.used_bytes(),
+                    size,
+                    limit
+                );
+                self.num_failures.fetch_add(1, Ordering::Relaxed);
+                Err(allocator::AllocError::NoMemory)
+            }
+        }
+    }
+
+    fn uncharge(&self, size: usize) {
+        self.used.fetch_sub(size, Ordering::AcqRel);
+        self.num_allocs.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn uncharge_failed(&self, size: usize) {
+        self.uncharge(size);
+        self.num_failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Bytes charged to an [`AllocZone`] by [`AllocZone::reserve`], they are
+/// given back to the zone when it is dropped.
+pub struct ZoneReservation {
+    zone: &'static AllocZone,
+    size: usize,
+}
+
+impl ZoneReservation {
+    /// Returns the zone the bytes are charged to.
+    pub fn zone(&self) -> &'static AllocZone {
+        self.zone
+    }
+
+    /// Returns the number of bytes charged.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Drop for ZoneReservation {
+    fn drop(&mut self) {
+        self.zone.uncharge(self.size);
+    }
+}
+
+unsafe impl Allocator for AllocZone {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            let dangling = unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+            return Ok(NonNull::slice_from_raw_parts(dangling, 0));
+        }
+        self.charge(layout.size()).map_err(|_| AllocError)?;
+        match global_allocator().alloc(layout) {
+            Ok(ptr) => Ok(NonNull::slice_from_raw_parts(ptr, layout.size())),
+            Err(_) => {
+                self.uncharge_failed(layout.size());
+                Err(AllocError)
+            }
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            global_allocator().dealloc(ptr, layout);
+            self.uncharge(layout.size());
+        }
+    }
+}
+
+/// Registers a user-defined zone, so that it is reported by [`zone_stats`].
+///
+/// Returns [`AllocError::NoMemory`](allocator::AllocError::NoMemory) if
+/// [`MAX_ZONES`] zones are already registered, or
+/// [`AllocError::MemoryOverlap`](allocator::AllocError::MemoryOverlap) if a
+/// zone with the same name exists.
+pub fn register_zone(zone: &'static AllocZone) -> AllocResult {
+    let mut zones = ZONES.lock();
+    if zones.iter().flatten().any(|z| z.name == zone.name) {
+        return Err(allocator::AllocError::MemoryOverlap);
+    }
+    let slot = zones
+        .iter_mut()
+        .find(|z| z.is_none())
+        .ok_or(allocator::AllocError::NoMemory)?;
+    *slot = Some(zone);
+    Ok(())
+}
+
+/// Looks up a registered zone by name.
+pub fn find_zone(name: &str) -> Option<&'static AllocZone> {
+    ZONES
+        .lock()
+        .iter()
+        .flatten()
+        .find(|z| z.name == name)
+        .copied()
+}
+
+/// Calls `f` with the statistics of every registered zone.
+pub fn zone_stats(mut f: impl FnMut(&ZoneStats)) {
+    let zones = *ZONES.lock();
+    for zone in zones.iter().flatten() {
+        f(&zone.stats());
+    }
+}
@@ -0,0 +1,82 @@
+//! Graphics and input devices added or removed after boot.
+
+use alloc::{collections::VecDeque, vec::Vec};
+use axdriver::hotplug::{DeviceId, DeviceNotifier};
+use axdriver::{prelude::*, AxDeviceEnum};
+use axsync::Mutex;
+
+use crate::{InputEvent, MAIN_DISPLAY};
+
+/// The ID of the main display if it's added after boot.
+static DISPLAY_ID: Mutex<Option<DeviceId>> = Mutex::new(None);
+
+/// The input devices added after boot.
+static INPUT_DEVS: Mutex<Vec<(DeviceId, AxInputDevice)>> = Mutex::new(Vec::new());
+
+/// Moves the pending events of the input devices added after boot into
+/// `events`.
+pub(crate) fn fetch_events(events: &mut VecDeque<InputEvent>) {
+    for (_, dev) in INPUT_DEVS.lock().iter_mut() {
+        while let Ok(event) = dev.read_event() {
+            events.push_back(event);
+        }
+    }
+}
+
+struct DisplayNotifier;
+
+impl DeviceNotifier for DisplayNotifier {
+    fn device_added(&self, id: DeviceId, dev: AxDeviceEnum) {
+        let AxDeviceEnum::Display(dev) = dev else {
+            return;
+        };
+        // locked in the same order as in `device_removed`
+        let mut display_id = DISPLAY_ID.lock();
+        let mut main_display = MAIN_DISPLAY.lock();
+        if main_display.is_some() {
+            warn!("graphics device {:?} is not used", dev.device_name());
+            return;
+        }
+        info!("use graphics device {:?}", dev.device_name());
+        *main_display = Some(dev);
+        *display_id = Some(id);
+    }
+
+    fn device_removed(&self, id: DeviceId) {
+        let mut display_id = DISPLAY_ID.lock();
+        if *display_id == Some(id) {
+            warn!("the main display is removed");
+            *display_id = None;
+            MAIN_DISPLAY.lock().take();
+        }
+    }
+}
+
+struct InputNotifier;
+
+impl DeviceNotifier for InputNotifier {
+    fn device_added(&self, id: DeviceId, dev: AxDeviceEnum) {
+        let AxDeviceEnum::Input(dev) = dev else {
+            return;
+        };
+        info!("use input device {:?}", dev.device_name());
+        INPUT_DEVS.lock().push((id, dev));
+    }
+
+    fn device_removed(&self, id: DeviceId) {
+        INPUT_DEVS.lock().retain(|&(i, _)| i != id);
+    }
+}
+
+static DISPLAY_NOTIFIER: DisplayNotifier = DisplayNotifier;
+static INPUT_NOTIFIER: InputNotifier = InputNotifier;
+
+/// Starts to receive the graphics devices added after boot.
+pub(crate) fn init_display() {
+    axdriver::hotplug::register_notifier(DeviceType::Display, &DISPLAY_NOTIFIER);
+}
+
+/// Starts to receive the input devices added after boot.
+pub(crate) fn init_input() {
+    axdriver::hotplug::register_notifier(DeviceType::Input, &INPUT_NOTIFIER);
+}
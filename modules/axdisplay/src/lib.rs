@@ -1,36 +1,112 @@
 //! [ArceOS](https://github.com/rcore-os/arceos) graphics module.
 //!
-//! Currently only supports direct writing to the framebuffer.
+//! Currently only supports direct writing to the framebuffer, and reading
+//! events from the input devices (e.g., keyboards and mice).
+//!
+//! # Cargo Features
+//!
+//! - `hotplug`: Accept the graphics and input devices added after boot. An
+//!   added graphics device becomes the main display if there is none, and an
+//!   added input device joins the event queue. The framebuffer of a removed
+//!   display must not be accessed anymore.
 
 #![no_std]
 
 #[macro_use]
 extern crate log;
+extern crate alloc;
+
+#[cfg(feature = "hotplug")]
+mod hotplug;
 
 #[doc(no_inline)]
 pub use driver_display::DisplayInfo;
+#[doc(no_inline)]
+pub use driver_input::{event_type, InputEvent};
 
+use alloc::{collections::VecDeque, vec::Vec};
 use axdriver::{prelude::*, AxDeviceContainer};
 use axsync::Mutex;
 use lazy_init::LazyInit;
 
-static MAIN_DISPLAY: LazyInit<Mutex<AxDisplayDevice>> = LazyInit::new();
+static MAIN_DISPLAY: Mutex<Option<AxDisplayDevice>> = Mutex::new(None);
+static INPUT: LazyInit<Mutex<InputQueue>> = LazyInit::new();
+
+/// The events of all input devices, in the order they are fetched.
+struct InputQueue {
+    devs: Vec<AxInputDevice>,
+    events: VecDeque<InputEvent>,
+}
+
+impl InputQueue {
+    /// Moves the pending events of all devices into the queue.
+    fn fetch(&mut self) {
+        for dev in self.devs.iter_mut() {
+            while let Ok(event) = dev.read_event() {
+                self.events.push_back(event);
+            }
+        }
+        #[cfg(feature = "hotplug")]
+        hotplug::fetch_events(&mut self.events);
+    }
+}
 
 /// Initializes the graphics subsystem by underlayer devices.
 pub fn init_display(mut display_devs: AxDeviceContainer<AxDisplayDevice>) {
     info!("Initialize graphics subsystem...");
 
-    let dev = display_devs.take_one().expect("No graphics device found!");
-    info!("  use graphics device 0: {:?}", dev.device_name());
-    MAIN_DISPLAY.init_by(Mutex::new(dev));
+    match display_devs.take_one() {
+        Some(dev) => {
+            info!("  use graphics device 0: {:?}", dev.device_name());
+            *MAIN_DISPLAY.lock() = Some(dev);
+        }
+        None if cfg!(feature = "hotplug") => warn!("  no graphics device yet"),
+        None => panic!("No graphics device found!"),
+    }
+    #[cfg(feature = "hotplug")]
+    hotplug::init_display();
+}
+
+/// Initializes the input event queue by underlayer devices.
+pub fn init_input(mut input_devs: AxDeviceContainer<AxInputDevice>) {
+    info!("Initialize input devices...");
+
+    let mut devs = Vec::new();
+    while let Some(dev) = input_devs.take_one() {
+        info!("  use input device {}: {:?}", devs.len(), dev.device_name());
+        devs.push(dev);
+    }
+    INPUT.init_by(Mutex::new(InputQueue {
+        devs,
+        events: VecDeque::new(),
+    }));
+    #[cfg(feature = "hotplug")]
+    hotplug::init_input();
 }
 
 /// Gets the framebuffer information.
 pub fn framebuffer_info() -> DisplayInfo {
-    MAIN_DISPLAY.lock().info()
+    with_main_display(|dev| dev.info())
 }
 
 /// Flushes the framebuffer, i.e. show on the screen.
 pub fn framebuffer_flush() {
-    MAIN_DISPLAY.lock().flush().unwrap();
+    with_main_display(|dev| dev.flush().unwrap());
+}
+
+fn with_main_display<R>(f: impl FnOnce(&mut AxDisplayDevice) -> R) -> R {
+    f(MAIN_DISPLAY
+        .lock()
+        .as_mut()
+        .expect("No graphics device found!"))
+}
+
+/// Pops the earliest event of all input devices, returns `None` if there is
+/// no pending event.
+pub fn read_input_event() -> Option<InputEvent> {
+    let mut queue = INPUT.try_get()?.lock();
+    if queue.events.is_empty() {
+        queue.fetch();
+    }
+    queue.events.pop_front()
 }
@@ -1,6 +1,12 @@
-const NET_DEV_FEATURES: &[&str] = &["ixgbe", "virtio-net"];
-const BLOCK_DEV_FEATURES: &[&str] = &["ramdisk", "bcm2835-sdhci", "virtio-blk"];
+const NET_DEV_FEATURES: &[&str] = &["ixgbe", "e1000", "virtio-net"];
+const BLOCK_DEV_FEATURES: &[&str] = &["ramdisk", "bcm2835-sdhci", "nvme", "virtio-blk"];
 const DISPLAY_DEV_FEATURES: &[&str] = &["virtio-gpu"];
+const CHAR_DEV_FEATURES: &[&str] = &["uart16550", "pl011", "virtio-console"];
+const RNG_DEV_FEATURES: &[&str] = &["virtio-rng"];
+const INPUT_DEV_FEATURES: &[&str] = &["virtio-input"];
+const GPIO_DEV_FEATURES: &[&str] = &["bcm2711-gpio"];
+const I2C_DEV_FEATURES: &[&str] = &["bcm2711-i2c"];
+const SPI_DEV_FEATURES: &[&str] = &["bcm2711-spi"];
 
 fn has_feature(feature: &str) -> bool {
     std::env::var(format!(
@@ -28,6 +34,12 @@ fn main() {
         ("net", NET_DEV_FEATURES),
         ("block", BLOCK_DEV_FEATURES),
         ("display", DISPLAY_DEV_FEATURES),
+        ("char", CHAR_DEV_FEATURES),
+        ("rng", RNG_DEV_FEATURES),
+        ("input", INPUT_DEV_FEATURES),
+        ("gpio", GPIO_DEV_FEATURES),
+        ("i2c", I2C_DEV_FEATURES),
+        ("spi", SPI_DEV_FEATURES),
     ] {
         if !has_feature(dev_kind) {
             continue;
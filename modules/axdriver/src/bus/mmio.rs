@@ -3,7 +3,13 @@ use crate::{prelude::*, AllDevices};
 
 impl AllDevices {
     pub(crate) fn probe_bus_devices(&mut self) {
-        // TODO: parse device tree
+        #[cfg(feature = "bus-mmio")]
+        if let Some(fdt) = axhal::dtb::fdt() {
+            self.probe_fdt_devices(&fdt);
+            return;
+        }
+
+        // no device tree, probe the regions in the platform configuration
         #[cfg(feature = "virtio")]
         for reg in axconfig::VIRTIO_MMIO_REGIONS {
             for_each_drivers!(type Driver, {
@@ -20,4 +26,65 @@ impl AllDevices {
             });
         }
     }
+
+    /// Probes the available device tree nodes that have a `compatible`
+    /// property.
+    #[cfg(feature = "bus-mmio")]
+    fn probe_fdt_devices(&mut self, fdt: &fdt_parser::Fdt) {
+        // the console is driven by axhal
+        let console = console_paddr(fdt);
+        let nodes = fdt.all_nodes().filter(|node| {
+            node.property("compatible").is_some()
+                && node.is_available()
+                && (console.is_none() || reg_paddr(node) != console)
+        });
+        #[allow(unused_variables)]
+        for node in nodes {
+            for_each_drivers!(type Driver, {
+                if let Some(dev) = Driver::probe_fdt(&node) {
+                    info!(
+                        "registered a new {:?} device at {}: {:?}",
+                        dev.device_type(),
+                        node.name(),
+                        dev.device_name(),
+                    );
+                    self.add_device(dev);
+                    continue; // skip to the next node
+                }
+            });
+        }
+    }
+}
+
+/// Returns the physical address of the first `reg` entry of the node.
+#[cfg(feature = "bus-mmio")]
+fn reg_paddr(node: &fdt_parser::Node) -> Option<u64> {
+    Some(node.reg()?.next()?.address)
+}
+
+/// Returns the physical address of the console, which is the node referred
+/// by `stdout-path` in `/chosen`, either by path or by alias.
+#[cfg(feature = "bus-mmio")]
+fn console_paddr(fdt: &fdt_parser::Fdt) -> Option<u64> {
+    let stdout = fdt.chosen()?.property("stdout-path")?.as_str()?;
+    // strip the options, e.g. "serial0:115200n8"
+    let path = stdout.split(':').next()?;
+    let path = match path.starts_with('/') {
+        true => path,
+        false => fdt.find_node("/aliases")?.property(path)?.as_str()?,
+    };
+    reg_paddr(&fdt.find_node(path)?)
+}
+
+/// Returns the frequency of the input clock of a device, from either its
+/// `clock-frequency` property or the first clock in its `clocks` property.
+#[cfg(feature = "bus-mmio")]
+#[allow(dead_code)]
+pub(crate) fn fdt_clock_hz(node: &fdt_parser::Node) -> Option<u32> {
+    if let Some(freq) = node.property("clock-frequency") {
+        return freq.as_u32();
+    }
+    let phandle = node.property("clocks")?.as_u32_cells().next()?;
+    let clock = axhal::dtb::fdt()?.find_phandle(phandle)?;
+    clock.property("clock-frequency")?.as_u32()
 }
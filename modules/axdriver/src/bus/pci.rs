@@ -1,12 +1,100 @@
 use crate::{prelude::*, AllDevices};
 use axhal::mem::phys_to_virt;
 use driver_pci::{
-    BarInfo, Cam, Command, DeviceFunction, HeaderType, MemoryBarType, PciRangeAllocator, PciRoot,
+    BarInfo, Cam, Command, ConfigSpace, DeviceFunction, HeaderType, MemoryBarType,
+    PciRangeAllocator, PciRoot,
 };
 
 const PCI_BAR_NUM: u8 = 6;
 
-fn config_pci_device(
+/// Returns the root complex of the PCI bus.
+pub(crate) fn pci_root() -> PciRoot {
+    let base_vaddr = phys_to_virt(axconfig::PCI_ECAM_BASE.into());
+    unsafe { PciRoot::new(base_vaddr.as_mut_ptr(), Cam::Ecam) }
+}
+
+/// Returns the accessor of the configuration space of the PCI devices.
+pub(crate) fn config_space() -> ConfigSpace {
+    let base_vaddr = phys_to_virt(axconfig::PCI_ECAM_BASE.into());
+    unsafe { ConfigSpace::new(base_vaddr.as_mut_ptr(), Cam::Ecam) }
+}
+
+/// Returns the mapped address of the memory BAR `bar` of the device.
+pub(crate) fn bar_vaddr(root: &mut PciRoot, bdf: DeviceFunction, bar: u8) -> Option<usize> {
+    match root.bar_info(bdf, bar).ok()? {
+        BarInfo::Memory { address, .. } if address != 0 => {
+            Some(phys_to_virt((address as usize).into()).as_usize())
+        }
+        _ => None,
+    }
+}
+
+/// Allocates an MSI for the device and enables MSI-X with the entry 0 of the
+/// table, the legacy INTx interrupt is disabled.
+///
+/// Returns the IRQ number, or `None` if the device does not support MSI-X or
+/// no vector is available.
+#[cfg(feature = "irq")]
+pub(crate) fn enable_msix(root: &mut PciRoot, bdf: DeviceFunction) -> Option<usize> {
+    use driver_pci::msi::{MsiMessage, MsixCapability};
+
+    let config = config_space();
+    let msix = MsixCapability::find(&config, bdf)?;
+    let (bar, offset) = msix.table_bar();
+    let table_vaddr = bar_vaddr(root, bdf, bar)? + offset as usize;
+    let vector = axhal::irq::alloc_msi()?;
+    let msg = MsiMessage {
+        address: vector.address,
+        data: vector.data,
+    };
+    unsafe { msix.set_entry(table_vaddr as *mut u8, 0, msg) };
+    msix.enable(&config, bdf);
+    disable_intx(root, bdf);
+    debug!("  MSI-X enabled, IRQ {}", vector.irq_num);
+    Some(vector.irq_num)
+}
+
+/// Allocates an MSI for the device and enables it, MSI-X is preferred (see
+/// [`enable_msix`]), or else MSI with a single vector.
+///
+/// Returns the IRQ number, or `None` if the device supports neither or no
+/// vector is available.
+#[cfg(feature = "irq")]
+#[allow(dead_code)] // for drivers of devices that may not support MSI-X
+pub(crate) fn enable_msi(root: &mut PciRoot, bdf: DeviceFunction) -> Option<usize> {
+    use driver_pci::msi::{MsiCapability, MsiMessage};
+
+    if let Some(irq_num) = enable_msix(root, bdf) {
+        return Some(irq_num);
+    }
+    let config = config_space();
+    let msi = MsiCapability::find(&config, bdf)?;
+    let vector = axhal::irq::alloc_msi()?;
+    let msg = MsiMessage {
+        address: vector.address,
+        data: vector.data,
+    };
+    if !msi.enable(&config, bdf, msg) {
+        warn!(
+            "  MSI address {:#x} is out of the 32-bit range",
+            msg.address
+        );
+        return None;
+    }
+    disable_intx(root, bdf);
+    debug!("  MSI enabled, IRQ {}", vector.irq_num);
+    Some(vector.irq_num)
+}
+
+#[cfg(feature = "irq")]
+fn disable_intx(root: &mut PciRoot, bdf: DeviceFunction) {
+    let (_status, cmd) = root.get_status_command(bdf);
+    root.set_command(bdf, cmd | Command::INTERRUPT_DISABLE);
+}
+
+/// Assigns the unassigned memory BARs of the device from `allocator`, and
+/// enables it.
+pub(crate) fn config_pci_device(
     root: &mut PciRoot,
     bdf: DeviceFunction,
     allocator: &mut Option<PciRangeAllocator>,
@@ -84,8 +172,7 @@ fn config_pci_device(
 
 impl AllDevices {
     pub(crate) fn probe_bus_devices(&mut self) {
-        let base_vaddr = phys_to_virt(axconfig::PCI_ECAM_BASE.into());
-        let mut root = unsafe { PciRoot::new(base_vaddr.as_mut_ptr(), Cam::Ecam) };
+        let mut root = pci_root();
 
         // PCI 32-bit MMIO space
         let mut allocator = axconfig::PCI_RANGES
@@ -93,6 +180,11 @@ impl AllDevices {
             .map(|range| PciRangeAllocator::new(range.0 as u64, range.1 as u64));
 
         for bus in 0..=axconfig::PCI_BUS_END as u8 {
+            // the devices in hot-plug slots are probed by `hotplug::poll`
+            #[cfg(feature = "hotplug")]
+            if crate::hotplug::is_slot_bus(bus) {
+                continue;
+            }
             for (bdf, dev_info) in root.enumerate_bus(bus) {
                 debug!("PCI {}: {}", bdf, dev_info);
                 if dev_info.header_type != HeaderType::Standard {
@@ -23,6 +23,13 @@ pub trait DriverProbe {
         None
     }
 
+    /// Probes the device of a device tree node, returns `None` if the node
+    /// is not compatible with the driver.
+    #[cfg(bus = "mmio")]
+    fn probe_fdt(_node: &fdt_parser::Node) -> Option<AxDeviceEnum> {
+        None
+    }
+
     #[cfg(bus = "pci")]
     fn probe_pci(
         _root: &mut PciRoot,
@@ -51,6 +58,24 @@ register_display_driver!(
     <virtio::VirtIoGpu as VirtIoDevMeta>::Device
 );
 
+#[cfg(char_dev = "virtio-console")]
+register_char_driver!(
+    <virtio::VirtIoConsole as VirtIoDevMeta>::Driver,
+    <virtio::VirtIoConsole as VirtIoDevMeta>::Device
+);
+
+#[cfg(rng_dev = "virtio-rng")]
+register_rng_driver!(
+    <virtio::VirtIoRng as VirtIoDevMeta>::Driver,
+    <virtio::VirtIoRng as VirtIoDevMeta>::Device
+);
+
+#[cfg(input_dev = "virtio-input")]
+register_input_driver!(
+    <virtio::VirtIoInput as VirtIoDevMeta>::Driver,
+    <virtio::VirtIoInput as VirtIoDevMeta>::Device
+);
+
 cfg_if::cfg_if! {
     if #[cfg(block_dev = "ramdisk")] {
         pub struct RamDiskDriver;
@@ -81,12 +106,51 @@ cfg_if::cfg_if! {
     }
 }
 
+cfg_if::cfg_if! {
+    if #[cfg(block_dev = "nvme")] {
+        use crate::nvme::NvmeHalImpl;
+        use driver_block::nvme::NvmeDev;
+
+        pub struct NvmeDriver;
+        register_block_driver!(NvmeDriver, NvmeDev<NvmeHalImpl>);
+
+        impl DriverProbe for NvmeDriver {
+            #[cfg(bus = "pci")]
+            fn probe_pci(
+                root: &mut PciRoot,
+                bdf: DeviceFunction,
+                dev_info: &DeviceFunctionInfo,
+            ) -> Option<AxDeviceEnum> {
+                // mass storage controller, non-volatile memory controller, NVMe
+                if (dev_info.class, dev_info.subclass, dev_info.prog_if) != (0x01, 0x08, 0x02) {
+                    return None;
+                }
+                const NUM_IO_QUEUES: u16 = 4;
+                info!("NVMe controller found at {}", bdf);
+                let regs = crate::bus::pci::bar_vaddr(root, bdf, 0)?;
+                match NvmeDev::<NvmeHalImpl>::try_new(regs, NUM_IO_QUEUES) {
+                    Ok(dev) => Some(AxDeviceEnum::from_block(dev)),
+                    Err(e) => {
+                        warn!("failed to initialize NVMe controller at {}: {:?}", bdf, e);
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
+
 cfg_if::cfg_if! {
     if #[cfg(net_dev = "ixgbe")] {
         use crate::ixgbe::IxgbeHalImpl;
         use axhal::mem::phys_to_virt;
         pub struct IxgbeDriver;
-        register_net_driver!(IxgbeDriver, driver_net::ixgbe::IxgbeNic<IxgbeHalImpl, 1024, 1>);
+        /// The ixgbe queue size.
+        const IXGBE_QS: usize = 1024;
+        /// The ixgbe queue num, one queue for each CPU, but no more than 4 to
+        /// bound the memory of the receive buffers.
+        const IXGBE_QN: u16 = if axconfig::SMP < 4 { axconfig::SMP as u16 } else { 4 };
+        register_net_driver!(IxgbeDriver, driver_net::ixgbe::IxgbeNic<IxgbeHalImpl, IXGBE_QS, IXGBE_QN>);
         impl DriverProbe for IxgbeDriver {
             fn probe_pci(
                     root: &mut driver_pci::PciRoot,
@@ -101,8 +165,10 @@ cfg_if::cfg_if! {
 
                         // Initialize the device
                         // These can be changed according to the requirments specified in the ixgbe init function.
-                        const QN: u16 = 1;
-                        const QS: usize = 1024;
+                        const QN: u16 = IXGBE_QN;
+                        const QS: usize = IXGBE_QS;
+                        // Jumbo frames are enabled by a larger MTU in the config.
+                        const MTU: usize = axconfig::IXGBE_MTU;
                         let bar_info = root.bar_info(bdf, 0).unwrap();
                         match bar_info {
                             driver_pci::BarInfo::Memory {
@@ -110,11 +176,17 @@ cfg_if::cfg_if! {
                                 size,
                                 ..
                             } => {
-                                let ixgbe_nic = IxgbeNic::<IxgbeHalImpl, QS, QN>::init(
+                                #[allow(unused_mut)]
+                                let mut ixgbe_nic = IxgbeNic::<IxgbeHalImpl, QS, QN>::init_with_mtu(
                                     phys_to_virt((address as usize).into()).into(),
-                                    size as usize
+                                    size as usize,
+                                    MTU,
                                 )
                                 .expect("failed to initialize ixgbe device");
+                                #[cfg(feature = "irq")]
+                                if let Some(irq_num) = crate::bus::pci::enable_msix(root, bdf) {
+                                    ixgbe_nic.enable_interrupts(irq_num);
+                                }
                                 return Some(AxDeviceEnum::from_net(ixgbe_nic));
                             }
                             driver_pci::BarInfo::IO { .. } => {
@@ -128,3 +200,329 @@ cfg_if::cfg_if! {
         }
     }
 }
+
+cfg_if::cfg_if! {
+    if #[cfg(net_dev = "e1000")] {
+        use crate::e1000::E1000HalImpl;
+        use driver_net::e1000::{E1000Nic, E1000_DEVICE_IDS, INTEL_VEND};
+
+        /// The size of the descriptor rings.
+        const E1000_QS: usize = 256;
+
+        pub struct E1000Driver;
+        register_net_driver!(E1000Driver, E1000Nic<E1000HalImpl, E1000_QS>);
+
+        impl DriverProbe for E1000Driver {
+            #[cfg(bus = "pci")]
+            fn probe_pci(
+                root: &mut PciRoot,
+                bdf: DeviceFunction,
+                dev_info: &DeviceFunctionInfo,
+            ) -> Option<AxDeviceEnum> {
+                if dev_info.vendor_id != INTEL_VEND
+                    || !E1000_DEVICE_IDS.contains(&dev_info.device_id)
+                {
+                    return None;
+                }
+                info!("e1000 NIC {:#06x} found at {}", dev_info.device_id, bdf);
+                let regs = crate::bus::pci::bar_vaddr(root, bdf, 0)?;
+                #[allow(unused_mut)]
+                let mut nic = match E1000Nic::<E1000HalImpl, E1000_QS>::init(regs) {
+                    Ok(nic) => nic,
+                    Err(e) => {
+                        warn!("failed to initialize e1000 NIC at {}: {:?}", bdf, e);
+                        return None;
+                    }
+                };
+                #[cfg(feature = "irq")]
+                if let Some(irq_num) = crate::bus::pci::enable_msix(root, bdf) {
+                    // 82574L
+                    nic.enable_msix_interrupts(irq_num);
+                } else if let Some(irq_num) = crate::bus::pci::enable_msi(root, bdf) {
+                    nic.enable_interrupts(irq_num);
+                }
+                Some(AxDeviceEnum::from_net(nic))
+            }
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(char_dev = "uart16550")] {
+        use driver_char::uart16550::{Uart16550, Uart16550Io};
+
+        pub struct Uart16550Driver;
+        register_char_driver!(Uart16550Driver, Uart16550);
+
+        /// Initializes the UART, or returns `None` if it's absent or fails.
+        fn init_uart16550(io: Uart16550Io, clock_hz: u32) -> Option<AxDeviceEnum> {
+            let mut uart = unsafe { Uart16550::new(io, clock_hz) };
+            if !uart.is_present() {
+                return None;
+            }
+            match uart.init() {
+                Ok(_) => Some(AxDeviceEnum::from_char(uart)),
+                Err(e) => {
+                    warn!("failed to initialize 16550 UART {:?}: {:?}", io, e);
+                    None
+                }
+            }
+        }
+
+        impl DriverProbe for Uart16550Driver {
+            /// Probes COM2 on PCs, COM1 is the console.
+            #[cfg(target_arch = "x86_64")]
+            fn probe_global() -> Option<AxDeviceEnum> {
+                const COM2_PORT: u16 = 0x2f8;
+                const COM_CLOCK_HZ: u32 = 1_843_200;
+                init_uart16550(Uart16550Io::Port(COM2_PORT), COM_CLOCK_HZ)
+            }
+
+            #[cfg(all(bus = "mmio", feature = "bus-mmio"))]
+            fn probe_fdt(node: &fdt_parser::Node) -> Option<AxDeviceEnum> {
+                use axhal::mem::phys_to_virt;
+                if !node.is_compatible("ns16550a")
+                    && !node.is_compatible("ns16550")
+                    && !node.is_compatible("snps,dw-apb-uart")
+                {
+                    return None;
+                }
+                let prop_u32 = |name| node.property(name).and_then(|p| p.as_u32());
+                let paddr = node.reg()?.next()?.address as usize;
+                let io = Uart16550Io::Mmio {
+                    base: phys_to_virt(paddr.into()).as_usize(),
+                    reg_shift: prop_u32("reg-shift").unwrap_or(0) as u8,
+                    reg_io_width: prop_u32("reg-io-width").unwrap_or(1) as u8,
+                };
+                let clock_hz = crate::bus::mmio::fdt_clock_hz(node)?;
+                init_uart16550(io, clock_hz)
+            }
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(char_dev = "pl011")] {
+        use driver_char::pl011::Pl011;
+
+        pub struct Pl011Driver;
+        register_char_driver!(Pl011Driver, Pl011);
+
+        impl DriverProbe for Pl011Driver {
+            #[cfg(all(bus = "mmio", feature = "bus-mmio"))]
+            fn probe_fdt(node: &fdt_parser::Node) -> Option<AxDeviceEnum> {
+                use axhal::mem::phys_to_virt;
+                if !node.is_compatible("arm,pl011") {
+                    return None;
+                }
+                let paddr = node.reg()?.next()?.address as usize;
+                let clock_hz = crate::bus::mmio::fdt_clock_hz(node)?;
+                let base = phys_to_virt(paddr.into()).as_mut_ptr();
+                let mut uart = unsafe { Pl011::new(base, clock_hz) };
+                match uart.init() {
+                    Ok(_) => Some(AxDeviceEnum::from_char(uart)),
+                    Err(e) => {
+                        warn!("failed to initialize PL011 UART at {:#x}: {:?}", paddr, e);
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The core clock of the BCM2711, the input clock of the I2C and SPI
+/// controllers if the device tree doesn't give its frequency.
+#[cfg(all(feature = "bcm2711-gpio", bus = "mmio", feature = "bus-mmio"))]
+const BCM2711_CORE_CLOCK_HZ: u32 = 500_000_000;
+
+/// Returns the physical address and the mapped registers of a BCM2711
+/// peripheral.
+///
+/// The device tree gives the VideoCore bus addresses of the peripherals
+/// (`0x7e00_0000..`), which are translated by the `ranges` of `/soc`, but
+/// `fdt_parser` doesn't, so they are translated here.
+#[cfg(all(feature = "bcm2711-gpio", bus = "mmio", feature = "bus-mmio"))]
+fn bcm2711_regs(node: &fdt_parser::Node) -> Option<(usize, *mut u8)> {
+    use axhal::mem::phys_to_virt;
+    const BUS_BASE: u64 = 0x7e00_0000;
+    const PHYS_BASE: u64 = 0xfe00_0000;
+    const SIZE: u64 = 0x0180_0000;
+    let addr = node.reg()?.next()?.address;
+    let paddr = match addr.checked_sub(BUS_BASE) {
+        Some(offset) if offset < SIZE => PHYS_BASE + offset,
+        _ => addr,
+    } as usize;
+    Some((paddr, phys_to_virt(paddr.into()).as_mut_ptr()))
+}
+
+/// Returns the frequency of the input clock of a BCM2711 I2C or SPI
+/// controller, from the first clock in its `clocks` property.
+///
+/// Unlike [`crate::bus::mmio::fdt_clock_hz`], the `clock-frequency` of the
+/// node is not used, which is the bus speed of I2C controllers.
+#[cfg(all(feature = "bcm2711-gpio", bus = "mmio", feature = "bus-mmio"))]
+#[allow(dead_code)]
+fn bcm2711_clock_hz(node: &fdt_parser::Node) -> u32 {
+    let clock_hz = || {
+        let phandle = node.property("clocks")?.as_u32_cells().next()?;
+        let clock = axhal::dtb::fdt()?.find_phandle(phandle)?;
+        clock.property("clock-frequency")?.as_u32()
+    };
+    clock_hz().unwrap_or(BCM2711_CORE_CLOCK_HZ)
+}
+
+/// Applies the pin groups in the `pinctrl-0` property of a BCM2711
+/// peripheral, which route its signals to the GPIO pins.
+///
+/// The groups have the `brcm,pins` to configure, and their `brcm,function`
+/// and optional `brcm,pull`, either one for each pin or one for all.
+#[cfg(all(feature = "bcm2711-gpio", bus = "mmio", feature = "bus-mmio"))]
+#[allow(dead_code)]
+fn bcm2711_apply_pinctrl(node: &fdt_parser::Node) {
+    use driver_gpio::{bcm2711::Bcm2711Gpio, GpioDriverOps, Pull};
+    let Some(fdt) = axhal::dtb::fdt() else {
+        return;
+    };
+    let Some(pinctrl) = node.property("pinctrl-0") else {
+        return;
+    };
+    let Some((_, base)) = fdt
+        .find_compatible(&["brcm,bcm2711-gpio"])
+        .next()
+        .and_then(|gpio| bcm2711_regs(&gpio))
+    else {
+        warn!("no GPIO controller for the pins of {}", node.name());
+        return;
+    };
+    // The GPIO controller may also be registered as a device, but the
+    // devices are not used until all of them are probed.
+    let mut gpio = unsafe { Bcm2711Gpio::new(base) };
+    for phandle in pinctrl.as_u32_cells() {
+        let Some(group) = fdt.find_phandle(phandle) else {
+            continue;
+        };
+        let cells = |name| {
+            group
+                .property(name)
+                .into_iter()
+                .flat_map(|p| p.as_u32_cells())
+        };
+        // the setting of the i-th pin, or the only one for all
+        let nth = |name, i| match cells(name).count() {
+            1 => cells(name).next(),
+            _ => cells(name).nth(i),
+        };
+        for (i, pin) in cells("brcm,pins").enumerate() {
+            let pin = pin as usize;
+            let mut res = match nth("brcm,function", i) {
+                Some(fsel) => gpio.set_function(pin, fsel),
+                None => Ok(()),
+            };
+            if let Some(pull) = nth("brcm,pull", i) {
+                let pull = match pull {
+                    1 => Pull::Down,
+                    2 => Pull::Up,
+                    _ => Pull::None,
+                };
+                res = res.and(gpio.set_pull(pin, pull));
+            }
+            if let Err(e) = res {
+                warn!(
+                    "failed to configure GPIO {} for {}: {:?}",
+                    pin,
+                    node.name(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(gpio_dev = "bcm2711-gpio")] {
+        use driver_gpio::bcm2711::Bcm2711Gpio;
+
+        pub struct Bcm2711GpioDriver;
+        register_gpio_driver!(Bcm2711GpioDriver, Bcm2711Gpio);
+
+        impl DriverProbe for Bcm2711GpioDriver {
+            #[cfg(all(bus = "mmio", feature = "bus-mmio"))]
+            fn probe_fdt(node: &fdt_parser::Node) -> Option<AxDeviceEnum> {
+                if !node.is_compatible("brcm,bcm2711-gpio") {
+                    return None;
+                }
+                let (_, base) = bcm2711_regs(node)?;
+                Some(AxDeviceEnum::from_gpio(unsafe { Bcm2711Gpio::new(base) }))
+            }
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(i2c_dev = "bcm2711-i2c")] {
+        use driver_i2c::bcm2711::Bcm2711I2c;
+
+        pub struct Bcm2711I2cDriver;
+        register_i2c_driver!(Bcm2711I2cDriver, Bcm2711I2c);
+
+        impl DriverProbe for Bcm2711I2cDriver {
+            #[cfg(all(bus = "mmio", feature = "bus-mmio"))]
+            fn probe_fdt(node: &fdt_parser::Node) -> Option<AxDeviceEnum> {
+                if !node.is_compatible("brcm,bcm2711-i2c")
+                    && !node.is_compatible("brcm,bcm2835-i2c")
+                {
+                    return None;
+                }
+                let (paddr, base) = bcm2711_regs(node)?;
+                let bus_speed = node
+                    .property("clock-frequency")
+                    .and_then(|p| p.as_u32())
+                    .unwrap_or(driver_i2c::STANDARD_SPEED);
+                bcm2711_apply_pinctrl(node);
+                let mut i2c = unsafe { Bcm2711I2c::new(base, bcm2711_clock_hz(node)) };
+                match i2c.init(bus_speed) {
+                    Ok(_) => Some(AxDeviceEnum::from_i2c(i2c)),
+                    Err(e) => {
+                        warn!("failed to initialize I2C controller at {:#x}: {:?}", paddr, e);
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(spi_dev = "bcm2711-spi")] {
+        use driver_spi::{bcm2711::Bcm2711Spi, SpiConfig, SpiMode};
+
+        pub struct Bcm2711SpiDriver;
+        register_spi_driver!(Bcm2711SpiDriver, Bcm2711Spi);
+
+        impl DriverProbe for Bcm2711SpiDriver {
+            #[cfg(all(bus = "mmio", feature = "bus-mmio"))]
+            fn probe_fdt(node: &fdt_parser::Node) -> Option<AxDeviceEnum> {
+                /// The clock rate until it's configured by the users.
+                const DEFAULT_SPEED: u32 = 1_000_000;
+                if !node.is_compatible("brcm,bcm2835-spi") {
+                    return None;
+                }
+                let (paddr, base) = bcm2711_regs(node)?;
+                bcm2711_apply_pinctrl(node);
+                let mut spi = unsafe { Bcm2711Spi::new(base, bcm2711_clock_hz(node)) };
+                let config = SpiConfig {
+                    speed_hz: DEFAULT_SPEED,
+                    mode: SpiMode::MODE0,
+                };
+                match spi.init(config) {
+                    Ok(_) => Some(AxDeviceEnum::from_spi(spi)),
+                    Err(e) => {
+                        warn!("failed to initialize SPI controller at {:#x}: {:?}", paddr, e);
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
@@ -69,6 +69,37 @@ cfg_if! {
     }
 }
 
+cfg_if! {
+    if #[cfg(char_dev = "dummy")] {
+        use driver_char::CharPollState;
+
+        pub struct DummyCharDev;
+        pub struct DummyCharDriver;
+        register_char_driver!(DummyCharDriver, DummyCharDev);
+
+        impl BaseDriverOps for DummyCharDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Char
+            }
+            fn device_name(&self) -> &str {
+                "dummy-char"
+            }
+        }
+
+        impl CharDriverOps for DummyCharDev {
+            fn read(&mut self, _: &mut [u8]) -> DevResult<usize> {
+                Err(DevError::Unsupported)
+            }
+            fn write(&mut self, _: &[u8]) -> DevResult<usize> {
+                Err(DevError::Unsupported)
+            }
+            fn poll(&mut self) -> CharPollState {
+                CharPollState::default()
+            }
+        }
+    }
+}
+
 cfg_if! {
     if #[cfg(display_dev = "dummy")] {
         pub struct DummyDisplayDev;
@@ -100,3 +131,154 @@ cfg_if! {
         }
     }
 }
+
+cfg_if! {
+    if #[cfg(rng_dev = "dummy")] {
+        pub struct DummyRngDev;
+        pub struct DummyRngDriver;
+        register_rng_driver!(DummyRngDriver, DummyRngDev);
+
+        impl BaseDriverOps for DummyRngDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Rng
+            }
+            fn device_name(&self) -> &str {
+                "dummy-rng"
+            }
+        }
+
+        impl RngDriverOps for DummyRngDev {
+            fn request_entropy(&mut self, _: &mut [u8]) -> DevResult<usize> {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(input_dev = "dummy")] {
+        pub struct DummyInputDev;
+        pub struct DummyInputDriver;
+        register_input_driver!(DummyInputDriver, DummyInputDev);
+
+        impl BaseDriverOps for DummyInputDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Input
+            }
+            fn device_name(&self) -> &str {
+                "dummy-input"
+            }
+        }
+
+        impl InputDriverOps for DummyInputDev {
+            fn read_event(&mut self) -> DevResult<driver_input::InputEvent> {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(gpio_dev = "dummy")] {
+        pub struct DummyGpioDev;
+        pub struct DummyGpioDriver;
+        register_gpio_driver!(DummyGpioDriver, DummyGpioDev);
+
+        impl BaseDriverOps for DummyGpioDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Gpio
+            }
+            fn device_name(&self) -> &str {
+                "dummy-gpio"
+            }
+        }
+
+        impl GpioDriverOps for DummyGpioDev {
+            fn num_pins(&self) -> usize {
+                0
+            }
+            fn mode(&self, _: usize) -> DevResult<PinMode> {
+                Err(DevError::Unsupported)
+            }
+            fn set_mode(&mut self, _: usize, _: PinMode) -> DevResult {
+                Err(DevError::Unsupported)
+            }
+            fn set_pull(&mut self, _: usize, _: Pull) -> DevResult {
+                Err(DevError::Unsupported)
+            }
+            fn read(&self, _: usize) -> DevResult<bool> {
+                Err(DevError::Unsupported)
+            }
+            fn write(&mut self, _: usize, _: bool) -> DevResult {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(i2c_dev = "dummy")] {
+        pub struct DummyI2cDev;
+        pub struct DummyI2cDriver;
+        register_i2c_driver!(DummyI2cDriver, DummyI2cDev);
+
+        impl BaseDriverOps for DummyI2cDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::I2c
+            }
+            fn device_name(&self) -> &str {
+                "dummy-i2c"
+            }
+        }
+
+        impl I2cDriverOps for DummyI2cDev {
+            fn bus_speed(&self) -> u32 {
+                0
+            }
+            fn set_bus_speed(&mut self, _: u32) -> DevResult {
+                Err(DevError::Unsupported)
+            }
+            fn read(&mut self, _: u8, _: &mut [u8]) -> DevResult {
+                Err(DevError::Unsupported)
+            }
+            fn write(&mut self, _: u8, _: &[u8]) -> DevResult {
+                Err(DevError::Unsupported)
+            }
+            fn write_read(&mut self, _: u8, _: &[u8], _: &mut [u8]) -> DevResult {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(spi_dev = "dummy")] {
+        pub struct DummySpiDev;
+        pub struct DummySpiDriver;
+        register_spi_driver!(DummySpiDriver, DummySpiDev);
+
+        impl BaseDriverOps for DummySpiDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Spi
+            }
+            fn device_name(&self) -> &str {
+                "dummy-spi"
+            }
+        }
+
+        impl SpiDriverOps for DummySpiDev {
+            fn num_chip_selects(&self) -> usize {
+                0
+            }
+            fn config(&self) -> SpiConfig {
+                unreachable!()
+            }
+            fn set_config(&mut self, _: SpiConfig) -> DevResult {
+                Err(DevError::Unsupported)
+            }
+            fn transfer(&mut self, _: usize, _: &[u8], _: &mut [u8]) -> DevResult {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use axalloc::global_allocator;
+use axhal::mem::virt_to_phys;
+use core::ptr::NonNull;
+use driver_net::e1000::{E1000Hal, PhysAddr, PAGE_SIZE};
+
+pub struct E1000HalImpl;
+
+unsafe impl E1000Hal for E1000HalImpl {
+    fn dma_alloc(pages: usize) -> (PhysAddr, NonNull<u8>) {
+        let vaddr = if let Ok(vaddr) = global_allocator().alloc_pages(pages, PAGE_SIZE) {
+            vaddr
+        } else {
+            return (0, NonNull::dangling());
+        };
+        let paddr = virt_to_phys(vaddr.into());
+        (paddr.as_usize(), NonNull::new(vaddr as _).unwrap())
+    }
+
+    unsafe fn dma_dealloc(_paddr: PhysAddr, vaddr: NonNull<u8>, pages: usize) -> i32 {
+        global_allocator().dealloc_pages(vaddr.as_ptr() as usize, pages);
+        0
+    }
+
+    fn virt_to_phys(vaddr: usize) -> PhysAddr {
+        virt_to_phys(vaddr.into()).as_usize()
+    }
+
+    fn busy_wait(duration: core::time::Duration) {
+        axhal::time::busy_wait(duration);
+    }
+}
@@ -0,0 +1,454 @@
+//! Runtime driver registry and PCI Express native hot-plug.
+//!
+//! With the `hotplug` feature, the devices in hot-plug slots (PCIe root ports
+//! or downstream ports that report the hot-plug capability) are not probed by
+//! [`init_drivers`](crate::init_drivers). Instead, [`poll`] watches the slots,
+//! probes the devices that appear in them with the registered [`Driver`]s,
+//! and hands them to the [`DeviceNotifier`] of their device category. When a
+//! device is to be removed (e.g., by the attention button, which QEMU
+//! `device_del` presses), the notifier detaches it before the slot is
+//! powered off.
+//!
+//! All the built-in drivers are registered at boot, and more drivers can be
+//! registered by [`register_driver`].
+//!
+//! # Limitations
+//!
+//! - The bus numbers and the memory window of the slots must be assigned by
+//!   the firmware, which is the case on x86_64 PCs, but not on the RISC-V and
+//!   AArch64 `virt` machines of QEMU.
+//! - Only the slots present at boot are watched, so the slots of a
+//!   hot-plugged switch are not supported.
+//! - The MSI vectors of the removed devices are not reclaimed.
+
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use driver_pci::{
+    ConfigSpace, DeviceFunction, DeviceFunctionInfo, HeaderType, PciRangeAllocator, PciRoot,
+};
+use spinlock::SpinNoIrq;
+
+use crate::bus::pci::{config_pci_device, config_space, pci_root};
+use crate::prelude::*;
+use crate::AxDeviceEnum;
+
+// the PCI Express capability
+const PCI_CAP_ID_EXP: u8 = 0x10;
+const PCI_EXP_FLAGS: u8 = 0x02;
+const PCI_EXP_SLTCAP: u8 = 0x14;
+const PCI_EXP_SLTCTL: u8 = 0x18;
+const PCI_EXP_SLTSTA: u8 = 0x1a;
+
+const PCI_EXP_FLAGS_SLOT: u16 = 1 << 8;
+const PCI_EXP_SLTCAP_HPC: u32 = 1 << 6;
+const PCI_EXP_SLTCTL_PIC: u16 = 0b11 << 8;
+const PCI_EXP_SLTCTL_PWR_IND_ON: u16 = 0b01 << 8;
+const PCI_EXP_SLTCTL_PWR_IND_OFF: u16 = 0b11 << 8;
+const PCI_EXP_SLTCTL_PCC: u16 = 1 << 10;
+const PCI_EXP_SLTSTA_ABP: u16 = 1 << 0;
+const PCI_EXP_SLTSTA_PDC: u16 = 1 << 3;
+const PCI_EXP_SLTSTA_PDS: u16 = 1 << 6;
+const PCI_EXP_SLTSTA_DLLSC: u16 = 1 << 8;
+
+// the type 1 (bridge) configuration header
+const PCI_PRIMARY_BUS: u8 = 0x18;
+const PCI_MEMORY_BASE: u8 = 0x20;
+
+/// How long to wait after powering on a slot before accessing the device.
+const POWER_ON_DELAY: Duration = Duration::from_millis(100);
+
+/// The identifier of a device added after boot, which is given to
+/// [`DeviceNotifier::device_added`] and [`DeviceNotifier::device_removed`].
+pub type DeviceId = usize;
+
+/// An entry in the match table of a [`Driver`].
+#[derive(Debug, Clone, Copy)]
+pub enum DeviceMatch {
+    /// Matches the vendor ID and the device ID.
+    PciId { vendor_id: u16, device_id: u16 },
+    /// Matches the class code.
+    PciClass {
+        class: u8,
+        subclass: u8,
+        prog_if: u8,
+    },
+}
+
+impl DeviceMatch {
+    fn matches(&self, info: &DeviceFunctionInfo) -> bool {
+        match *self {
+            Self::PciId {
+                vendor_id,
+                device_id,
+            } => info.vendor_id == vendor_id && info.device_id == device_id,
+            Self::PciClass {
+                class,
+                subclass,
+                prog_if,
+            } => (info.class, info.subclass, info.prog_if) == (class, subclass, prog_if),
+        }
+    }
+}
+
+/// A PCI device driver in the registry.
+#[derive(Clone, Copy)]
+pub struct Driver {
+    /// The name of the driver.
+    pub name: &'static str,
+    /// The devices supported by the driver. If it's empty, the driver is
+    /// tried on all devices, and `probe` decides whether to accept them.
+    pub match_table: &'static [DeviceMatch],
+    /// Initializes a matched device, returns `None` if the device is not
+    /// supported or fails to initialize.
+    pub probe: fn(&mut PciRoot, DeviceFunction, &DeviceFunctionInfo) -> Option<AxDeviceEnum>,
+}
+
+impl Driver {
+    fn matches(&self, info: &DeviceFunctionInfo) -> bool {
+        self.match_table.is_empty() || self.match_table.iter().any(|m| m.matches(info))
+    }
+}
+
+/// The callbacks of a subsystem (e.g., the filesystem or the network stack)
+/// for the devices of one category that are added or removed after boot.
+///
+/// The callbacks are called from [`poll`] or [`register_notifier`], without
+/// any lock of this module held.
+pub trait DeviceNotifier: Send + Sync {
+    /// Called when a device is added, the notifier takes over the device.
+    fn device_added(&self, id: DeviceId, dev: AxDeviceEnum);
+
+    /// Called when the device `id` is to be removed. The notifier must stop
+    /// using and drop the device before returning, as the slot is powered
+    /// off afterwards.
+    fn device_removed(&self, id: DeviceId);
+}
+
+/// A PCI Express hot-plug slot, i.e. the port that it's connected to.
+#[derive(Clone)]
+struct Slot {
+    port: DeviceFunction,
+    /// The offset of the PCI Express capability of the port.
+    cap: u8,
+    /// The buses below the port.
+    buses: RangeInclusive<u8>,
+    /// The memory window of the port, as `(base, size)`.
+    window: (u64, u64),
+    /// Whether the devices in the slot are probed.
+    occupied: bool,
+    /// Whether the slot needs to be scanned regardless of presence changes,
+    /// which is the case for the devices present at boot.
+    scan: bool,
+}
+
+impl Slot {
+    fn status(&self, config: &ConfigSpace) -> u16 {
+        config.read_u16(self.port, self.cap + PCI_EXP_SLTSTA)
+    }
+
+    fn control(&self, config: &ConfigSpace) -> u16 {
+        config.read_u16(self.port, self.cap + PCI_EXP_SLTCTL)
+    }
+
+    /// Writes the slot control register. The slot status register shares
+    /// the same 32-bit register, and is written with zeros, as its bits are
+    /// cleared by writing ones.
+    fn set_control(&self, config: &ConfigSpace, ctl: u16) {
+        config.write_u32(self.port, self.cap + PCI_EXP_SLTCTL, ctl as u32);
+    }
+
+    fn clear_status(&self, config: &ConfigSpace, bits: u16) {
+        let ctl = self.control(config) as u32;
+        let value = ctl | (bits as u32) << 16;
+        config.write_u32(self.port, self.cap + PCI_EXP_SLTCTL, value);
+    }
+
+    fn set_power(&self, config: &ConfigSpace, on: bool) {
+        let ctl = self.control(config) & !(PCI_EXP_SLTCTL_PCC | PCI_EXP_SLTCTL_PIC);
+        if on {
+            self.set_control(config, ctl | PCI_EXP_SLTCTL_PWR_IND_ON);
+        } else {
+            let ctl = ctl | PCI_EXP_SLTCTL_PCC | PCI_EXP_SLTCTL_PWR_IND_OFF;
+            self.set_control(config, ctl);
+        }
+    }
+}
+
+struct Registry {
+    drivers: Vec<Driver>,
+    notifiers: Vec<(DeviceType, &'static dyn DeviceNotifier)>,
+    slots: Vec<Slot>,
+    /// The enabled functions in the slots that no driver accepts.
+    unbound: Vec<(DeviceFunction, DeviceFunctionInfo)>,
+    /// The functions in the slots that are bound to a driver.
+    bound: Vec<(DeviceId, DeviceFunction, DeviceType)>,
+    /// The devices that are not taken by a notifier yet.
+    unclaimed: Vec<(DeviceId, AxDeviceEnum)>,
+    /// Whether drivers are registered since the last poll.
+    rescan: bool,
+    next_id: DeviceId,
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Self {
+            drivers: Vec::new(),
+            notifiers: Vec::new(),
+            slots: Vec::new(),
+            unbound: Vec::new(),
+            bound: Vec::new(),
+            unclaimed: Vec::new(),
+            rescan: false,
+            next_id: 0,
+        }
+    }
+
+    fn notifier(&self, ty: DeviceType) -> Option<&'static dyn DeviceNotifier> {
+        self.notifiers
+            .iter()
+            .find(|(t, _)| *t == ty)
+            .map(|&(_, notifier)| notifier)
+    }
+}
+
+static REGISTRY: SpinNoIrq<Registry> = SpinNoIrq::new(Registry::new());
+static POLLING: AtomicBool = AtomicBool::new(false);
+
+/// Registers a driver. The devices in the slots that no driver accepts are
+/// probed with it at the next [`poll`].
+pub fn register_driver(driver: Driver) {
+    debug!("hot-plug: register driver {:?}", driver.name);
+    let mut reg = REGISTRY.lock();
+    reg.drivers.push(driver);
+    reg.rescan = true;
+}
+
+/// Registers the notifier of the devices of type `ty`, and adds the devices
+/// of the type that are not taken yet to it.
+///
+/// Each device type has at most one notifier, the previous one is replaced.
+pub fn register_notifier(ty: DeviceType, notifier: &'static dyn DeviceNotifier) {
+    let mut reg = REGISTRY.lock();
+    reg.notifiers.retain(|(t, _)| *t != ty);
+    reg.notifiers.push((ty, notifier));
+    let (added, unclaimed) = core::mem::take(&mut reg.unclaimed)
+        .into_iter()
+        .partition::<Vec<_>, _>(|(_, dev)| dev.device_type() == ty);
+    reg.unclaimed = unclaimed;
+    drop(reg);
+    for (id, dev) in added {
+        notifier.device_added(id, dev);
+    }
+}
+
+/// Handles the events of the hot-plug slots: probes the devices that are
+/// inserted, and removes the devices that are requested to be removed or
+/// are gone.
+///
+/// It should be called periodically (e.g., every 100 ms) from a task, as it
+/// may busy-wait for a slot to power on. Concurrent calls return at once.
+pub fn poll() {
+    if POLLING.swap(true, Ordering::Acquire) {
+        return;
+    }
+    let config = config_space();
+    let mut root = pci_root();
+    let slots = REGISTRY.lock().slots.clone();
+    for (idx, mut slot) in slots.into_iter().enumerate() {
+        let status = slot.status(&config);
+        let events = status & (PCI_EXP_SLTSTA_ABP | PCI_EXP_SLTSTA_PDC | PCI_EXP_SLTSTA_DLLSC);
+        if events != 0 {
+            slot.clear_status(&config, events);
+        }
+        let present = status & PCI_EXP_SLTSTA_PDS != 0;
+        if slot.occupied && (!present || status & PCI_EXP_SLTSTA_ABP != 0) {
+            info!("hot-plug: removing the devices at {}", slot.port);
+            remove_devices(&slot.buses);
+            slot.set_power(&config, false);
+            slot.occupied = false;
+        } else if !slot.occupied && present && (slot.scan || status & PCI_EXP_SLTSTA_PDC != 0) {
+            info!("hot-plug: device inserted at {}", slot.port);
+            slot.set_power(&config, true);
+            axhal::time::busy_wait(POWER_ON_DELAY);
+            scan_slot(&mut root, &slot);
+            slot.occupied = true;
+        }
+        slot.scan = false;
+        REGISTRY.lock().slots[idx] = slot;
+    }
+
+    let unbound = {
+        let mut reg = REGISTRY.lock();
+        if core::mem::take(&mut reg.rescan) {
+            core::mem::take(&mut reg.unbound)
+        } else {
+            Vec::new()
+        }
+    };
+    for (bdf, info) in unbound {
+        probe_function(&mut root, bdf, info);
+    }
+    POLLING.store(false, Ordering::Release);
+}
+
+/// Returns whether the bus is below a hot-plug slot.
+pub(crate) fn is_slot_bus(bus: u8) -> bool {
+    REGISTRY
+        .lock()
+        .slots
+        .iter()
+        .any(|slot| slot.buses.contains(&bus))
+}
+
+/// Finds the hot-plug slots, and registers the built-in drivers.
+pub(crate) fn init() {
+    let config = config_space();
+    let root = pci_root();
+    let mut slots = Vec::new();
+    for bus in 0..=axconfig::PCI_BUS_END as u8 {
+        for (bdf, info) in root.enumerate_bus(bus) {
+            if info.header_type != HeaderType::PciPciBridge {
+                continue;
+            }
+            let Some(cap) = config
+                .capabilities(bdf)
+                .find(|&(_, id)| id == PCI_CAP_ID_EXP)
+                .map(|(cap, _)| cap)
+            else {
+                continue;
+            };
+            if config.read_u16(bdf, cap + PCI_EXP_FLAGS) & PCI_EXP_FLAGS_SLOT == 0
+                || config.read_u32(bdf, cap + PCI_EXP_SLTCAP) & PCI_EXP_SLTCAP_HPC == 0
+            {
+                continue;
+            }
+
+            let bus_numbers = config.read_u32(bdf, PCI_PRIMARY_BUS);
+            let (secondary, subordinate) = ((bus_numbers >> 8) as u8, (bus_numbers >> 16) as u8);
+            // bits 31:20 of the base and the limit, the limit is inclusive
+            let memory = config.read_u32(bdf, PCI_MEMORY_BASE);
+            let base = ((memory & 0xfff0) as u64) << 16;
+            let limit = (((memory >> 16) & 0xfff0) as u64) << 16 | 0xf_ffff;
+            if secondary == 0 || base > limit {
+                warn!("hot-plug slot at {} is not configured, ignored", bdf);
+                continue;
+            }
+            info!(
+                "hot-plug slot at {}: bus [{:#x}, {:#x}], MEM [{:#x}, {:#x}]",
+                bdf, secondary, subordinate, base, limit
+            );
+            let slot = Slot {
+                port: bdf,
+                cap,
+                buses: secondary..=subordinate,
+                window: (base, limit - base + 1),
+                occupied: false,
+                scan: true,
+            };
+            // the events before boot are handled by the first scan
+            slot.clear_status(
+                &config,
+                PCI_EXP_SLTSTA_ABP | PCI_EXP_SLTSTA_PDC | PCI_EXP_SLTSTA_DLLSC,
+            );
+            slots.push(slot);
+        }
+    }
+
+    let mut reg = REGISTRY.lock();
+    reg.slots = slots;
+    for_each_drivers!(type D, {
+        reg.drivers.push(Driver {
+            name: core::any::type_name::<D>(),
+            match_table: &[],
+            probe: D::probe_pci,
+        });
+    });
+}
+
+/// Enables and probes the devices below a slot that is just powered on.
+fn scan_slot(root: &mut PciRoot, slot: &Slot) {
+    let mut allocator = Some(PciRangeAllocator::new(slot.window.0, slot.window.1));
+    for bus in slot.buses.clone() {
+        for (bdf, info) in root.enumerate_bus(bus) {
+            debug!("PCI {}: {}", bdf, info);
+            if info.header_type != HeaderType::Standard {
+                continue;
+            }
+            match config_pci_device(root, bdf, &mut allocator) {
+                Ok(_) => probe_function(root, bdf, info),
+                Err(e) => warn!("failed to enable PCI device at {}({}): {:?}", bdf, info, e),
+            }
+        }
+    }
+}
+
+/// Probes the function with the registered drivers, and adds the device if
+/// a driver accepts it.
+fn probe_function(root: &mut PciRoot, bdf: DeviceFunction, info: DeviceFunctionInfo) {
+    let drivers = REGISTRY.lock().drivers.clone();
+    for driver in drivers.iter().filter(|driver| driver.matches(&info)) {
+        if let Some(dev) = (driver.probe)(root, bdf, &info) {
+            info!(
+                "hot-plug: added a new {:?} device at {}: {:?}",
+                dev.device_type(),
+                bdf,
+                dev.device_name(),
+            );
+            add_device(bdf, dev);
+            return;
+        }
+    }
+    debug!("hot-plug: no driver for PCI device at {}({})", bdf, info);
+    REGISTRY.lock().unbound.push((bdf, info));
+}
+
+/// Binds the device to the function, and gives it to the notifier of its
+/// type, or keeps it until such a notifier is registered.
+fn add_device(bdf: DeviceFunction, dev: AxDeviceEnum) {
+    let ty = dev.device_type();
+    let mut reg = REGISTRY.lock();
+    let id = reg.next_id;
+    reg.next_id += 1;
+    reg.bound.push((id, bdf, ty));
+    match reg.notifier(ty) {
+        Some(notifier) => {
+            drop(reg);
+            notifier.device_added(id, dev);
+        }
+        None => reg.unclaimed.push((id, dev)),
+    }
+}
+
+/// Detaches all the devices on the buses from their notifiers, and forgets
+/// the functions.
+fn remove_devices(buses: &RangeInclusive<u8>) {
+    let mut reg = REGISTRY.lock();
+    reg.unbound.retain(|(bdf, _)| !buses.contains(&bdf.bus));
+    let mut removed = Vec::new();
+    reg.bound.retain(|&(id, bdf, ty)| {
+        let on_buses = buses.contains(&bdf.bus);
+        if on_buses {
+            removed.push((id, ty));
+        }
+        !on_buses
+    });
+
+    let mut dropped = Vec::new();
+    let mut notified = Vec::new();
+    for (id, ty) in removed {
+        if let Some(pos) = reg.unclaimed.iter().position(|(i, _)| *i == id) {
+            dropped.push(reg.unclaimed.remove(pos));
+        } else if let Some(notifier) = reg.notifier(ty) {
+            notified.push((id, notifier));
+        }
+    }
+    drop(reg);
+    drop(dropped);
+    for (id, notifier) in notified {
+        debug!("hot-plug: remove device {}", id);
+        notifier.device_removed(id);
+    }
+}
@@ -8,8 +8,10 @@
 //! driver they want.
 //!
 //! For each device category (i.e., net, block, display, etc.), an unified type
-//! is used to represent all devices in that category. Currently, there are 3
-//! categories: [`AxNetDevice`], [`AxBlockDevice`], and [`AxDisplayDevice`].
+//! is used to represent all devices in that category. Currently, there are 9
+//! categories: [`AxNetDevice`], [`AxBlockDevice`], [`AxDisplayDevice`],
+//! [`AxCharDevice`], [`AxRngDevice`], [`AxInputDevice`], [`AxGpioDevice`],
+//! [`AxI2cDevice`] and [`AxSpiDevice`].
 //!
 //! # Concepts
 //!
@@ -32,22 +34,46 @@
 //! |-|-|-|
 //! | Block | `ramdisk` | A RAM disk that stores data in a vector |
 //! | Block | `virtio-blk` | VirtIO block device |
+//! | Block | `nvme` | NVMe SSD (PCI only) |
 //! | Network | `virtio-net` | VirtIO network device |
+//! | Network | `e1000` | Intel e1000/e1000e NIC (PCI only) |
 //! | Display | `virtio-gpu` | VirtIO graphics device |
+//! | Char | `uart16550` | 16550 compatible UARTs other than the console |
+//! | Char | `pl011` | ARM PL011 UARTs other than the console |
+//! | Char | `virtio-console` | VirtIO console device |
+//! | RNG | `virtio-rng` | VirtIO entropy device |
+//! | Input | `virtio-input` | VirtIO input device (keyboard, mouse, etc.) |
+//! | GPIO | `bcm2711-gpio` | BCM2711 (Raspberry Pi 4) GPIO controller |
+//! | I2C | `bcm2711-i2c` | BCM2711 (Raspberry Pi 4) I2C controllers |
+//! | SPI | `bcm2711-spi` | BCM2711 (Raspberry Pi 4) SPI controllers |
 //!
 //! # Other Cargo Features
 //!
 //! - `dyn`: use the dynamic device model (see above).
-//! - `bus-mmio`: use device tree to probe all MMIO devices. This feature is
-//!    enabeld by default.
+//! - `bus-mmio`: use device tree to probe all MMIO devices, the drivers are
+//!    matched by the `compatible` property of the device tree nodes. If there
+//!    is no device tree, the VirtIO MMIO regions in the platform configuration
+//!    are probed instead. This feature is enabeld by default.
 //! - `bus-pci`: use PCI bus to probe all PCI devices.
-//! - `virtio`: use VirtIO devices. This is enabled if any of `virtio-blk`,
-//!   `virtio-net` or `virtio-gpu` is enabled.
+//! - `hotplug`: watch the PCI Express hot-plug slots, and add or remove the
+//!    devices in them at runtime (see the [`hotplug`] module). It implies
+//!    `dyn` and `bus-pci`.
+//! - `irq`: enable MSI-X for the PCI devices that support it, and report the
+//!    IRQ by [`BaseDriverOps::irq_num`](driver_common::BaseDriverOps::irq_num).
+//! - `virtio`: use VirtIO devices. This is enabled if any of the `virtio-*`
+//!   device features is enabled.
 //! - `net`: use network devices. This is enabled if any feature of network
 //!    devices is selected. If this feature is enabled without any network device
 //!    features, a dummy struct is used for [`AxNetDevice`].
 //! - `block`: use block storage devices. Similar to the `net` feature.
 //! - `display`: use graphics display devices. Similar to the `net` feature.
+//! - `char`: use character devices (e.g., serial ports). Similar to the `net`
+//!    feature.
+//! - `rng`: use random number generators. Similar to the `net` feature.
+//! - `input`: use input devices (e.g., keyboards). Similar to the `net` feature.
+//! - `gpio`, `i2c`, `spi`: use GPIO, I2C and SPI controllers. Similar to the
+//!    `net` feature. The controllers are configured by their device tree
+//!    nodes, including the pins routed to them by `pinctrl-0`.
 //!
 //! [`VirtioNetDev`]: driver_virtio::VirtIoNetDev
 //! [`Box<dyn NetDriverOps>`]: driver_net::NetDriverOps
@@ -75,9 +101,17 @@ mod structs;
 #[cfg(feature = "virtio")]
 mod virtio;
 
+#[cfg(feature = "e1000")]
+mod e1000;
 #[cfg(feature = "ixgbe")]
 mod ixgbe;
 
+#[cfg(feature = "nvme")]
+mod nvme;
+
+#[cfg(feature = "hotplug")]
+pub mod hotplug;
+
 pub mod prelude;
 
 #[allow(unused_imports)]
@@ -86,10 +120,22 @@ pub use self::structs::{AxDeviceContainer, AxDeviceEnum};
 
 #[cfg(feature = "block")]
 pub use self::structs::AxBlockDevice;
+#[cfg(feature = "char")]
+pub use self::structs::AxCharDevice;
 #[cfg(feature = "display")]
 pub use self::structs::AxDisplayDevice;
+#[cfg(feature = "gpio")]
+pub use self::structs::AxGpioDevice;
+#[cfg(feature = "i2c")]
+pub use self::structs::AxI2cDevice;
+#[cfg(feature = "input")]
+pub use self::structs::AxInputDevice;
 #[cfg(feature = "net")]
 pub use self::structs::AxNetDevice;
+#[cfg(feature = "rng")]
+pub use self::structs::AxRngDevice;
+#[cfg(feature = "spi")]
+pub use self::structs::AxSpiDevice;
 
 /// A structure that contains all device drivers, organized by their category.
 #[derive(Default)]
@@ -103,6 +149,24 @@ pub struct AllDevices {
     /// All graphics device drivers.
     #[cfg(feature = "display")]
     pub display: AxDeviceContainer<AxDisplayDevice>,
+    /// All character device drivers.
+    #[cfg(feature = "char")]
+    pub char: AxDeviceContainer<AxCharDevice>,
+    /// All random number generator drivers.
+    #[cfg(feature = "rng")]
+    pub rng: AxDeviceContainer<AxRngDevice>,
+    /// All input device drivers.
+    #[cfg(feature = "input")]
+    pub input: AxDeviceContainer<AxInputDevice>,
+    /// All GPIO controller drivers.
+    #[cfg(feature = "gpio")]
+    pub gpio: AxDeviceContainer<AxGpioDevice>,
+    /// All I2C controller drivers.
+    #[cfg(feature = "i2c")]
+    pub i2c: AxDeviceContainer<AxI2cDevice>,
+    /// All SPI controller drivers.
+    #[cfg(feature = "spi")]
+    pub spi: AxDeviceContainer<AxSpiDevice>,
 }
 
 impl AllDevices {
@@ -143,6 +207,18 @@ impl AllDevices {
             AxDeviceEnum::Block(dev) => self.block.push(dev),
             #[cfg(feature = "display")]
             AxDeviceEnum::Display(dev) => self.display.push(dev),
+            #[cfg(feature = "char")]
+            AxDeviceEnum::Char(dev) => self.char.push(dev),
+            #[cfg(feature = "rng")]
+            AxDeviceEnum::Rng(dev) => self.rng.push(dev),
+            #[cfg(feature = "input")]
+            AxDeviceEnum::Input(dev) => self.input.push(dev),
+            #[cfg(feature = "gpio")]
+            AxDeviceEnum::Gpio(dev) => self.gpio.push(dev),
+            #[cfg(feature = "i2c")]
+            AxDeviceEnum::I2c(dev) => self.i2c.push(dev),
+            #[cfg(feature = "spi")]
+            AxDeviceEnum::Spi(dev) => self.spi.push(dev),
         }
     }
 }
@@ -152,6 +228,9 @@ pub fn init_drivers() -> AllDevices {
     info!("Initialize device drivers...");
     info!("  device model: {}", AllDevices::device_model());
 
+    #[cfg(feature = "hotplug")]
+    hotplug::init();
+
     let mut all_devs = AllDevices::default();
     all_devs.probe();
 
@@ -179,6 +258,54 @@ pub fn init_drivers() -> AllDevices {
             debug!("  graphics device {}: {:?}", i, dev.device_name());
         }
     }
+    #[cfg(feature = "char")]
+    {
+        debug!("number of character devices: {}", all_devs.char.len());
+        for (i, dev) in all_devs.char.iter().enumerate() {
+            assert_eq!(dev.device_type(), DeviceType::Char);
+            debug!("  character device {}: {:?}", i, dev.device_name());
+        }
+    }
+    #[cfg(feature = "rng")]
+    {
+        debug!("number of random number generators: {}", all_devs.rng.len());
+        for (i, dev) in all_devs.rng.iter().enumerate() {
+            assert_eq!(dev.device_type(), DeviceType::Rng);
+            debug!("  random number generator {}: {:?}", i, dev.device_name());
+        }
+    }
+    #[cfg(feature = "input")]
+    {
+        debug!("number of input devices: {}", all_devs.input.len());
+        for (i, dev) in all_devs.input.iter().enumerate() {
+            assert_eq!(dev.device_type(), DeviceType::Input);
+            debug!("  input device {}: {:?}", i, dev.device_name());
+        }
+    }
+    #[cfg(feature = "gpio")]
+    {
+        debug!("number of GPIO controllers: {}", all_devs.gpio.len());
+        for (i, dev) in all_devs.gpio.iter().enumerate() {
+            assert_eq!(dev.device_type(), DeviceType::Gpio);
+            debug!("  GPIO controller {}: {:?}", i, dev.device_name());
+        }
+    }
+    #[cfg(feature = "i2c")]
+    {
+        debug!("number of I2C controllers: {}", all_devs.i2c.len());
+        for (i, dev) in all_devs.i2c.iter().enumerate() {
+            assert_eq!(dev.device_type(), DeviceType::I2c);
+            debug!("  I2C controller {}: {:?}", i, dev.device_name());
+        }
+    }
+    #[cfg(feature = "spi")]
+    {
+        debug!("number of SPI controllers: {}", all_devs.spi.len());
+        for (i, dev) in all_devs.spi.iter().enumerate() {
+            assert_eq!(dev.device_type(), DeviceType::Spi);
+            debug!("  SPI controller {}: {:?}", i, dev.device_name());
+        }
+    }
 
     all_devs
 }
@@ -26,6 +26,54 @@ macro_rules! register_display_driver {
     };
 }
 
+macro_rules! register_char_driver {
+    ($driver_type:ty, $device_type:ty) => {
+        /// The unified type of the character devices.
+        #[cfg(not(feature = "dyn"))]
+        pub type AxCharDevice = $device_type;
+    };
+}
+
+macro_rules! register_rng_driver {
+    ($driver_type:ty, $device_type:ty) => {
+        /// The unified type of the random number generators.
+        #[cfg(not(feature = "dyn"))]
+        pub type AxRngDevice = $device_type;
+    };
+}
+
+macro_rules! register_input_driver {
+    ($driver_type:ty, $device_type:ty) => {
+        /// The unified type of the input devices.
+        #[cfg(not(feature = "dyn"))]
+        pub type AxInputDevice = $device_type;
+    };
+}
+
+macro_rules! register_gpio_driver {
+    ($driver_type:ty, $device_type:ty) => {
+        /// The unified type of the GPIO controllers.
+        #[cfg(not(feature = "dyn"))]
+        pub type AxGpioDevice = $device_type;
+    };
+}
+
+macro_rules! register_i2c_driver {
+    ($driver_type:ty, $device_type:ty) => {
+        /// The unified type of the I2C controllers.
+        #[cfg(not(feature = "dyn"))]
+        pub type AxI2cDevice = $device_type;
+    };
+}
+
+macro_rules! register_spi_driver {
+    ($driver_type:ty, $device_type:ty) => {
+        /// The unified type of the SPI controllers.
+        #[cfg(not(feature = "dyn"))]
+        pub type AxSpiDevice = $device_type;
+    };
+}
+
 macro_rules! for_each_drivers {
     (type $drv_type:ident, $code:block) => {{
         #[allow(unused_imports)]
@@ -49,6 +97,21 @@ macro_rules! for_each_drivers {
             type $drv_type = <virtio::VirtIoGpu as VirtIoDevMeta>::Driver;
             $code
         }
+        #[cfg(char_dev = "virtio-console")]
+        {
+            type $drv_type = <virtio::VirtIoConsole as VirtIoDevMeta>::Driver;
+            $code
+        }
+        #[cfg(rng_dev = "virtio-rng")]
+        {
+            type $drv_type = <virtio::VirtIoRng as VirtIoDevMeta>::Driver;
+            $code
+        }
+        #[cfg(input_dev = "virtio-input")]
+        {
+            type $drv_type = <virtio::VirtIoInput as VirtIoDevMeta>::Driver;
+            $code
+        }
         #[cfg(block_dev = "ramdisk")]
         {
             type $drv_type = crate::drivers::RamDiskDriver;
@@ -59,10 +122,45 @@ macro_rules! for_each_drivers {
             type $drv_type = crate::drivers::BcmSdhciDriver;
             $code
         }
+        #[cfg(block_dev = "nvme")]
+        {
+            type $drv_type = crate::drivers::NvmeDriver;
+            $code
+        }
         #[cfg(net_dev = "ixgbe")]
         {
             type $drv_type = crate::drivers::IxgbeDriver;
             $code
         }
+        #[cfg(net_dev = "e1000")]
+        {
+            type $drv_type = crate::drivers::E1000Driver;
+            $code
+        }
+        #[cfg(char_dev = "uart16550")]
+        {
+            type $drv_type = crate::drivers::Uart16550Driver;
+            $code
+        }
+        #[cfg(char_dev = "pl011")]
+        {
+            type $drv_type = crate::drivers::Pl011Driver;
+            $code
+        }
+        #[cfg(gpio_dev = "bcm2711-gpio")]
+        {
+            type $drv_type = crate::drivers::Bcm2711GpioDriver;
+            $code
+        }
+        #[cfg(i2c_dev = "bcm2711-i2c")]
+        {
+            type $drv_type = crate::drivers::Bcm2711I2cDriver;
+            $code
+        }
+        #[cfg(spi_dev = "bcm2711-spi")]
+        {
+            type $drv_type = crate::drivers::Bcm2711SpiDriver;
+            $code
+        }
     }};
 }
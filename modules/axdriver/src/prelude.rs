@@ -3,8 +3,29 @@
 pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
 
 #[cfg(feature = "block")]
-pub use {crate::structs::AxBlockDevice, driver_block::BlockDriverOps};
+pub use {
+    crate::structs::AxBlockDevice,
+    driver_block::{BlockDriverOps, BlockOp, BlockRequest, BlockSegment, RequestId},
+};
+#[cfg(feature = "char")]
+pub use {crate::structs::AxCharDevice, driver_char::CharDriverOps};
 #[cfg(feature = "display")]
 pub use {crate::structs::AxDisplayDevice, driver_display::DisplayDriverOps};
+#[cfg(feature = "gpio")]
+pub use {
+    crate::structs::AxGpioDevice,
+    driver_gpio::{GpioDriverOps, PinMode, Pull},
+};
+#[cfg(feature = "i2c")]
+pub use {crate::structs::AxI2cDevice, driver_i2c::I2cDriverOps};
+#[cfg(feature = "input")]
+pub use {crate::structs::AxInputDevice, driver_input::InputDriverOps};
 #[cfg(feature = "net")]
 pub use {crate::structs::AxNetDevice, driver_net::NetDriverOps};
+#[cfg(feature = "rng")]
+pub use {crate::structs::AxRngDevice, driver_rng::RngDriverOps};
+#[cfg(feature = "spi")]
+pub use {
+    crate::structs::AxSpiDevice,
+    driver_spi::{SpiConfig, SpiDriverOps, SpiMode},
+};
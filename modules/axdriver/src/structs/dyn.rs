@@ -12,6 +12,24 @@ pub type AxBlockDevice = Box<dyn BlockDriverOps>;
 /// The unified type of the graphics display devices.
 #[cfg(feature = "display")]
 pub type AxDisplayDevice = Box<dyn DisplayDriverOps>;
+/// The unified type of the character devices.
+#[cfg(feature = "char")]
+pub type AxCharDevice = Box<dyn CharDriverOps>;
+/// The unified type of the random number generators.
+#[cfg(feature = "rng")]
+pub type AxRngDevice = Box<dyn RngDriverOps>;
+/// The unified type of the input devices.
+#[cfg(feature = "input")]
+pub type AxInputDevice = Box<dyn InputDriverOps>;
+/// The unified type of the GPIO controllers.
+#[cfg(feature = "gpio")]
+pub type AxGpioDevice = Box<dyn GpioDriverOps>;
+/// The unified type of the I2C controllers.
+#[cfg(feature = "i2c")]
+pub type AxI2cDevice = Box<dyn I2cDriverOps>;
+/// The unified type of the SPI controllers.
+#[cfg(feature = "spi")]
+pub type AxSpiDevice = Box<dyn SpiDriverOps>;
 
 impl super::AxDeviceEnum {
     /// Constructs a network device.
@@ -31,6 +49,42 @@ impl super::AxDeviceEnum {
     pub fn from_display(dev: impl DisplayDriverOps + 'static) -> Self {
         Self::Display(Box::new(dev))
     }
+
+    /// Constructs a character device.
+    #[cfg(feature = "char")]
+    pub fn from_char(dev: impl CharDriverOps + 'static) -> Self {
+        Self::Char(Box::new(dev))
+    }
+
+    /// Constructs a random number generator.
+    #[cfg(feature = "rng")]
+    pub fn from_rng(dev: impl RngDriverOps + 'static) -> Self {
+        Self::Rng(Box::new(dev))
+    }
+
+    /// Constructs an input device.
+    #[cfg(feature = "input")]
+    pub fn from_input(dev: impl InputDriverOps + 'static) -> Self {
+        Self::Input(Box::new(dev))
+    }
+
+    /// Constructs a GPIO controller.
+    #[cfg(feature = "gpio")]
+    pub fn from_gpio(dev: impl GpioDriverOps + 'static) -> Self {
+        Self::Gpio(Box::new(dev))
+    }
+
+    /// Constructs an I2C controller.
+    #[cfg(feature = "i2c")]
+    pub fn from_i2c(dev: impl I2cDriverOps + 'static) -> Self {
+        Self::I2c(Box::new(dev))
+    }
+
+    /// Constructs an SPI controller.
+    #[cfg(feature = "spi")]
+    pub fn from_spi(dev: impl SpiDriverOps + 'static) -> Self {
+        Self::Spi(Box::new(dev))
+    }
 }
 
 /// A structure that contains all device drivers of a certain category.
@@ -59,6 +113,13 @@ impl<D> AxDeviceContainer<D> {
         }
     }
 
+    /// Takes the first device that satisfies the predicate out of the
+    /// container (will remove it from the container).
+    pub fn take_first(&mut self, f: impl FnMut(&D) -> bool) -> Option<D> {
+        let idx = self.0.iter().position(f)?;
+        Some(self.0.remove(idx))
+    }
+
     /// Constructs the container from one device.
     pub fn from_one(dev: D) -> Self {
         Self(vec![dev])
@@ -18,6 +18,24 @@ pub enum AxDeviceEnum {
     /// Graphic display device.
     #[cfg(feature = "display")]
     Display(AxDisplayDevice),
+    /// Character device.
+    #[cfg(feature = "char")]
+    Char(AxCharDevice),
+    /// Random number generator.
+    #[cfg(feature = "rng")]
+    Rng(AxRngDevice),
+    /// Input device.
+    #[cfg(feature = "input")]
+    Input(AxInputDevice),
+    /// GPIO controller.
+    #[cfg(feature = "gpio")]
+    Gpio(AxGpioDevice),
+    /// I2C controller.
+    #[cfg(feature = "i2c")]
+    I2c(AxI2cDevice),
+    /// SPI controller.
+    #[cfg(feature = "spi")]
+    Spi(AxSpiDevice),
 }
 
 impl BaseDriverOps for AxDeviceEnum {
@@ -31,6 +49,18 @@ impl BaseDriverOps for AxDeviceEnum {
             Self::Block(_) => DeviceType::Block,
             #[cfg(feature = "display")]
             Self::Display(_) => DeviceType::Display,
+            #[cfg(feature = "char")]
+            Self::Char(_) => DeviceType::Char,
+            #[cfg(feature = "rng")]
+            Self::Rng(_) => DeviceType::Rng,
+            #[cfg(feature = "input")]
+            Self::Input(_) => DeviceType::Input,
+            #[cfg(feature = "gpio")]
+            Self::Gpio(_) => DeviceType::Gpio,
+            #[cfg(feature = "i2c")]
+            Self::I2c(_) => DeviceType::I2c,
+            #[cfg(feature = "spi")]
+            Self::Spi(_) => DeviceType::Spi,
             _ => unreachable!(),
         }
     }
@@ -45,6 +75,44 @@ impl BaseDriverOps for AxDeviceEnum {
             Self::Block(dev) => dev.device_name(),
             #[cfg(feature = "display")]
             Self::Display(dev) => dev.device_name(),
+            #[cfg(feature = "char")]
+            Self::Char(dev) => dev.device_name(),
+            #[cfg(feature = "rng")]
+            Self::Rng(dev) => dev.device_name(),
+            #[cfg(feature = "input")]
+            Self::Input(dev) => dev.device_name(),
+            #[cfg(feature = "gpio")]
+            Self::Gpio(dev) => dev.device_name(),
+            #[cfg(feature = "i2c")]
+            Self::I2c(dev) => dev.device_name(),
+            #[cfg(feature = "spi")]
+            Self::Spi(dev) => dev.device_name(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[inline]
+    #[allow(unreachable_patterns)]
+    fn irq_num(&self) -> Option<usize> {
+        match self {
+            #[cfg(feature = "net")]
+            Self::Net(dev) => dev.irq_num(),
+            #[cfg(feature = "block")]
+            Self::Block(dev) => dev.irq_num(),
+            #[cfg(feature = "display")]
+            Self::Display(dev) => dev.irq_num(),
+            #[cfg(feature = "char")]
+            Self::Char(dev) => dev.irq_num(),
+            #[cfg(feature = "rng")]
+            Self::Rng(dev) => dev.irq_num(),
+            #[cfg(feature = "input")]
+            Self::Input(dev) => dev.irq_num(),
+            #[cfg(feature = "gpio")]
+            Self::Gpio(dev) => dev.irq_num(),
+            #[cfg(feature = "i2c")]
+            Self::I2c(dev) => dev.irq_num(),
+            #[cfg(feature = "spi")]
+            Self::Spi(dev) => dev.irq_num(),
             _ => unreachable!(),
         }
     }
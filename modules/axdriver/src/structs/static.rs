@@ -1,9 +1,21 @@
 #[cfg(feature = "block")]
 pub use crate::drivers::AxBlockDevice;
+#[cfg(feature = "char")]
+pub use crate::drivers::AxCharDevice;
 #[cfg(feature = "display")]
 pub use crate::drivers::AxDisplayDevice;
+#[cfg(feature = "gpio")]
+pub use crate::drivers::AxGpioDevice;
+#[cfg(feature = "i2c")]
+pub use crate::drivers::AxI2cDevice;
+#[cfg(feature = "input")]
+pub use crate::drivers::AxInputDevice;
 #[cfg(feature = "net")]
 pub use crate::drivers::AxNetDevice;
+#[cfg(feature = "rng")]
+pub use crate::drivers::AxRngDevice;
+#[cfg(feature = "spi")]
+pub use crate::drivers::AxSpiDevice;
 
 impl super::AxDeviceEnum {
     /// Constructs a network device.
@@ -23,6 +35,42 @@ impl super::AxDeviceEnum {
     pub const fn from_display(dev: AxDisplayDevice) -> Self {
         Self::Display(dev)
     }
+
+    /// Constructs a character device.
+    #[cfg(feature = "char")]
+    pub const fn from_char(dev: AxCharDevice) -> Self {
+        Self::Char(dev)
+    }
+
+    /// Constructs a random number generator.
+    #[cfg(feature = "rng")]
+    pub const fn from_rng(dev: AxRngDevice) -> Self {
+        Self::Rng(dev)
+    }
+
+    /// Constructs an input device.
+    #[cfg(feature = "input")]
+    pub const fn from_input(dev: AxInputDevice) -> Self {
+        Self::Input(dev)
+    }
+
+    /// Constructs a GPIO controller.
+    #[cfg(feature = "gpio")]
+    pub const fn from_gpio(dev: AxGpioDevice) -> Self {
+        Self::Gpio(dev)
+    }
+
+    /// Constructs an I2C controller.
+    #[cfg(feature = "i2c")]
+    pub const fn from_i2c(dev: AxI2cDevice) -> Self {
+        Self::I2c(dev)
+    }
+
+    /// Constructs an SPI controller.
+    #[cfg(feature = "spi")]
+    pub const fn from_spi(dev: AxSpiDevice) -> Self {
+        Self::Spi(dev)
+    }
 }
 
 /// A structure that contains all device drivers of a certain category.
@@ -51,6 +99,16 @@ impl<D> AxDeviceContainer<D> {
         self.0.take()
     }
 
+    /// Takes the first device that satisfies the predicate out of the
+    /// container (will remove it from the container).
+    pub fn take_first(&mut self, f: impl FnOnce(&D) -> bool) -> Option<D> {
+        if self.0.as_ref().is_some_and(f) {
+            self.0.take()
+        } else {
+            None
+        }
+    }
+
     /// Constructs the container from one device.
     pub const fn from_one(dev: D) -> Self {
         Self(Some(dev))
@@ -25,7 +25,8 @@ pub trait VirtIoDevMeta {
     type Device: BaseDriverOps;
     type Driver = VirtIoDriver<Self>;
 
-    fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum>;
+    /// Creates the device, `irq_num` is the IRQ of the transport if known.
+    fn try_new(transport: VirtIoTransport, irq_num: Option<usize>) -> DevResult<AxDeviceEnum>;
 }
 
 cfg_if! {
@@ -36,8 +37,8 @@ cfg_if! {
             const DEVICE_TYPE: DeviceType = DeviceType::Net;
             type Device = driver_virtio::VirtIoNetDev<VirtIoHalImpl, VirtIoTransport, 64>;
 
-            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
-                Ok(AxDeviceEnum::from_net(Self::Device::try_new(transport)?))
+            fn try_new(transport: VirtIoTransport, irq_num: Option<usize>) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_net(Self::Device::try_new(transport, irq_num)?))
             }
         }
     }
@@ -51,8 +52,8 @@ cfg_if! {
             const DEVICE_TYPE: DeviceType = DeviceType::Block;
             type Device = driver_virtio::VirtIoBlkDev<VirtIoHalImpl, VirtIoTransport>;
 
-            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
-                Ok(AxDeviceEnum::from_block(Self::Device::try_new(transport)?))
+            fn try_new(transport: VirtIoTransport, irq_num: Option<usize>) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_block(Self::Device::try_new(transport, irq_num)?))
             }
         }
     }
@@ -66,25 +67,74 @@ cfg_if! {
             const DEVICE_TYPE: DeviceType = DeviceType::Display;
             type Device = driver_virtio::VirtIoGpuDev<VirtIoHalImpl, VirtIoTransport>;
 
-            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
+            fn try_new(transport: VirtIoTransport, _irq_num: Option<usize>) -> DevResult<AxDeviceEnum> {
                 Ok(AxDeviceEnum::from_display(Self::Device::try_new(transport)?))
             }
         }
     }
 }
 
+cfg_if! {
+    if #[cfg(char_dev = "virtio-console")] {
+        pub struct VirtIoConsole;
+
+        impl VirtIoDevMeta for VirtIoConsole {
+            const DEVICE_TYPE: DeviceType = DeviceType::Char;
+            type Device = driver_virtio::VirtIoConsoleDev<VirtIoHalImpl, VirtIoTransport>;
+
+            fn try_new(transport: VirtIoTransport, _irq_num: Option<usize>) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_char(Self::Device::try_new(transport)?))
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(rng_dev = "virtio-rng")] {
+        pub struct VirtIoRng;
+
+        impl VirtIoDevMeta for VirtIoRng {
+            const DEVICE_TYPE: DeviceType = DeviceType::Rng;
+            type Device = driver_virtio::VirtIoRngDev<VirtIoHalImpl, VirtIoTransport>;
+
+            fn try_new(transport: VirtIoTransport, _irq_num: Option<usize>) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_rng(Self::Device::try_new(transport)?))
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(input_dev = "virtio-input")] {
+        pub struct VirtIoInput;
+
+        impl VirtIoDevMeta for VirtIoInput {
+            const DEVICE_TYPE: DeviceType = DeviceType::Input;
+            type Device = driver_virtio::VirtIoInputDev<VirtIoHalImpl, VirtIoTransport>;
+
+            fn try_new(transport: VirtIoTransport, _irq_num: Option<usize>) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_input(Self::Device::try_new(transport)?))
+            }
+        }
+    }
+}
+
 /// A common driver for all VirtIO devices that implements [`DriverProbe`].
 pub struct VirtIoDriver<D: VirtIoDevMeta + ?Sized>(PhantomData<D>);
 
-impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
+impl<D: VirtIoDevMeta> VirtIoDriver<D> {
     #[cfg(bus = "mmio")]
-    fn probe_mmio(mmio_base: usize, mmio_size: usize) -> Option<AxDeviceEnum> {
+    fn probe_mmio_with_irq(
+        mmio_base: usize,
+        mmio_size: usize,
+        irq_num: Option<usize>,
+    ) -> Option<AxDeviceEnum> {
         let base_vaddr = phys_to_virt(mmio_base.into());
         if let Some((ty, transport)) =
             driver_virtio::probe_mmio_device(base_vaddr.as_mut_ptr(), mmio_size)
         {
             if ty == D::DEVICE_TYPE {
-                match D::try_new(transport) {
+                match D::try_new(transport, irq_num) {
                     Ok(dev) => return Some(dev),
                     Err(e) => {
                         warn!(
@@ -100,6 +150,23 @@ impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
         }
         None
     }
+}
+
+impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
+    #[cfg(bus = "mmio")]
+    fn probe_mmio(mmio_base: usize, mmio_size: usize) -> Option<AxDeviceEnum> {
+        Self::probe_mmio_with_irq(mmio_base, mmio_size, mmio_irq_num(mmio_base))
+    }
+
+    #[cfg(bus = "mmio")]
+    fn probe_fdt(node: &fdt_parser::Node) -> Option<AxDeviceEnum> {
+        if !node.is_compatible("virtio,mmio") {
+            return None;
+        }
+        let reg = node.reg()?.next()?;
+        let (mmio_base, mmio_size) = (reg.address as usize, reg.size? as usize);
+        Self::probe_mmio_with_irq(mmio_base, mmio_size, fdt_irq_num(node))
+    }
 
     #[cfg(bus = "pci")]
     fn probe_pci(
@@ -114,15 +181,28 @@ impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
             (DeviceType::Net, 0x1000) | (DeviceType::Net, 0x1040) => {}
             (DeviceType::Block, 0x1001) | (DeviceType::Block, 0x1041) => {}
             (DeviceType::Display, 0x1050) => {}
+            (DeviceType::Char, 0x1003) | (DeviceType::Char, 0x1043) => {}
+            (DeviceType::Rng, 0x1005) | (DeviceType::Rng, 0x1044) => {}
+            (DeviceType::Input, 0x1052) => {}
             _ => return None,
         }
 
+        #[cfg(feature = "irq")]
+        let irq_num = crate::bus::pci::enable_msix(root, bdf);
+        #[cfg(not(feature = "irq"))]
+        let irq_num = None;
         if let Some((ty, transport)) =
             driver_virtio::probe_pci_device::<VirtIoHalImpl>(root, bdf, dev_info)
         {
             if ty == D::DEVICE_TYPE {
-                match D::try_new(transport) {
-                    Ok(dev) => return Some(dev),
+                match D::try_new(transport, irq_num) {
+                    Ok(dev) => {
+                        // the vectors are reset with the device in `try_new`
+                        if irq_num.is_some() && !set_pci_msix_vectors(root, bdf) {
+                            warn!("failed to route the interrupts of PCI device at {}", bdf);
+                        }
+                        return Some(dev);
+                    }
                     Err(e) => {
                         warn!(
                             "failed to initialize PCI device at {}({}): {:?}",
@@ -137,6 +217,88 @@ impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
     }
 }
 
+/// Routes the configuration change and the queue interrupts of a VirtIO PCI
+/// device to the MSI-X table entry 0, returns `false` if the device rejects
+/// the vector.
+///
+/// It's done by the common configuration structure, which is located by a
+/// vendor-specific capability.
+#[cfg(bus = "pci")]
+fn set_pci_msix_vectors(root: &mut PciRoot, bdf: DeviceFunction) -> bool {
+    // the capability and the fields of `virtio_pci_common_cfg`
+    const PCI_CAP_ID_VNDR: u8 = 0x09;
+    const VIRTIO_PCI_CAP_COMMON_CFG: u8 = 1;
+    const MSIX_CONFIG: usize = 0x10;
+    const NUM_QUEUES: usize = 0x12;
+    const QUEUE_SELECT: usize = 0x16;
+    const QUEUE_MSIX_VECTOR: usize = 0x1a;
+    const VECTOR: u16 = 0;
+
+    let config = crate::bus::pci::config_space();
+    let Some((bar, offset)) = config
+        .capabilities(bdf)
+        .filter(|&(_, id)| id == PCI_CAP_ID_VNDR)
+        .find(|&(cap, _)| (config.read_u32(bdf, cap) >> 24) as u8 == VIRTIO_PCI_CAP_COMMON_CFG)
+        .map(|(cap, _)| {
+            (
+                config.read_u32(bdf, cap + 4) as u8,
+                config.read_u32(bdf, cap + 8),
+            )
+        })
+    else {
+        return false;
+    };
+    let Some(bar_vaddr) = crate::bus::pci::bar_vaddr(root, bdf, bar) else {
+        return false;
+    };
+    let reg = |field: usize| (bar_vaddr + offset as usize + field) as *mut u16;
+    // Safe because the common configuration is in the mapped BAR.
+    unsafe {
+        reg(MSIX_CONFIG).write_volatile(VECTOR);
+        if reg(MSIX_CONFIG).read_volatile() != VECTOR {
+            return false;
+        }
+        for queue in 0..reg(NUM_QUEUES).read_volatile() {
+            reg(QUEUE_SELECT).write_volatile(queue);
+            reg(QUEUE_MSIX_VECTOR).write_volatile(VECTOR);
+            if reg(QUEUE_MSIX_VECTOR).read_volatile() != VECTOR {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Returns the IRQ of the VirtIO MMIO region at `mmio_base`, the regions use
+/// consecutive IRQs starting from `VIRTIO_MMIO_IRQ_BASE`.
+#[cfg(bus = "mmio")]
+fn mmio_irq_num(mmio_base: usize) -> Option<usize> {
+    if axconfig::VIRTIO_MMIO_IRQ_BASE == 0 {
+        return None;
+    }
+    axconfig::VIRTIO_MMIO_REGIONS
+        .iter()
+        .position(|reg| reg.0 == mmio_base)
+        .map(|idx| axconfig::VIRTIO_MMIO_IRQ_BASE + idx)
+}
+
+/// Returns the IRQ number in the `interrupts` property of a device tree node.
+///
+/// Only the GIC is supported, which uses 3 cells (type, number, flags). The
+/// number of a shared peripheral interrupt (SPI) is offset by 32, that of a
+/// private one (PPI) by 16. The 1-cell interrupts of the RISC-V PLIC are not
+/// supported by axhal yet.
+#[cfg(bus = "mmio")]
+fn fdt_irq_num(node: &fdt_parser::Node) -> Option<usize> {
+    let prop = node.property("interrupts")?;
+    if !cfg!(target_arch = "aarch64") || prop.value.len() != 12 {
+        return None;
+    }
+    let mut cells = prop.as_u32_cells();
+    let (ty, num) = (cells.next()?, cells.next()? as usize);
+    Some(if ty == 0 { num + 32 } else { num + 16 })
+}
+
 pub struct VirtIoHalImpl;
 
 unsafe impl VirtIoHal for VirtIoHalImpl {
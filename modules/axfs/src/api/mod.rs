@@ -5,10 +5,19 @@ mod file;
 
 pub use self::dir::{DirBuilder, DirEntry, ReadDir};
 pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
+#[cfg(feature = "devfs")]
+pub use driver_char::{Parity, SerialConfig};
+#[cfg(feature = "devfs")]
+pub use driver_gpio::{PinMode, Pull};
+#[cfg(feature = "devfs")]
+pub use driver_spi::{SpiConfig, SpiMode};
 
 use alloc::{string::String, vec::Vec};
 use axio::{self as io, prelude::*};
 
+#[cfg(feature = "devfs")]
+use crate::{gpio::GpioNode, i2c::I2cNode, spi::SpiNode, tty::TtyNode};
+
 /// Returns an iterator over the entries within a directory.
 pub fn read_dir(path: &str) -> io::Result<ReadDir> {
     ReadDir::new(path)
@@ -87,3 +96,91 @@ pub fn remove_file(path: &str) -> io::Result<()> {
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
     crate::root::rename(old, new)
 }
+
+/// Returns the line settings of the serial port at `path` (e.g.,
+/// `/dev/ttyS0`).
+#[cfg(feature = "devfs")]
+pub fn serial_config(path: &str) -> io::Result<SerialConfig> {
+    with_dev(path, "not a serial port", |tty: &TtyNode| Ok(tty.config()))
+}
+
+/// Changes the line settings of the serial port at `path` (e.g.,
+/// `/dev/ttyS0`), like `tcsetattr` with the speed and `c_cflag`.
+#[cfg(feature = "devfs")]
+pub fn set_serial_config(path: &str, config: &SerialConfig) -> io::Result<()> {
+    with_dev(path, "not a serial port", |tty: &TtyNode| {
+        tty.set_config(config)
+    })
+}
+
+/// Changes the function of a pin of the GPIO controller at `path` (e.g.,
+/// `/dev/gpiochip0`).
+#[cfg(feature = "devfs")]
+pub fn gpio_set_mode(path: &str, pin: usize, mode: PinMode) -> io::Result<()> {
+    with_dev(path, "not a GPIO controller", |gpio: &GpioNode| {
+        gpio.set_mode(pin, mode)
+    })
+}
+
+/// Changes the pull resistor of a pin of the GPIO controller at `path`
+/// (e.g., `/dev/gpiochip0`).
+#[cfg(feature = "devfs")]
+pub fn gpio_set_pull(path: &str, pin: usize, pull: Pull) -> io::Result<()> {
+    with_dev(path, "not a GPIO controller", |gpio: &GpioNode| {
+        gpio.set_pull(pin, pull)
+    })
+}
+
+/// Writes `wbuf` to the target at the 7-bit address `addr` on the I2C bus at
+/// `path` (e.g., `/dev/i2c-1`), then reads `rbuf.len()` bytes from it after
+/// a repeated start condition.
+#[cfg(feature = "devfs")]
+pub fn i2c_write_read(path: &str, addr: u8, wbuf: &[u8], rbuf: &mut [u8]) -> io::Result<()> {
+    with_dev(path, "not an I2C controller", |i2c: &I2cNode| {
+        i2c.write_read(addr, wbuf, rbuf)
+    })
+}
+
+/// Returns the configuration of the SPI bus of the target at `path` (e.g.,
+/// `/dev/spidev0.0`).
+#[cfg(feature = "devfs")]
+pub fn spi_config(path: &str) -> io::Result<SpiConfig> {
+    with_dev(path, "not an SPI target", |spi: &SpiNode| Ok(spi.config()))
+}
+
+/// Changes the configuration of the SPI bus of the target at `path` (e.g.,
+/// `/dev/spidev0.0`), which applies to all the targets on the bus.
+#[cfg(feature = "devfs")]
+pub fn set_spi_config(path: &str, config: SpiConfig) -> io::Result<()> {
+    with_dev(path, "not an SPI target", |spi: &SpiNode| {
+        spi.set_config(config)
+    })
+}
+
+/// Selects the SPI target at `path` (e.g., `/dev/spidev0.0`), and exchanges
+/// `max(write.len(), read.len())` bytes with it in full duplex. The bytes
+/// past `write` are sent as zeros, and the received bytes past `read` are
+/// dropped.
+#[cfg(feature = "devfs")]
+pub fn spi_transfer(path: &str, write: &[u8], read: &mut [u8]) -> io::Result<()> {
+    with_dev(path, "not an SPI target", |spi: &SpiNode| {
+        spi.transfer(write, read)
+    })
+}
+
+/// Calls `f` with the device node at `path`, which must be an `N`.
+#[cfg(feature = "devfs")]
+fn with_dev<N: 'static, T>(
+    path: &str,
+    mismatch: &str,
+    f: impl FnOnce(&N) -> io::Result<T>,
+) -> io::Result<T> {
+    let node = crate::root::lookup(None, path)?;
+    if !node.get_attr()?.file_type().is_char_device() {
+        return axerrno::ax_err!(InvalidInput, "not a character device");
+    }
+    match node.as_any().downcast_ref::<N>() {
+        Some(dev) => f(dev),
+        None => axerrno::ax_err!(InvalidInput, mismatch),
+    }
+}
@@ -1,6 +1,10 @@
+use alloc::collections::VecDeque;
 use axdriver::prelude::*;
 
-const BLOCK_SIZE: usize = 512;
+pub(crate) const BLOCK_SIZE: usize = 512;
+
+/// The maximum number of blocks transferred by one request.
+const MAX_REQUEST_BLOCKS: usize = 64;
 
 /// A disk device with a cursor.
 pub struct Disk {
@@ -36,14 +40,14 @@ impl Disk {
         self.offset = pos as usize % BLOCK_SIZE;
     }
 
-    /// Read within one block, returns the number of bytes read.
+    /// Read within one block, or as many whole blocks as possible if the
+    /// cursor is at the start of a block, returns the number of bytes read.
     pub fn read_one(&mut self, buf: &mut [u8]) -> DevResult<usize> {
         let read_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev
-                .read_block(self.block_id, &mut buf[0..BLOCK_SIZE])?;
-            self.block_id += 1;
-            BLOCK_SIZE
+            // whole blocks
+            let len = buf.len() / BLOCK_SIZE * BLOCK_SIZE;
+            unsafe { self.transfer_blocks(BlockOp::Read, buf.as_mut_ptr(), len)? };
+            len
         } else {
             // partial block
             let mut data = [0u8; BLOCK_SIZE];
@@ -63,13 +67,15 @@ impl Disk {
         Ok(read_size)
     }
 
-    /// Write within one block, returns the number of bytes written.
+    /// Write within one block, or as many whole blocks as possible if the
+    /// cursor is at the start of a block, returns the number of bytes written.
     pub fn write_one(&mut self, buf: &[u8]) -> DevResult<usize> {
         let write_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev.write_block(self.block_id, &buf[0..BLOCK_SIZE])?;
-            self.block_id += 1;
-            BLOCK_SIZE
+            // whole blocks
+            let len = buf.len() / BLOCK_SIZE * BLOCK_SIZE;
+            let ptr = buf.as_ptr() as *mut u8;
+            unsafe { self.transfer_blocks(BlockOp::Write, ptr, len)? };
+            len
         } else {
             // partial block
             let mut data = [0u8; BLOCK_SIZE];
@@ -89,4 +95,64 @@ impl Disk {
         };
         Ok(write_size)
     }
+
+    /// Transfers `len` bytes of whole blocks from the cursor, and moves the
+    /// cursor past them on success.
+    ///
+    /// The buffer is split into requests of at most [`MAX_REQUEST_BLOCKS`]
+    /// blocks, and up to [`BlockDriverOps::queue_depth`] of them are kept in
+    /// flight.
+    unsafe fn transfer_blocks(&mut self, op: BlockOp, ptr: *mut u8, len: usize) -> DevResult {
+        let num_blocks = len / BLOCK_SIZE;
+        let depth = self.dev.queue_depth().max(1);
+        let mut inflight = VecDeque::with_capacity(depth);
+        let mut submitted = 0;
+        let mut result = Ok(());
+
+        while (result.is_ok() && submitted < num_blocks) || !inflight.is_empty() {
+            if result.is_ok() && submitted < num_blocks && inflight.len() < depth {
+                let count = (num_blocks - submitted).min(MAX_REQUEST_BLOCKS);
+                let segments = [BlockSegment {
+                    ptr: ptr.add(submitted * BLOCK_SIZE),
+                    len: count * BLOCK_SIZE,
+                }];
+                let req = BlockRequest {
+                    op,
+                    block_id: self.block_id + submitted as u64,
+                    segments: &segments,
+                };
+                match self.dev.submit_request(&req) {
+                    Ok(id) => {
+                        inflight.push_back(id);
+                        submitted += count;
+                        continue;
+                    }
+                    // wait for the oldest request below and try again
+                    Err(DevError::Again) if !inflight.is_empty() => {}
+                    Err(e) => {
+                        result = Err(e);
+                        continue;
+                    }
+                }
+            }
+
+            // Wait for the oldest request, the buffers of all requests must
+            // not be released before they complete, even if one has failed.
+            let id = *inflight.front().unwrap();
+            match self.dev.poll_request(id) {
+                Ok(false) => core::hint::spin_loop(),
+                Ok(true) => {
+                    inflight.pop_front();
+                }
+                Err(e) => {
+                    inflight.pop_front();
+                    result = result.and(Err(e));
+                }
+            }
+        }
+
+        result?;
+        self.block_id += num_blocks as u64;
+        Ok(())
+    }
 }
@@ -24,6 +24,7 @@ pub type FilePerm = axfs_vfs::VfsNodePerm;
 pub struct File {
     node: WithCap<VfsNodeRef>,
     is_append: bool,
+    is_nonblocking: bool,
     offset: u64,
 }
 
@@ -155,6 +156,7 @@ impl File {
         Ok(Self {
             node: WithCap::new(node, access_cap),
             is_append: opts.append,
+            is_nonblocking: false,
             offset: 0,
         })
     }
@@ -177,7 +179,7 @@ impl File {
     /// After the read, the cursor will be advanced by the number of bytes read.
     pub fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
         let node = self.node.access(Cap::READ)?;
-        let read_len = node.read_at(self.offset, buf)?;
+        let read_len = self.read_node_at(node, self.offset, buf)?;
         self.offset += read_len as u64;
         Ok(read_len)
     }
@@ -187,7 +189,7 @@ impl File {
     /// It does not update the file cursor.
     pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
         let node = self.node.access(Cap::READ)?;
-        let read_len = node.read_at(offset, buf)?;
+        let read_len = self.read_node_at(node, offset, buf)?;
         Ok(read_len)
     }
 
@@ -240,6 +242,30 @@ impl File {
     pub fn get_attr(&self) -> AxResult<FileAttr> {
         self.node.access(Cap::empty())?.get_attr()
     }
+
+    /// Returns whether the file is in nonblocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking
+    }
+
+    /// Moves the file into or out of nonblocking mode.
+    ///
+    /// In nonblocking mode, reading a character device that has no input
+    /// available fails with [`WouldBlock`](axerrno::AxError::WouldBlock)
+    /// instead of waiting. It has no effect on regular files.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.is_nonblocking = nonblocking;
+    }
+
+    fn read_node_at(&self, node: &VfsNodeRef, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
+        #[cfg(feature = "devfs")]
+        if self.is_nonblocking {
+            if let Some(tty) = node.as_any().downcast_ref::<crate::tty::TtyNode>() {
+                return Ok(tty.try_read(buf)?);
+            }
+        }
+        Ok(node.read_at(offset, buf)?)
+    }
 }
 
 impl Directory {
@@ -0,0 +1,74 @@
+//! GPIO controllers in devfs.
+
+use axdriver::prelude::*;
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+use crate::tty::as_vfs_err;
+
+/// The names of the GPIO controllers in devfs.
+pub(crate) const GPIO_NAMES: [&str; 4] = ["gpiochip0", "gpiochip1", "gpiochip2", "gpiochip3"];
+
+/// A GPIO controller in devfs.
+///
+/// The file offset is the number of the first pin to access, and each byte
+/// is the level of a pin, 0 for low and 1 for high. For example, reading 2
+/// bytes at offset 17 gets the levels of pins 17 and 18.
+pub(crate) struct GpioNode {
+    dev: Mutex<AxGpioDevice>,
+}
+
+impl GpioNode {
+    pub fn new(dev: AxGpioDevice) -> Self {
+        Self {
+            dev: Mutex::new(dev),
+        }
+    }
+
+    pub fn set_mode(&self, pin: usize, mode: PinMode) -> VfsResult {
+        self.dev.lock().set_mode(pin, mode).map_err(as_vfs_err)
+    }
+
+    pub fn set_pull(&self, pin: usize, pull: Pull) -> VfsResult {
+        self.dev.lock().set_pull(pin, pull).map_err(as_vfs_err)
+    }
+}
+
+impl VfsNodeOps for GpioNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let num_pins = self.dev.lock().num_pins() as u64;
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::default_file(),
+            VfsNodeType::CharDevice,
+            num_pins,
+            0,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let dev = self.dev.lock();
+        let first = offset.min(dev.num_pins() as u64) as usize;
+        let len = buf.len().min(dev.num_pins() - first);
+        for (i, b) in buf[..len].iter_mut().enumerate() {
+            *b = dev.read(first + i).map_err(as_vfs_err)? as u8;
+        }
+        Ok(len)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut dev = self.dev.lock();
+        if offset + buf.len() as u64 > dev.num_pins() as u64 {
+            return Err(VfsError::InvalidInput);
+        }
+        for (i, &b) in buf.iter().enumerate() {
+            dev.write(offset as usize + i, b != 0).map_err(as_vfs_err)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
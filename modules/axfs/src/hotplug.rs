@@ -0,0 +1,133 @@
+//! Block devices added or removed after boot.
+//!
+//! The hot-plugged disks are not mounted, they are added to devfs as
+//! `/dev/disk0`, `/dev/disk1`, etc., for raw access.
+
+use alloc::{sync::Arc, vec::Vec};
+use axdriver::hotplug::{DeviceId, DeviceNotifier};
+use axdriver::{prelude::*, AxDeviceEnum};
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+use crate::dev::{Disk, BLOCK_SIZE};
+use crate::tty::as_vfs_err;
+
+/// The names of the hot-plugged disks in devfs.
+const DISK_NAMES: [&str; 8] = [
+    "disk0", "disk1", "disk2", "disk3", "disk4", "disk5", "disk6", "disk7",
+];
+
+/// The disks in devfs, and the names of their nodes.
+static DISKS: Mutex<Vec<(DeviceId, &'static str, Arc<DiskNode>)>> = Mutex::new(Vec::new());
+
+/// A disk in devfs.
+///
+/// The disk is taken away when the device is removed, then all the accesses
+/// of the opened files fail with [`VfsError::Io`].
+struct DiskNode {
+    disk: Mutex<Option<Disk>>,
+}
+
+impl DiskNode {
+    fn with_disk<R>(&self, f: impl FnOnce(&mut Disk) -> VfsResult<R>) -> VfsResult<R> {
+        f(self.disk.lock().as_mut().ok_or(VfsError::Io)?)
+    }
+}
+
+impl VfsNodeOps for DiskNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = self.disk.lock().as_ref().map_or(0, |disk| disk.size());
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::default_file(),
+            VfsNodeType::BlockDevice,
+            size,
+            size / BLOCK_SIZE as u64,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.with_disk(|disk| {
+            let len = disk.size().saturating_sub(offset).min(buf.len() as u64) as usize;
+            disk.set_position(offset);
+            let mut read = 0;
+            while read < len {
+                read += disk.read_one(&mut buf[read..len]).map_err(as_vfs_err)?;
+            }
+            Ok(len)
+        })
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.with_disk(|disk| {
+            let len = disk.size().saturating_sub(offset).min(buf.len() as u64) as usize;
+            disk.set_position(offset);
+            let mut written = 0;
+            while written < len {
+                written += disk.write_one(&buf[written..len]).map_err(as_vfs_err)?;
+            }
+            Ok(len)
+        })
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+struct BlockNotifier;
+
+impl DeviceNotifier for BlockNotifier {
+    fn device_added(&self, id: DeviceId, dev: AxDeviceEnum) {
+        let AxDeviceEnum::Block(dev) = dev else {
+            return;
+        };
+        if dev.block_size() != BLOCK_SIZE {
+            warn!(
+                "block device {:?} of {}-byte blocks is not supported",
+                dev.device_name(),
+                dev.block_size()
+            );
+            return;
+        }
+        let mut disks = DISKS.lock();
+        let Some(&name) = DISK_NAMES
+            .iter()
+            .find(|&&name| disks.iter().all(|&(_, n, _)| n != name))
+        else {
+            warn!("block device {:?} is not added", dev.device_name());
+            return;
+        };
+        info!("use block device {:?} as /dev/{}", dev.device_name(), name);
+        let node = Arc::new(DiskNode {
+            disk: Mutex::new(Some(Disk::new(dev))),
+        });
+        let devfs = crate::mounts::DEVFS
+            .try_get()
+            .expect("devfs is not mounted");
+        devfs.add(name, node.clone());
+        disks.push((id, name, node));
+    }
+
+    fn device_removed(&self, id: DeviceId) {
+        let mut disks = DISKS.lock();
+        let Some(idx) = disks.iter().position(|&(i, _, _)| i == id) else {
+            return;
+        };
+        let (_, name, node) = disks.remove(idx);
+        info!("remove /dev/{}", name);
+        if let Some(devfs) = crate::mounts::DEVFS.try_get() {
+            devfs.remove_node(name);
+        }
+        // waits for the ongoing accesses, and drops the device
+        node.disk.lock().take();
+    }
+}
+
+static BLOCK_NOTIFIER: BlockNotifier = BlockNotifier;
+
+/// Starts to receive the block devices added after boot.
+pub(crate) fn init() {
+    axdriver::hotplug::register_notifier(DeviceType::Block, &BLOCK_NOTIFIER);
+}
@@ -0,0 +1,70 @@
+//! I2C controllers in devfs.
+
+use axdriver::prelude::*;
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+use crate::tty::as_vfs_err;
+
+/// The names of the I2C controllers in devfs.
+pub(crate) const I2C_NAMES: [&str; 8] = [
+    "i2c-0", "i2c-1", "i2c-2", "i2c-3", "i2c-4", "i2c-5", "i2c-6", "i2c-7",
+];
+
+/// An I2C controller in devfs.
+///
+/// The file offset is the 7-bit address of the target, a read or a write is
+/// a transfer from or to the target. As the offset of a file advances after
+/// each access, use [`crate::api::i2c_write_read`] or seek before each access.
+pub(crate) struct I2cNode {
+    dev: Mutex<AxI2cDevice>,
+}
+
+impl I2cNode {
+    pub fn new(dev: AxI2cDevice) -> Self {
+        Self {
+            dev: Mutex::new(dev),
+        }
+    }
+
+    pub fn write_read(&self, addr: u8, wbuf: &[u8], rbuf: &mut [u8]) -> VfsResult {
+        let mut dev = self.dev.lock();
+        dev.write_read(addr, wbuf, rbuf).map_err(as_vfs_err)
+    }
+}
+
+impl VfsNodeOps for I2cNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::default_file(),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let addr = target_addr(offset)?;
+        self.dev.lock().read(addr, buf).map_err(as_vfs_err)?;
+        Ok(buf.len())
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let addr = target_addr(offset)?;
+        self.dev.lock().write(addr, buf).map_err(as_vfs_err)?;
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+fn target_addr(offset: u64) -> VfsResult<u8> {
+    u8::try_from(offset)
+        .ok()
+        .filter(|&addr| addr <= driver_i2c::MAX_ADDR)
+        .ok_or(VfsError::InvalidInput)
+}
@@ -6,8 +6,14 @@
 //!
 //! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. This feature
 //!    is **enabled** by default.
-//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`. This feature is
-//!    **enabled** by default.
+//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`, the character
+//!    devices are added as `/dev/ttyS*` by [`init_char_devices`], and the
+//!    GPIO, I2C and SPI controllers as `/dev/gpiochip*`, `/dev/i2c-*` and
+//!    `/dev/spidev*` by [`init_bus_devices`]. This feature is **enabled** by
+//!    default.
+//! - `hotplug`: Add the block devices plugged in after boot to devfs as
+//!    `/dev/disk*`, and remove them when they are unplugged. It implies
+//!    `devfs`.
 //! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
 //!    **enabled** by default.
 //! - `myfs`: Allow users to define their custom filesystems to override the
@@ -28,8 +34,18 @@ extern crate alloc;
 
 mod dev;
 mod fs;
+#[cfg(feature = "devfs")]
+mod gpio;
+#[cfg(feature = "hotplug")]
+mod hotplug;
+#[cfg(feature = "devfs")]
+mod i2c;
 mod mounts;
 mod root;
+#[cfg(feature = "devfs")]
+mod spi;
+#[cfg(feature = "devfs")]
+mod tty;
 
 pub mod api;
 pub mod fops;
@@ -43,4 +59,109 @@ pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
     let dev = blk_devs.take_one().expect("No block device found!");
     info!("  use block device 0: {:?}", dev.device_name());
     self::root::init_rootfs(self::dev::Disk::new(dev));
+
+    #[cfg(feature = "hotplug")]
+    self::hotplug::init();
+}
+
+/// Adds the character devices to devfs, the serial ports as `/dev/ttyS0`,
+/// `/dev/ttyS1`, etc., and the VirtIO consoles as `/dev/hvc0`, `/dev/hvc1`,
+/// etc.
+///
+/// It must be called after [`init_filesystems`].
+pub fn init_char_devices(char_devs: AxDeviceContainer<AxCharDevice>) {
+    #[cfg(feature = "devfs")]
+    let mut char_devs = char_devs;
+    #[cfg(feature = "devfs")]
+    {
+        let devfs = self::mounts::DEVFS.try_get().expect("devfs is not mounted");
+        let mut tty_names = self::tty::TTY_NAMES.iter();
+        let mut hvc_names = self::tty::HVC_NAMES.iter();
+        while let Some(dev) = char_devs.take_one() {
+            let names = if dev.device_name() == "virtio-console" {
+                &mut hvc_names
+            } else {
+                &mut tty_names
+            };
+            let Some(&name) = names.next() else {
+                warn!("  character device {:?} is not added", dev.device_name());
+                continue;
+            };
+            info!(
+                "  use character device {:?} as /dev/{}",
+                dev.device_name(),
+                name
+            );
+            devfs.add(name, alloc::sync::Arc::new(self::tty::TtyNode::new(dev)));
+        }
+    }
+    if !char_devs.is_empty() {
+        warn!("  {} character devices are not added", char_devs.len());
+    }
+}
+
+/// Adds the GPIO, I2C and SPI controllers to devfs, as `/dev/gpiochip0`,
+/// `/dev/i2c-0`, etc. Each target on an SPI bus has its own node by the chip
+/// select line, e.g., `/dev/spidev0.1` for the chip select 1 of the first
+/// controller.
+///
+/// It must be called after [`init_filesystems`].
+pub fn init_bus_devices(
+    gpio_devs: AxDeviceContainer<AxGpioDevice>,
+    i2c_devs: AxDeviceContainer<AxI2cDevice>,
+    spi_devs: AxDeviceContainer<AxSpiDevice>,
+) {
+    #[cfg(feature = "devfs")]
+    let (mut gpio_devs, mut i2c_devs, mut spi_devs) = (gpio_devs, i2c_devs, spi_devs);
+    #[cfg(feature = "devfs")]
+    {
+        use alloc::sync::Arc;
+        let devfs = self::mounts::DEVFS.try_get().expect("devfs is not mounted");
+        for &name in self::gpio::GPIO_NAMES.iter() {
+            let Some(dev) = gpio_devs.take_one() else {
+                break;
+            };
+            info!(
+                "  use GPIO controller {:?} as /dev/{}",
+                dev.device_name(),
+                name
+            );
+            devfs.add(name, Arc::new(self::gpio::GpioNode::new(dev)));
+        }
+        for &name in self::i2c::I2C_NAMES.iter() {
+            let Some(dev) = i2c_devs.take_one() else {
+                break;
+            };
+            info!(
+                "  use I2C controller {:?} as /dev/{}",
+                dev.device_name(),
+                name
+            );
+            devfs.add(name, Arc::new(self::i2c::I2cNode::new(dev)));
+        }
+        for names in self::spi::SPI_NAMES.iter() {
+            let Some(dev) = spi_devs.take_one() else {
+                break;
+            };
+            info!(
+                "  use SPI controller {:?} as /dev/{}",
+                dev.device_name(),
+                names[0]
+            );
+            let num_cs = dev.num_chip_selects();
+            let dev = Arc::new(axsync::Mutex::new(dev));
+            for (cs, &name) in names.iter().enumerate().take(num_cs) {
+                devfs.add(name, Arc::new(self::spi::SpiNode::new(dev.clone(), cs)));
+            }
+        }
+    }
+    for (kind, len) in [
+        ("GPIO", gpio_devs.len()),
+        ("I2C", i2c_devs.len()),
+        ("SPI", spi_devs.len()),
+    ] {
+        if len > 0 {
+            warn!("  {} {} controllers are not added", len, kind);
+        }
+    }
 }
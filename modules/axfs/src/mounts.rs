@@ -1,8 +1,14 @@
 use alloc::sync::Arc;
 use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
+#[cfg(feature = "devfs")]
+use lazy_init::LazyInit;
 
 use crate::fs;
 
+/// The devfs mounted on `/dev`, where more devices can be added.
+#[cfg(feature = "devfs")]
+pub(crate) static DEVFS: LazyInit<Arc<fs::devfs::DeviceFileSystem>> = LazyInit::new();
+
 #[cfg(feature = "devfs")]
 pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     let null = fs::devfs::NullDev;
@@ -13,7 +19,9 @@ pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     devfs.add("null", Arc::new(null));
     devfs.add("zero", Arc::new(zero));
     foo_dir.add("bar", Arc::new(bar));
-    Arc::new(devfs)
+    let devfs = Arc::new(devfs);
+    DEVFS.init_by(devfs.clone());
+    devfs
 }
 
 #[cfg(feature = "ramfs")]
@@ -0,0 +1,76 @@
+//! SPI controllers in devfs.
+
+use alloc::sync::Arc;
+use axdriver::prelude::*;
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+use crate::tty::as_vfs_err;
+
+/// The names of the targets of the SPI controllers in devfs, by the
+/// controller and the chip select line.
+pub(crate) const SPI_NAMES: [[&str; 2]; 4] = [
+    ["spidev0.0", "spidev0.1"],
+    ["spidev1.0", "spidev1.1"],
+    ["spidev2.0", "spidev2.1"],
+    ["spidev3.0", "spidev3.1"],
+];
+
+/// A target on an SPI bus in devfs, one for each chip select line of the
+/// controller.
+///
+/// A write sends the bytes and drops the bytes received, and a read sends
+/// zeros and returns the bytes received. Use [`crate::api::spi_transfer`] for
+/// full duplex transfers.
+pub(crate) struct SpiNode {
+    dev: Arc<Mutex<AxSpiDevice>>,
+    cs: usize,
+}
+
+impl SpiNode {
+    pub fn new(dev: Arc<Mutex<AxSpiDevice>>, cs: usize) -> Self {
+        Self { dev, cs }
+    }
+
+    pub fn config(&self) -> SpiConfig {
+        self.dev.lock().config()
+    }
+
+    /// Changes the configuration of the bus, which applies to all the
+    /// targets on it.
+    pub fn set_config(&self, config: SpiConfig) -> VfsResult {
+        self.dev.lock().set_config(config).map_err(as_vfs_err)
+    }
+
+    pub fn transfer(&self, write: &[u8], read: &mut [u8]) -> VfsResult {
+        let mut dev = self.dev.lock();
+        dev.transfer(self.cs, write, read).map_err(as_vfs_err)
+    }
+}
+
+impl VfsNodeOps for SpiNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::default_file(),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        ))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.transfer(&[], buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.transfer(buf, &mut [])?;
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
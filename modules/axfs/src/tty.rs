@@ -0,0 +1,103 @@
+//! Character devices in devfs.
+
+use axdriver::prelude::*;
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::Mutex;
+use driver_char::SerialConfig;
+
+/// The names of the serial ports in devfs.
+pub(crate) const TTY_NAMES: [&str; 8] = [
+    "ttyS0", "ttyS1", "ttyS2", "ttyS3", "ttyS4", "ttyS5", "ttyS6", "ttyS7",
+];
+
+/// The names of the VirtIO consoles in devfs.
+pub(crate) const HVC_NAMES: [&str; 8] = [
+    "hvc0", "hvc1", "hvc2", "hvc3", "hvc4", "hvc5", "hvc6", "hvc7",
+];
+
+/// A character device (e.g., a serial port) in devfs.
+///
+/// Reads wait until some input is available, unless the file is in nonblocking
+/// mode (see [`TtyNode::try_read`]). Writes wait until all the bytes are
+/// accepted by the device.
+pub(crate) struct TtyNode {
+    dev: Mutex<AxCharDevice>,
+}
+
+impl TtyNode {
+    pub fn new(dev: AxCharDevice) -> Self {
+        Self {
+            dev: Mutex::new(dev),
+        }
+    }
+
+    pub fn config(&self) -> SerialConfig {
+        self.dev.lock().config()
+    }
+
+    pub fn set_config(&self, config: &SerialConfig) -> VfsResult {
+        self.dev.lock().set_config(config).map_err(as_vfs_err)
+    }
+
+    /// Reads the available input without waiting, fails with
+    /// [`VfsError::WouldBlock`] if there is none.
+    pub fn try_read(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        self.dev.lock().read(buf).map_err(as_vfs_err)
+    }
+}
+
+impl VfsNodeOps for TtyNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::default_file(),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        ))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            // release the device while waiting, so that writers can go on
+            match self.try_read(buf) {
+                Err(VfsError::WouldBlock) => axtask::yield_now(),
+                res => return res,
+            }
+        }
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut dev = self.dev.lock();
+        let mut written = 0;
+        while written < buf.len() {
+            match dev.write(&buf[written..]) {
+                Ok(n) => written += n,
+                Err(DevError::Again) => core::hint::spin_loop(),
+                Err(e) => return Err(as_vfs_err(e)),
+            }
+        }
+        Ok(written)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+pub(crate) const fn as_vfs_err(err: DevError) -> VfsError {
+    match err {
+        DevError::AlreadyExists => VfsError::AlreadyExists,
+        DevError::Again => VfsError::WouldBlock,
+        DevError::BadState => VfsError::BadState,
+        DevError::InvalidParam => VfsError::InvalidInput,
+        DevError::Io => VfsError::Io,
+        DevError::NoMemory => VfsError::NoMemory,
+        DevError::ResourceBusy => VfsError::ResourceBusy,
+        DevError::Unsupported => VfsError::Unsupported,
+    }
+}
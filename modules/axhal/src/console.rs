@@ -0,0 +1,52 @@
+//! Console input and output.
+//!
+//! The platform console (e.g., the UART used by the firmware) is used, unless
+//! a console device (e.g., virtio-console) is registered by
+//! [`set_console_device`].
+
+use lazy_init::LazyInit;
+
+/// A console device that replaces the platform console.
+pub struct ConsoleDevice {
+    /// Writes a slice of bytes to the device, returns `false` if the device
+    /// cannot be used now (e.g., it is busy), and the platform console should
+    /// be used instead.
+    pub write_bytes: fn(&[u8]) -> bool,
+    /// Reads a byte from the device, returns `None` if there is no input.
+    pub getchar: fn() -> Option<u8>,
+}
+
+static CONSOLE_DEVICE: LazyInit<ConsoleDevice> = LazyInit::new();
+
+/// Registers a console device, all the console input and output goes through
+/// it afterwards.
+///
+/// It can be called only once.
+pub fn set_console_device(dev: ConsoleDevice) {
+    CONSOLE_DEVICE.init_by(dev);
+}
+
+/// Writes a byte to the console.
+pub fn putchar(c: u8) {
+    write_bytes(&[c]);
+}
+
+/// Reads a byte from the console, returns `None` if there is no input.
+pub fn getchar() -> Option<u8> {
+    match CONSOLE_DEVICE.try_get() {
+        Some(dev) => (dev.getchar)(),
+        None => crate::platform::console::getchar(),
+    }
+}
+
+/// Write a slice of bytes to the console.
+pub fn write_bytes(bytes: &[u8]) {
+    if let Some(dev) = CONSOLE_DEVICE.try_get() {
+        if (dev.write_bytes)(bytes) {
+            return;
+        }
+    }
+    for c in bytes {
+        crate::platform::console::putchar(*c);
+    }
+}
@@ -0,0 +1,54 @@
+//! The device tree blob (DTB) passed by the firmware.
+//!
+//! The bootloader passes the physical address of the DTB on RISC-V and
+//! AArch64 platforms, it's recorded at boot and parsed on demand by
+//! [`fdt_parser`]. There is no DTB on x86 platforms.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use fdt_parser::Fdt;
+use memory_addr::PhysAddr;
+
+use crate::mem::phys_to_virt;
+
+static DTB_PADDR: AtomicUsize = AtomicUsize::new(0);
+
+/// Records the DTB at `dtb_paddr` if it's valid.
+///
+/// It must be called before the DTB is used, it's usually called by the
+/// bootstrapping code of the primary CPU.
+#[allow(dead_code)]
+pub(crate) fn init(dtb_paddr: usize) {
+    if dtb_paddr == 0 {
+        return;
+    }
+    let ptr = phys_to_virt(dtb_paddr.into()).as_ptr();
+    if unsafe { Fdt::from_ptr(ptr) }.is_ok() {
+        DTB_PADDR.store(dtb_paddr, Ordering::Release);
+    }
+}
+
+/// Returns the physical address of the DTB, or `None` if there is no valid
+/// DTB.
+pub fn dtb_paddr() -> Option<PhysAddr> {
+    match DTB_PADDR.load(Ordering::Acquire) {
+        0 => None,
+        paddr => Some(paddr.into()),
+    }
+}
+
+/// Returns the parsed DTB, or `None` if there is no valid DTB.
+pub fn fdt() -> Option<Fdt<'static>> {
+    let ptr = phys_to_virt(dtb_paddr()?).as_ptr();
+    // it was validated in `init`
+    unsafe { Fdt::from_ptr(ptr) }.ok()
+}
+
+/// Returns the number of available CPUs in the DTB, or `None` if there is no
+/// valid DTB or it lists no CPU.
+pub fn cpu_count() -> Option<usize> {
+    match fdt()?.cpu_count() {
+        0 => None,
+        n => Some(n),
+    }
+}
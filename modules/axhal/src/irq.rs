@@ -4,11 +4,25 @@ use handler_table::HandlerTable;
 
 use crate::platform::irq::MAX_IRQ_COUNT;
 
-pub use crate::platform::irq::{dispatch_irq, register_handler, set_enable};
+pub use crate::platform::irq::{alloc_msi, dispatch_irq, register_handler, set_enable};
 
 /// The type if an IRQ handler.
 pub type IrqHandler = handler_table::Handler;
 
+/// A message signaled interrupt (MSI) allocated by [`alloc_msi`].
+///
+/// A PCI device raises the interrupt by writing `data` to `address`, and the
+/// handler of `irq_num` is called.
+#[derive(Debug, Clone, Copy)]
+pub struct MsiVector {
+    /// The IRQ number to register the handler for.
+    pub irq_num: usize,
+    /// The address that the device writes to.
+    pub address: u64,
+    /// The data that the device writes.
+    pub data: u32,
+}
+
 static IRQ_HANDLER_TABLE: HandlerTable<MAX_IRQ_COUNT> = HandlerTable::new();
 
 /// Platform-independent IRQ dispatching.
@@ -38,8 +38,11 @@ extern crate log;
 mod platform;
 
 pub mod arch;
+pub mod console;
 pub mod cpu;
+pub mod dtb;
 pub mod mem;
+pub mod random;
 pub mod time;
 pub mod trap;
 
@@ -52,18 +55,6 @@ pub mod irq;
 #[cfg(feature = "paging")]
 pub mod paging;
 
-/// Console input and output.
-pub mod console {
-    pub use super::platform::console::*;
-
-    /// Write a slice of bytes to the console.
-    pub fn write_bytes(bytes: &[u8]) {
-        for c in bytes {
-            putchar(*c);
-        }
-    }
-}
-
 /// Miscellaneous operation, e.g. terminate the system.
 pub mod misc {
     pub use super::platform::misc::*;
@@ -72,7 +72,9 @@ pub const fn phys_to_virt(paddr: PhysAddr) -> VirtAddr {
 
 /// Returns an iterator over all physical memory regions.
 pub fn memory_regions() -> impl Iterator<Item = MemRegion> {
-    kernel_image_regions().chain(crate::platform::mem::platform_regions())
+    kernel_image_regions()
+        .chain(dtb_region())
+        .chain(crate::platform::mem::platform_regions())
 }
 
 /// Returns the memory regions of the kernel image (code and data sections).
@@ -112,6 +114,28 @@ fn kernel_image_regions() -> impl Iterator<Item = MemRegion> {
     .into_iter()
 }
 
+/// Returns the memory region of the device tree blob if there is one.
+fn dtb_region() -> Option<MemRegion> {
+    let (start, end) = dtb_range()?;
+    Some(MemRegion {
+        paddr: start.into(),
+        size: end - start,
+        flags: MemRegionFlags::RESERVED | MemRegionFlags::READ,
+        name: "fdt",
+    })
+}
+
+/// Returns the page-aligned physical range `[start, end)` of the device tree
+/// blob.
+fn dtb_range() -> Option<(usize, usize)> {
+    let start = crate::dtb::dtb_paddr()?;
+    let end = start + crate::dtb::fdt()?.total_size();
+    Some((
+        start.align_down_4k().as_usize(),
+        end.align_up_4k().as_usize(),
+    ))
+}
+
 /// Returns the default MMIO memory regions (from [`axconfig::MMIO_REGIONS`]).
 #[allow(dead_code)]
 pub(crate) fn default_mmio_regions() -> impl Iterator<Item = MemRegion> {
@@ -126,14 +150,128 @@ pub(crate) fn default_mmio_regions() -> impl Iterator<Item = MemRegion> {
     })
 }
 
+/// The maximum number of physical memory ranges read from the device tree.
+const MAX_MEMORY_RANGES: usize = 8;
+/// The maximum number of reserved memory ranges read from the device tree.
+const MAX_RESERVED_RANGES: usize = 16;
+
 /// Returns the default free memory regions (kernel image end to physical memory end).
 #[allow(dead_code)]
 pub(crate) fn default_free_regions() -> impl Iterator<Item = MemRegion> {
-    let start = virt_to_phys((_ekernel as usize).into()).align_up_4k();
-    let end = PhysAddr::from(axconfig::PHYS_MEMORY_END).align_down_4k();
-    core::iter::once(MemRegion {
-        paddr: start,
-        size: end.as_usize() - start.as_usize(),
+    free_regions_in(
+        &[(axconfig::PHYS_MEMORY_BASE, axconfig::PHYS_MEMORY_END)],
+        &[],
+    )
+}
+
+/// Returns the free memory regions in the `memory` nodes of the device tree,
+/// or the default ones if there is no device tree.
+///
+/// The memory reservation block (`/memreserve/`) and the `/reserved-memory`
+/// nodes are excluded. Only the memory after the kernel image is used, and
+/// the boot page table must map all of it.
+#[allow(dead_code)]
+pub(crate) fn dtb_free_regions() -> impl Iterator<Item = MemRegion> {
+    let mut ranges = [(0, 0); MAX_MEMORY_RANGES];
+    let mut num = 0;
+    let mut reserved = [(0, 0); MAX_RESERVED_RANGES];
+    let mut num_reserved = 0;
+    if let Some(fdt) = crate::dtb::fdt() {
+        for reg in fdt.memory_regions().take(MAX_MEMORY_RANGES) {
+            if let Some(range) = reg_range(reg.address, reg.size.unwrap_or(0)) {
+                ranges[num] = range;
+                num += 1;
+            }
+        }
+        let reserved_regs = fdt.reserved_regions().chain(
+            fdt.reserved_memory()
+                .map(|reg| (reg.address, reg.size.unwrap_or(0))),
+        );
+        for (addr, size) in reserved_regs {
+            if num_reserved == MAX_RESERVED_RANGES {
+                warn!("too many reserved memory regions, ignore the rest");
+                break;
+            }
+            // a region beyond the address space reserves up to its end
+            let Ok(start) = usize::try_from(addr) else {
+                continue;
+            };
+            let end = usize::try_from(size).map_or(usize::MAX, |size| start.saturating_add(size));
+            reserved[num_reserved] = (start, end);
+            num_reserved += 1;
+        }
+    }
+    if num == 0 {
+        ranges[0] = (axconfig::PHYS_MEMORY_BASE, axconfig::PHYS_MEMORY_END);
+        num = 1;
+    }
+    free_regions_in(&ranges[..num], &reserved[..num_reserved])
+}
+
+/// Converts a `reg` entry to an address range, `None` if it does not fit in
+/// the address space.
+fn reg_range(addr: u64, size: u64) -> Option<(usize, usize)> {
+    let start = usize::try_from(addr).ok()?;
+    let end = start.checked_add(usize::try_from(size).ok()?)?;
+    Some((start, end))
+}
+
+/// Returns the free memory regions in the physical memory `ranges`, which
+/// are the parts after the kernel image and outside the `reserved` ranges
+/// and the device tree blob.
+fn free_regions_in(
+    ranges: &[(usize, usize)],
+    reserved: &[(usize, usize)],
+) -> impl Iterator<Item = MemRegion> {
+    let kernel_end = virt_to_phys((_ekernel as usize).into())
+        .align_up_4k()
+        .as_usize();
+
+    // each reserved range splits at most one range into two
+    let mut free = [(0, 0); MAX_MEMORY_RANGES + MAX_RESERVED_RANGES + 1];
+    let mut num = 0;
+    for &(start, end) in ranges.iter().take(MAX_MEMORY_RANGES) {
+        let start = PhysAddr::from(start.max(kernel_end))
+            .align_up_4k()
+            .as_usize();
+        let end = PhysAddr::from(end).align_down_4k().as_usize();
+        if start < end {
+            free[num] = (start, end);
+            num += 1;
+        }
+    }
+
+    let dtb = dtb_range();
+    for &(res_start, res_end) in reserved.iter().chain(&dtb) {
+        let mut i = 0;
+        while i < num {
+            let (start, end) = free[i];
+            if res_end <= start || end <= res_start {
+                i += 1;
+                continue;
+            }
+            // replace the range with its parts before and after the reserved one
+            num -= 1;
+            free[i] = free[num];
+            let before = (start, PhysAddr::from(res_start).align_down_4k().as_usize());
+            let after = if res_end < end {
+                (PhysAddr::from(res_end).align_up_4k().as_usize(), end)
+            } else {
+                (end, end)
+            };
+            for (start, end) in [before, after] {
+                if start < end && num < free.len() {
+                    free[num] = (start, end);
+                    num += 1;
+                }
+            }
+        }
+    }
+
+    free[..num].sort_unstable();
+    free.into_iter().take(num).map(|(start, end)| MemRegion {
+        paddr: start.into(),
+        size: end - start,
         flags: MemRegionFlags::FREE | MemRegionFlags::READ | MemRegionFlags::WRITE,
         name: "free memory",
     })
@@ -1,3 +1,5 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::{irq::IrqHandler, mem::phys_to_virt};
 use arm_gic::gic_v2::{GicCpuInterface, GicDistributor};
 use arm_gic::{translate_irq, InterruptType};
@@ -37,6 +39,35 @@ pub fn register_handler(irq_num: usize, handler: IrqHandler) -> bool {
     crate::irq::register_handler_common(irq_num, handler)
 }
 
+/// Allocates an MSI vector from the GICv2m frame in the device tree, which
+/// turns the write of the device into an SPI.
+///
+/// It returns `None` if there is no GICv2m frame or all its SPIs are used.
+pub fn alloc_msi() -> Option<crate::irq::MsiVector> {
+    // offsets in the GICv2m frame
+    const V2M_MSI_TYPER: usize = 0x8;
+    const V2M_MSI_SETSPI_NS: u64 = 0x40;
+    static NEXT_MSI_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+    let fdt = crate::dtb::fdt()?;
+    let frame = fdt.find_compatible(&["arm,gic-v2m-frame"]).next()?;
+    let frame_paddr = frame.reg()?.next()?.address;
+    let typer_ptr = phys_to_virt(PhysAddr::from(frame_paddr as usize + V2M_MSI_TYPER));
+    let typer = unsafe { (typer_ptr.as_ptr() as *const u32).read_volatile() };
+    let (spi_base, spi_count) = ((typer >> 16) & 0x3ff, typer & 0x3ff);
+    let index = NEXT_MSI_INDEX
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |i| {
+            (i < spi_count as usize).then_some(i + 1)
+        })
+        .ok()?;
+    let irq_num = spi_base as usize + index;
+    Some(crate::irq::MsiVector {
+        irq_num,
+        address: frame_paddr + V2M_MSI_SETSPI_NS,
+        data: irq_num as u32,
+    })
+}
+
 /// Dispatches the IRQ.
 ///
 /// This function is called by the common interrupt handler. It looks
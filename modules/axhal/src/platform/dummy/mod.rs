@@ -71,6 +71,11 @@ pub mod irq {
         false
     }
 
+    /// Allocates an MSI vector.
+    pub fn alloc_msi() -> Option<crate::irq::MsiVector> {
+        None
+    }
+
     /// Dispatches the IRQ.
     ///
     /// This function is called by the common interrupt handler. It looks
@@ -11,8 +11,11 @@ static mut BOOT_PT_SV39: [u64; 512] = [0; 512];
 unsafe fn init_boot_page_table() {
     // 0x8000_0000..0xc000_0000, VRWX_GAD, 1G block
     BOOT_PT_SV39[2] = (0x80000 << 10) | 0xef;
-    // 0xffff_ffc0_8000_0000..0xffff_ffc0_c000_0000, VRWX_GAD, 1G block
-    BOOT_PT_SV39[0x102] = (0x80000 << 10) | 0xef;
+    // 0xffff_ffc0_8000_0000..0xffff_ffd0_8000_0000, VRWX_GAD, 64 x 1G blocks,
+    // for the RAM of any size described by the device tree
+    for i in 0..64 {
+        BOOT_PT_SV39[0x102 + i] = ((0x80000 + i as u64 * 0x40000) << 10) | 0xef;
+    }
 
     // 0x0000_0000..0x4000_0000, VRWX_GAD, 1G block
     BOOT_PT_SV39[0] = (0x00000 << 10) | 0xef;
@@ -59,6 +59,12 @@ pub fn register_handler(scause: usize, handler: IrqHandler) -> bool {
     )
 }
 
+/// Allocates an MSI vector, which is not supported without the AIA (the
+/// PLIC has no MSI), so it always returns `None`.
+pub fn alloc_msi() -> Option<crate::irq::MsiVector> {
+    None
+}
+
 /// Dispatches the IRQ.
 ///
 /// This function is called by the common interrupt handler. It looks
@@ -14,6 +14,9 @@ pub(super) mod vectors {
     pub const APIC_TIMER_VECTOR: u8 = 0xf0;
     pub const APIC_SPURIOUS_VECTOR: u8 = 0xf1;
     pub const APIC_ERROR_VECTOR: u8 = 0xf2;
+    /// The vectors in `MSI_VECTOR_START..MSI_VECTOR_END` are used by MSIs.
+    pub const MSI_VECTOR_START: u8 = 0x40;
+    pub const MSI_VECTOR_END: u8 = 0xe0;
 }
 
 /// The maximum number of IRQs.
@@ -23,6 +26,9 @@ pub const MAX_IRQ_COUNT: usize = 256;
 pub const TIMER_IRQ_NUM: usize = APIC_TIMER_VECTOR as usize;
 
 const IO_APIC_BASE: PhysAddr = PhysAddr::from(0xFEC0_0000);
+/// The address that MSIs are written to, the destination APIC ID is in bits
+/// 12..20.
+const MSI_ADDRESS_BASE: u64 = 0xFEE0_0000;
 
 static mut LOCAL_APIC: Option<LocalApic> = None;
 static mut IS_X2APIC: bool = false;
@@ -31,8 +37,9 @@ static IO_APIC: LazyInit<SpinNoIrq<IoApic>> = LazyInit::new();
 /// Enables or disables the given IRQ.
 #[cfg(feature = "irq")]
 pub fn set_enable(vector: usize, enabled: bool) {
-    // should not affect LAPIC interrupts
-    if vector < APIC_TIMER_VECTOR as _ {
+    // should not affect LAPIC interrupts and MSIs
+    let is_msi = (MSI_VECTOR_START as usize..MSI_VECTOR_END as usize).contains(&vector);
+    if vector < APIC_TIMER_VECTOR as _ && !is_msi {
         unsafe {
             if enabled {
                 IO_APIC.lock().enable_irq(vector as u8);
@@ -63,6 +70,31 @@ pub fn dispatch_irq(vector: usize) {
     unsafe { local_apic().end_of_interrupt() };
 }
 
+/// Allocates an MSI vector, which is delivered to the current CPU.
+///
+/// It returns `None` if all vectors are used.
+#[cfg(feature = "irq")]
+pub fn alloc_msi() -> Option<crate::irq::MsiVector> {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    static NEXT_MSI_VECTOR: AtomicUsize = AtomicUsize::new(MSI_VECTOR_START as usize);
+    let vector = NEXT_MSI_VECTOR
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+            (v < MSI_VECTOR_END as usize).then_some(v + 1)
+        })
+        .ok()?;
+    let raw_id = unsafe { local_apic().id() };
+    let apic_id = if unsafe { IS_X2APIC } {
+        raw_id
+    } else {
+        raw_id >> 24
+    };
+    Some(crate::irq::MsiVector {
+        irq_num: vector,
+        address: MSI_ADDRESS_BASE | (apic_id as u64 & 0xff) << 12,
+        data: vector as u32,
+    })
+}
+
 pub(super) fn local_apic<'a>() -> &'a mut LocalApic {
     // It's safe as LAPIC is per-cpu.
     unsafe { LOCAL_APIC.as_mut().unwrap() }
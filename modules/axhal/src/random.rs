@@ -0,0 +1,151 @@
+//! Random number generation.
+//!
+//! The numbers come from the first available one of:
+//!
+//! 1. An entropy source device (e.g., virtio-rng) registered by
+//!    [`set_entropy_source`].
+//! 2. The hardware random number generator of the CPU: `RDRAND` on x86_64 and
+//!    `RNDR` (FEAT_RNG) on AArch64.
+//! 3. A generator seeded by the clock, which is NOT suitable for cryptography.
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use lazy_init::LazyInit;
+
+/// A function that fills the buffer with bytes from an entropy source,
+/// returns the number of bytes filled.
+pub type EntropySource = fn(&mut [u8]) -> usize;
+
+static ENTROPY_SOURCE: LazyInit<EntropySource> = LazyInit::new();
+
+/// Registers an entropy source device, which takes precedence over the CPU
+/// random number generator.
+///
+/// It can be called only once.
+pub fn set_entropy_source(source: EntropySource) {
+    ENTROPY_SOURCE.init_by(source);
+}
+
+/// Fills `buf` from the registered entropy source, returns `false` if there
+/// is no source or it fails to fill the whole buffer.
+fn fill_from_source(buf: &mut [u8]) -> bool {
+    let Some(source) = ENTROPY_SOURCE.try_get() else {
+        return false;
+    };
+    let mut filled = 0;
+    while filled < buf.len() {
+        match source(&mut buf[filled..]) {
+            0 => return false,
+            n => filled += n,
+        }
+    }
+    true
+}
+
+/// Returns whether the CPU has a hardware random number generator.
+pub fn has_hw_rng() -> bool {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "x86_64")] {
+            raw_cpuid::CpuId::new()
+                .get_feature_info()
+                .is_some_and(|info| info.has_rdrand())
+        } else if #[cfg(target_arch = "aarch64")] {
+            let isar0: u64;
+            unsafe { core::arch::asm!("mrs {}, id_aa64isar0_el1", out(reg) isar0) };
+            // ID_AA64ISAR0_EL1.RNDR, bits [63:60]
+            isar0 >> 60 != 0
+        } else {
+            false
+        }
+    }
+}
+
+/// Reads a random number from the hardware random number generator, returns
+/// `None` if it fails or there is no one.
+fn hw_random_u64() -> Option<u64> {
+    if !has_hw_rng() {
+        return None;
+    }
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "x86_64")] {
+            #[target_feature(enable = "rdrand")]
+            unsafe fn rdrand() -> Option<u64> {
+                let mut val = 0;
+                // retry a few times as recommended by Intel
+                for _ in 0..10 {
+                    if core::arch::x86_64::_rdrand64_step(&mut val) == 1 {
+                        return Some(val);
+                    }
+                }
+                None
+            }
+            unsafe { rdrand() }
+        } else if #[cfg(target_arch = "aarch64")] {
+            for _ in 0..10 {
+                let (val, failed): (u64, u64);
+                // `RNDR` sets NZCV to 0b0100 on failure
+                unsafe {
+                    core::arch::asm!(
+                        "mrs {val}, s3_3_c2_c4_0",
+                        "cset {failed}, eq",
+                        val = out(reg) val,
+                        failed = out(reg) failed,
+                        options(nomem, nostack),
+                    )
+                };
+                if failed == 0 {
+                    return Some(val);
+                }
+            }
+            None
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns a number from the fallback generator (splitmix64), whose state is
+/// mixed with the clock on every call.
+fn soft_random_u64() -> u64 {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    static STATE: AtomicU64 = AtomicU64::new(0x9E37_79B9_7F4A_7C15);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        warn!("no hardware RNG, random numbers are predictable");
+    }
+
+    let ticks = crate::time::current_ticks();
+    let mut z = STATE
+        .fetch_add(0x9E37_79B9_7F4A_7C15 ^ ticks, Ordering::Relaxed)
+        .wrapping_add(ticks);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Returns a random 64-bit number.
+pub fn random_u64() -> u64 {
+    let mut bytes = [0; 8];
+    if fill_from_source(&mut bytes) {
+        return u64::from_ne_bytes(bytes);
+    }
+    hw_random_u64().unwrap_or_else(soft_random_u64)
+}
+
+/// Fills `buf` with random bytes.
+///
+/// Returns `false` if some bytes come from the fallback generator, which is
+/// predictable, as there is neither an entropy source nor a hardware random
+/// number generator.
+pub fn fill_random(buf: &mut [u8]) -> bool {
+    if fill_from_source(buf) {
+        return true;
+    }
+    let mut unpredictable = true;
+    for chunk in buf.chunks_mut(8) {
+        let val = hw_random_u64().unwrap_or_else(|| {
+            unpredictable = false;
+            soft_random_u64()
+        });
+        chunk.copy_from_slice(&val.to_ne_bytes()[..chunk.len()]);
+    }
+    unpredictable
+}
@@ -0,0 +1,184 @@
+//! Minimal ELF64 parser, only the parts needed to load static executables.
+
+use axerrno::{ax_err, AxResult};
+
+const ELF_MAGIC: [u8; 4] = *b"\x7fELF";
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const EV_CURRENT: u8 = 1;
+
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+
+/// Executable file.
+pub const ET_EXEC: u16 = 2;
+
+/// Loadable segment.
+pub const PT_LOAD: u32 = 1;
+/// Dynamic linking information.
+pub const PT_DYNAMIC: u32 = 2;
+/// Program interpreter.
+pub const PT_INTERP: u32 = 3;
+/// Location of the program header table itself.
+pub const PT_PHDR: u32 = 6;
+/// Thread-local storage template.
+pub const PT_TLS: u32 = 7;
+
+/// Segment is executable.
+pub const PF_X: u32 = 1 << 0;
+/// Segment is writable.
+pub const PF_W: u32 = 1 << 1;
+/// Segment is readable.
+pub const PF_R: u32 = 1 << 2;
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        const EM_CURRENT: u16 = 62; // EM_X86_64
+    } else if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
+        const EM_CURRENT: u16 = 243; // EM_RISCV
+    } else if #[cfg(target_arch = "aarch64")] {
+        const EM_CURRENT: u16 = 183; // EM_AARCH64
+    }
+}
+
+/// A program header (`Elf64_Phdr`).
+#[derive(Debug, Clone, Copy)]
+pub struct ProgramHeader {
+    /// Segment type (`PT_*`).
+    pub p_type: u32,
+    /// Segment flags (`PF_*`).
+    pub p_flags: u32,
+    /// Offset of the segment in the file.
+    pub p_offset: u64,
+    /// Virtual address of the segment in memory.
+    pub p_vaddr: u64,
+    /// Number of bytes of the segment in the file.
+    pub p_filesz: u64,
+    /// Number of bytes of the segment in memory.
+    pub p_memsz: u64,
+    /// Alignment of the segment.
+    pub p_align: u64,
+}
+
+/// A parsed ELF64 image, borrowing the raw file data.
+pub struct ElfFile<'a> {
+    data: &'a [u8],
+    e_type: u16,
+    e_entry: u64,
+    e_phoff: u64,
+    e_phentsize: u16,
+    e_phnum: u16,
+}
+
+impl<'a> ElfFile<'a> {
+    /// Parses and validates the ELF header of `data`.
+    ///
+    /// Only little-endian ELF64 files built for the current architecture are
+    /// accepted.
+    pub fn parse(data: &'a [u8]) -> AxResult<Self> {
+        if data.len() < EHDR_SIZE || data[..4] != ELF_MAGIC {
+            return ax_err!(InvalidData, "not an ELF file");
+        }
+        if data[4] != ELFCLASS64 || data[5] != ELFDATA2LSB || data[6] != EV_CURRENT {
+            return ax_err!(Unsupported, "only little-endian ELF64 is supported");
+        }
+        if read_u16(data, 18) != EM_CURRENT {
+            return ax_err!(Unsupported, "ELF machine type mismatch");
+        }
+        let elf = Self {
+            data,
+            e_type: read_u16(data, 16),
+            e_entry: read_u64(data, 24),
+            e_phoff: read_u64(data, 32),
+            e_phentsize: read_u16(data, 54),
+            e_phnum: read_u16(data, 56),
+        };
+        if elf.e_phnum > 0 && (elf.e_phentsize as usize) < PHDR_SIZE {
+            return ax_err!(InvalidData, "bad program header size");
+        }
+        let ph_end = (elf.e_phentsize as usize)
+            .checked_mul(elf.e_phnum as usize)
+            .and_then(|size| size.checked_add(usize::try_from(elf.e_phoff).ok()?));
+        if ph_end.map_or(true, |end| end > data.len()) {
+            return ax_err!(InvalidData, "program headers out of range");
+        }
+        Ok(elf)
+    }
+
+    /// The object file type (`ET_*`).
+    pub const fn file_type(&self) -> u16 {
+        self.e_type
+    }
+
+    /// The virtual address of the entry point.
+    pub const fn entry(&self) -> usize {
+        self.e_entry as usize
+    }
+
+    /// The size of one program header entry.
+    pub const fn phent(&self) -> usize {
+        self.e_phentsize as usize
+    }
+
+    /// The number of program headers.
+    pub const fn phnum(&self) -> usize {
+        self.e_phnum as usize
+    }
+
+    /// The file offset of the program header table.
+    pub const fn phoff(&self) -> usize {
+        self.e_phoff as usize
+    }
+
+    /// Returns an iterator over all program headers.
+    pub fn program_headers(&self) -> impl Iterator<Item = ProgramHeader> + '_ {
+        (0..self.phnum()).map(move |i| {
+            let off = self.phoff() + i * self.phent();
+            ProgramHeader {
+                p_type: read_u32(self.data, off),
+                p_flags: read_u32(self.data, off + 4),
+                p_offset: read_u64(self.data, off + 8),
+                p_vaddr: read_u64(self.data, off + 16),
+                p_filesz: read_u64(self.data, off + 32),
+                p_memsz: read_u64(self.data, off + 40),
+                p_align: read_u64(self.data, off + 48),
+            }
+        })
+    }
+
+    /// Returns the file content of the given segment.
+    pub fn segment_data(&self, ph: &ProgramHeader) -> AxResult<&'a [u8]> {
+        let end = ph.p_offset.checked_add(ph.p_filesz);
+        if ph.p_filesz > ph.p_memsz || end.map_or(true, |end| end > self.data.len() as u64) {
+            return ax_err!(InvalidData, "segment out of range");
+        }
+        Ok(&self.data[ph.p_offset as usize..(ph.p_offset + ph.p_filesz) as usize])
+    }
+
+    /// Returns the virtual address where the program headers are mapped, if
+    /// they are covered by a loadable segment.
+    pub fn phdr_vaddr(&self) -> Option<usize> {
+        if let Some(ph) = self.program_headers().find(|ph| ph.p_type == PT_PHDR) {
+            return Some(ph.p_vaddr as usize);
+        }
+        let phoff = self.e_phoff;
+        self.program_headers()
+            .find(|ph| {
+                ph.p_type == PT_LOAD && ph.p_offset <= phoff && phoff - ph.p_offset < ph.p_filesz
+            })
+            .and_then(|ph| ph.p_vaddr.checked_add(phoff - ph.p_offset))
+            .map(|vaddr| vaddr as usize)
+    }
+}
+
+fn read_u16(data: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes(data[off..off + 2].try_into().unwrap())
+}
+
+fn read_u32(data: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(data[off..off + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], off: usize) -> u64 {
+    u64::from_le_bytes(data[off..off + 8].try_into().unwrap())
+}
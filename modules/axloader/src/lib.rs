@@ -0,0 +1,352 @@
+//! [ArceOS](https://github.com/rcore-os/arceos) ELF loader.
+//!
+//! It loads statically linked ELF64 executables into a fresh address space,
+//! so that separately compiled applications can be launched without
+//! re-linking the kernel. Each `PT_LOAD` segment is mapped with the
+//! permissions given by its flags, a stack is prepared with `argv`, `envp`
+//! and the auxiliary vector, and a static TLS block is allocated if the
+//! image has a `PT_TLS` segment.
+//!
+//! The new address space also contains the kernel mappings (all regions in
+//! [`axhal::mem::memory_regions`]), so the kernel keeps running after
+//! switching to it. Applications run with the same privilege as the kernel.
+//!
+//! # Cargo Features
+//!
+//! - `fs`: Enable [`load_elf_from_path`] to load images from the filesystem.
+
+#![cfg_attr(not(test), no_std)]
+#![feature(doc_auto_cfg)]
+
+#[macro_use]
+extern crate log;
+extern crate alloc;
+
+mod elf;
+mod stack;
+mod tls;
+
+#[cfg(test)]
+mod tests;
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use axalloc::GlobalPage;
+use axerrno::{ax_err, ax_err_type, AxError, AxResult};
+use axhal::mem::{memory_regions, phys_to_virt, virt_to_phys, PAGE_SIZE_4K};
+use axhal::paging::{MappingFlags, PageSize, PageTable, PagingError};
+use memory_addr::{align_down_4k, align_up_4k, PhysAddr, VirtAddr};
+
+pub use self::elf::{ElfFile, ProgramHeader};
+pub use self::stack::init_stack;
+pub use self::tls::{TlsBlock, TlsTemplate};
+
+/// The top address of the application stack.
+pub const APP_STACK_TOP: usize = 0x3f_ffff_0000;
+/// The default size of the application stack.
+pub const APP_STACK_SIZE: usize = 0x4_0000; // 256 K
+/// The virtual addresses where the segments can be loaded, from above the
+/// null page to below the stack. The kernel mappings are outside of it.
+pub const APP_LOAD_RANGE: Range<usize> = PAGE_SIZE_4K..APP_STACK_TOP - APP_STACK_SIZE;
+
+/// An application loaded into its own address space, ready to run.
+pub struct LoadedApp {
+    entry: VirtAddr,
+    stack_ptr: VirtAddr,
+    page_table: PageTable,
+    tls: Option<TlsBlock>,
+    _frames: Vec<GlobalPage>,
+}
+
+impl LoadedApp {
+    /// The entry point of the application.
+    pub fn entry(&self) -> VirtAddr {
+        self.entry
+    }
+
+    /// The initial stack pointer, which points to `argc`.
+    pub fn stack_ptr(&self) -> VirtAddr {
+        self.stack_ptr
+    }
+
+    /// The initial thread pointer if the application has a `PT_TLS` segment.
+    pub fn tls_ptr(&self) -> Option<usize> {
+        self.tls.as_ref().map(|tls| tls.tls_ptr())
+    }
+
+    /// The physical address of the root page table of the address space.
+    pub fn page_table_root(&self) -> PhysAddr {
+        self.page_table.root_paddr()
+    }
+
+    /// Switches to the application address space, sets up the thread
+    /// pointer and stack pointer, and jumps to the entry point.
+    ///
+    /// # Safety
+    ///
+    /// The loaded image must be a valid program for the current platform.
+    /// The caller must keep `self` alive as long as the application runs.
+    pub unsafe fn enter(&self) -> ! {
+        info!(
+            "enter application: entry={:#x}, sp={:#x}",
+            self.entry, self.stack_ptr
+        );
+        axhal::arch::write_page_table_root(self.page_table_root());
+        axhal::arch::flush_tlb(None);
+        if let Some(tp) = self.tls_ptr() {
+            axhal::arch::write_thread_pointer(tp);
+        }
+        jump_to_entry(self.entry.as_usize(), self.stack_ptr.as_usize())
+    }
+}
+
+/// Loads the ELF image in memory, with the given arguments and environment
+/// variables.
+pub fn load_elf(image: &[u8], args: &[&str], envs: &[&str]) -> AxResult<LoadedApp> {
+    let elf = ElfFile::parse(image)?;
+    if elf.file_type() != elf::ET_EXEC {
+        return ax_err!(Unsupported, "only static non-PIE executables are supported");
+    }
+    if elf
+        .program_headers()
+        .any(|ph| ph.p_type == elf::PT_INTERP || ph.p_type == elf::PT_DYNAMIC)
+    {
+        return ax_err!(
+            Unsupported,
+            "dynamically linked executables are not supported"
+        );
+    }
+
+    let mut page_table = PageTable::try_new().map_err(paging_err_to_ax_err)?;
+    map_kernel_memory(&mut page_table)?;
+
+    let mut frames = Vec::new();
+    let mut loaded = Vec::new();
+    let mut tls = None;
+    for ph in elf.program_headers() {
+        match ph.p_type {
+            elf::PT_LOAD => {
+                let data = elf.segment_data(&ph)?;
+                let range = segment_range(&ph)?;
+                frames.push(map_segment(&mut page_table, &ph, &range, data, &loaded)?);
+                loaded.push(range);
+            }
+            elf::PT_TLS => {
+                let template = TlsTemplate {
+                    vaddr: ph.p_vaddr as usize,
+                    file_size: ph.p_filesz as usize,
+                    mem_size: ph.p_memsz as usize,
+                    align: ph.p_align as usize,
+                };
+                tls = Some(TlsBlock::new(&template, elf.segment_data(&ph)?)?);
+            }
+            _ => {}
+        }
+    }
+
+    let mut auxv = Vec::new();
+    if let Some(phdr) = elf.phdr_vaddr() {
+        auxv.push((stack::AT_PHDR, phdr));
+    }
+    auxv.push((stack::AT_PHENT, elf.phent()));
+    auxv.push((stack::AT_PHNUM, elf.phnum()));
+    auxv.push((stack::AT_PAGESZ, PAGE_SIZE_4K));
+    auxv.push((stack::AT_BASE, 0));
+    auxv.push((stack::AT_FLAGS, 0));
+    auxv.push((stack::AT_ENTRY, elf.entry()));
+    let (stack_data, sp) = init_stack(APP_STACK_TOP, args, envs, &auxv, random_bytes());
+    if stack_data.len() > APP_STACK_SIZE {
+        return ax_err!(InvalidInput, "arguments too long");
+    }
+    frames.push(map_stack(&mut page_table, &stack_data)?);
+
+    debug!(
+        "loaded ELF: entry={:#x}, sp={:#x}, {} segments",
+        elf.entry(),
+        sp,
+        frames.len() - 1
+    );
+    Ok(LoadedApp {
+        entry: elf.entry().into(),
+        stack_ptr: sp.into(),
+        page_table,
+        tls,
+        _frames: frames,
+    })
+}
+
+/// Loads the ELF image from the file at `path`, with the given arguments
+/// and environment variables.
+#[cfg(feature = "fs")]
+pub fn load_elf_from_path(path: &str, args: &[&str], envs: &[&str]) -> AxResult<LoadedApp> {
+    let image = axfs::api::read(path)?;
+    load_elf(&image, args, envs)
+}
+
+fn map_kernel_memory(pt: &mut PageTable) -> AxResult {
+    for r in memory_regions() {
+        pt.map_region(phys_to_virt(r.paddr), r.paddr, r.size, r.flags.into(), true)
+            .map_err(paging_err_to_ax_err)?;
+    }
+    Ok(())
+}
+
+/// Returns the page-aligned range of the segment, which must be in
+/// [`APP_LOAD_RANGE`].
+fn segment_range(ph: &ProgramHeader) -> AxResult<Range<usize>> {
+    let end = ph
+        .p_vaddr
+        .checked_add(ph.p_memsz)
+        .and_then(|end| usize::try_from(end).ok())
+        .filter(|&end| end <= APP_LOAD_RANGE.end);
+    match end {
+        Some(end) if ph.p_vaddr as usize >= APP_LOAD_RANGE.start => {
+            Ok(align_down_4k(ph.p_vaddr as usize)..align_up_4k(end))
+        }
+        _ => ax_err!(InvalidData, "segment out of the user address range"),
+    }
+}
+
+/// Maps the segment to `range` (see [`segment_range`]). Its first or last
+/// page may be shared with a segment that is already mapped in one of the
+/// `loaded` ranges, but it must not overlap any other mapping.
+fn map_segment(
+    pt: &mut PageTable,
+    ph: &ProgramHeader,
+    range: &Range<usize>,
+    data: &[u8],
+    loaded: &[Range<usize>],
+) -> AxResult<GlobalPage> {
+    let vaddr = ph.p_vaddr as usize;
+    let (start, end) = (range.start, range.end);
+    let mut flags = MappingFlags::empty();
+    if ph.p_flags & elf::PF_R != 0 {
+        flags |= MappingFlags::READ;
+    }
+    if ph.p_flags & elf::PF_W != 0 {
+        flags |= MappingFlags::WRITE;
+    }
+    if ph.p_flags & elf::PF_X != 0 {
+        flags |= MappingFlags::EXECUTE;
+    }
+    debug!(
+        "map segment: [{:#x}, {:#x}) {:?}",
+        vaddr,
+        vaddr + ph.p_memsz as usize,
+        flags
+    );
+
+    let num_pages = (end - start) / PAGE_SIZE_4K;
+    for i in 0..num_pages {
+        let page_vaddr = start + i * PAGE_SIZE_4K;
+        if pt.query(page_vaddr.into()).is_ok() && !loaded.iter().any(|r| r.contains(&page_vaddr)) {
+            return ax_err!(InvalidData, "segment overlaps an existing mapping");
+        }
+    }
+    let mut frames = GlobalPage::alloc_contiguous(num_pages, PAGE_SIZE_4K)?;
+    frames.zero();
+    let paddr = frames.start_paddr(virt_to_phys);
+
+    // The first or last page may be shared with another segment: take over
+    // its content and merge the permissions. It's always a 4K page mapped by
+    // the loader, as checked above.
+    let mut new_pages = Vec::with_capacity(num_pages);
+    for i in 0..num_pages {
+        let page_vaddr = VirtAddr::from(start + i * PAGE_SIZE_4K);
+        if let Ok((old_paddr, old_flags, _)) = pt.query(page_vaddr) {
+            let old = unsafe {
+                core::slice::from_raw_parts(phys_to_virt(old_paddr).as_ptr(), PAGE_SIZE_4K)
+            };
+            let off = i * PAGE_SIZE_4K;
+            frames.as_slice_mut()[off..off + PAGE_SIZE_4K].copy_from_slice(old);
+            pt.update(page_vaddr, Some(paddr + off), Some(old_flags | flags))
+                .map_err(paging_err_to_ax_err)?;
+        } else {
+            new_pages.push(i);
+        }
+    }
+
+    let off = vaddr - start;
+    let seg = &mut frames.as_slice_mut()[off..off + ph.p_memsz as usize];
+    seg[..data.len()].copy_from_slice(data);
+    seg[data.len()..].fill(0);
+
+    for i in new_pages {
+        let off = i * PAGE_SIZE_4K;
+        pt.map((start + off).into(), paddr + off, PageSize::Size4K, flags)
+            .map_err(paging_err_to_ax_err)?;
+    }
+    Ok(frames)
+}
+
+fn map_stack(pt: &mut PageTable, init_data: &[u8]) -> AxResult<GlobalPage> {
+    let num_pages = APP_STACK_SIZE / PAGE_SIZE_4K;
+    let mut frames = GlobalPage::alloc_contiguous(num_pages, PAGE_SIZE_4K)?;
+    frames.zero();
+    let size = frames.size();
+    frames.as_slice_mut()[size - init_data.len()..].copy_from_slice(init_data);
+    pt.map_region(
+        VirtAddr::from(APP_STACK_TOP - APP_STACK_SIZE),
+        frames.start_paddr(virt_to_phys),
+        APP_STACK_SIZE,
+        MappingFlags::READ | MappingFlags::WRITE,
+        false,
+    )
+    .map_err(paging_err_to_ax_err)?;
+    Ok(frames)
+}
+
+fn random_bytes() -> [u8; 16] {
+    let mut seed = axhal::time::current_time_nanos() | 1;
+    let mut bytes = [0; 16];
+    for b in bytes.iter_mut() {
+        // xorshift64
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        *b = seed as u8;
+    }
+    bytes
+}
+
+fn paging_err_to_ax_err(e: PagingError) -> AxError {
+    match e {
+        PagingError::NoMemory => ax_err_type!(NoMemory),
+        PagingError::AlreadyMapped => ax_err_type!(AlreadyExists, "overlapped mapping"),
+        _ => ax_err_type!(InvalidInput, "bad mapping"),
+    }
+}
+
+unsafe fn jump_to_entry(entry: usize, sp: usize) -> ! {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "x86_64")] {
+            core::arch::asm!("
+                mov     rsp, {sp}
+                xor     rdx, rdx
+                jmp     {entry}",
+                sp = in(reg) sp,
+                entry = in(reg) entry,
+                options(noreturn),
+            )
+        } else if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
+            core::arch::asm!("
+                mv      sp, {sp}
+                li      a0, 0
+                jr      {entry}",
+                sp = in(reg) sp,
+                entry = in(reg) entry,
+                options(noreturn),
+            )
+        } else if #[cfg(target_arch = "aarch64")] {
+            core::arch::asm!("
+                mov     sp, {sp}
+                mov     x0, xzr
+                br      {entry}",
+                sp = in(reg) sp,
+                entry = in(reg) entry,
+                options(noreturn),
+            )
+        }
+    }
+}
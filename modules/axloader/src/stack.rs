@@ -0,0 +1,93 @@
+//! Initial process stack layout (System V ABI).
+//!
+//! ```text
+//!  stack_top -> +----------------------+
+//!               | argv/envp strings    |
+//!               | AT_RANDOM bytes (16) |
+//!               | padding              |
+//!               +----------------------+
+//!               | AT_NULL              |
+//!               | auxv pairs ...       |
+//!               | NULL                 |
+//!               | envp[n] ...          |
+//!               | NULL                 |
+//!               | argv[n] ...          |
+//!         sp -> | argc                 |
+//!               +----------------------+
+//! ```
+
+use alloc::vec::Vec;
+
+/// End of the auxiliary vector.
+pub const AT_NULL: usize = 0;
+/// Program headers of the program.
+pub const AT_PHDR: usize = 3;
+/// Size of one program header entry.
+pub const AT_PHENT: usize = 4;
+/// Number of program headers.
+pub const AT_PHNUM: usize = 5;
+/// System page size.
+pub const AT_PAGESZ: usize = 6;
+/// Base address of the interpreter.
+pub const AT_BASE: usize = 7;
+/// Flags.
+pub const AT_FLAGS: usize = 8;
+/// Entry point of the program.
+pub const AT_ENTRY: usize = 9;
+/// Address of 16 random bytes.
+pub const AT_RANDOM: usize = 25;
+
+const STACK_ALIGN: usize = 16;
+const USIZE: usize = core::mem::size_of::<usize>();
+
+/// Builds the initial stack content for a process.
+///
+/// Returns the bytes to be placed at `[sp, stack_top)` and the resulting
+/// stack pointer. An `AT_RANDOM` entry pointing to `random` is appended to
+/// `auxv` automatically, and the vector is terminated by `AT_NULL`.
+pub fn init_stack(
+    stack_top: usize,
+    args: &[&str],
+    envs: &[&str],
+    auxv: &[(usize, usize)],
+    random: [u8; 16],
+) -> (Vec<u8>, usize) {
+    // Strings and random bytes, growing down from `stack_top`.
+    let mut strings = Vec::new();
+    let mut push_str = |s: &str| {
+        strings.extend_from_slice(s.as_bytes());
+        strings.push(0);
+        strings.len() - s.len() - 1
+    };
+    let arg_offs: Vec<usize> = args.iter().map(|s| push_str(s)).collect();
+    let env_offs: Vec<usize> = envs.iter().map(|s| push_str(s)).collect();
+    let random_off = strings.len();
+    strings.extend_from_slice(&random);
+
+    let strings_base = stack_top - strings.len();
+    let words_len = 1 + (args.len() + 1) + (envs.len() + 1) + (auxv.len() + 2) * 2;
+    let sp = (strings_base - words_len * USIZE) & !(STACK_ALIGN - 1);
+
+    let mut words = Vec::with_capacity(words_len);
+    words.push(args.len());
+    words.extend(arg_offs.iter().map(|off| strings_base + off));
+    words.push(0);
+    words.extend(env_offs.iter().map(|off| strings_base + off));
+    words.push(0);
+    for &(key, val) in auxv {
+        words.push(key);
+        words.push(val);
+    }
+    words.push(AT_RANDOM);
+    words.push(strings_base + random_off);
+    words.push(AT_NULL);
+    words.push(0);
+
+    let mut data = Vec::with_capacity(stack_top - sp);
+    for w in words {
+        data.extend_from_slice(&w.to_ne_bytes());
+    }
+    data.resize(strings_base - sp, 0);
+    data.extend_from_slice(&strings);
+    (data, sp)
+}
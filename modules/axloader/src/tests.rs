@@ -0,0 +1,118 @@
+use crate::elf::{ElfFile, PF_R, PF_X, PT_LOAD, PT_TLS};
+use crate::stack::{AT_NULL, AT_PAGESZ, AT_RANDOM};
+use crate::{init_stack, segment_range};
+
+const EM_X86_64: u16 = 62;
+
+fn build_elf(phdrs: &[(u32, u32, u64, u64, u64, u64)]) -> Vec<u8> {
+    let mut data = vec![0u8; 64];
+    data[..4].copy_from_slice(b"\x7fELF");
+    data[4] = 2; // ELFCLASS64
+    data[5] = 1; // ELFDATA2LSB
+    data[6] = 1; // EV_CURRENT
+    data[16..18].copy_from_slice(&2u16.to_le_bytes()); // ET_EXEC
+    data[18..20].copy_from_slice(&EM_X86_64.to_le_bytes());
+    data[24..32].copy_from_slice(&0x40_1000u64.to_le_bytes()); // e_entry
+    data[32..40].copy_from_slice(&64u64.to_le_bytes()); // e_phoff
+    data[54..56].copy_from_slice(&56u16.to_le_bytes()); // e_phentsize
+    data[56..58].copy_from_slice(&(phdrs.len() as u16).to_le_bytes()); // e_phnum
+    for &(p_type, p_flags, p_offset, p_vaddr, p_filesz, p_memsz) in phdrs {
+        data.extend_from_slice(&p_type.to_le_bytes());
+        data.extend_from_slice(&p_flags.to_le_bytes());
+        data.extend_from_slice(&p_offset.to_le_bytes());
+        data.extend_from_slice(&p_vaddr.to_le_bytes());
+        data.extend_from_slice(&p_vaddr.to_le_bytes()); // p_paddr
+        data.extend_from_slice(&p_filesz.to_le_bytes());
+        data.extend_from_slice(&p_memsz.to_le_bytes());
+        data.extend_from_slice(&0x1000u64.to_le_bytes()); // p_align
+    }
+    data.resize(0x200, 0xaa);
+    data
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_parse_elf() {
+    let data = build_elf(&[
+        (PT_LOAD, PF_R | PF_X, 0, 0x40_0000, 0x200, 0x200),
+        (PT_TLS, PF_R, 0x100, 0x40_0100, 0x10, 0x20),
+    ]);
+    let elf = ElfFile::parse(&data).unwrap();
+    assert_eq!(elf.entry(), 0x40_1000);
+    assert_eq!(elf.phnum(), 2);
+
+    let phdrs: Vec<_> = elf.program_headers().collect();
+    assert_eq!(phdrs[0].p_type, PT_LOAD);
+    assert_eq!(phdrs[0].p_vaddr, 0x40_0000);
+    assert_eq!(phdrs[1].p_type, PT_TLS);
+    assert_eq!(elf.segment_data(&phdrs[1]).unwrap(), &data[0x100..0x110]);
+    // program headers are covered by the first segment
+    assert_eq!(elf.phdr_vaddr(), Some(0x40_0040));
+
+    let data = build_elf(&[(PT_LOAD, PF_R, 0x100, 0x40_0000, 0x1000, 0x1000)]);
+    let elf = ElfFile::parse(&data).unwrap();
+    let ph = elf.program_headers().next().unwrap();
+    assert!(elf.segment_data(&ph).is_err()); // beyond the end of file
+}
+
+#[test]
+fn test_parse_bad_elf() {
+    assert!(ElfFile::parse(b"not an elf").is_err());
+
+    let mut data = build_elf(&[]);
+    data[4] = 1; // ELFCLASS32
+    assert!(ElfFile::parse(&data).is_err());
+
+    // the program header table wraps around
+    let mut data = build_elf(&[]);
+    data[32..40].copy_from_slice(&u64::MAX.to_le_bytes()); // e_phoff
+    data[56..58].copy_from_slice(&1u16.to_le_bytes()); // e_phnum
+    assert!(ElfFile::parse(&data).is_err());
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_bad_segments() {
+    let data = build_elf(&[
+        (PT_LOAD, PF_R, u64::MAX, 0x40_0000, 0x10, 0x10),
+        (PT_LOAD, PF_R, 0, u64::MAX - 0xf, 0x10, 0x20),
+        (PT_LOAD, PF_R, 0, 0xffff_8000_0000_0000, 0x10, 0x10),
+        (PT_LOAD, PF_R, 0, 0, 0x10, 0x10),
+        (PT_LOAD, PF_R, 0, 0x40_0000, 0x10, 0x10),
+    ]);
+    let elf = ElfFile::parse(&data).unwrap();
+    let phdrs: Vec<_> = elf.program_headers().collect();
+    // file offset wraps around
+    assert!(elf.segment_data(&phdrs[0]).is_err());
+    // virtual address wraps around, in the kernel space, in the null page
+    for ph in &phdrs[1..4] {
+        assert!(segment_range(ph).is_err());
+    }
+    assert_eq!(segment_range(&phdrs[4]).unwrap(), 0x40_0000..0x40_1000);
+}
+
+#[test]
+fn test_init_stack() {
+    let top = 0x8000_0000;
+    let random = [7u8; 16];
+    let (data, sp) = init_stack(top, &["app", "-v"], &["A=1"], &[(AT_PAGESZ, 4096)], random);
+    assert_eq!(sp % 16, 0);
+    assert_eq!(sp + data.len(), top);
+
+    let word = |i: usize| usize::from_ne_bytes(data[i * 8..i * 8 + 8].try_into().unwrap());
+    let string_at = |addr: usize| {
+        let bytes = &data[addr - sp..];
+        let len = bytes.iter().position(|&b| b == 0).unwrap();
+        core::str::from_utf8(&bytes[..len]).unwrap()
+    };
+    assert_eq!(word(0), 2); // argc
+    assert_eq!(string_at(word(1)), "app");
+    assert_eq!(string_at(word(2)), "-v");
+    assert_eq!(word(3), 0);
+    assert_eq!(string_at(word(4)), "A=1");
+    assert_eq!(word(5), 0);
+    assert_eq!((word(6), word(7)), (AT_PAGESZ, 4096));
+    assert_eq!(word(8), AT_RANDOM);
+    assert_eq!(&data[word(9) - sp..word(9) - sp + 16], &random);
+    assert_eq!((word(10), word(11)), (AT_NULL, 0));
+}
@@ -0,0 +1,96 @@
+//! Static TLS block for a loaded application, built from its `PT_TLS` segment.
+//!
+//! The layout is the same as the kernel's own TLS area (see [`axhal::tls`]),
+//! i.e. variant II on x86_64 and variant I on AArch64 and RISC-V.
+
+use core::alloc::Layout;
+use core::ptr::NonNull;
+
+use axerrno::{ax_err, AxResult};
+use memory_addr::align_up;
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        const TCB_SIZE: usize = 8; // to store TLS self pointer
+        const GAP_ABOVE_TP: usize = 0;
+    } else if #[cfg(target_arch = "aarch64")] {
+        const TCB_SIZE: usize = 0;
+        const GAP_ABOVE_TP: usize = 16;
+    } else if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
+        const TCB_SIZE: usize = 0;
+        const GAP_ABOVE_TP: usize = 0;
+    }
+}
+
+/// The TLS initialization image described by a `PT_TLS` segment.
+#[derive(Debug, Clone, Copy)]
+pub struct TlsTemplate {
+    /// Virtual address of the `.tdata` image in the application.
+    pub vaddr: usize,
+    /// Size of the `.tdata` image.
+    pub file_size: usize,
+    /// Total size of `.tdata` and `.tbss`.
+    pub mem_size: usize,
+    /// Required alignment of the TLS block.
+    pub align: usize,
+}
+
+/// An allocated and initialized static TLS block.
+pub struct TlsBlock {
+    base: NonNull<u8>,
+    layout: Layout,
+    tp_offset: usize,
+}
+
+impl TlsBlock {
+    /// Allocates a TLS block for `template`, and copies the `.tdata` image
+    /// from `tdata` into it.
+    pub fn new(template: &TlsTemplate, tdata: &[u8]) -> AxResult<Self> {
+        let align = template.align.max(TCB_SIZE.max(1));
+        let (align, tls_size) = match align.checked_next_power_of_two() {
+            Some(align) if template.mem_size <= isize::MAX as usize - align => {
+                (align, align_up(template.mem_size, align))
+            }
+            _ => return ax_err!(InvalidData, "bad TLS segment size"),
+        };
+        let (static_tls_offset, tp_offset, area_size) = if cfg!(target_arch = "x86_64") {
+            (0, tls_size, tls_size + TCB_SIZE)
+        } else {
+            let offset = align_up(TCB_SIZE + GAP_ABOVE_TP, align);
+            (offset, offset - GAP_ABOVE_TP, offset + tls_size)
+        };
+
+        let layout = match Layout::from_size_align(area_size.max(1), align) {
+            Ok(layout) => layout,
+            Err(_) => return ax_err!(InvalidData, "bad TLS alignment"),
+        };
+        let base = match NonNull::new(unsafe { alloc::alloc::alloc_zeroed(layout) }) {
+            Some(base) => base,
+            None => return ax_err!(NoMemory),
+        };
+        unsafe {
+            let tls = base.as_ptr().add(static_tls_offset);
+            core::ptr::copy_nonoverlapping(tdata.as_ptr(), tls, tdata.len());
+            if cfg!(target_arch = "x86_64") {
+                let tp = base.as_ptr().add(tp_offset).cast::<usize>();
+                tp.write(tp as usize); // write self pointer
+            }
+        }
+        Ok(Self {
+            base,
+            layout,
+            tp_offset,
+        })
+    }
+
+    /// Returns the value for the thread pointer register.
+    pub fn tls_ptr(&self) -> usize {
+        self.base.as_ptr() as usize + self.tp_offset
+    }
+}
+
+impl Drop for TlsBlock {
+    fn drop(&mut self) {
+        unsafe { alloc::alloc::dealloc(self.base.as_ptr(), self.layout) }
+    }
+}
@@ -4,20 +4,65 @@
 //! using various underlying network stacks. Currently, only [smoltcp] is
 //! supported.
 //!
+//! Both IPv4 and IPv6 are supported. The IPv4 address is configured statically
+//! or by DHCP. The interface always has an IPv6 link-local address, and the
+//! global IPv6 address is configured statically (`AX_IP6` and `AX_GW6`
+//! environment variables at compile time) or by SLAAC.
+//!
+//! Every NIC becomes an interface (`eth0`, `eth1`, ...), besides the loopback
+//! interface `lo` (`127.0.0.1/8` and `::1/128`). The egress interface of a
+//! socket is chosen by a routing table, which holds the networks of the
+//! interface addresses and the default routes via the gateways. A socket bound
+//! to an interface address always uses that interface. The static
+//! configuration only applies to `eth0`, and DHCP and SLAAC run on every NIC.
+//!
+//! The MTU of an interface is reported by its NIC driver (65536 for `lo`).
+//! Larger IPv4 packets, up to 64 KiB, are fragmented when sent and reassembled
+//! when received, with at most 4 datagrams being reassembled at once.
+//!
+//! The socket buffers and the packets queued on `lo` are charged to the `net`
+//! allocation zone of axalloc ([`axalloc::NET_ZONE`]), whose limit is
+//! `net-zone-limit` in the platform configuration. Creating a socket fails
+//! with [`NoMemory`](axerrno::AxError::NoMemory) once the limit is reached.
+//!
+//! A NIC may have multiple queues (e.g., ixgbe with one queue for each CPU),
+//! and spreads the received packets over them by receive-side scaling. Each
+//! CPU is bound to a queue, and services it with a lock of that queue only:
+//! the received packets are moved to a backlog of the queue, and the
+//! transmitted buffers are given back, so the CPUs service their queues in
+//! parallel. The packets are then processed by smoltcp under the lock of the
+//! interface, and transmitted on the queue of the CPU. A CPU also services the
+//! queues that no other CPU is servicing, as their CPUs may not poll.
+//!
 //! # Organization
 //!
 //! - [`TcpSocket`]: A TCP socket that provides POSIX-like APIs.
 //! - [`UdpSocket`]: A UDP socket that provides POSIX-like APIs.
+//! - [`IcmpSocket`]: An ICMP echo (ping) socket that provides POSIX-like APIs.
+//! - [`RawSocket`]: A raw IP socket that provides POSIX-like APIs.
 //! - [`dns_query`]: Function for DNS query.
 //!
 //! # Cargo Features
 //!
 //! - `smoltcp`: Use [smoltcp] as the underlying network stack. This is enabled
 //!   by default.
+//! - `dhcp`: Configure the interface address, gateway and DNS servers by
+//!   DHCPv4. The static configuration (`AX_IP` and `AX_GW` environment
+//!   variables at compile time) is used if no lease is acquired in time.
+//! - `irq` and `multitask`: Receive packets by NIC interrupts. A softirq task
+//!   polls the interfaces on interrupts and smoltcp timers, and the blocked
+//!   sockets sleep until they are ready, instead of polling by themselves.
+//!   NICs without an IRQ are polled by the task every millisecond.
+//! - `pcap`: Capture the packets of the NICs in the pcap format, see [`pcap`].
+//! - `hotplug`: Accept the NICs added after boot. Four more interfaces are
+//!   created without NICs, a NIC added after boot is attached to the first
+//!   free one, and its addresses are configured like at boot. When the NIC is
+//!   removed, the interface loses its addresses and routes.
 //!
 //! [smoltcp]: https://github.com/smoltcp-rs/smoltcp
 
 #![no_std]
+#![feature(allocator_api)]
 #![feature(ip_in_core)]
 #![feature(new_uninit)]
 
@@ -36,14 +81,28 @@ pub use self::net_impl::TcpSocket;
 pub use self::net_impl::UdpSocket;
 pub use self::net_impl::{bench_receive, bench_transmit};
 pub use self::net_impl::{dns_query, poll_interfaces};
+pub use self::net_impl::{IcmpSocket, RawSocket};
+
+#[cfg(feature = "pcap")]
+pub use self::net_impl::pcap;
 
+use alloc::vec::Vec;
 use axdriver::{prelude::*, AxDeviceContainer};
 
 /// Initializes the network subsystem by NIC devices.
+///
+/// Each NIC becomes an interface `eth<N>`, and the loopback interface `lo` is
+/// always created, even if there are no NICs.
 pub fn init_network(mut net_devs: AxDeviceContainer<AxNetDevice>) {
     info!("Initialize network subsystem...");
 
-    let dev = net_devs.take_one().expect("No NIC device found!");
-    info!("  use NIC 0: {:?}", dev.device_name());
-    net_impl::init(dev);
+    let mut devs = Vec::new();
+    while let Some(dev) = net_devs.take_one() {
+        info!("  use NIC {}: {:?}", devs.len(), dev.device_name());
+        devs.push(dev);
+    }
+    if devs.is_empty() {
+        warn!("No NIC device found, only the loopback interface is available");
+    }
+    net_impl::init(devs);
 }
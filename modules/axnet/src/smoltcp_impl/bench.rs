@@ -7,7 +7,7 @@ const MB: usize = 1000 * KB;
 const KB: usize = 1000;
 
 impl DeviceWrapper {
-    pub fn bench_transmit_bandwidth(&mut self) {
+    pub fn bench_transmit_bandwidth(&self) {
         // 10 Gb
         const MAX_SEND_BYTES: usize = 10 * GB;
         let mut send_bytes: usize = 0;
@@ -16,7 +16,8 @@ impl DeviceWrapper {
 
         // Send bytes
         while send_bytes < MAX_SEND_BYTES {
-            if let Some(tx_token) = self.transmit(InterfaceWrapper::current_time()) {
+            let mut dev = self.queue(self.this_cpu_queue());
+            if let Some(tx_token) = dev.transmit(InterfaceWrapper::current_time()) {
                 AxNetTxToken::consume(tx_token, STANDARD_MTU, |tx_buf| {
                     tx_buf[0..12].fill(1);
                     // ether type: IPv4
@@ -42,7 +43,7 @@ impl DeviceWrapper {
         }
     }
 
-    pub fn bench_receive_bandwidth(&mut self) {
+    pub fn bench_receive_bandwidth(&self) {
         // 10 Gb
         const MAX_RECEIVE_BYTES: usize = 10 * GB;
         let mut receive_bytes: usize = 0;
@@ -50,7 +51,8 @@ impl DeviceWrapper {
         let mut past_time = InterfaceWrapper::current_time();
         // Receive bytes
         while receive_bytes < MAX_RECEIVE_BYTES {
-            if let Some(rx_token) = self.receive(InterfaceWrapper::current_time()) {
+            let mut dev = self.service_queues();
+            if let Some(rx_token) = dev.receive(InterfaceWrapper::current_time()) {
                 AxNetRxToken::consume(rx_token.0, |rx_buf| {
                     receive_bytes += rx_buf.len();
                 });
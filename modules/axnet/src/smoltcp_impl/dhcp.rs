@@ -0,0 +1,134 @@
+//! DHCPv4 client for automatic interface configuration.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use lazy_init::LazyInit;
+use smoltcp::socket::dhcpv4::{self, Event};
+use smoltcp::wire::{IpAddress, IpCidr, IpVersion, Ipv4Address, Ipv4Cidr};
+
+use super::{InterfaceWrapper, SocketHandle, SOCKET_SET};
+
+/// How long to wait for the first leases before falling back to the static
+/// configuration.
+const DHCP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The DHCP client of an interface.
+pub(crate) struct DhcpClient {
+    handle: LazyInit<SocketHandle>,
+    configured: AtomicBool,
+}
+
+/// A lease copied out of the DHCP socket.
+struct Lease {
+    address: Ipv4Cidr,
+    router: Option<Ipv4Address>,
+    dns_servers: Vec<IpAddress>,
+}
+
+impl DhcpClient {
+    pub const fn new() -> Self {
+        Self {
+            handle: LazyInit::new(),
+            configured: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the interface is configured by a lease.
+    pub fn is_configured(&self) -> bool {
+        self.configured.load(Ordering::Acquire)
+    }
+}
+
+/// Starts the DHCP clients on `ifaces`, and waits for their first leases.
+///
+/// The clients that get no lease in time keep running, and their interfaces
+/// will be reconfigured once a lease is acquired later.
+pub(crate) fn init(ifaces: &[InterfaceWrapper]) {
+    for iface in ifaces {
+        let handle = SOCKET_SET.add(iface.index, dhcpv4::Socket::new());
+        iface.dhcp.handle.init_by(handle);
+    }
+
+    // the interfaces without NICs get leases after the NICs are added
+    let pending = || {
+        ifaces
+            .iter()
+            .filter(|iface| iface.ethernet_address().is_some() && !iface.dhcp.is_configured())
+    };
+    let deadline = axhal::time::current_time() + DHCP_TIMEOUT;
+    while axhal::time::current_time() < deadline {
+        SOCKET_SET.poll_interfaces();
+        if pending().next().is_none() {
+            return;
+        }
+        axtask::yield_now();
+    }
+    for iface in pending() {
+        warn!(
+            "DHCP: no lease for {:?} within {:?}",
+            iface.name(),
+            DHCP_TIMEOUT
+        );
+    }
+}
+
+/// Restarts the DHCP client of `iface`, e.g. when its NIC is replaced.
+#[cfg(feature = "hotplug")]
+pub(crate) fn restart(iface: &InterfaceWrapper) {
+    let Some(&handle) = iface.dhcp.handle.try_get() else {
+        return;
+    };
+    SOCKET_SET.with_socket_mut::<dhcpv4::Socket, _, _>(handle, |socket| socket.reset());
+    iface.dhcp.configured.store(false, Ordering::Release);
+}
+
+/// Handles the events of the DHCP client of `iface`.
+///
+/// It's called after each poll of the interface, as lease renewal and
+/// rebinding are driven by the polling.
+pub(crate) fn poll(iface: &InterfaceWrapper) {
+    let Some(&handle) = iface.dhcp.handle.try_get() else {
+        return;
+    };
+    let event = SOCKET_SET.with_socket_mut::<dhcpv4::Socket, _, _>(handle, |socket| {
+        socket.poll().map(|event| match event {
+            Event::Configured(config) => Some(Lease {
+                address: config.address,
+                router: config.router,
+                dns_servers: config
+                    .dns_servers
+                    .iter()
+                    .map(|&s| IpAddress::Ipv4(s))
+                    .collect(),
+            }),
+            Event::Deconfigured => None,
+        })
+    });
+
+    match event {
+        None => {}
+        Some(Some(lease)) => {
+            info!("DHCP: leased {} on {:?}", lease.address, iface.name());
+            iface.setup_ip_addr(IpCidr::Ipv4(lease.address));
+            match lease.router {
+                Some(router) => {
+                    info!("  gateway:  {}", router);
+                    iface.setup_gateway(IpAddress::Ipv4(router));
+                }
+                None => iface.remove_gateway(IpVersion::Ipv4),
+            }
+            if !lease.dns_servers.is_empty() {
+                info!("  dns:      {:?}", lease.dns_servers);
+                super::setup_dns_servers(&lease.dns_servers);
+            }
+            iface.dhcp.configured.store(true, Ordering::Release);
+        }
+        Some(None) => {
+            warn!("DHCP: lease lost on {:?}", iface.name());
+            iface.dhcp.configured.store(false, Ordering::Release);
+            super::setup_static_config(iface);
+        }
+    }
+}
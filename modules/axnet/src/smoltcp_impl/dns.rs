@@ -2,12 +2,12 @@ use alloc::vec::Vec;
 use axerrno::{ax_err_type, AxError, AxResult};
 use core::net::IpAddr;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::dns::{self, GetQueryResultError, StartQueryError};
 use smoltcp::wire::DnsQueryType;
 
 use super::addr::into_core_ipaddr;
-use super::{SocketSetWrapper, ETH0, SOCKET_SET};
+use super::wait::{self, SocketWaiter};
+use super::{dns_servers, SocketHandle, SocketSetWrapper, ROUTES, SOCKET_SET};
 
 /// A DNS socket.
 struct DnsSocket {
@@ -15,12 +15,16 @@ struct DnsSocket {
 }
 
 impl DnsSocket {
-    #[allow(clippy::new_without_default)]
-    /// Creates a new DNS socket.
-    pub fn new() -> Self {
+    /// Creates a new DNS socket on the interface that routes to the first
+    /// reachable DNS server.
+    pub fn new() -> AxResult<Self> {
+        let iface = dns_servers()
+            .into_iter()
+            .find_map(|server| ROUTES.lookup(server))
+            .ok_or_else(|| ax_err_type!(ConnectionRefused, "DNS server unreachable"))?;
         let socket = SocketSetWrapper::new_dns_socket();
-        let handle = Some(SOCKET_SET.add(socket));
-        Self { handle }
+        let handle = Some(SOCKET_SET.add(iface, socket));
+        Ok(Self { handle })
     }
 
     #[allow(dead_code)]
@@ -35,7 +39,7 @@ impl DnsSocket {
     pub fn query(&self, name: &str, query_type: DnsQueryType) -> AxResult<Vec<IpAddr>> {
         // let local_addr = self.local_addr.unwrap_or_else(f);
         let handle = self.handle.ok_or_else(|| ax_err_type!(InvalidInput))?;
-        let iface = &ETH0.iface;
+        let iface = &handle.iface().iface;
         let query_handle = SOCKET_SET
             .with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
                 socket.start_query(iface.lock().context(), name, query_type)
@@ -51,27 +55,21 @@ impl DnsSocket {
                     ax_err_type!(InvalidInput, "socket query() failed: too long name")
                 }
             })?;
-        loop {
-            SOCKET_SET.poll_interfaces();
-            match SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
+        wait::kick();
+
+        let waiter = SocketWaiter::new();
+        let n = waiter.block_on(false, None, || {
+            SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
+                waiter.register(|waker| socket.register_query_waker(query_handle, waker));
                 socket.get_query_result(query_handle).map_err(|e| match e {
                     GetQueryResultError::Pending => AxError::WouldBlock,
                     GetQueryResultError::Failed => {
                         ax_err_type!(ConnectionRefused, "socket query() failed")
                     }
                 })
-            }) {
-                Ok(n) => {
-                    let mut res = Vec::with_capacity(n.capacity());
-                    for ip in n {
-                        res.push(into_core_ipaddr(ip))
-                    }
-                    return Ok(res);
-                }
-                Err(AxError::WouldBlock) => axtask::yield_now(),
-                Err(e) => return Err(e),
-            }
-        }
+            })
+        })?;
+        Ok(n.into_iter().map(into_core_ipaddr).collect())
     }
 }
 
@@ -84,7 +82,19 @@ impl Drop for DnsSocket {
 }
 
 /// Public function for DNS query.
+///
+/// It queries both the A and AAAA records, and returns the IPv4 addresses
+/// followed by the IPv6 addresses. It fails only if both queries fail.
 pub fn dns_query(name: &str) -> AxResult<alloc::vec::Vec<IpAddr>> {
-    let socket = DnsSocket::new();
-    socket.query(name, DnsQueryType::A)
+    let socket = DnsSocket::new()?;
+    let v4 = socket.query(name, DnsQueryType::A);
+    let v6 = socket.query(name, DnsQueryType::Aaaa);
+    match (v4, v6) {
+        (Ok(mut v4), Ok(v6)) => {
+            v4.extend(v6);
+            Ok(v4)
+        }
+        (Ok(res), Err(_)) | (Err(_), Ok(res)) => Ok(res),
+        (Err(e), Err(_)) => Err(e),
+    }
 }
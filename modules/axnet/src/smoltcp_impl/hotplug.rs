@@ -0,0 +1,165 @@
+//! NICs added or removed after boot.
+//!
+//! There are [`HOTPLUG_IFACES`] more interfaces than the NICs at boot, which
+//! are created without NICs. A NIC added after boot is attached to the first
+//! interface without a NIC, and detached when it's removed.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use axdriver::hotplug::{DeviceId, DeviceNotifier};
+use axdriver::{prelude::*, AxDeviceEnum};
+use axsync::Mutex;
+use smoltcp::iface::{Config, Interface, SocketSet};
+use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
+use smoltcp::time::Instant;
+use smoltcp::wire::{EthernetAddress, HardwareAddress, IpVersion, ETHERNET_HEADER_LEN};
+
+use super::{ipv6, nics, DeviceWrapper, InterfaceWrapper, NetDevice, ROUTES, STANDARD_MTU};
+
+/// The number of interfaces for the NICs added after boot.
+pub(super) const HOTPLUG_IFACES: usize = 4;
+
+/// The interfaces that have NICs added after boot, indexed like `IFACES`.
+static ATTACHED: Mutex<Vec<(DeviceId, usize)>> = Mutex::new(Vec::new());
+
+/// The device of an interface without a NIC, which drops all packets.
+pub(super) struct DetachedDev;
+
+/// The token of [`DetachedDev`], which is never created.
+pub(super) enum NoToken {}
+
+impl Device for DetachedDev {
+    type RxToken<'a> = NoToken;
+    type TxToken<'a> = NoToken;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        None
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        None
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.max_transmission_unit = STANDARD_MTU + ETHERNET_HEADER_LEN;
+        caps.max_burst_size = None;
+        caps.medium = Medium::Ethernet;
+        caps
+    }
+}
+
+impl RxToken for NoToken {
+    fn preprocess(&self, _sockets: &mut SocketSet<'_>) {
+        match *self {}
+    }
+
+    fn consume<R, F>(self, _f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        match self {}
+    }
+}
+
+impl TxToken for NoToken {
+    fn consume<R, F>(self, _len: usize, _f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        match self {}
+    }
+}
+
+impl InterfaceWrapper {
+    /// Creates an interface without a NIC.
+    pub(super) fn new_detached(name: String, index: usize) -> Self {
+        let mut config = Config::new(HardwareAddress::Ethernet(EthernetAddress::default()));
+        config.random_seed = axhal::random::random_u64();
+
+        let mut dev = DetachedDev;
+        let iface = Interface::new(config, &mut dev, Self::current_time());
+        Self::from_parts(name, index, None, NetDevice::Detached(dev), iface)
+    }
+
+    /// Attaches the NIC to the interface, and configures the addresses like
+    /// the NICs at boot.
+    fn attach(&self, dev: AxNetDevice) {
+        let ether_addr = EthernetAddress(dev.mac_address().0);
+        *self.dev.lock() = NetDevice::Nic(Arc::new(DeviceWrapper::new(dev, self.index)));
+        self.iface
+            .lock()
+            .set_hardware_addr(HardwareAddress::Ethernet(ether_addr));
+        *self.ether_addr.lock() = Some(ether_addr);
+
+        info!("attached a NIC to net interface {:?}:", self.name());
+        info!("  ether:    {}", ether_addr);
+        info!("  mtu:      {}", self.mtu());
+        #[cfg(all(feature = "irq", feature = "multitask"))]
+        super::wait::register_hotplug_irq(self);
+        ipv6::init(self);
+        #[cfg(feature = "dhcp")]
+        super::dhcp::restart(self);
+    }
+
+    /// Drops the NIC of the interface, and removes its addresses and routes.
+    fn detach(&self) {
+        *self.dev.lock() = NetDevice::Detached(DetachedDev);
+        *self.ether_addr.lock() = None;
+        self.slaac.reset();
+        self.remove_gateway(IpVersion::Ipv4);
+        self.remove_gateway(IpVersion::Ipv6);
+        self.iface.lock().update_ip_addrs(|ip_addrs| {
+            for cidr in ip_addrs.iter() {
+                ROUTES.remove(*cidr, self.index);
+            }
+            ip_addrs.clear();
+        });
+        info!("detached the NIC from net interface {:?}", self.name());
+    }
+}
+
+struct NetNotifier;
+
+impl DeviceNotifier for NetNotifier {
+    fn device_added(&self, id: DeviceId, dev: AxDeviceEnum) {
+        #[allow(unreachable_patterns)] // if NICs are the only devices
+        let dev = match dev {
+            AxDeviceEnum::Net(dev) => dev,
+            _ => return,
+        };
+        let mut attached = ATTACHED.lock();
+        let Some(iface) = nics().iter().find(|iface| {
+            iface.ethernet_address().is_none()
+                && attached.iter().all(|&(_, index)| index != iface.index)
+        }) else {
+            warn!(
+                "NIC {:?} is not added, no free interface",
+                dev.device_name()
+            );
+            return;
+        };
+        attached.push((id, iface.index));
+        drop(attached);
+        iface.attach(dev);
+    }
+
+    fn device_removed(&self, id: DeviceId) {
+        let mut attached = ATTACHED.lock();
+        let Some(idx) = attached.iter().position(|&(i, _)| i == id) else {
+            return;
+        };
+        let (_, index) = attached.remove(idx);
+        drop(attached);
+        super::IFACES[index].detach();
+    }
+}
+
+static NET_NOTIFIER: NetNotifier = NetNotifier;
+
+/// Starts to receive the NICs added after boot.
+pub(super) fn init() {
+    axdriver::hotplug::register_notifier(DeviceType::Net, &NET_NOTIFIER);
+}
@@ -0,0 +1,328 @@
+use alloc::{vec, vec::Vec};
+use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use axerrno::{ax_err, ax_err_type, AxError, AxResult};
+use axio::PollState;
+use axsync::Mutex;
+use spin::RwLock;
+
+use smoltcp::socket::icmp::{self, BindError, Endpoint, SendError};
+use smoltcp::wire::IpAddress;
+
+use super::addr::{from_core_ipaddr, into_core_ipaddr, is_unspecified};
+use super::wait::{self, SocketWaiter};
+use super::{addressed_ifaces, egress_iface, iface_of_addr, SocketHandle, SocketSetWrapper};
+use super::{DEFAULT_HOP_LIMIT, SOCKET_SET};
+
+const ICMPV4_ECHO_REQUEST: u8 = 8;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+/// Length of the ICMP echo header: type, code, checksum, identifier and
+/// sequence number.
+const ICMP_ECHO_HEADER_LEN: usize = 8;
+
+/// Options of an ICMP socket.
+#[derive(Debug, Clone, Copy)]
+struct IcmpOptions {
+    hop_limit: Option<u8>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+/// An ICMP echo socket (ping socket) that provides POSIX-like APIs.
+///
+/// Like the Linux `SOCK_DGRAM` sockets of `IPPROTO_ICMP`, it sends ICMP or
+/// ICMPv6 echo requests, with the identifier replaced by the one the socket
+/// is bound to, and only receives the echo replies of that identifier. The
+/// messages contain the ICMP header but not the IP header, and the checksum
+/// is computed by the stack.
+pub struct IcmpSocket {
+    handles: RwLock<Vec<SocketHandle>>,
+    local_addr: RwLock<Option<(IpAddress, u16)>>,
+    nonblock: AtomicBool,
+    options: Mutex<IcmpOptions>,
+    waiter: SocketWaiter,
+}
+
+impl IcmpSocket {
+    /// Creates a new ICMP socket.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            handles: RwLock::new(Vec::new()),
+            local_addr: RwLock::new(None),
+            nonblock: AtomicBool::new(false),
+            options: Mutex::new(IcmpOptions {
+                hop_limit: None,
+                read_timeout: None,
+                write_timeout: None,
+            }),
+            waiter: SocketWaiter::new(),
+        }
+    }
+
+    /// Returns the local address and the identifier as the port, or
+    /// [`Err(NotConnected)`](AxError::NotConnected) if not bound.
+    pub fn local_addr(&self) -> AxResult<SocketAddr> {
+        match *self.local_addr.read() {
+            Some((addr, ident)) => Ok(SocketAddr::new(into_core_ipaddr(addr), ident)),
+            None => Err(AxError::NotConnected),
+        }
+    }
+
+    /// Returns whether this socket is in nonblocking mode.
+    #[inline]
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Moves this ICMP socket into or out of nonblocking mode.
+    #[inline]
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblock.store(nonblocking, Ordering::Release);
+    }
+
+    /// Returns the time-to-live (or hop limit of IPv6) of the outgoing
+    /// packets (`IP_TTL`).
+    pub fn ttl(&self) -> u8 {
+        self.options.lock().hop_limit.unwrap_or(DEFAULT_HOP_LIMIT)
+    }
+
+    /// Sets the time-to-live (or hop limit of IPv6) of the outgoing packets
+    /// (`IP_TTL`).
+    pub fn set_ttl(&self, ttl: u8) -> AxResult {
+        if ttl == 0 {
+            return ax_err!(InvalidInput, "zero TTL");
+        }
+        self.options.lock().hop_limit = Some(ttl);
+        for &handle in self.handles.read().iter() {
+            SOCKET_SET.with_socket_mut::<icmp::Socket, _, _>(handle, |socket| {
+                socket.set_hop_limit(Some(ttl))
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the timeout of the receive operations (`SO_RCVTIMEO`).
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.options.lock().read_timeout
+    }
+
+    /// Sets the timeout of the receive operations (`SO_RCVTIMEO`), after which
+    /// they fail with [`Err(WouldBlock)`](AxError::WouldBlock). `None` means
+    /// no timeout.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout.is_some_and(|t| t.is_zero()) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.options.lock().read_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of the send operations (`SO_SNDTIMEO`).
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.options.lock().write_timeout
+    }
+
+    /// Sets the timeout of the send operations (`SO_SNDTIMEO`), after which
+    /// they fail with [`Err(WouldBlock)`](AxError::WouldBlock). `None` means
+    /// no timeout.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout.is_some_and(|t| t.is_zero()) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.options.lock().write_timeout = timeout;
+        Ok(())
+    }
+
+    /// Binds an unbound socket to the given address, and the port as the
+    /// identifier of the echo messages.
+    ///
+    /// An identifier is generated if the port is 0. The socket is bound to
+    /// the unspecified address automatically on the first
+    /// [`send_to`](Self::send_to).
+    pub fn bind(&self, local_addr: SocketAddr) -> AxResult {
+        let mut self_local_addr = self.local_addr.write();
+        if self_local_addr.is_some() {
+            return ax_err!(InvalidInput, "socket bind() failed: already bound");
+        }
+
+        let addr = from_core_ipaddr(local_addr.ip());
+        let ident = match local_addr.port() {
+            0 => get_ephemeral_ident(),
+            port => port,
+        };
+        let ifaces = if is_unspecified(addr) {
+            addressed_ifaces()
+        } else {
+            let iface = iface_of_addr(addr).ok_or_else(|| {
+                ax_err_type!(InvalidInput, "socket bind() failed: address not available")
+            })?;
+            vec![iface]
+        };
+
+        let hop_limit = self.options.lock().hop_limit;
+        // create all the sockets first, so that none is left behind if the
+        // buffers run out
+        let sockets = ifaces
+            .iter()
+            .map(|_| SocketSetWrapper::new_icmp_socket())
+            .collect::<AxResult<Vec<_>>>()?;
+        let mut handles = Vec::new();
+        for (iface, mut socket) in ifaces.into_iter().zip(sockets) {
+            socket.set_hop_limit(hop_limit);
+            let handle = SOCKET_SET.add_charged(iface, socket);
+            handles.push(handle);
+            let res = SOCKET_SET.with_socket_mut::<icmp::Socket, _, _>(handle, |socket| {
+                socket.bind(Endpoint::Ident(ident)).or_else(|e| match e {
+                    BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
+                    BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
+                })
+            });
+            if let Err(e) = res {
+                for handle in handles {
+                    SOCKET_SET.remove(handle);
+                }
+                return Err(e);
+            }
+            debug!("ICMP socket {}: bound on ident {}", handle, ident);
+        }
+
+        *self.handles.write() = handles;
+        *self_local_addr = Some((addr, ident));
+        Ok(())
+    }
+
+    /// Sends an echo request to the given address. On success, returns the
+    /// number of bytes written.
+    ///
+    /// `buf` must be an ICMP (or ICMPv6 to an IPv6 address) echo request with
+    /// the header, otherwise [`Err(InvalidInput)`](AxError::InvalidInput) is
+    /// returned.
+    pub fn send_to(&self, buf: &[u8], remote_addr: IpAddr) -> AxResult<usize> {
+        let echo_request = match remote_addr {
+            IpAddr::V4(_) => ICMPV4_ECHO_REQUEST,
+            IpAddr::V6(_) => ICMPV6_ECHO_REQUEST,
+        };
+        if buf.len() < ICMP_ECHO_HEADER_LEN || buf[0] != echo_request || buf[1] != 0 {
+            return ax_err!(InvalidInput, "socket send_to() failed: not an echo request");
+        }
+        if remote_addr.is_unspecified() {
+            return ax_err!(InvalidInput, "socket send_to() failed: invalid address");
+        }
+        if self.local_addr.read().is_none() {
+            self.bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))?;
+        }
+        let (local_addr, ident) = self.local_addr.read().unwrap();
+        let remote_addr = from_core_ipaddr(remote_addr);
+        let local_addr = (!is_unspecified(local_addr)).then_some(local_addr);
+        let iface = egress_iface(local_addr, remote_addr)
+            .ok_or_else(|| ax_err_type!(ConnectionRefused, "socket send_to() failed: no route"))?;
+        let handle = *self
+            .handles
+            .read()
+            .iter()
+            .find(|handle| handle.iface == iface)
+            .ok_or_else(|| ax_err_type!(ConnectionRefused, "socket send_to() failed"))?;
+
+        let len = self.block_on(self.write_timeout(), || {
+            SOCKET_SET.with_socket_mut::<icmp::Socket, _, _>(handle, |socket| {
+                if !socket.can_send() {
+                    // tx buffer is full
+                    self.waiter
+                        .register(|waker| socket.register_send_waker(waker));
+                    return Err(AxError::WouldBlock);
+                }
+                let packet = socket.send(buf.len(), remote_addr).map_err(|e| match e {
+                    SendError::BufferFull => AxError::WouldBlock,
+                    SendError::Unaddressable => {
+                        ax_err_type!(ConnectionRefused, "socket send_to() failed")
+                    }
+                })?;
+                packet.copy_from_slice(buf);
+                packet[4..6].copy_from_slice(&ident.to_be_bytes());
+                Ok(buf.len())
+            })
+        })?;
+        wait::kick();
+        Ok(len)
+    }
+
+    /// Receives an echo reply on the socket. On success, returns the number of
+    /// bytes read and the origin.
+    pub fn recv_from(&self, buf: &mut [u8]) -> AxResult<(usize, IpAddr)> {
+        if self.local_addr.read().is_none() {
+            return ax_err!(NotConnected, "socket recv_from() failed");
+        }
+
+        self.block_on(self.read_timeout(), || {
+            for &handle in self.handles.read().iter() {
+                let res = SOCKET_SET.with_socket_mut::<icmp::Socket, _, _>(handle, |socket| {
+                    self.waiter
+                        .register(|waker| socket.register_recv_waker(waker));
+                    socket.can_recv().then(|| match socket.recv_slice(buf) {
+                        Ok((len, addr)) => Ok((len, into_core_ipaddr(addr))),
+                        Err(_) => ax_err!(BadState, "socket recv_from() failed"),
+                    })
+                });
+                if let Some(res) = res {
+                    return res;
+                }
+            }
+            // no more data
+            Err(AxError::WouldBlock)
+        })
+    }
+
+    /// Close the socket.
+    pub fn shutdown(&self) -> AxResult {
+        for handle in self.handles.write().drain(..) {
+            debug!("ICMP socket {}: shutting down", handle);
+            SOCKET_SET.remove(handle);
+        }
+        Ok(())
+    }
+
+    /// Whether the socket is readable or writable.
+    pub fn poll(&self) -> AxResult<PollState> {
+        // no handles if not bound
+        let mut state = PollState {
+            readable: false,
+            writable: self.local_addr.read().is_none(),
+        };
+        for &handle in self.handles.read().iter() {
+            SOCKET_SET.with_socket::<icmp::Socket, _, _>(handle, |socket| {
+                state.readable |= socket.can_recv();
+                state.writable |= socket.can_send();
+            });
+        }
+        Ok(state)
+    }
+}
+
+/// Private methods
+impl IcmpSocket {
+    fn block_on<F, T>(&self, timeout: Option<Duration>, f: F) -> AxResult<T>
+    where
+        F: FnMut() -> AxResult<T>,
+    {
+        self.waiter.block_on(self.is_nonblocking(), timeout, f)
+    }
+}
+
+impl Drop for IcmpSocket {
+    fn drop(&mut self) {
+        self.shutdown().ok();
+    }
+}
+
+fn get_ephemeral_ident() -> u16 {
+    const IDENT_START: u16 = 0x4000;
+    static CURR: Mutex<u16> = Mutex::new(IDENT_START);
+    let mut curr = CURR.lock();
+
+    let ident = *curr;
+    *curr = curr.checked_add(1).unwrap_or(IDENT_START);
+    ident
+}
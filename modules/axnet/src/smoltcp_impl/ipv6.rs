@@ -0,0 +1,170 @@
+//! IPv6 address configuration: link-local, static and stateless
+//! autoconfiguration (SLAAC).
+
+use alloc::vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use spin::Mutex;
+
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::socket::icmp;
+use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr, IpVersion};
+use smoltcp::wire::{Icmpv6Packet, Icmpv6Repr, Ipv6Address, Ipv6Cidr, Ipv6Packet};
+use smoltcp::wire::{NdiscPrefixInfoFlags, NdiscRepr};
+
+use super::{InterfaceWrapper, ETH0, GATEWAY6, IP6, IPV6_PREFIX, SOCKET_SET};
+
+/// A Router Solicitation message without options (RFC 4861, section 4.1).
+const ROUTER_SOLICIT: [u8; 8] = [133, 0, 0, 0, 0, 0, 0, 0];
+
+/// The configuration learned from a Router Advertisement.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct RouterInfo {
+    /// The global address formed from the advertised prefix.
+    address: Option<Ipv6Address>,
+    /// The router, if it can be used as the default router.
+    router: Option<Ipv6Address>,
+}
+
+/// The SLAAC state of an interface.
+///
+/// The interface is locked while receiving packets, so the advertisement is
+/// recorded in `pending`, and applied in [`poll`].
+pub(crate) struct Slaac {
+    enabled: AtomicBool,
+    pending: Mutex<Option<RouterInfo>>,
+    applied: Mutex<Option<RouterInfo>>,
+}
+
+impl Slaac {
+    pub const fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            pending: Mutex::new(None),
+            applied: Mutex::new(None),
+        }
+    }
+
+    /// Forgets the learned configuration, and stops SLAAC until the next
+    /// [`init`].
+    #[cfg(feature = "hotplug")]
+    pub fn reset(&self) {
+        self.enabled.store(false, Ordering::Release);
+        *self.pending.lock() = None;
+        *self.applied.lock() = None;
+    }
+}
+
+/// Forms an address from a /64 prefix and the modified EUI-64 interface
+/// identifier of `mac` (RFC 4291, appendix A).
+fn eui64_addr(prefix: Ipv6Address, mac: EthernetAddress) -> Ipv6Address {
+    let m = mac.0;
+    let mut addr = prefix.0;
+    addr[8..].copy_from_slice(&[m[0] ^ 0x02, m[1], m[2], 0xff, 0xfe, m[3], m[4], m[5]]);
+    Ipv6Address(addr)
+}
+
+/// Assigns the link-local address, then applies the static configuration
+/// from `AX_IP6` and `AX_GW6` to `eth0`, or solicits routers for SLAAC if no
+/// static address is given.
+pub(crate) fn init(iface: &InterfaceWrapper) {
+    let Some(mac) = iface.ethernet_address() else {
+        return; // loopback
+    };
+    let link_local = eui64_addr(Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0), mac);
+    iface.setup_ip_addr(IpCidr::new(IpAddress::Ipv6(link_local), IPV6_PREFIX));
+    info!("  ip6:      {}/{} (link-local)", link_local, IPV6_PREFIX);
+
+    if iface.index == ETH0 && !IP6.is_empty() {
+        let ip = IP6.parse().expect("invalid IPv6 address");
+        iface.setup_ip_addr(IpCidr::new(IpAddress::Ipv6(ip), IPV6_PREFIX));
+        info!("  ip6:      {}/{}", ip, IPV6_PREFIX);
+        if !GATEWAY6.is_empty() {
+            let gateway = GATEWAY6.parse().expect("invalid IPv6 gateway address");
+            iface.setup_gateway(IpAddress::Ipv6(gateway));
+            info!("  gateway6: {}", gateway);
+        }
+        return;
+    }
+
+    iface.slaac.enabled.store(true, Ordering::Release);
+    // the solicitation is sent once, routers also advertise periodically
+    let mut socket = icmp::Socket::new(
+        icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![0; 64]),
+        icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![0; 64]),
+    );
+    // NDP messages must be sent with hop limit 255
+    socket.set_hop_limit(Some(255));
+    let all_routers = Ipv6Address::new(0xff02, 0, 0, 0, 0, 0, 0, 2);
+    if socket
+        .send_slice(&ROUTER_SOLICIT, IpAddress::Ipv6(all_routers))
+        .is_ok()
+    {
+        let handle = SOCKET_SET.add(iface.index, socket);
+        SOCKET_SET.poll_interfaces();
+        SOCKET_SET.remove(handle);
+    }
+}
+
+/// Records the prefix and the router of a Router Advertisement, it's called
+/// for each ICMPv6 packet received by `iface`.
+pub(crate) fn snoop_router_advert(
+    iface: &InterfaceWrapper,
+    packet: &Ipv6Packet<&[u8]>,
+) -> Result<(), smoltcp::wire::Error> {
+    let Some(mac) = iface.ethernet_address() else {
+        return Ok(());
+    };
+    if !iface.slaac.enabled.load(Ordering::Acquire) {
+        return Ok(()); // static configuration
+    }
+    let src_addr = IpAddress::Ipv6(packet.src_addr());
+    let dst_addr = IpAddress::Ipv6(packet.dst_addr());
+    let icmp_packet = Icmpv6Packet::new_checked(packet.payload())?;
+    let icmp_repr = Icmpv6Repr::parse(
+        &src_addr,
+        &dst_addr,
+        &icmp_packet,
+        &ChecksumCapabilities::default(),
+    )?;
+    if let Icmpv6Repr::Ndisc(NdiscRepr::RouterAdvert {
+        router_lifetime,
+        prefix_info,
+        ..
+    }) = icmp_repr
+    {
+        let address = prefix_info
+            .filter(|info| {
+                info.flags.contains(NdiscPrefixInfoFlags::ADDRCONF)
+                    && info.prefix_len == IPV6_PREFIX
+                    && info.valid_lifetime.total_millis() > 0
+            })
+            .map(|info| eui64_addr(info.prefix, mac));
+        let router = (router_lifetime.total_millis() > 0).then_some(packet.src_addr());
+        *iface.slaac.pending.lock() = Some(RouterInfo { address, router });
+    }
+    Ok(())
+}
+
+/// Applies the last Router Advertisement received by `iface`, if it changes
+/// the configuration.
+pub(crate) fn poll(iface: &InterfaceWrapper) {
+    let Some(info) = iface.slaac.pending.lock().take() else {
+        return;
+    };
+    let mut applied = iface.slaac.applied.lock();
+    if *applied == Some(info) {
+        return;
+    }
+    if let Some(addr) = info.address {
+        info!("SLAAC: {}/{} on {:?}", addr, IPV6_PREFIX, iface.name());
+        iface.setup_ip_addr(IpCidr::Ipv6(Ipv6Cidr::new(addr, IPV6_PREFIX)));
+    }
+    match info.router {
+        Some(router) => {
+            info!("  gateway6: {}", router);
+            iface.setup_gateway(IpAddress::Ipv6(router));
+        }
+        None => iface.remove_gateway(IpVersion::Ipv6),
+    }
+    *applied = Some(info);
+}
@@ -1,26 +1,34 @@
-use alloc::{boxed::Box, collections::VecDeque};
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
 use core::ops::{Deref, DerefMut};
+use core::task::Waker;
 
 use axerrno::{ax_err, AxError, AxResult};
 use axsync::Mutex;
-use smoltcp::iface::{SocketHandle, SocketSet};
+use smoltcp::iface::SocketSet;
 use smoltcp::socket::tcp::{self, State};
 use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
-use super::{SocketSetWrapper, LISTEN_QUEUE_SIZE, SOCKET_SET};
+use super::tcp::TcpOptions;
+use super::{SocketHandle, LISTEN_QUEUE_SIZE, SOCKET_SET};
 
 const PORT_NUM: usize = 65536;
 
 struct ListenTableEntry {
     listen_endpoint: IpListenEndpoint,
     syn_queue: VecDeque<SocketHandle>,
+    /// Options of the listener, for the new connections.
+    options: TcpOptions,
+    /// Woken when a connection in the SYN queue is established.
+    waker: Option<Waker>,
 }
 
 impl ListenTableEntry {
-    pub fn new(listen_endpoint: IpListenEndpoint) -> Self {
+    pub fn new(listen_endpoint: IpListenEndpoint, options: TcpOptions) -> Self {
         Self {
             listen_endpoint,
             syn_queue: VecDeque::with_capacity(LISTEN_QUEUE_SIZE),
+            options,
+            waker: None,
         }
     }
 
@@ -61,12 +69,12 @@ impl ListenTable {
         self.tcp[port as usize].lock().is_none()
     }
 
-    pub fn listen(&self, listen_endpoint: IpListenEndpoint) -> AxResult {
+    pub fn listen(&self, listen_endpoint: IpListenEndpoint, options: TcpOptions) -> AxResult {
         let port = listen_endpoint.port;
         assert_ne!(port, 0);
         let mut entry = self.tcp[port as usize].lock();
         if entry.is_none() {
-            *entry = Some(Box::new(ListenTableEntry::new(listen_endpoint)));
+            *entry = Some(Box::new(ListenTableEntry::new(listen_endpoint, options)));
             Ok(())
         } else {
             ax_err!(AddrInUse, "socket listen() failed")
@@ -78,6 +86,14 @@ impl ListenTable {
         *self.tcp[port as usize].lock() = None;
     }
 
+    /// Updates the options of the listener on `port`, which only apply to
+    /// the connections that are not in the SYN queue yet.
+    pub fn set_options(&self, port: u16, options: TcpOptions) {
+        if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
+            entry.options = options;
+        }
+    }
+
     pub fn can_accept(&self, port: u16) -> AxResult<bool> {
         if let Some(entry) = self.tcp[port as usize].lock().deref() {
             Ok(entry.syn_queue.iter().any(|&handle| is_connected(handle)))
@@ -86,6 +102,25 @@ impl ListenTable {
         }
     }
 
+    /// Registers the waker of the listener on `port`, to be woken when a new
+    /// connection is established.
+    pub fn register_waker(&self, port: u16, waker: &Waker) {
+        let handles: Vec<_> = match self.tcp[port as usize].lock().deref_mut() {
+            Some(entry) => {
+                entry.waker = Some(waker.clone());
+                entry.syn_queue.iter().copied().collect()
+            }
+            None => return,
+        };
+        // don't lock the socket sets with the entry locked, as the interfaces
+        // lock them in the other order.
+        for handle in handles {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker)
+            });
+        }
+    }
+
     pub fn accept(&self, port: u16) -> AxResult<(SocketHandle, (IpEndpoint, IpEndpoint))> {
         if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
             let syn_queue = &mut entry.syn_queue;
@@ -114,6 +149,7 @@ impl ListenTable {
         &self,
         src: IpEndpoint,
         dst: IpEndpoint,
+        iface: usize,
         sockets: &mut SocketSet<'_>,
     ) {
         if let Some(entry) = self.tcp[dst.port as usize].lock().deref_mut() {
@@ -126,9 +162,19 @@ impl ListenTable {
                 warn!("SYN queue overflow!");
                 return;
             }
-            let mut socket = SocketSetWrapper::new_tcp_socket();
+            let Ok(mut socket) = entry.options.new_socket() else {
+                // out of buffers, drop the packet
+                return;
+            };
+            if let Some(waker) = &entry.waker {
+                socket.register_recv_waker(waker);
+            }
             if socket.listen(entry.listen_endpoint).is_ok() {
-                let handle = sockets.add(socket);
+                let handle = SocketHandle {
+                    iface,
+                    inner: sockets.add(socket.socket),
+                };
+                SOCKET_SET.hold_charge(handle, socket.charge);
                 debug!(
                     "TCP socket {}: prepare for connection {} -> {}",
                     handle, src, entry.listen_endpoint
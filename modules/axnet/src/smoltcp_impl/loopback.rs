@@ -0,0 +1,90 @@
+//! The loopback device, which receives everything it transmits.
+//!
+//! The queued packets are allocated in [`NET_ZONE`], a packet is dropped if
+//! the zone is full.
+
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use axalloc::{AllocZone, NET_ZONE};
+use smoltcp::iface::SocketSet;
+use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
+use smoltcp::time::Instant;
+
+/// The MTU of the loopback device, as in Linux.
+const LOOPBACK_MTU: usize = 65536;
+
+type PacketBuf = Vec<u8, &'static AllocZone>;
+
+pub(super) struct LoopbackDev {
+    queue: VecDeque<PacketBuf>,
+    iface: usize,
+}
+
+impl LoopbackDev {
+    pub fn new(iface: usize) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            iface,
+        }
+    }
+}
+
+impl Device for LoopbackDev {
+    type RxToken<'a> = LoopbackRxToken where Self: 'a;
+    type TxToken<'a> = LoopbackTxToken<'a> where Self: 'a;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        self.queue.pop_front().map(|buf| {
+            let rx = LoopbackRxToken(buf, self.iface);
+            let tx = LoopbackTxToken(&mut self.queue);
+            (rx, tx)
+        })
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        Some(LoopbackTxToken(&mut self.queue))
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.max_transmission_unit = LOOPBACK_MTU;
+        caps.max_burst_size = None;
+        caps.medium = Medium::Ip;
+        caps
+    }
+}
+
+pub(super) struct LoopbackRxToken(PacketBuf, usize);
+pub(super) struct LoopbackTxToken<'a>(&'a mut VecDeque<PacketBuf>);
+
+impl RxToken for LoopbackRxToken {
+    fn preprocess(&self, sockets: &mut SocketSet<'_>) {
+        super::snoop_ip_packet(self.1, &self.0, sockets).ok();
+    }
+
+    fn consume<R, F>(mut self, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        f(&mut self.0)
+    }
+}
+
+impl<'a> TxToken for LoopbackTxToken<'a> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut buf = Vec::new_in(&NET_ZONE);
+        if buf.try_reserve_exact(len).is_err() {
+            // out of buffers, the packet is dropped
+            return f(&mut vec![0; len]);
+        }
+        buf.resize(len, 0);
+        let ret = f(&mut buf);
+        self.0.push_back(buf);
+        ret
+    }
+}
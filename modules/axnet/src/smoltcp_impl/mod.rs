@@ -1,28 +1,51 @@
 mod addr;
 mod bench;
+#[cfg(feature = "dhcp")]
+mod dhcp;
 mod dns;
+#[cfg(feature = "hotplug")]
+mod hotplug;
+mod icmp;
+mod ipv6;
 mod listen_table;
+mod loopback;
+#[cfg(feature = "pcap")]
+pub mod pcap;
+mod raw;
+mod route;
 mod tcp;
 mod udp;
+mod wait;
 
-use alloc::vec;
-use core::cell::RefCell;
-use core::ops::DerefMut;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
 
+use axalloc::{ZoneReservation, NET_ZONE};
 use axdriver::prelude::*;
+use axerrno::{ax_err_type, AxResult};
 use axhal::time::{current_time_nanos, NANOS_PER_MICROS};
 use axsync::Mutex;
 use driver_net::{DevError, NetBufPtr};
 use lazy_init::LazyInit;
-use smoltcp::iface::{Config, Interface, SocketHandle, SocketSet};
+use smoltcp::iface::{self, Config, Interface, SocketSet};
 use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
 use smoltcp::socket::{self, AnySocket};
 use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr};
+use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr, IpProtocol, IpVersion};
+use smoltcp::wire::{Ipv4Address, Ipv6Address, ETHERNET_HEADER_LEN};
 
 use self::listen_table::ListenTable;
+use self::loopback::LoopbackDev;
+use self::route::RouteTable;
 
 pub use self::dns::dns_query;
+pub use self::icmp::IcmpSocket;
+pub use self::raw::RawSocket;
 pub use self::tcp::TcpSocket;
 pub use self::udp::UdpSocket;
 
@@ -40,73 +63,264 @@ const GATEWAY: &str = env_or_default!("AX_GW");
 const DNS_SEVER: &str = "8.8.8.8";
 const IP_PREFIX: u8 = 24;
 
-const STANDARD_MTU: usize = 1500;
+const IP6: &str = env_or_default!("AX_IP6");
+const GATEWAY6: &str = env_or_default!("AX_GW6");
+const IPV6_PREFIX: u8 = 64;
 
-const RANDOM_SEED: u64 = 0xA2CE_05A2_CE05_A2CE;
+const STANDARD_MTU: usize = 1500;
 
 const TCP_RX_BUF_LEN: usize = 64 * 1024;
 const TCP_TX_BUF_LEN: usize = 64 * 1024;
 const UDP_RX_BUF_LEN: usize = 64 * 1024;
 const UDP_TX_BUF_LEN: usize = 64 * 1024;
+const RAW_RX_BUF_LEN: usize = 64 * 1024;
+const RAW_TX_BUF_LEN: usize = 64 * 1024;
+/// The bounds of the socket buffer sizes set by users.
+const SOCKET_BUF_LEN_RANGE: core::ops::RangeInclusive<usize> = 1024..=4 * 1024 * 1024;
 const LISTEN_QUEUE_SIZE: usize = 512;
+/// The most packets moved from a receive queue to its backlog at a time.
+const RX_BATCH_SIZE: usize = 64;
+/// The hop limit of smoltcp sockets by default.
+const DEFAULT_HOP_LIMIT: u8 = 64;
+
+/// Index of the loopback interface in [`IFACES`].
+const LOOPBACK: usize = 0;
+/// Index of `eth0` in [`IFACES`], the other NICs follow it.
+const ETH0: usize = 1;
 
 static LISTEN_TABLE: LazyInit<ListenTable> = LazyInit::new();
 static SOCKET_SET: LazyInit<SocketSetWrapper> = LazyInit::new();
-static ETH0: LazyInit<InterfaceWrapper> = LazyInit::new();
+static IFACES: LazyInit<Vec<InterfaceWrapper>> = LazyInit::new();
+static ROUTES: RouteTable = RouteTable::new();
+static DNS_SERVERS: Mutex<Vec<IpAddress>> = Mutex::new(Vec::new());
+
+/// A handle to a socket in the socket set of an interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct SocketHandle {
+    iface: usize,
+    inner: iface::SocketHandle,
+}
+
+/// The socket sets of all interfaces, indexed like [`IFACES`].
+///
+/// A socket is only processed by the interface whose set holds it, so its
+/// packets never go out through another interface.
+///
+/// The buffers of the sockets are charged to [`NET_ZONE`], the charges are
+/// held here until the sockets are removed.
+struct SocketSetWrapper<'a> {
+    sets: Vec<Mutex<SocketSet<'a>>>,
+    charges: Mutex<BTreeMap<SocketHandle, ZoneReservation>>,
+}
 
-struct SocketSetWrapper<'a>(Mutex<SocketSet<'a>>);
+/// A new socket whose buffers are charged to [`NET_ZONE`].
+struct ChargedSocket<T> {
+    socket: T,
+    charge: ZoneReservation,
+}
 
+/// A NIC, whose queues are serviced by the CPUs bound to them.
+///
+/// Each CPU is bound to the queue `cpu_id % num_queues`. Before polling the
+/// interface, a CPU services its queue without the lock of the interface: it
+/// gives back the transmitted buffers, and moves the received packets to the
+/// backlog of the queue. The packets are then processed by smoltcp under the
+/// lock of the interface, and transmitted on the queue of the CPU.
 struct DeviceWrapper {
-    inner: RefCell<AxNetDevice>, // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
+    /// The driver, only locked during each call to it.
+    inner: Mutex<AxNetDevice>,
+    iface: usize,
+    queues: Vec<NetQueue>,
+}
+
+/// A pair of receive and transmit queues of a NIC.
+struct NetQueue {
+    /// The received packets that are not processed yet.
+    ///
+    /// It is locked while the queue is serviced, so each queue is serviced by
+    /// one CPU at a time, and different queues in parallel.
+    backlog: Mutex<VecDeque<RxPacket>>,
+}
+
+/// A packet received from a queue, owned by its backlog.
+struct RxPacket(NetBufPtr);
+
+// The buffer is not shared, whoever holds the packet owns it.
+unsafe impl Send for RxPacket {}
+
+/// A NIC seen by smoltcp, which transmits on the given queue.
+struct NicQueue<'a> {
+    nic: &'a DeviceWrapper,
+    queue: usize,
+}
+
+enum NetDevice {
+    Nic(Arc<DeviceWrapper>),
+    Loopback(LoopbackDev),
+    /// An interface whose NIC is not added yet or is removed.
+    #[cfg(feature = "hotplug")]
+    Detached(hotplug::DetachedDev),
 }
 
 struct InterfaceWrapper {
-    name: &'static str,
-    ether_addr: EthernetAddress,
-    dev: Mutex<DeviceWrapper>,
+    name: String,
+    index: usize,
+    ether_addr: Mutex<Option<EthernetAddress>>,
+    dev: Mutex<NetDevice>,
     iface: Mutex<Interface>,
+    slaac: ipv6::Slaac,
+    #[cfg(feature = "dhcp")]
+    dhcp: dhcp::DhcpClient,
+}
+
+impl SocketHandle {
+    /// Returns the interface whose socket set holds the socket.
+    fn iface(&self) -> &'static InterfaceWrapper {
+        &IFACES[self.iface]
+    }
+}
+
+impl fmt::Display for SocketHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.inner, self.iface().name())
+    }
+}
+
+impl<T> ChargedSocket<T> {
+    /// Charges `size` bytes to [`NET_ZONE`], then creates the socket.
+    fn new(size: usize, f: impl FnOnce() -> T) -> AxResult<Self> {
+        let charge = NET_ZONE
+            .reserve(size)
+            .map_err(|_| ax_err_type!(NoMemory, "network buffer limit exceeded"))?;
+        Ok(Self {
+            socket: f(),
+            charge,
+        })
+    }
+}
+
+impl<T> Deref for ChargedSocket<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.socket
+    }
+}
+
+impl<T> DerefMut for ChargedSocket<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.socket
+    }
 }
 
 impl<'a> SocketSetWrapper<'a> {
-    fn new() -> Self {
-        Self(Mutex::new(SocketSet::new(vec![])))
+    fn new(num_ifaces: usize) -> Self {
+        Self {
+            sets: (0..num_ifaces)
+                .map(|_| Mutex::new(SocketSet::new(vec![])))
+                .collect(),
+            charges: Mutex::new(BTreeMap::new()),
+        }
     }
 
-    pub fn new_tcp_socket() -> socket::tcp::Socket<'a> {
-        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_RX_BUF_LEN]);
-        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_TX_BUF_LEN]);
-        socket::tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
+    pub fn new_tcp_socket(
+        rx_buf_len: usize,
+        tx_buf_len: usize,
+    ) -> AxResult<ChargedSocket<socket::tcp::Socket<'a>>> {
+        ChargedSocket::new(rx_buf_len + tx_buf_len, || {
+            let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; rx_buf_len]);
+            let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; tx_buf_len]);
+            socket::tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
+        })
     }
 
-    pub fn new_udp_socket() -> socket::udp::Socket<'a> {
-        let udp_rx_buffer = socket::udp::PacketBuffer::new(
-            vec![socket::udp::PacketMetadata::EMPTY; 8],
-            vec![0; UDP_RX_BUF_LEN],
-        );
-        let udp_tx_buffer = socket::udp::PacketBuffer::new(
-            vec![socket::udp::PacketMetadata::EMPTY; 8],
-            vec![0; UDP_TX_BUF_LEN],
-        );
-        socket::udp::Socket::new(udp_rx_buffer, udp_tx_buffer)
+    pub fn new_udp_socket(
+        rx_buf_len: usize,
+        tx_buf_len: usize,
+    ) -> AxResult<ChargedSocket<socket::udp::Socket<'a>>> {
+        let meta_len = 2 * 8 * core::mem::size_of::<socket::udp::PacketMetadata>();
+        ChargedSocket::new(rx_buf_len + tx_buf_len + meta_len, || {
+            let udp_rx_buffer = socket::udp::PacketBuffer::new(
+                vec![socket::udp::PacketMetadata::EMPTY; 8],
+                vec![0; rx_buf_len],
+            );
+            let udp_tx_buffer = socket::udp::PacketBuffer::new(
+                vec![socket::udp::PacketMetadata::EMPTY; 8],
+                vec![0; tx_buf_len],
+            );
+            socket::udp::Socket::new(udp_rx_buffer, udp_tx_buffer)
+        })
+    }
+
+    pub fn new_icmp_socket() -> AxResult<ChargedSocket<socket::icmp::Socket<'a>>> {
+        let meta_len = 2 * 8 * core::mem::size_of::<socket::icmp::PacketMetadata>();
+        ChargedSocket::new(RAW_RX_BUF_LEN + RAW_TX_BUF_LEN + meta_len, || {
+            let icmp_rx_buffer = socket::icmp::PacketBuffer::new(
+                vec![socket::icmp::PacketMetadata::EMPTY; 8],
+                vec![0; RAW_RX_BUF_LEN],
+            );
+            let icmp_tx_buffer = socket::icmp::PacketBuffer::new(
+                vec![socket::icmp::PacketMetadata::EMPTY; 8],
+                vec![0; RAW_TX_BUF_LEN],
+            );
+            socket::icmp::Socket::new(icmp_rx_buffer, icmp_tx_buffer)
+        })
+    }
+
+    pub fn new_raw_socket(
+        version: IpVersion,
+        protocol: IpProtocol,
+    ) -> AxResult<ChargedSocket<socket::raw::Socket<'a>>> {
+        let meta_len = 2 * 8 * core::mem::size_of::<socket::raw::PacketMetadata>();
+        ChargedSocket::new(RAW_RX_BUF_LEN + RAW_TX_BUF_LEN + meta_len, || {
+            let raw_rx_buffer = socket::raw::PacketBuffer::new(
+                vec![socket::raw::PacketMetadata::EMPTY; 8],
+                vec![0; RAW_RX_BUF_LEN],
+            );
+            let raw_tx_buffer = socket::raw::PacketBuffer::new(
+                vec![socket::raw::PacketMetadata::EMPTY; 8],
+                vec![0; RAW_TX_BUF_LEN],
+            );
+            socket::raw::Socket::new(version, protocol, raw_rx_buffer, raw_tx_buffer)
+        })
     }
 
     pub fn new_dns_socket() -> socket::dns::Socket<'a> {
-        let server_addr = DNS_SEVER.parse().expect("invalid DNS server address");
-        socket::dns::Socket::new(&[server_addr], vec![])
+        socket::dns::Socket::new(&dns_servers(), vec![])
     }
 
-    pub fn add<T: AnySocket<'a>>(&self, socket: T) -> SocketHandle {
-        let handle = self.0.lock().add(socket);
+    /// Adds a socket to the socket set of the interface `iface`.
+    pub fn add<T: AnySocket<'a>>(&self, iface: usize, socket: T) -> SocketHandle {
+        let inner = self.sets[iface].lock().add(socket);
+        let handle = SocketHandle { iface, inner };
         debug!("socket {}: created", handle);
         handle
     }
 
+    /// Adds a socket to the socket set of the interface `iface`, its charge
+    /// is released when it is removed.
+    pub fn add_charged<T: AnySocket<'a>>(
+        &self,
+        iface: usize,
+        socket: ChargedSocket<T>,
+    ) -> SocketHandle {
+        let handle = self.add(iface, socket.socket);
+        self.hold_charge(handle, socket.charge);
+        handle
+    }
+
+    /// Holds the charge of a socket added to a set directly, until the
+    /// socket is removed.
+    pub fn hold_charge(&self, handle: SocketHandle, charge: ZoneReservation) {
+        self.charges.lock().insert(handle, charge);
+    }
+
     pub fn with_socket<T: AnySocket<'a>, R, F>(&self, handle: SocketHandle, f: F) -> R
     where
         F: FnOnce(&T) -> R,
     {
-        let set = self.0.lock();
-        let socket = set.get(handle);
+        let set = self.sets[handle.iface].lock();
+        let socket = set.get(handle.inner);
         f(socket)
     }
 
@@ -114,33 +328,76 @@ impl<'a> SocketSetWrapper<'a> {
     where
         F: FnOnce(&mut T) -> R,
     {
-        let mut set = self.0.lock();
-        let socket = set.get_mut(handle);
+        let mut set = self.sets[handle.iface].lock();
+        let socket = set.get_mut(handle.inner);
         f(socket)
     }
 
     pub fn poll_interfaces(&self) {
-        ETH0.poll(&self.0);
+        for (iface, sockets) in IFACES.iter().zip(&self.sets) {
+            iface.poll(sockets);
+        }
+        for iface in nics() {
+            ipv6::poll(iface);
+            #[cfg(feature = "dhcp")]
+            dhcp::poll(iface);
+        }
+    }
+
+    /// Returns how long until the interfaces should be polled again, `None`
+    /// if no socket is waiting for a timer.
+    #[cfg(all(feature = "irq", feature = "multitask"))]
+    pub fn poll_delay(&self) -> Option<core::time::Duration> {
+        IFACES
+            .iter()
+            .zip(&self.sets)
+            .filter_map(|(iface, sockets)| iface.poll_delay(sockets))
+            .min()
     }
 
     pub fn remove(&self, handle: SocketHandle) {
-        self.0.lock().remove(handle);
+        self.sets[handle.iface].lock().remove(handle.inner);
+        self.charges.lock().remove(&handle);
         debug!("socket {}: destroyed", handle);
     }
 }
 
 impl InterfaceWrapper {
-    fn new(name: &'static str, dev: AxNetDevice, ether_addr: EthernetAddress) -> Self {
+    fn new(name: String, index: usize, dev: AxNetDevice) -> Self {
+        let ether_addr = EthernetAddress(dev.mac_address().0);
         let mut config = Config::new(HardwareAddress::Ethernet(ether_addr));
-        config.random_seed = RANDOM_SEED;
+        config.random_seed = axhal::random::random_u64();
+
+        let dev = Arc::new(DeviceWrapper::new(dev, index));
+        let iface = Interface::new(config, &mut dev.queue(0), Self::current_time());
+        Self::from_parts(name, index, Some(ether_addr), NetDevice::Nic(dev), iface)
+    }
+
+    fn new_loopback(index: usize) -> Self {
+        let mut config = Config::new(HardwareAddress::Ip);
+        config.random_seed = axhal::random::random_u64();
+
+        let mut dev = LoopbackDev::new(index);
+        let iface = Interface::new(config, &mut dev, Self::current_time());
+        Self::from_parts("lo".into(), index, None, NetDevice::Loopback(dev), iface)
+    }
 
-        let mut dev = DeviceWrapper::new(dev);
-        let iface = Mutex::new(Interface::new(config, &mut dev, Self::current_time()));
+    fn from_parts(
+        name: String,
+        index: usize,
+        ether_addr: Option<EthernetAddress>,
+        dev: NetDevice,
+        iface: Interface,
+    ) -> Self {
         Self {
             name,
-            ether_addr,
+            index,
+            ether_addr: Mutex::new(ether_addr),
             dev: Mutex::new(dev),
-            iface,
+            iface: Mutex::new(iface),
+            slaac: ipv6::Slaac::new(),
+            #[cfg(feature = "dhcp")]
+            dhcp: dhcp::DhcpClient::new(),
         }
     }
 
@@ -149,78 +406,289 @@ impl InterfaceWrapper {
     }
 
     pub fn name(&self) -> &str {
-        self.name
+        &self.name
     }
 
-    pub fn ethernet_address(&self) -> EthernetAddress {
-        self.ether_addr
+    /// Returns the MAC address, or `None` for the loopback interface and the
+    /// interfaces without NICs.
+    pub fn ethernet_address(&self) -> Option<EthernetAddress> {
+        *self.ether_addr.lock()
     }
 
-    pub fn setup_ip_addr(&self, ip: IpAddress, prefix_len: u8) {
+    /// Returns the MTU, i.e. the size of the largest IP packet that can be
+    /// sent without fragmentation.
+    pub fn mtu(&self) -> usize {
+        match self.dev.lock().deref() {
+            NetDevice::Nic(dev) => dev.inner.lock().mtu(),
+            NetDevice::Loopback(dev) => dev.capabilities().max_transmission_unit,
+            #[cfg(feature = "hotplug")]
+            NetDevice::Detached(dev) => {
+                dev.capabilities().max_transmission_unit - ETHERNET_HEADER_LEN
+            }
+        }
+    }
+
+    /// Replaces the IP address of the same family as `cidr`, and the route to
+    /// its network.
+    ///
+    /// The IPv6 link-local address is kept when a global IPv6 address is set.
+    pub fn setup_ip_addr(&self, cidr: IpCidr) {
         let mut iface = self.iface.lock();
         iface.update_ip_addrs(|ip_addrs| {
-            ip_addrs.push(IpCidr::new(ip, prefix_len)).unwrap();
+            ip_addrs.retain(|old| {
+                let keep = match (old, cidr) {
+                    (IpCidr::Ipv4(_), IpCidr::Ipv4(_)) => false,
+                    (IpCidr::Ipv6(old), IpCidr::Ipv6(_)) => old.address().is_link_local(),
+                    _ => true,
+                };
+                if !keep {
+                    ROUTES.remove(*old, self.index);
+                }
+                keep
+            });
+            ip_addrs.push(cidr).unwrap();
         });
+        ROUTES.add(cidr, self.index);
     }
 
+    /// Replaces the default gateway of the same family as `gateway`, and makes
+    /// the interface the default route of that family.
     pub fn setup_gateway(&self, gateway: IpAddress) {
         let mut iface = self.iface.lock();
         match gateway {
             IpAddress::Ipv4(v4) => iface.routes_mut().add_default_ipv4_route(v4).unwrap(),
+            IpAddress::Ipv6(v6) => iface.routes_mut().add_default_ipv6_route(v6).unwrap(),
         };
+        ROUTES.add(default_cidr(gateway.version()), self.index);
+    }
+
+    /// Removes the default gateway of the given IP version.
+    pub fn remove_gateway(&self, version: IpVersion) {
+        let mut iface = self.iface.lock();
+        match version {
+            IpVersion::Ipv4 => iface.routes_mut().remove_default_ipv4_route(),
+            IpVersion::Ipv6 => iface.routes_mut().remove_default_ipv6_route(),
+        };
+        ROUTES.remove(default_cidr(version), self.index);
+    }
+
+    /// Returns whether the interface has any address, the interfaces without
+    /// NICs have none.
+    pub fn has_ip_addrs(&self) -> bool {
+        !self.iface.lock().ip_addrs().is_empty()
+    }
+
+    /// Returns whether `addr` is one of the addresses of the interface.
+    pub fn has_ip_addr(&self, addr: IpAddress) -> bool {
+        self.iface.lock().has_ip_addr(addr)
+    }
+
+    /// Chooses the source address of the packets sent to `remote`.
+    ///
+    /// An IPv6 link-local address is only used for link-local destinations if
+    /// the interface has a global one.
+    pub fn source_addr(&self, remote: IpAddress) -> Option<IpAddress> {
+        let iface = self.iface.lock();
+        let mut addrs = iface
+            .ip_addrs()
+            .iter()
+            .map(|cidr| cidr.address())
+            .filter(|addr| addr.version() == remote.version());
+        match remote {
+            IpAddress::Ipv6(v6) if !v6.is_link_local() => {
+                let addrs: Vec<_> = addrs.collect();
+                addrs
+                    .iter()
+                    .find(|addr| !matches!(addr, IpAddress::Ipv6(a) if a.is_link_local()))
+                    .or(addrs.first())
+                    .copied()
+            }
+            _ => addrs.next(),
+        }
+    }
+
+    /// The IRQ of the NIC, or `None` if the interface can only be polled.
+    #[cfg(all(feature = "irq", feature = "multitask"))]
+    pub fn irq_num(&self) -> Option<usize> {
+        match self.dev.lock().deref() {
+            NetDevice::Nic(dev) => dev.inner.lock().irq_num(),
+            _ => None,
+        }
+    }
+
+    /// Acknowledges the pending interrupt of the NIC.
+    #[cfg(all(feature = "irq", feature = "multitask"))]
+    pub fn ack_interrupt(&self) -> bool {
+        match self.dev.lock().deref() {
+            NetDevice::Nic(dev) => dev.inner.lock().ack_interrupt(),
+            _ => false,
+        }
+    }
+
+    #[cfg(all(feature = "irq", feature = "multitask"))]
+    fn poll_delay(&self, sockets: &Mutex<SocketSet>) -> Option<core::time::Duration> {
+        let mut iface = self.iface.lock();
+        let sockets = sockets.lock();
+        let delay = iface.poll_delay(Self::current_time(), &sockets)?;
+        Some(core::time::Duration::from_micros(delay.total_micros()))
     }
 
     pub fn poll(&self, sockets: &Mutex<SocketSet>) {
         let mut dev = self.dev.lock();
+        let nic = match dev.deref_mut() {
+            NetDevice::Nic(nic) => nic.clone(),
+            NetDevice::Loopback(dev) => return self.poll_device(dev, sockets),
+            #[cfg(feature = "hotplug")]
+            NetDevice::Detached(dev) => return self.poll_device(dev, sockets),
+        };
+        // the queues of a NIC are serviced without the lock of the interface
+        drop(dev);
+        self.poll_device(&mut nic.service_queues(), sockets);
+    }
+
+    fn poll_device<D: Device>(&self, dev: &mut D, sockets: &Mutex<SocketSet>) {
         let mut iface = self.iface.lock();
         let mut sockets = sockets.lock();
-        let timestamp = Self::current_time();
-        iface.poll(timestamp, dev.deref_mut(), &mut sockets);
+        iface.poll(Self::current_time(), dev, &mut sockets);
     }
 }
 
 impl DeviceWrapper {
-    fn new(inner: AxNetDevice) -> Self {
+    fn new(inner: AxNetDevice, iface: usize) -> Self {
+        let num_queues = inner.num_queues().max(1);
+        if num_queues > 1 {
+            info!("{} queues of {:?}", num_queues, inner.device_name());
+        }
         Self {
-            inner: RefCell::new(inner),
+            inner: Mutex::new(inner),
+            iface,
+            queues: (0..num_queues)
+                .map(|_| NetQueue {
+                    backlog: Mutex::new(VecDeque::new()),
+                })
+                .collect(),
         }
     }
-}
 
-impl Device for DeviceWrapper {
-    type RxToken<'a> = AxNetRxToken<'a> where Self: 'a;
-    type TxToken<'a> = AxNetTxToken<'a> where Self: 'a;
+    /// Returns the NIC seen by smoltcp, which transmits on `queue`.
+    fn queue(&self, queue: usize) -> NicQueue<'_> {
+        NicQueue { nic: self, queue }
+    }
 
-    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
-        let mut dev = self.inner.borrow_mut();
-        if let Err(e) = dev.recycle_tx_buffers() {
-            warn!("recycle_tx_buffers failed: {:?}", e);
-            return None;
+    /// Returns the queue bound to the current CPU.
+    fn this_cpu_queue(&self) -> usize {
+        axhal::cpu::this_cpu_id() % self.queues.len()
+    }
+
+    /// Services the queue of the current CPU, then the other queues that are
+    /// not being serviced, as their CPUs may not poll.
+    ///
+    /// Returns the NIC seen by smoltcp, which transmits on the queue of the
+    /// current CPU.
+    fn service_queues(&self) -> NicQueue<'_> {
+        let queue = self.this_cpu_queue();
+        self.service(queue, &mut self.queues[queue].backlog.lock());
+        for (i, other) in self.queues.iter().enumerate() {
+            if i != queue {
+                if let Some(mut backlog) = other.backlog.try_lock() {
+                    self.service(i, &mut backlog);
+                }
+            }
         }
+        self.queue(queue)
+    }
 
-        if !dev.can_transmit() {
-            return None;
+    /// Gives back the transmitted buffers of `queue`, and moves the received
+    /// packets to its backlog.
+    fn service(&self, queue: usize, backlog: &mut VecDeque<RxPacket>) {
+        let mut dev = self.inner.lock();
+        if let Err(e) = dev.recycle_tx_buffers_on(queue) {
+            warn!("recycle_tx_buffers failed: {:?}", e);
         }
-        let rx_buf = match dev.receive() {
-            Ok(buf) => buf,
-            Err(err) => {
-                if !matches!(err, DevError::Again) {
-                    warn!("receive failed: {:?}", err);
+        while backlog.len() < RX_BATCH_SIZE {
+            match dev.receive_on(queue) {
+                Ok(buf) => backlog.push_back(RxPacket(buf)),
+                Err(DevError::Again) => break,
+                Err(e) => {
+                    warn!("receive failed: {:?}", e);
+                    break;
                 }
-                return None;
             }
-        };
-        Some((AxNetRxToken(&self.inner, rx_buf), AxNetTxToken(&self.inner)))
+        }
     }
 
-    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
-        let mut dev = self.inner.borrow_mut();
-        if let Err(e) = dev.recycle_tx_buffers() {
+    /// Takes a received packet from the backlog of `queue`, or from the
+    /// backlogs of the other queues that are not being serviced.
+    ///
+    /// Returns the packet and the queue it comes from.
+    fn pop_backlog(&self, queue: usize) -> Option<(usize, NetBufPtr)> {
+        if let Some(packet) = self.queues[queue].backlog.lock().pop_front() {
+            return Some((queue, packet.0));
+        }
+        let num_queues = self.queues.len();
+        (1..num_queues)
+            .map(|i| (queue + i) % num_queues)
+            .find_map(|other| {
+                let packet = self.queues[other].backlog.try_lock()?.pop_front()?;
+                Some((other, packet.0))
+            })
+    }
+}
+
+impl Drop for DeviceWrapper {
+    fn drop(&mut self) {
+        // give back the packets that are not processed
+        let dev = self.inner.get_mut();
+        for (i, queue) in self.queues.iter_mut().enumerate() {
+            for packet in queue.backlog.get_mut().drain(..) {
+                dev.recycle_rx_buffer_on(i, packet.0).ok();
+            }
+        }
+    }
+}
+
+impl NicQueue<'_> {
+    /// Gives back the transmitted buffers, and returns whether a packet can be
+    /// transmitted.
+    fn can_transmit(&self) -> bool {
+        let mut dev = self.nic.inner.lock();
+        if let Err(e) = dev.recycle_tx_buffers_on(self.queue) {
             warn!("recycle_tx_buffers failed: {:?}", e);
+            return false;
+        }
+        dev.can_transmit_on(self.queue)
+    }
+
+    fn tx_token(&self) -> AxNetTxToken<'_> {
+        AxNetTxToken {
+            nic: self.nic,
+            queue: self.queue,
+        }
+    }
+}
+
+impl Device for NicQueue<'_> {
+    type RxToken<'a> = AxNetRxToken<'a> where Self: 'a;
+    type TxToken<'a> = AxNetTxToken<'a> where Self: 'a;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        if !self.can_transmit() {
             return None;
         }
-        if dev.can_transmit() {
-            Some(AxNetTxToken(&self.inner))
+        let (queue, rx_buf) = self.nic.pop_backlog(self.queue)?;
+        Some((
+            AxNetRxToken {
+                nic: self.nic,
+                rx_buf,
+                queue,
+            },
+            self.tx_token(),
+        ))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        if self.can_transmit() {
+            Some(self.tx_token())
         } else {
             None
         }
@@ -228,33 +696,49 @@ impl Device for DeviceWrapper {
 
     fn capabilities(&self) -> DeviceCapabilities {
         let mut caps = DeviceCapabilities::default();
-        caps.max_transmission_unit = 1514;
+        caps.max_transmission_unit = self.nic.inner.lock().mtu() + ETHERNET_HEADER_LEN;
         caps.max_burst_size = None;
         caps.medium = Medium::Ethernet;
         caps
     }
 }
 
-struct AxNetRxToken<'a>(&'a RefCell<AxNetDevice>, NetBufPtr);
-struct AxNetTxToken<'a>(&'a RefCell<AxNetDevice>);
+struct AxNetRxToken<'a> {
+    nic: &'a DeviceWrapper,
+    rx_buf: NetBufPtr,
+    /// The receive queue that the buffer is given back to.
+    queue: usize,
+}
+
+/// A token to transmit a packet on the given queue.
+struct AxNetTxToken<'a> {
+    nic: &'a DeviceWrapper,
+    queue: usize,
+}
 
 impl<'a> RxToken for AxNetRxToken<'a> {
     fn preprocess(&self, sockets: &mut SocketSet<'_>) {
-        snoop_tcp_packet(self.1.packet(), sockets).ok();
+        snoop_ether_packet(self.nic.iface, self.rx_buf.packet(), sockets).ok();
     }
 
     fn consume<R, F>(self, f: F) -> R
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        let mut rx_buf = self.1;
+        let mut rx_buf = self.rx_buf;
         trace!(
             "RECV {} bytes: {:02X?}",
             rx_buf.packet_len(),
             rx_buf.packet()
         );
+        #[cfg(feature = "pcap")]
+        pcap::capture(rx_buf.packet());
         let result = f(rx_buf.packet_mut());
-        self.0.borrow_mut().recycle_rx_buffer(rx_buf).unwrap();
+        self.nic
+            .inner
+            .lock()
+            .recycle_rx_buffer_on(self.queue, rx_buf)
+            .unwrap();
         result
     }
 }
@@ -264,34 +748,139 @@ impl<'a> TxToken for AxNetTxToken<'a> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        let mut dev = self.0.borrow_mut();
-        let mut tx_buf = dev.alloc_tx_buffer(len).unwrap();
+        let mut tx_buf = self.nic.inner.lock().alloc_tx_buffer(len).unwrap();
         let ret = f(tx_buf.packet_mut());
         trace!("SEND {} bytes: {:02X?}", len, tx_buf.packet());
-        dev.transmit(tx_buf).unwrap();
+        #[cfg(feature = "pcap")]
+        pcap::capture(tx_buf.packet());
+        self.nic
+            .inner
+            .lock()
+            .transmit_on(self.queue, tx_buf)
+            .unwrap();
         ret
     }
 }
 
-fn snoop_tcp_packet(buf: &[u8], sockets: &mut SocketSet<'_>) -> Result<(), smoltcp::wire::Error> {
-    use smoltcp::wire::{EthernetFrame, IpProtocol, Ipv4Packet, TcpPacket};
+fn snoop_ether_packet(
+    iface: usize,
+    buf: &[u8],
+    sockets: &mut SocketSet<'_>,
+) -> Result<(), smoltcp::wire::Error> {
+    use smoltcp::wire::{EthernetFrame, EthernetProtocol};
 
     let ether_frame = EthernetFrame::new_checked(buf)?;
-    let ipv4_packet = Ipv4Packet::new_checked(ether_frame.payload())?;
-
-    if ipv4_packet.next_header() == IpProtocol::Tcp {
-        let tcp_packet = TcpPacket::new_checked(ipv4_packet.payload())?;
-        let src_addr = (ipv4_packet.src_addr(), tcp_packet.src_port()).into();
-        let dst_addr = (ipv4_packet.dst_addr(), tcp_packet.dst_port()).into();
-        let is_first = tcp_packet.syn() && !tcp_packet.ack();
-        if is_first {
-            // create a socket for the first incoming TCP packet, as the later accept() returns.
-            LISTEN_TABLE.incoming_tcp_packet(src_addr, dst_addr, sockets);
+    match ether_frame.ethertype() {
+        EthernetProtocol::Ipv4 | EthernetProtocol::Ipv6 => {
+            snoop_ip_packet(iface, ether_frame.payload(), sockets)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn snoop_ip_packet(
+    iface: usize,
+    buf: &[u8],
+    sockets: &mut SocketSet<'_>,
+) -> Result<(), smoltcp::wire::Error> {
+    use smoltcp::wire::{Ipv4Packet, Ipv6Packet};
+
+    match IpVersion::of_packet(buf)? {
+        IpVersion::Ipv4 => {
+            let ipv4_packet = Ipv4Packet::new_checked(buf)?;
+            if ipv4_packet.next_header() == IpProtocol::Tcp {
+                let src_addr = IpAddress::Ipv4(ipv4_packet.src_addr());
+                let dst_addr = IpAddress::Ipv4(ipv4_packet.dst_addr());
+                snoop_tcp_packet(iface, src_addr, dst_addr, ipv4_packet.payload(), sockets)?;
+            }
         }
+        IpVersion::Ipv6 => {
+            let ipv6_packet = Ipv6Packet::new_checked(buf)?;
+            match ipv6_packet.next_header() {
+                IpProtocol::Tcp => {
+                    let src_addr = IpAddress::Ipv6(ipv6_packet.src_addr());
+                    let dst_addr = IpAddress::Ipv6(ipv6_packet.dst_addr());
+                    snoop_tcp_packet(iface, src_addr, dst_addr, ipv6_packet.payload(), sockets)?;
+                }
+                IpProtocol::Icmpv6 => ipv6::snoop_router_advert(&IFACES[iface], &ipv6_packet)?,
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn snoop_tcp_packet(
+    iface: usize,
+    src_ip: IpAddress,
+    dst_ip: IpAddress,
+    payload: &[u8],
+    sockets: &mut SocketSet<'_>,
+) -> Result<(), smoltcp::wire::Error> {
+    use smoltcp::wire::TcpPacket;
+
+    let tcp_packet = TcpPacket::new_checked(payload)?;
+    let src_addr = (src_ip, tcp_packet.src_port()).into();
+    let dst_addr = (dst_ip, tcp_packet.dst_port()).into();
+    let is_first = tcp_packet.syn() && !tcp_packet.ack();
+    if is_first {
+        // create a socket for the first incoming TCP packet, as the later accept() returns.
+        LISTEN_TABLE.incoming_tcp_packet(src_addr, dst_addr, iface, sockets);
     }
     Ok(())
 }
 
+/// Returns the route to all addresses of the given IP version.
+fn default_cidr(version: IpVersion) -> IpCidr {
+    match version {
+        IpVersion::Ipv4 => IpCidr::new(IpAddress::Ipv4(Ipv4Address::UNSPECIFIED), 0),
+        IpVersion::Ipv6 => IpCidr::new(IpAddress::Ipv6(Ipv6Address::UNSPECIFIED), 0),
+    }
+}
+
+/// Returns all interfaces except the loopback interface.
+fn nics() -> &'static [InterfaceWrapper] {
+    &IFACES[ETH0..]
+}
+
+/// Returns the interfaces that a socket bound to the unspecified address
+/// listens on, i.e. those with addresses.
+///
+/// The interfaces without NICs are skipped, so no socket buffers are charged
+/// for them.
+fn addressed_ifaces() -> Vec<usize> {
+    IFACES
+        .iter()
+        .filter(|iface| iface.has_ip_addrs())
+        .map(|iface| iface.index)
+        .collect()
+}
+
+/// Returns the interface that has the address `addr`.
+fn iface_of_addr(addr: IpAddress) -> Option<usize> {
+    IFACES.iter().position(|iface| iface.has_ip_addr(addr))
+}
+
+/// Chooses the interface for a socket bound to `local` to reach `remote`.
+///
+/// A socket bound to an address always uses the interface of that address,
+/// otherwise the routing table decides.
+fn egress_iface(local: Option<IpAddress>, remote: IpAddress) -> Option<usize> {
+    match local {
+        Some(addr) => iface_of_addr(addr),
+        None => ROUTES.lookup(remote),
+    }
+}
+
+fn dns_servers() -> Vec<IpAddress> {
+    DNS_SERVERS.lock().clone()
+}
+
+/// Replaces the DNS servers used by new DNS queries.
+fn setup_dns_servers(servers: &[IpAddress]) {
+    *DNS_SERVERS.lock() = servers.to_vec();
+}
+
 /// Poll the network stack.
 ///
 /// It may receive packets from the NIC and process them, and transmit queued
@@ -300,31 +889,92 @@ pub fn poll_interfaces() {
     SOCKET_SET.poll_interfaces();
 }
 
+fn with_eth0_device<F: FnOnce(&DeviceWrapper)>(f: F) {
+    let eth0 = nics().first().expect("No NIC device found!");
+    let nic = match eth0.dev.lock().deref() {
+        NetDevice::Nic(nic) => nic.clone(),
+        _ => panic!("No NIC device attached to eth0!"),
+    };
+    f(&nic)
+}
+
 /// Benchmark raw socket transmit bandwidth.
 pub fn bench_transmit() {
-    ETH0.dev.lock().bench_transmit_bandwidth();
+    with_eth0_device(|dev| dev.bench_transmit_bandwidth());
 }
 
 /// Benchmark raw socket receive bandwidth.
 pub fn bench_receive() {
-    ETH0.dev.lock().bench_receive_bandwidth();
+    with_eth0_device(|dev| dev.bench_receive_bandwidth());
 }
 
-pub(crate) fn init(net_dev: AxNetDevice) {
-    let ether_addr = EthernetAddress(net_dev.mac_address().0);
-    let eth0 = InterfaceWrapper::new("eth0", net_dev, ether_addr);
-
+/// Applies the static configuration from `AX_IP` and `AX_GW` to `eth0`.
+fn setup_static_config(iface: &InterfaceWrapper) {
+    if iface.index != ETH0 || (cfg!(feature = "dhcp") && IP.is_empty()) {
+        warn!("no static IP address for {:?}", iface.name());
+        return;
+    }
     let ip = IP.parse().expect("invalid IP address");
     let gateway = GATEWAY.parse().expect("invalid gateway IP address");
-    eth0.setup_ip_addr(ip, IP_PREFIX);
-    eth0.setup_gateway(gateway);
-
-    ETH0.init_by(eth0);
-    SOCKET_SET.init_by(SocketSetWrapper::new());
-    LISTEN_TABLE.init_by(ListenTable::new());
+    let dns_server = DNS_SEVER.parse().expect("invalid DNS server address");
+    iface.setup_ip_addr(IpCidr::new(ip, IP_PREFIX));
+    iface.setup_gateway(gateway);
+    setup_dns_servers(&[dns_server]);
 
-    info!("created net interface {:?}:", ETH0.name());
-    info!("  ether:    {}", ETH0.ethernet_address());
     info!("  ip:       {}/{}", ip, IP_PREFIX);
     info!("  gateway:  {}", gateway);
 }
+
+pub(crate) fn init(net_devs: Vec<AxNetDevice>) {
+    let mut ifaces = vec![InterfaceWrapper::new_loopback(LOOPBACK)];
+    for dev in net_devs {
+        let index = ifaces.len();
+        let name = format!("eth{}", index - ETH0);
+        ifaces.push(InterfaceWrapper::new(name, index, dev));
+    }
+    #[cfg(feature = "hotplug")]
+    for _ in 0..hotplug::HOTPLUG_IFACES {
+        let index = ifaces.len();
+        let name = format!("eth{}", index - ETH0);
+        ifaces.push(InterfaceWrapper::new_detached(name, index));
+    }
+
+    SOCKET_SET.init_by(SocketSetWrapper::new(ifaces.len()));
+    IFACES.init_by(ifaces);
+    LISTEN_TABLE.init_by(ListenTable::new());
+
+    let lo = &IFACES[LOOPBACK];
+    lo.setup_ip_addr(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8));
+    lo.setup_ip_addr(IpCidr::new(IpAddress::v6(0, 0, 0, 0, 0, 0, 0, 1), 128));
+    info!("created net interface {:?}:", lo.name());
+    info!("  ip:       127.0.0.1/8");
+    info!("  ip6:      ::1/128");
+    info!("  mtu:      {}", lo.mtu());
+
+    for iface in nics() {
+        info!("created net interface {:?}:", iface.name());
+        let Some(ether_addr) = iface.ethernet_address() else {
+            info!("  no NIC attached");
+            continue;
+        };
+        info!("  ether:    {}", ether_addr);
+        info!("  mtu:      {}", iface.mtu());
+        ipv6::init(iface);
+    }
+
+    #[cfg(feature = "dhcp")]
+    dhcp::init(nics());
+    if let Some(eth0) = nics().first() {
+        #[cfg(feature = "dhcp")]
+        let configured = eth0.dhcp.is_configured();
+        #[cfg(not(feature = "dhcp"))]
+        let configured = false;
+        if !configured {
+            setup_static_config(eth0);
+        }
+    }
+
+    wait::init();
+    #[cfg(feature = "hotplug")]
+    hotplug::init();
+}
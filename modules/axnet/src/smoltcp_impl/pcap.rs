@@ -0,0 +1,308 @@
+//! Packet capture in the [pcap] format.
+//!
+//! The ethernet frames received and transmitted by the NICs are recorded
+//! while a capture is running, either into a ring buffer in memory that can
+//! be dumped later, or directly into a writer such as a file. The loopback
+//! interface is not captured.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use axnet::pcap::{self, Filter, Output};
+//!
+//! // capture the TCP segments of port 80 into a 1 MiB ring buffer
+//! let filter = Filter {
+//!     protocol: Some(6),
+//!     port: Some(80),
+//! };
+//! pcap::start(filter, Output::Ring(1024 * 1024));
+//! // ...
+//! pcap::stop();
+//! // print the capture as hex, it can be restored by `xxd -r -p`
+//! pcap::dump_to_console().unwrap();
+//! ```
+//!
+//! [pcap]: https://wiki.wireshark.org/Development/LibpcapFileFormat
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{ax_err, AxResult};
+use axio::Write;
+use axsync::Mutex;
+use smoltcp::phy::{PcapLinkType, PcapSink};
+use smoltcp::time::Instant;
+use smoltcp::wire::{EthernetFrame, EthernetProtocol, IpProtocol};
+use smoltcp::wire::{Ipv4Packet, Ipv6Packet};
+
+/// Which packets to capture. A packet is captured if it matches all the
+/// options that are set.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Filter {
+    /// The IP protocol number, e.g. 6 for TCP and 17 for UDP. Non-IP frames
+    /// (e.g. ARP) never match.
+    pub protocol: Option<u8>,
+    /// The TCP or UDP port, which matches either the source or the
+    /// destination port. Packets of other protocols never match.
+    pub port: Option<u16>,
+}
+
+/// Where the captured packets go.
+pub enum Output {
+    /// A ring buffer of the given size in bytes, the oldest packets are
+    /// dropped when it is full. It's read by [`dump`] or [`dump_to_console`].
+    Ring(usize),
+    /// A writer (e.g. a file) that receives a pcap file as packets arrive.
+    Writer(Box<dyn Write + Send>),
+}
+
+struct RingBuffer {
+    packets: VecDeque<(Instant, Vec<u8>)>,
+    len: usize,
+    capacity: usize,
+}
+
+struct Capture {
+    filter: Filter,
+    output: Output,
+}
+
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+static CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+/// The ring buffer of the last capture to [`Output::Ring`].
+static RING: Mutex<Option<RingBuffer>> = Mutex::new(None);
+
+/// Adapts an [`axio::Write`] to [`PcapSink`], keeping the first error.
+struct WriterSink<'a> {
+    writer: &'a mut dyn Write,
+    result: AxResult,
+}
+
+impl PcapSink for WriterSink<'_> {
+    fn write(&mut self, data: &[u8]) {
+        if self.result.is_ok() {
+            self.result = self.writer.write_all(data);
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.result.is_ok() {
+            self.result = self.writer.flush();
+        }
+    }
+}
+
+/// Writes bytes to the console as hex, 32 bytes per line.
+struct HexConsole {
+    column: usize,
+}
+
+impl Write for HexConsole {
+    fn write(&mut self, buf: &[u8]) -> AxResult<usize> {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+        for &b in buf {
+            let hex = [HEX[(b >> 4) as usize], HEX[(b & 0xf) as usize]];
+            axhal::console::write_bytes(&hex);
+            self.column += 1;
+            if self.column == 32 {
+                axhal::console::write_bytes(b"\n");
+                self.column = 0;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> AxResult {
+        if self.column != 0 {
+            axhal::console::write_bytes(b"\n");
+            self.column = 0;
+        }
+        Ok(())
+    }
+}
+
+impl RingBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            packets: VecDeque::new(),
+            len: 0,
+            capacity,
+        }
+    }
+
+    fn push(&mut self, timestamp: Instant, packet: &[u8]) {
+        if packet.len() > self.capacity {
+            return;
+        }
+        while self.len + packet.len() > self.capacity {
+            let (_, oldest) = self.packets.pop_front().unwrap();
+            self.len -= oldest.len();
+        }
+        self.len += packet.len();
+        self.packets.push_back((timestamp, packet.to_vec()));
+    }
+}
+
+impl Filter {
+    /// Whether the ethernet frame `frame` matches the filter.
+    fn matches(&self, frame: &[u8]) -> bool {
+        if self.protocol.is_none() && self.port.is_none() {
+            return true;
+        }
+        let Some((protocol, payload)) = ip_payload(frame) else {
+            return false;
+        };
+        if self
+            .protocol
+            .is_some_and(|p| IpProtocol::from(p) != protocol)
+        {
+            return false;
+        }
+        match self.port {
+            Some(port) => match protocol {
+                IpProtocol::Tcp | IpProtocol::Udp if payload.len() >= 4 => {
+                    let src_port = u16::from_be_bytes([payload[0], payload[1]]);
+                    let dst_port = u16::from_be_bytes([payload[2], payload[3]]);
+                    src_port == port || dst_port == port
+                }
+                _ => false,
+            },
+            None => true,
+        }
+    }
+}
+
+/// Returns the protocol and the payload of the IP packet in an ethernet
+/// frame. The payload is empty if it's not the first fragment.
+fn ip_payload(frame: &[u8]) -> Option<(IpProtocol, &[u8])> {
+    let frame = EthernetFrame::new_checked(frame).ok()?;
+    match frame.ethertype() {
+        EthernetProtocol::Ipv4 => {
+            let packet = Ipv4Packet::new_checked(frame.payload()).ok()?;
+            let payload = match packet.frag_offset() {
+                0 => &frame.payload()[packet.header_len() as usize..],
+                _ => &[],
+            };
+            Some((packet.next_header(), payload))
+        }
+        EthernetProtocol::Ipv6 => {
+            // extension headers are not skipped
+            let packet = Ipv6Packet::new_checked(frame.payload()).ok()?;
+            let payload = &frame.payload()[packet.header_len()..];
+            Some((packet.next_header(), payload))
+        }
+        _ => None,
+    }
+}
+
+fn current_time() -> Instant {
+    Instant::from_micros(axhal::time::current_time().as_micros() as i64)
+}
+
+/// Starts capturing packets that match `filter` into `output`, replacing the
+/// running capture if any.
+///
+/// The pcap header is written to the writer of [`Output::Writer`] at once,
+/// and the capture stops if writing fails.
+pub fn start(filter: Filter, output: Output) {
+    let mut capture = CAPTURE.lock();
+    let output = match output {
+        Output::Ring(capacity) => {
+            *RING.lock() = Some(RingBuffer::new(capacity));
+            Output::Ring(capacity)
+        }
+        Output::Writer(mut writer) => {
+            let mut sink = WriterSink {
+                writer: writer.as_mut(),
+                result: Ok(()),
+            };
+            sink.global_header(PcapLinkType::Ethernet);
+            if let Err(e) = sink.result {
+                warn!("pcap: failed to write the header: {:?}", e);
+                return;
+            }
+            Output::Writer(writer)
+        }
+    };
+    *capture = Some(Capture { filter, output });
+    CAPTURING.store(true, Ordering::Release);
+    info!("pcap: capture started with {:?}", filter);
+}
+
+/// Stops the running capture. The ring buffer is kept for [`dump`].
+pub fn stop() {
+    let mut capture = CAPTURE.lock();
+    CAPTURING.store(false, Ordering::Release);
+    if let Some(Capture {
+        output: Output::Writer(mut writer),
+        ..
+    }) = capture.take()
+    {
+        writer.flush().ok();
+    }
+}
+
+/// Writes the packets in the ring buffer of the last capture to `writer`, as
+/// a pcap file.
+pub fn dump(writer: &mut dyn Write) -> AxResult {
+    let ring = RING.lock();
+    let Some(ring) = ring.as_ref() else {
+        return ax_err!(NotFound, "pcap: no ring buffer to dump");
+    };
+    let mut sink = WriterSink {
+        writer,
+        result: Ok(()),
+    };
+    sink.global_header(PcapLinkType::Ethernet);
+    for (timestamp, packet) in ring.packets.iter() {
+        sink.packet(*timestamp, packet);
+    }
+    sink.flush();
+    sink.result
+}
+
+/// Prints the packets in the ring buffer of the last capture to the console,
+/// as a pcap file in hex. It can be restored by `xxd -r -p`.
+pub fn dump_to_console() -> AxResult {
+    axhal::console::write_bytes(b"---- pcap begin ----\n");
+    let res = dump(&mut HexConsole { column: 0 });
+    axhal::console::write_bytes(b"---- pcap end ----\n");
+    res
+}
+
+/// Records an ethernet frame received or transmitted by a NIC.
+pub(super) fn capture(frame: &[u8]) {
+    if !CAPTURING.load(Ordering::Acquire) {
+        return;
+    }
+    let mut capture = CAPTURE.lock();
+    let Some(Capture { filter, output }) = capture.as_mut() else {
+        return;
+    };
+    if !filter.matches(frame) {
+        return;
+    }
+
+    let timestamp = current_time();
+    match output {
+        Output::Ring(_) => {
+            if let Some(ring) = RING.lock().as_mut() {
+                ring.push(timestamp, frame);
+            }
+        }
+        Output::Writer(writer) => {
+            let mut sink = WriterSink {
+                writer: writer.as_mut(),
+                result: Ok(()),
+            };
+            sink.packet(timestamp, frame);
+            if let Err(e) = sink.result {
+                warn!("pcap: failed to write, capture stopped: {:?}", e);
+                *capture = None;
+                CAPTURING.store(false, Ordering::Release);
+            }
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use alloc::vec::Vec;
+use core::net::IpAddr;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use axerrno::{ax_err, ax_err_type, AxError, AxResult};
+use axio::PollState;
+use axsync::Mutex;
+
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::socket::raw::{self, SendError};
+use smoltcp::wire::{Icmpv6Packet, IpAddress, IpProtocol, IpVersion};
+use smoltcp::wire::{Ipv4Packet, Ipv4Repr, Ipv6Packet, Ipv6Repr};
+
+use super::addr::{from_core_ipaddr, into_core_ipaddr};
+use super::wait::{self, SocketWaiter};
+use super::DEFAULT_HOP_LIMIT;
+use super::{addressed_ifaces, SocketHandle, SocketSetWrapper, IFACES, ROUTES, SOCKET_SET};
+
+/// Options of a raw socket.
+#[derive(Debug, Clone, Copy)]
+struct RawOptions {
+    hop_limit: u8,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+/// A raw IP socket that provides POSIX-like APIs.
+///
+/// As the Linux `SOCK_RAW` sockets, it receives all packets of its IP version
+/// and protocol, with the IP header for IPv4 and without it for IPv6. The
+/// packets sent only contain the payload, the IP header is built by the
+/// socket. The checksum of ICMPv6 packets is computed by the socket, while
+/// the other payloads are sent as they are.
+pub struct RawSocket {
+    handles: Vec<SocketHandle>,
+    version: IpVersion,
+    protocol: IpProtocol,
+    nonblock: AtomicBool,
+    options: Mutex<RawOptions>,
+    waiter: SocketWaiter,
+}
+
+impl RawSocket {
+    /// Creates a new raw socket of the given IP version and protocol number.
+    ///
+    /// It receives packets on all interfaces with addresses at once (i.e., not
+    /// on the interfaces without NICs when it's created). Returns
+    /// [`Err(NoMemory)`](AxError::NoMemory) if the network buffers run out.
+    pub fn new(ipv6: bool, protocol: u8) -> AxResult<Self> {
+        let version = match ipv6 {
+            false => IpVersion::Ipv4,
+            true => IpVersion::Ipv6,
+        };
+        let protocol = IpProtocol::from(protocol);
+        let ifaces = addressed_ifaces();
+        let sockets = ifaces
+            .iter()
+            .map(|_| SocketSetWrapper::new_raw_socket(version, protocol))
+            .collect::<AxResult<Vec<_>>>()?;
+        let handles = ifaces
+            .into_iter()
+            .zip(sockets)
+            .map(|(iface, socket)| SOCKET_SET.add_charged(iface, socket))
+            .collect();
+        Ok(Self {
+            handles,
+            version,
+            protocol,
+            nonblock: AtomicBool::new(false),
+            options: Mutex::new(RawOptions {
+                hop_limit: DEFAULT_HOP_LIMIT,
+                read_timeout: None,
+                write_timeout: None,
+            }),
+            waiter: SocketWaiter::new(),
+        })
+    }
+
+    /// Returns whether this socket is in nonblocking mode.
+    #[inline]
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Moves this raw socket into or out of nonblocking mode.
+    #[inline]
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblock.store(nonblocking, Ordering::Release);
+    }
+
+    /// Returns the time-to-live (or hop limit of IPv6) of the outgoing
+    /// packets (`IP_TTL`).
+    pub fn ttl(&self) -> u8 {
+        self.options.lock().hop_limit
+    }
+
+    /// Sets the time-to-live (or hop limit of IPv6) of the outgoing packets
+    /// (`IP_TTL`).
+    pub fn set_ttl(&self, ttl: u8) -> AxResult {
+        if ttl == 0 {
+            return ax_err!(InvalidInput, "zero TTL");
+        }
+        self.options.lock().hop_limit = ttl;
+        Ok(())
+    }
+
+    /// Returns the timeout of the receive operations (`SO_RCVTIMEO`).
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.options.lock().read_timeout
+    }
+
+    /// Sets the timeout of the receive operations (`SO_RCVTIMEO`), after which
+    /// they fail with [`Err(WouldBlock)`](AxError::WouldBlock). `None` means
+    /// no timeout.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout.is_some_and(|t| t.is_zero()) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.options.lock().read_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of the send operations (`SO_SNDTIMEO`).
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.options.lock().write_timeout
+    }
+
+    /// Sets the timeout of the send operations (`SO_SNDTIMEO`), after which
+    /// they fail with [`Err(WouldBlock)`](AxError::WouldBlock). `None` means
+    /// no timeout.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout.is_some_and(|t| t.is_zero()) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.options.lock().write_timeout = timeout;
+        Ok(())
+    }
+
+    /// Sends a packet with the payload `buf` to the given address. On success,
+    /// returns the number of bytes written.
+    pub fn send_to(&self, buf: &[u8], remote_addr: IpAddr) -> AxResult<usize> {
+        let dst_addr = from_core_ipaddr(remote_addr);
+        if dst_addr.version() != self.version || dst_addr.is_unspecified() {
+            return ax_err!(InvalidInput, "socket send_to() failed: invalid address");
+        }
+        let iface = ROUTES
+            .lookup(dst_addr)
+            .ok_or_else(|| ax_err_type!(ConnectionRefused, "socket send_to() failed: no route"))?;
+        let src_addr = IFACES[iface].source_addr(dst_addr).ok_or_else(|| {
+            ax_err_type!(ConnectionRefused, "socket send_to() failed: no address")
+        })?;
+        let handle = *self
+            .handles
+            .iter()
+            .find(|handle| handle.iface == iface)
+            .ok_or_else(|| ax_err_type!(ConnectionRefused, "socket send_to() failed"))?;
+        let hop_limit = self.ttl();
+
+        self.block_on(self.write_timeout(), || {
+            SOCKET_SET.with_socket_mut::<raw::Socket, _, _>(handle, |socket| {
+                if !socket.can_send() {
+                    // tx buffer is full
+                    self.waiter
+                        .register(|waker| socket.register_send_waker(waker));
+                    return Err(AxError::WouldBlock);
+                }
+                self.emit_packet(socket, src_addr, dst_addr, hop_limit, buf)
+            })
+        })?;
+        wait::kick();
+        Ok(buf.len())
+    }
+
+    /// Receives a single packet on the socket. On success, returns the number
+    /// of bytes read and the origin.
+    pub fn recv_from(&self, buf: &mut [u8]) -> AxResult<(usize, IpAddr)> {
+        self.block_on(self.read_timeout(), || {
+            for &handle in &self.handles {
+                let res = SOCKET_SET.with_socket_mut::<raw::Socket, _, _>(handle, |socket| {
+                    self.waiter
+                        .register(|waker| socket.register_recv_waker(waker));
+                    socket.can_recv().then(|| -> AxResult<(usize, IpAddr)> {
+                        let err = || ax_err_type!(BadState, "socket recv_from() failed");
+                        let packet = socket.recv().map_err(|_| err())?;
+                        let (src_addr, data) = match self.version {
+                            IpVersion::Ipv4 => {
+                                let ipv4_packet =
+                                    Ipv4Packet::new_checked(packet).map_err(|_| err())?;
+                                (IpAddress::Ipv4(ipv4_packet.src_addr()), packet)
+                            }
+                            IpVersion::Ipv6 => {
+                                let ipv6_packet =
+                                    Ipv6Packet::new_checked(packet).map_err(|_| err())?;
+                                let src_addr = IpAddress::Ipv6(ipv6_packet.src_addr());
+                                (src_addr, &packet[ipv6_packet.header_len()..])
+                            }
+                        };
+                        let len = data.len().min(buf.len());
+                        buf[..len].copy_from_slice(&data[..len]);
+                        Ok((len, into_core_ipaddr(src_addr)))
+                    })
+                });
+                if let Some(res) = res {
+                    return res;
+                }
+            }
+            // no more data
+            Err(AxError::WouldBlock)
+        })
+    }
+
+    /// Whether the socket is readable or writable.
+    pub fn poll(&self) -> AxResult<PollState> {
+        let mut state = PollState {
+            readable: false,
+            writable: false,
+        };
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket::<raw::Socket, _, _>(handle, |socket| {
+                state.readable |= socket.can_recv();
+                state.writable |= socket.can_send();
+            });
+        }
+        Ok(state)
+    }
+}
+
+/// Private methods
+impl RawSocket {
+    /// Writes an IP packet with the payload `buf` to the tx buffer of `socket`.
+    fn emit_packet(
+        &self,
+        socket: &mut raw::Socket,
+        src_addr: IpAddress,
+        dst_addr: IpAddress,
+        hop_limit: u8,
+        buf: &[u8],
+    ) -> AxResult {
+        let map_err = |e: SendError| match e {
+            SendError::BufferFull => AxError::WouldBlock,
+        };
+        match (src_addr, dst_addr) {
+            (IpAddress::Ipv4(src_addr), IpAddress::Ipv4(dst_addr)) => {
+                let repr = Ipv4Repr {
+                    src_addr,
+                    dst_addr,
+                    next_header: self.protocol,
+                    payload_len: buf.len(),
+                    hop_limit,
+                };
+                let packet = socket
+                    .send(repr.buffer_len() + buf.len())
+                    .map_err(map_err)?;
+                let mut ipv4_packet = Ipv4Packet::new_unchecked(packet);
+                repr.emit(&mut ipv4_packet, &ChecksumCapabilities::default());
+                ipv4_packet.payload_mut().copy_from_slice(buf);
+            }
+            (IpAddress::Ipv6(src_addr), IpAddress::Ipv6(dst_addr)) => {
+                let repr = Ipv6Repr {
+                    src_addr,
+                    dst_addr,
+                    next_header: self.protocol,
+                    payload_len: buf.len(),
+                    hop_limit,
+                };
+                let packet = socket
+                    .send(repr.buffer_len() + buf.len())
+                    .map_err(map_err)?;
+                let mut ipv6_packet = Ipv6Packet::new_unchecked(packet);
+                repr.emit(&mut ipv6_packet);
+                let payload = ipv6_packet.payload_mut();
+                payload.copy_from_slice(buf);
+                if self.protocol == IpProtocol::Icmpv6 {
+                    if let Ok(mut icmp_packet) = Icmpv6Packet::new_checked(payload) {
+                        icmp_packet
+                            .fill_checksum(&IpAddress::Ipv6(src_addr), &IpAddress::Ipv6(dst_addr));
+                    }
+                }
+            }
+            _ => return ax_err!(InvalidInput, "socket send_to() failed: invalid address"),
+        }
+        Ok(())
+    }
+
+    fn block_on<F, T>(&self, timeout: Option<Duration>, f: F) -> AxResult<T>
+    where
+        F: FnMut() -> AxResult<T>,
+    {
+        self.waiter.block_on(self.is_nonblocking(), timeout, f)
+    }
+}
+
+impl Drop for RawSocket {
+    fn drop(&mut self) {
+        for &handle in &self.handles {
+            SOCKET_SET.remove(handle);
+        }
+    }
+}
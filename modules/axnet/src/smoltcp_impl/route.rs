@@ -0,0 +1,58 @@
+//! The routing table that chooses the egress interface for each destination.
+
+use alloc::vec::Vec;
+use spin::RwLock;
+
+use smoltcp::wire::{IpAddress, IpCidr};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Route {
+    cidr: IpCidr,
+    iface: usize,
+}
+
+/// Routes to the networks of the interfaces, and the default routes.
+///
+/// The gateways themselves are handled by the route table of each smoltcp
+/// interface, this table only decides which interface to use.
+pub struct RouteTable {
+    // sorted by prefix length in descending order
+    routes: RwLock<Vec<Route>>,
+}
+
+impl RouteTable {
+    pub const fn new() -> Self {
+        Self {
+            routes: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Adds a route to `cidr` via the interface `iface`.
+    pub fn add(&self, cidr: IpCidr, iface: usize) {
+        let route = Route { cidr, iface };
+        let mut routes = self.routes.write();
+        if routes.contains(&route) {
+            return;
+        }
+        let idx = routes
+            .iter()
+            .position(|r| r.cidr.prefix_len() < cidr.prefix_len())
+            .unwrap_or(routes.len());
+        routes.insert(idx, route);
+    }
+
+    /// Removes the route to `cidr` via the interface `iface`.
+    pub fn remove(&self, cidr: IpCidr, iface: usize) {
+        let route = Route { cidr, iface };
+        self.routes.write().retain(|r| *r != route);
+    }
+
+    /// Returns the interface to reach `dst`, by the longest prefix match.
+    pub fn lookup(&self, dst: IpAddress) -> Option<usize> {
+        self.routes
+            .read()
+            .iter()
+            .find(|r| r.cidr.contains_addr(&dst))
+            .map(|r| r.iface)
+    }
+}
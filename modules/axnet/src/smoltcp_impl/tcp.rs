@@ -1,17 +1,20 @@
 use core::cell::UnsafeCell;
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use core::time::Duration;
 
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
 use axio::PollState;
 use axsync::Mutex;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::tcp::{self, ConnectError, State};
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use super::addr::{from_core_sockaddr, into_core_sockaddr, is_unspecified, UNSPECIFIED_ENDPOINT};
-use super::{SocketSetWrapper, ETH0, LISTEN_TABLE, SOCKET_SET};
+use super::wait::{self, SocketWaiter};
+use super::{egress_iface, iface_of_addr, ChargedSocket, SocketHandle, SocketSetWrapper};
+use super::{DEFAULT_HOP_LIMIT, LISTEN_TABLE, SOCKET_BUF_LEN_RANGE, SOCKET_SET};
+use super::{TCP_RX_BUF_LEN, TCP_TX_BUF_LEN};
 
 // State transitions:
 // CLOSED -(connect)-> BUSY -> CONNECTING -> CONNECTED -(shutdown)-> BUSY -> CLOSED
@@ -25,6 +28,58 @@ const STATE_CONNECTING: u8 = 2;
 const STATE_CONNECTED: u8 = 3;
 const STATE_LISTENING: u8 = 4;
 
+/// The default keep-alive interval, as `tcp_keepalive_time` of Linux.
+const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(7200);
+
+/// Options of a TCP socket.
+///
+/// A listener passes its options to the connections it accepts.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TcpOptions {
+    nodelay: bool,
+    keep_alive: bool,
+    keep_alive_interval: Duration,
+    hop_limit: Option<u8>,
+    recv_buf_len: usize,
+    send_buf_len: usize,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    reuse_addr: bool,
+    linger: Option<Duration>,
+}
+
+impl TcpOptions {
+    const fn new() -> Self {
+        Self {
+            nodelay: false,
+            keep_alive: false,
+            keep_alive_interval: DEFAULT_KEEP_ALIVE_INTERVAL,
+            hop_limit: None,
+            recv_buf_len: TCP_RX_BUF_LEN,
+            send_buf_len: TCP_TX_BUF_LEN,
+            read_timeout: None,
+            write_timeout: None,
+            reuse_addr: false,
+            linger: None,
+        }
+    }
+
+    /// Creates a smoltcp socket with the options.
+    pub fn new_socket<'a>(&self) -> AxResult<ChargedSocket<tcp::Socket<'a>>> {
+        let mut socket = SocketSetWrapper::new_tcp_socket(self.recv_buf_len, self.send_buf_len)?;
+        self.apply(&mut socket);
+        Ok(socket)
+    }
+
+    /// Applies the options to a smoltcp socket, except for the buffer sizes
+    /// which are fixed once the socket is created.
+    fn apply(&self, socket: &mut tcp::Socket) {
+        socket.set_nagle_enabled(!self.nodelay);
+        socket.set_keep_alive(self.keep_alive.then_some(self.keep_alive_interval));
+        socket.set_hop_limit(self.hop_limit);
+    }
+}
+
 /// A TCP socket that provides POSIX-like APIs.
 ///
 /// - [`connect`] is for TCP clients.
@@ -41,27 +96,33 @@ pub struct TcpSocket {
     local_addr: UnsafeCell<IpEndpoint>,
     peer_addr: UnsafeCell<IpEndpoint>,
     nonblock: AtomicBool,
+    options: Mutex<TcpOptions>,
+    waiter: SocketWaiter,
 }
 
 unsafe impl Sync for TcpSocket {}
 
 impl TcpSocket {
     /// Creates a new TCP socket.
-    pub const fn new() -> Self {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
         Self {
             state: AtomicU8::new(STATE_CLOSED),
             handle: UnsafeCell::new(None),
             local_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             peer_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             nonblock: AtomicBool::new(false),
+            options: Mutex::new(TcpOptions::new()),
+            waiter: SocketWaiter::new(),
         }
     }
 
     /// Creates a new TCP socket that is already connected.
-    const fn new_connected(
+    fn new_connected(
         handle: SocketHandle,
         local_addr: IpEndpoint,
         peer_addr: IpEndpoint,
+        options: TcpOptions,
     ) -> Self {
         Self {
             state: AtomicU8::new(STATE_CONNECTED),
@@ -69,6 +130,8 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(local_addr),
             peer_addr: UnsafeCell::new(peer_addr),
             nonblock: AtomicBool::new(false),
+            options: Mutex::new(options),
+            waiter: SocketWaiter::new(),
         }
     }
 
@@ -115,19 +178,172 @@ impl TcpSocket {
         self.nonblock.store(nonblocking, Ordering::Release);
     }
 
+    /// Returns whether the Nagle's algorithm is disabled (`TCP_NODELAY`).
+    pub fn nodelay(&self) -> bool {
+        self.options.lock().nodelay
+    }
+
+    /// Disables or enables the Nagle's algorithm (`TCP_NODELAY`).
+    pub fn set_nodelay(&self, nodelay: bool) {
+        self.update_options(|opts| opts.nodelay = nodelay);
+    }
+
+    /// Returns whether keep-alive packets are sent (`SO_KEEPALIVE`).
+    pub fn keep_alive(&self) -> bool {
+        self.options.lock().keep_alive
+    }
+
+    /// Enables or disables sending keep-alive packets (`SO_KEEPALIVE`).
+    pub fn set_keep_alive(&self, keep_alive: bool) {
+        self.update_options(|opts| opts.keep_alive = keep_alive);
+    }
+
+    /// Returns the keep-alive interval.
+    pub fn keep_alive_interval(&self) -> Duration {
+        self.options.lock().keep_alive_interval
+    }
+
+    /// Sets how long the connection is idle before a keep-alive packet is
+    /// sent. The packets are repeated at the same interval until the remote
+    /// answers.
+    pub fn set_keep_alive_interval(&self, interval: Duration) -> AxResult {
+        if interval.is_zero() {
+            return ax_err!(InvalidInput, "zero keep-alive interval");
+        }
+        self.update_options(|opts| opts.keep_alive_interval = interval);
+        Ok(())
+    }
+
+    /// Returns the time-to-live (or hop limit of IPv6) of the outgoing
+    /// packets (`IP_TTL`).
+    pub fn ttl(&self) -> u8 {
+        self.options.lock().hop_limit.unwrap_or(DEFAULT_HOP_LIMIT)
+    }
+
+    /// Sets the time-to-live (or hop limit of IPv6) of the outgoing packets
+    /// (`IP_TTL`).
+    pub fn set_ttl(&self, ttl: u8) -> AxResult {
+        if ttl == 0 {
+            return ax_err!(InvalidInput, "zero TTL");
+        }
+        self.update_options(|opts| opts.hop_limit = Some(ttl));
+        Ok(())
+    }
+
+    /// Returns the size of the receive buffer (`SO_RCVBUF`).
+    pub fn recv_buffer_size(&self) -> usize {
+        self.options.lock().recv_buf_len
+    }
+
+    /// Sets the size of the receive buffer (`SO_RCVBUF`).
+    ///
+    /// The size is clamped to a sane range. It only takes effect for the
+    /// connections established afterwards.
+    pub fn set_recv_buffer_size(&self, size: usize) {
+        let size = size.clamp(*SOCKET_BUF_LEN_RANGE.start(), *SOCKET_BUF_LEN_RANGE.end());
+        self.update_options(|opts| opts.recv_buf_len = size);
+    }
+
+    /// Returns the size of the send buffer (`SO_SNDBUF`).
+    pub fn send_buffer_size(&self) -> usize {
+        self.options.lock().send_buf_len
+    }
+
+    /// Sets the size of the send buffer (`SO_SNDBUF`).
+    ///
+    /// The size is clamped to a sane range. It only takes effect for the
+    /// connections established afterwards.
+    pub fn set_send_buffer_size(&self, size: usize) {
+        let size = size.clamp(*SOCKET_BUF_LEN_RANGE.start(), *SOCKET_BUF_LEN_RANGE.end());
+        self.update_options(|opts| opts.send_buf_len = size);
+    }
+
+    /// Returns the timeout of [`recv`](Self::recv) and [`accept`](Self::accept)
+    /// (`SO_RCVTIMEO`).
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.options.lock().read_timeout
+    }
+
+    /// Sets the timeout of [`recv`](Self::recv) and [`accept`](Self::accept)
+    /// (`SO_RCVTIMEO`), after which they fail with
+    /// [`Err(WouldBlock)`](AxError::WouldBlock). `None` means no timeout.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout.is_some_and(|t| t.is_zero()) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.update_options(|opts| opts.read_timeout = timeout);
+        Ok(())
+    }
+
+    /// Returns the timeout of [`send`](Self::send) and
+    /// [`connect`](Self::connect) (`SO_SNDTIMEO`).
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.options.lock().write_timeout
+    }
+
+    /// Sets the timeout of [`send`](Self::send) and
+    /// [`connect`](Self::connect) (`SO_SNDTIMEO`), after which they fail with
+    /// [`Err(WouldBlock)`](AxError::WouldBlock). `None` means no timeout.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout.is_some_and(|t| t.is_zero()) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.update_options(|opts| opts.write_timeout = timeout);
+        Ok(())
+    }
+
+    /// Returns whether `SO_REUSEADDR` is set.
+    pub fn reuse_address(&self) -> bool {
+        self.options.lock().reuse_addr
+    }
+
+    /// Sets `SO_REUSEADDR`.
+    ///
+    /// It's recorded only: a port is never held by closed connections, so it
+    /// can always be bound again once its listener is closed.
+    pub fn set_reuse_address(&self, reuse: bool) {
+        self.update_options(|opts| opts.reuse_addr = reuse);
+    }
+
+    /// Returns the linger timeout (`SO_LINGER`).
+    pub fn linger(&self) -> Option<Duration> {
+        self.options.lock().linger
+    }
+
+    /// Sets the linger timeout (`SO_LINGER`).
+    ///
+    /// If it's set, [`shutdown`](Self::shutdown) of a blocking socket waits
+    /// until the sent data is acknowledged or the timeout expires, and a zero
+    /// timeout resets the connection instead of closing it gracefully.
+    pub fn set_linger(&self, linger: Option<Duration>) {
+        self.update_options(|opts| opts.linger = linger);
+    }
+
     /// Connects to the given address and port.
     ///
     /// The local port is generated automatically.
     pub fn connect(&self, remote_addr: SocketAddr) -> AxResult {
         self.update_state(STATE_CLOSED, STATE_CONNECTING, || {
-            // SAFETY: no other threads can read or write these fields.
-            let handle = unsafe { self.handle.get().read() }
-                .unwrap_or_else(|| SOCKET_SET.add(SocketSetWrapper::new_tcp_socket()));
-
-            // TODO: check remote addr unreachable
             let remote_endpoint = from_core_sockaddr(remote_addr);
             let bound_endpoint = self.bound_endpoint()?;
-            let iface = &ETH0.iface;
+            let iface = egress_iface(bound_endpoint.addr, remote_endpoint.addr)
+                .ok_or_else(|| ax_err_type!(ConnectionRefused, "no route to host"))?;
+
+            // SAFETY: no other threads can read or write these fields.
+            let handle = match unsafe { self.handle.get().read() } {
+                Some(handle) if handle.iface == iface => handle,
+                old => {
+                    // the socket must be in the socket set of the egress interface
+                    if let Some(old) = old {
+                        SOCKET_SET.remove(old);
+                    }
+                    let socket = self.options.lock().new_socket()?;
+                    let handle = SOCKET_SET.add_charged(iface, socket);
+                    unsafe { self.handle.get().write(Some(handle)) };
+                    handle
+                }
+            };
+            let iface = &handle.iface().iface;
             let (local_endpoint, remote_endpoint) = SOCKET_SET
                 .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                     socket
@@ -152,6 +368,7 @@ impl TcpSocket {
                 self.peer_addr.get().write(remote_endpoint);
                 self.handle.get().write(Some(handle));
             }
+            wait::kick(); // send the SYN
             Ok(())
         })
         .unwrap_or_else(|_| ax_err!(AlreadyExists, "socket connect() failed: already connected"))?; // EISCONN
@@ -160,7 +377,15 @@ impl TcpSocket {
         if self.is_nonblocking() {
             Err(AxError::WouldBlock)
         } else {
-            self.block_on(|| {
+            // SAFETY: `self.handle` is initialized above.
+            let handle = unsafe { self.handle.get().read().unwrap() };
+            self.block_on(self.write_timeout(), || {
+                self.waiter.register(|waker| {
+                    SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                        socket.register_recv_waker(waker);
+                        socket.register_send_waker(waker);
+                    })
+                });
                 let PollState { writable, .. } = self.poll_connect()?;
                 if !writable {
                     Err(AxError::WouldBlock)
@@ -181,7 +406,10 @@ impl TcpSocket {
     /// [`accept`](Self::accept).
     pub fn bind(&self, mut local_addr: SocketAddr) -> AxResult {
         self.update_state(STATE_CLOSED, STATE_CLOSED, || {
-            // TODO: check addr is available
+            let addr = from_core_sockaddr(local_addr).addr;
+            if !is_unspecified(addr) && iface_of_addr(addr).is_none() {
+                return ax_err!(InvalidInput, "socket bind() failed: address not available");
+            }
             if local_addr.port() == 0 {
                 local_addr.set_port(get_ephemeral_port()?);
             }
@@ -209,7 +437,7 @@ impl TcpSocket {
             unsafe {
                 (*self.local_addr.get()).port = bound_endpoint.port;
             }
-            LISTEN_TABLE.listen(bound_endpoint)?;
+            LISTEN_TABLE.listen(bound_endpoint, *self.options.lock())?;
             debug!("TCP socket listening on {}", bound_endpoint);
             Ok(())
         })
@@ -229,10 +457,15 @@ impl TcpSocket {
 
         // SAFETY: `self.local_addr` should be initialized after `bind()`.
         let local_port = unsafe { self.local_addr.get().read().port };
-        self.block_on(|| {
+        self.block_on(self.read_timeout(), || {
+            self.waiter
+                .register(|waker| LISTEN_TABLE.register_waker(local_port, waker));
             let (handle, (local_addr, peer_addr)) = LISTEN_TABLE.accept(local_port)?;
             debug!("TCP socket accepted a new connection {}", peer_addr);
-            Ok(TcpSocket::new_connected(handle, local_addr, peer_addr))
+            let options = *self.options.lock();
+            Ok(TcpSocket::new_connected(
+                handle, local_addr, peer_addr, options,
+            ))
         })
     }
 
@@ -243,12 +476,20 @@ impl TcpSocket {
             // SAFETY: `self.handle` should be initialized in a connected socket, and
             // no other threads can read or write it.
             let handle = unsafe { self.handle.get().read().unwrap() };
+            let linger = self.linger();
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                 debug!("TCP socket {}: shutting down", handle);
-                socket.close();
+                if linger.is_some_and(|t| t.is_zero()) {
+                    socket.abort();
+                } else {
+                    socket.close();
+                }
             });
             unsafe { self.local_addr.get().write(UNSPECIFIED_ENDPOINT) }; // clear bound address
             SOCKET_SET.poll_interfaces();
+            if let Some(linger) = linger.filter(|t| !t.is_zero()) {
+                self.linger_on(handle, linger);
+            }
             Ok(())
         })
         .unwrap_or(Ok(()))?;
@@ -279,7 +520,7 @@ impl TcpSocket {
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
+        self.block_on(self.read_timeout(), || {
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                 if !socket.is_active() {
                     // not open
@@ -296,6 +537,8 @@ impl TcpSocket {
                     Ok(len)
                 } else {
                     // no more data
+                    self.waiter
+                        .register(|waker| socket.register_recv_waker(waker));
                     Err(AxError::WouldBlock)
                 }
             })
@@ -312,7 +555,7 @@ impl TcpSocket {
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
+        let len = self.block_on(self.write_timeout(), || {
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                 if !socket.is_active() || !socket.may_send() {
                     // closed by remote
@@ -326,10 +569,14 @@ impl TcpSocket {
                     Ok(len)
                 } else {
                     // tx buffer is full
+                    self.waiter
+                        .register(|waker| socket.register_send_waker(waker));
                     Err(AxError::WouldBlock)
                 }
             })
-        })
+        })?;
+        wait::kick();
+        Ok(len)
     }
 
     /// Whether the socket is readable or writable.
@@ -474,21 +721,50 @@ impl TcpSocket {
     /// If the socket is non-blocking, it calls the function once and returns
     /// immediately. Otherwise, it may call the function multiple times if it
     /// returns [`Err(WouldBlock)`](AxError::WouldBlock).
-    fn block_on<F, T>(&self, mut f: F) -> AxResult<T>
+    fn block_on<F, T>(&self, timeout: Option<Duration>, f: F) -> AxResult<T>
     where
         F: FnMut() -> AxResult<T>,
     {
-        if self.is_nonblocking() {
-            f()
-        } else {
-            loop {
-                SOCKET_SET.poll_interfaces();
-                match f() {
-                    Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
-                    Err(e) => return Err(e),
-                }
+        self.waiter.block_on(self.is_nonblocking(), timeout, f)
+    }
+
+    /// Updates the options, and applies them to the smoltcp socket or the
+    /// listen table entry.
+    fn update_options<F: FnOnce(&mut TcpOptions)>(&self, f: F) {
+        let mut options = self.options.lock();
+        f(&mut options);
+        match self.get_state() {
+            STATE_CONNECTING | STATE_CONNECTED => {
+                // SAFETY: `self.handle` should be initialized in a connected socket.
+                let handle = unsafe { self.handle.get().read().unwrap() };
+                SOCKET_SET
+                    .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| options.apply(socket));
+            }
+            STATE_LISTENING => {
+                // SAFETY: `self.local_addr` should be initialized in a listening socket.
+                let local_port = unsafe { self.local_addr.get().read().port };
+                LISTEN_TABLE.set_options(local_port, *options);
             }
+            _ => {}
+        }
+    }
+
+    /// Waits until the sent data is acknowledged, or the `linger` timeout
+    /// expires, after the connection is closed.
+    fn linger_on(&self, handle: SocketHandle, linger: Duration) {
+        let res = self.block_on(Some(linger), || {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                if !socket.is_active() || socket.send_queue() == 0 {
+                    Ok(())
+                } else {
+                    self.waiter
+                        .register(|waker| socket.register_send_waker(waker));
+                    Err(AxError::WouldBlock)
+                }
+            })
+        });
+        if res.is_err() {
+            debug!("TCP socket {}: linger timeout", handle);
         }
     }
 }
@@ -1,37 +1,64 @@
+use alloc::{vec, vec::Vec};
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
 
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
 use axio::PollState;
 use axsync::Mutex;
 use spin::RwLock;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::udp::{self, BindError, SendError};
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use super::addr::{from_core_sockaddr, into_core_sockaddr, is_unspecified, UNSPECIFIED_ENDPOINT};
-use super::{SocketSetWrapper, SOCKET_SET};
+use super::wait::{self, SocketWaiter};
+use super::{addressed_ifaces, egress_iface, iface_of_addr, SocketHandle, SocketSetWrapper};
+use super::{DEFAULT_HOP_LIMIT, SOCKET_BUF_LEN_RANGE, SOCKET_SET, UDP_RX_BUF_LEN, UDP_TX_BUF_LEN};
+
+/// Options of a UDP socket.
+#[derive(Debug, Clone, Copy)]
+struct UdpOptions {
+    hop_limit: Option<u8>,
+    recv_buf_len: usize,
+    send_buf_len: usize,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    reuse_addr: bool,
+}
 
 /// A UDP socket that provides POSIX-like APIs.
+///
+/// A socket bound to an unspecified address has one smoltcp socket on each
+/// interface with addresses (i.e., not on the interfaces without NICs when it's
+/// bound), otherwise it only has one on the interface of its address.
 pub struct UdpSocket {
-    handle: SocketHandle,
+    handles: RwLock<Vec<SocketHandle>>,
     local_addr: RwLock<Option<IpEndpoint>>,
     peer_addr: RwLock<Option<IpEndpoint>>,
     nonblock: AtomicBool,
+    options: Mutex<UdpOptions>,
+    waiter: SocketWaiter,
 }
 
 impl UdpSocket {
     /// Creates a new UDP socket.
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let socket = SocketSetWrapper::new_udp_socket();
-        let handle = SOCKET_SET.add(socket);
         Self {
-            handle,
+            handles: RwLock::new(Vec::new()),
             local_addr: RwLock::new(None),
             peer_addr: RwLock::new(None),
             nonblock: AtomicBool::new(false),
+            options: Mutex::new(UdpOptions {
+                hop_limit: None,
+                recv_buf_len: UDP_RX_BUF_LEN,
+                send_buf_len: UDP_TX_BUF_LEN,
+                read_timeout: None,
+                write_timeout: None,
+                reuse_addr: false,
+            }),
+            waiter: SocketWaiter::new(),
         }
     }
 
@@ -69,6 +96,99 @@ impl UdpSocket {
         self.nonblock.store(nonblocking, Ordering::Release);
     }
 
+    /// Returns the time-to-live (or hop limit of IPv6) of the outgoing
+    /// packets (`IP_TTL`).
+    pub fn ttl(&self) -> u8 {
+        self.options.lock().hop_limit.unwrap_or(DEFAULT_HOP_LIMIT)
+    }
+
+    /// Sets the time-to-live (or hop limit of IPv6) of the outgoing packets
+    /// (`IP_TTL`).
+    pub fn set_ttl(&self, ttl: u8) -> AxResult {
+        if ttl == 0 {
+            return ax_err!(InvalidInput, "zero TTL");
+        }
+        self.options.lock().hop_limit = Some(ttl);
+        for &handle in self.handles.read().iter() {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.set_hop_limit(Some(ttl))
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the size of the receive buffer (`SO_RCVBUF`).
+    pub fn recv_buffer_size(&self) -> usize {
+        self.options.lock().recv_buf_len
+    }
+
+    /// Sets the size of the receive buffer (`SO_RCVBUF`).
+    ///
+    /// The size is clamped to a sane range. It only takes effect if the socket
+    /// is not bound yet.
+    pub fn set_recv_buffer_size(&self, size: usize) {
+        let size = size.clamp(*SOCKET_BUF_LEN_RANGE.start(), *SOCKET_BUF_LEN_RANGE.end());
+        self.options.lock().recv_buf_len = size;
+    }
+
+    /// Returns the size of the send buffer (`SO_SNDBUF`).
+    pub fn send_buffer_size(&self) -> usize {
+        self.options.lock().send_buf_len
+    }
+
+    /// Sets the size of the send buffer (`SO_SNDBUF`).
+    ///
+    /// The size is clamped to a sane range. It only takes effect if the socket
+    /// is not bound yet.
+    pub fn set_send_buffer_size(&self, size: usize) {
+        let size = size.clamp(*SOCKET_BUF_LEN_RANGE.start(), *SOCKET_BUF_LEN_RANGE.end());
+        self.options.lock().send_buf_len = size;
+    }
+
+    /// Returns the timeout of the receive operations (`SO_RCVTIMEO`).
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.options.lock().read_timeout
+    }
+
+    /// Sets the timeout of the receive operations (`SO_RCVTIMEO`), after which
+    /// they fail with [`Err(WouldBlock)`](AxError::WouldBlock). `None` means
+    /// no timeout.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout.is_some_and(|t| t.is_zero()) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.options.lock().read_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of the send operations (`SO_SNDTIMEO`).
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.options.lock().write_timeout
+    }
+
+    /// Sets the timeout of the send operations (`SO_SNDTIMEO`), after which
+    /// they fail with [`Err(WouldBlock)`](AxError::WouldBlock). `None` means
+    /// no timeout.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout.is_some_and(|t| t.is_zero()) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.options.lock().write_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns whether `SO_REUSEADDR` is set.
+    pub fn reuse_address(&self) -> bool {
+        self.options.lock().reuse_addr
+    }
+
+    /// Sets `SO_REUSEADDR`.
+    ///
+    /// It's recorded only, as binding an address in use is always allowed.
+    pub fn set_reuse_address(&self, reuse: bool) {
+        self.options.lock().reuse_addr = reuse;
+    }
+
     /// Binds an unbound socket to the given address and port.
     ///
     /// It's must be called before [`send_to`](Self::send_to) and
@@ -88,15 +208,45 @@ impl UdpSocket {
             addr: (!is_unspecified(local_endpoint.addr)).then_some(local_endpoint.addr),
             port: local_endpoint.port,
         };
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            socket.bind(endpoint).or_else(|e| match e {
-                BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
-                BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
-            })
-        })?;
+        let ifaces = match endpoint.addr {
+            Some(addr) => {
+                let iface = iface_of_addr(addr).ok_or_else(|| {
+                    ax_err_type!(InvalidInput, "socket bind() failed: address not available")
+                })?;
+                vec![iface]
+            }
+            None => addressed_ifaces(),
+        };
 
+        let options = *self.options.lock();
+        // create all the sockets first, so that none is left behind if the
+        // buffers run out
+        let sockets = ifaces
+            .iter()
+            .map(|_| SocketSetWrapper::new_udp_socket(options.recv_buf_len, options.send_buf_len))
+            .collect::<AxResult<Vec<_>>>()?;
+        let mut handles = Vec::new();
+        for (iface, mut socket) in ifaces.into_iter().zip(sockets) {
+            socket.set_hop_limit(options.hop_limit);
+            let handle = SOCKET_SET.add_charged(iface, socket);
+            handles.push(handle);
+            let res = SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.bind(endpoint).or_else(|e| match e {
+                    BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
+                    BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
+                })
+            });
+            if let Err(e) = res {
+                for handle in handles {
+                    SOCKET_SET.remove(handle);
+                }
+                return Err(e);
+            }
+            debug!("UDP socket {}: bound on {}", handle, endpoint);
+        }
+
+        *self.handles.write() = handles;
         *self_local_addr = Some(local_endpoint);
-        debug!("UDP socket {}: bound on {}", self.handle, endpoint);
         Ok(())
     }
 
@@ -142,7 +292,7 @@ impl UdpSocket {
         }
 
         *self_peer_addr = Some(from_core_sockaddr(addr));
-        debug!("UDP socket {}: connected to {}", self.handle, addr);
+        debug!("UDP socket: connected to {}", addr);
         Ok(())
     }
 
@@ -172,28 +322,30 @@ impl UdpSocket {
 
     /// Close the socket.
     pub fn shutdown(&self) -> AxResult {
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            debug!("UDP socket {}: shutting down", self.handle);
-            socket.close();
-        });
+        for &handle in self.handles.read().iter() {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                debug!("UDP socket {}: shutting down", handle);
+                socket.close();
+            });
+        }
         SOCKET_SET.poll_interfaces();
         Ok(())
     }
 
     /// Whether the socket is readable or writable.
     pub fn poll(&self) -> AxResult<PollState> {
-        if self.local_addr.read().is_none() {
-            return Ok(PollState {
-                readable: false,
-                writable: false,
+        // no handles if not bound
+        let mut state = PollState {
+            readable: false,
+            writable: false,
+        };
+        for &handle in self.handles.read().iter() {
+            SOCKET_SET.with_socket::<udp::Socket, _, _>(handle, |socket| {
+                state.readable |= socket.can_recv();
+                state.writable |= socket.can_send();
             });
         }
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            Ok(PollState {
-                readable: socket.can_recv(),
-                writable: socket.can_send(),
-            })
-        })
+        Ok(state)
     }
 }
 
@@ -207,12 +359,22 @@ impl UdpSocket {
     }
 
     fn send_impl(&self, buf: &[u8], remote_endpoint: IpEndpoint) -> AxResult<usize> {
-        if self.local_addr.read().is_none() {
-            return ax_err!(NotConnected, "socket send() failed");
-        }
-
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+        let local_addr = match *self.local_addr.read() {
+            Some(addr) => (!is_unspecified(addr.addr)).then_some(addr.addr),
+            None => return ax_err!(NotConnected, "socket send() failed"),
+        };
+        let iface = egress_iface(local_addr, remote_endpoint.addr).ok_or_else(|| {
+            ax_err_type!(ConnectionRefused, "socket send() failed: no route to host")
+        })?;
+        let handle = *self
+            .handles
+            .read()
+            .iter()
+            .find(|handle| handle.iface == iface)
+            .ok_or_else(|| ax_err_type!(ConnectionRefused, "socket send() failed"))?;
+
+        let len = self.block_on(self.write_timeout(), || {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
                 if socket.can_send() {
                     socket
                         .send_slice(buf, remote_endpoint)
@@ -225,10 +387,14 @@ impl UdpSocket {
                     Ok(buf.len())
                 } else {
                     // tx buffer is full
+                    self.waiter
+                        .register(|waker| socket.register_send_waker(waker));
                     Err(AxError::WouldBlock)
                 }
             })
-        })
+        })?;
+        wait::kick();
+        Ok(len)
     }
 
     fn recv_impl<F, T>(&self, mut op: F) -> AxResult<T>
@@ -239,42 +405,37 @@ impl UdpSocket {
             return ax_err!(NotConnected, "socket send() failed");
         }
 
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if socket.can_recv() {
+        self.block_on(self.read_timeout(), || {
+            for &handle in self.handles.read().iter() {
+                let res = SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                    self.waiter
+                        .register(|waker| socket.register_recv_waker(waker));
                     // data available
-                    op(socket)
-                } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
+                    socket.can_recv().then(|| op(socket))
+                });
+                if let Some(res) = res {
+                    return res;
                 }
-            })
+            }
+            // no more data
+            Err(AxError::WouldBlock)
         })
     }
 
-    fn block_on<F, T>(&self, mut f: F) -> AxResult<T>
+    fn block_on<F, T>(&self, timeout: Option<Duration>, f: F) -> AxResult<T>
     where
         F: FnMut() -> AxResult<T>,
     {
-        if self.is_nonblocking() {
-            f()
-        } else {
-            loop {
-                SOCKET_SET.poll_interfaces();
-                match f() {
-                    Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
-                    Err(e) => return Err(e),
-                }
-            }
-        }
+        self.waiter.block_on(self.is_nonblocking(), timeout, f)
     }
 }
 
 impl Drop for UdpSocket {
     fn drop(&mut self) {
         self.shutdown().ok();
-        SOCKET_SET.remove(self.handle);
+        for &handle in self.handles.read().iter() {
+            SOCKET_SET.remove(handle);
+        }
     }
 }
 
@@ -0,0 +1,252 @@
+//! Blocking on sockets, and the network softirq task.
+//!
+//! With both the `irq` and `multitask` features, a softirq task polls the
+//! interfaces when a NIC raises an interrupt or a smoltcp timer expires, and
+//! the blocked sockets sleep until smoltcp wakes them on readiness. Otherwise,
+//! the blocked sockets poll the interfaces by themselves, and yield between
+//! polls.
+
+use core::task::Waker;
+use core::time::Duration;
+
+use axerrno::{AxError, AxResult};
+
+use super::SOCKET_SET;
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "irq", feature = "multitask"))] {
+        use alloc::sync::Arc;
+        use alloc::task::Wake;
+        use alloc::vec::Vec;
+        use core::sync::atomic::{AtomicBool, Ordering};
+
+        use axtask::WaitQueue;
+        use lazy_init::LazyInit;
+
+        use super::nics;
+
+        /// The longest time the softirq task sleeps if some NIC can only be
+        /// polled.
+        const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+        /// A wait queue with a flag, so that a notification before waiting is
+        /// not lost.
+        struct Event {
+            ready: AtomicBool,
+            wq: WaitQueue,
+        }
+
+        impl Event {
+            const fn new() -> Self {
+                Self {
+                    ready: AtomicBool::new(false),
+                    wq: WaitQueue::new(),
+                }
+            }
+
+            fn wait(&self) {
+                self.wq.wait_until(|| self.ready.swap(false, Ordering::AcqRel));
+            }
+
+            fn wait_timeout(&self, dur: Duration) {
+                self.wq
+                    .wait_timeout_until(dur, || self.ready.swap(false, Ordering::AcqRel));
+            }
+
+            fn notify(&self) {
+                self.ready.store(true, Ordering::Release);
+                self.wq.notify_all(false);
+            }
+        }
+
+        impl Wake for Event {
+            fn wake(self: Arc<Self>) {
+                self.notify();
+            }
+
+            fn wake_by_ref(self: &Arc<Self>) {
+                self.notify();
+            }
+        }
+
+        static SOFTIRQ_EVENT: Event = Event::new();
+        /// The IRQs of the NICs, masked by the IRQ handler until the softirq
+        /// task has polled the interfaces.
+        static NIC_IRQS: LazyInit<Vec<usize>> = LazyInit::new();
+
+        /// Where a blocked socket sleeps until it's ready.
+        pub(crate) struct SocketWaiter(Arc<Event>);
+
+        impl SocketWaiter {
+            pub fn new() -> Self {
+                Self(Arc::new(Event::new()))
+            }
+
+            /// Calls `register` with the waker of the socket, which should be
+            /// registered to the smoltcp sockets before checking readiness.
+            pub fn register<F: FnOnce(&Waker)>(&self, register: F) {
+                register(&Waker::from(self.0.clone()));
+            }
+
+            /// Blocks the current task until `f` completes or fails.
+            ///
+            /// If `nonblocking` is true, it calls `f` once and returns
+            /// immediately. Otherwise, it sleeps after each
+            /// [`Err(WouldBlock)`](AxError::WouldBlock) of `f`, until the
+            /// registered waker is woken, or `timeout` expires which also
+            /// returns [`Err(WouldBlock)`](AxError::WouldBlock).
+            pub fn block_on<F, T>(
+                &self,
+                nonblocking: bool,
+                timeout: Option<Duration>,
+                mut f: F,
+            ) -> AxResult<T>
+            where
+                F: FnMut() -> AxResult<T>,
+            {
+                if nonblocking {
+                    return f();
+                }
+                let deadline = timeout.map(|t| axhal::time::current_time() + t);
+                loop {
+                    match f() {
+                        Err(AxError::WouldBlock) => match deadline {
+                            Some(deadline) => {
+                                let now = axhal::time::current_time();
+                                if now >= deadline {
+                                    return Err(AxError::WouldBlock);
+                                }
+                                self.0.wait_timeout(deadline - now);
+                            }
+                            None => self.0.wait(),
+                        },
+                        res => return res,
+                    }
+                }
+            }
+        }
+
+        /// Asks the softirq task to poll the interfaces, e.g. when there are
+        /// new packets to transmit.
+        pub(crate) fn kick() {
+            SOFTIRQ_EVENT.notify();
+        }
+
+        fn nic_irq_handler() {
+            // the line stays masked until the device is acknowledged
+            for &irq in NIC_IRQS.iter() {
+                axhal::irq::set_enable(irq, false);
+            }
+            SOFTIRQ_EVENT.notify();
+        }
+
+        fn softirq_task(all_irqs: bool) {
+            loop {
+                for iface in nics() {
+                    iface.ack_interrupt();
+                }
+                SOCKET_SET.poll_interfaces();
+                for &irq in NIC_IRQS.iter() {
+                    axhal::irq::set_enable(irq, true);
+                }
+
+                let delay = match SOCKET_SET.poll_delay() {
+                    Some(delay) if !all_irqs => Some(delay.min(POLL_INTERVAL)),
+                    None if !all_irqs => Some(POLL_INTERVAL),
+                    delay => delay,
+                };
+                match delay {
+                    Some(delay) if delay.is_zero() => {}
+                    Some(delay) => SOFTIRQ_EVENT.wait_timeout(delay),
+                    None => SOFTIRQ_EVENT.wait(),
+                }
+            }
+        }
+
+        /// Registers the IRQ handler of a NIC added after boot.
+        ///
+        /// The interfaces of such NICs have no IRQs at boot, so they are
+        /// polled anyway, the IRQ only speeds up the response. The NICs
+        /// added after boot use MSIs, which need no masking.
+        #[cfg(feature = "hotplug")]
+        pub(crate) fn register_hotplug_irq(iface: &super::InterfaceWrapper) {
+            if let Some(irq) = iface.irq_num() {
+                if axhal::irq::register_handler(irq, kick) {
+                    info!("  {:?} uses IRQ {}", iface.name(), irq);
+                }
+            }
+        }
+
+        /// Registers the IRQ handlers of the NICs, and starts the softirq task.
+        pub(crate) fn init() {
+            let mut irqs = Vec::new();
+            let mut all_irqs = true;
+            for iface in nics() {
+                match iface.irq_num() {
+                    Some(irq) if irqs.contains(&irq) => {}
+                    Some(irq) if axhal::irq::register_handler(irq, nic_irq_handler) => {
+                        info!("  {:?} uses IRQ {}", iface.name(), irq);
+                        irqs.push(irq);
+                    }
+                    _ => {
+                        warn!("  {:?} has no IRQ, fall back to polling", iface.name());
+                        all_irqs = false;
+                    }
+                }
+            }
+            NIC_IRQS.init_by(irqs);
+            axtask::spawn(move || softirq_task(all_irqs));
+        }
+    } else {
+        /// Where a blocked socket waits until it's ready.
+        pub(crate) struct SocketWaiter;
+
+        impl SocketWaiter {
+            pub fn new() -> Self {
+                Self
+            }
+
+            /// Does nothing, as the blocked sockets poll by themselves.
+            pub fn register<F: FnOnce(&Waker)>(&self, _register: F) {}
+
+            /// Blocks the current task until `f` completes or fails.
+            ///
+            /// If `nonblocking` is true, it calls `f` once and returns
+            /// immediately. Otherwise, it may call `f` multiple times if it
+            /// returns [`Err(WouldBlock)`](AxError::WouldBlock), until
+            /// `timeout` expires.
+            pub fn block_on<F, T>(
+                &self,
+                nonblocking: bool,
+                timeout: Option<Duration>,
+                mut f: F,
+            ) -> AxResult<T>
+            where
+                F: FnMut() -> AxResult<T>,
+            {
+                if nonblocking {
+                    return f();
+                }
+                let deadline = timeout.map(|t| axhal::time::current_time() + t);
+                loop {
+                    SOCKET_SET.poll_interfaces();
+                    match f() {
+                        Err(AxError::WouldBlock) => {
+                            if deadline.is_some_and(|d| axhal::time::current_time() >= d) {
+                                return Err(AxError::WouldBlock);
+                            }
+                            axtask::yield_now();
+                        }
+                        res => return res,
+                    }
+                }
+            }
+        }
+
+        /// Does nothing, the interfaces are polled by the blocked sockets.
+        pub(crate) fn kick() {}
+
+        /// Does nothing without the `irq` and `multitask` features.
+        pub(crate) fn init() {}
+    }
+}
@@ -0,0 +1,44 @@
+use axdriver::{prelude::*, AxDeviceContainer};
+use axhal::console::ConsoleDevice;
+use lazy_init::LazyInit;
+use spinlock::SpinNoIrq;
+
+static CONSOLE: LazyInit<SpinNoIrq<AxCharDevice>> = LazyInit::new();
+
+fn write_bytes(bytes: &[u8]) -> bool {
+    // The device is busy if the driver itself is logging, or another CPU is
+    // writing. Let the platform console print it instead of deadlocking.
+    let Some(mut dev) = CONSOLE.try_lock() else {
+        return false;
+    };
+    let mut written = 0;
+    while written < bytes.len() {
+        match dev.write(&bytes[written..]) {
+            Ok(n) => written += n,
+            Err(DevError::Again) => core::hint::spin_loop(),
+            Err(_) => return false,
+        }
+    }
+    true
+}
+
+fn getchar() -> Option<u8> {
+    let mut c = [0];
+    match CONSOLE.lock().read(&mut c) {
+        Ok(1) => Some(c[0]),
+        _ => None,
+    }
+}
+
+/// Uses the first VirtIO console as the console of [`axhal::console`], it is
+/// taken out of `char_devs` and not added to devfs.
+pub fn init_console(char_devs: &mut AxDeviceContainer<AxCharDevice>) {
+    if let Some(dev) = char_devs.take_first(|dev| dev.device_name() == "virtio-console") {
+        info!("Use console device: {:?}", dev.device_name());
+        CONSOLE.init_by(SpinNoIrq::new(dev));
+        axhal::console::set_console_device(ConsoleDevice {
+            write_bytes,
+            getchar,
+        });
+    }
+}
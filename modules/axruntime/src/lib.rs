@@ -6,6 +6,7 @@
 //! # Cargo Features
 //!
 //! - `alloc`: Enable global memory allocator.
+//! - `alloc-debug`: Enable heap debugging of the global memory allocator.
 //! - `paging`: Enable page table manipulation support.
 //! - `irq`: Enable interrupt handling support.
 //! - `multitask`: Enable multi-threading support.
@@ -13,6 +14,11 @@
 //! - `fs`: Enable filesystem support.
 //! - `net`: Enable networking support.
 //! - `display`: Enable graphics support.
+//! - `rng`: Use the random number generator device as the entropy source.
+//! - `console`: Use the VirtIO console as the console, instead of the one of
+//!   the platform.
+//! - `hotplug`: Add and remove the PCI devices in hot-plug slots at runtime,
+//!   a task polls the slots every 100 ms.
 //!
 //! All the features are optional and disabled by default.
 
@@ -29,6 +35,12 @@ mod trap;
 #[cfg(feature = "smp")]
 mod mp;
 
+#[cfg(feature = "rng")]
+mod rng;
+
+#[cfg(feature = "console")]
+mod console;
+
 #[cfg(feature = "smp")]
 pub use self::mp::rust_main_secondary;
 
@@ -84,12 +96,34 @@ impl axlog::LogIf for LogIfImpl {
     }
 }
 
+#[cfg(feature = "alloc-debug")]
+struct AllocDebugIfImpl;
+
+#[cfg(feature = "alloc-debug")]
+#[crate_interface::impl_interface]
+impl axalloc::AllocDebugIf for AllocDebugIfImpl {
+    fn current_stack_range() -> Option<core::ops::Range<usize>> {
+        #[cfg(feature = "multitask")]
+        {
+            axtask::current_may_uninit().and_then(|curr| curr.kernel_stack_range())
+        }
+        #[cfg(not(feature = "multitask"))]
+        None
+    }
+}
+
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 static INITED_CPUS: AtomicUsize = AtomicUsize::new(0);
 
+/// The number of CPUs to start, which is the number of CPUs in the device tree
+/// if there is one, but no more than `axconfig::SMP`.
+fn cpu_count() -> usize {
+    axhal::dtb::cpu_count().map_or(axconfig::SMP, |n| n.min(axconfig::SMP))
+}
+
 fn is_init_ok() -> bool {
-    INITED_CPUS.load(Ordering::Acquire) == axconfig::SMP
+    INITED_CPUS.load(Ordering::Acquire) == cpu_count()
 }
 
 /// The main entry point of the ArceOS runtime.
@@ -125,6 +159,14 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     axlog::set_max_level(option_env!("AX_LOG").unwrap_or("")); // no effect if set `log-level-*` features
     info!("Logging is enabled.");
     info!("Primary CPU {} started, dtb = {:#x}.", cpu_id, dtb);
+    if let Some(fdt) = axhal::dtb::fdt() {
+        info!(
+            "Found device tree: {} bytes, {} CPUs, {} CPUs to start.",
+            fdt.total_size(),
+            fdt.cpu_count(),
+            cpu_count()
+        );
+    }
 
     info!("Found physcial memory regions:");
     for r in axhal::mem::memory_regions() {
@@ -152,21 +194,47 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     #[cfg(feature = "multitask")]
     axtask::init_scheduler();
 
-    #[cfg(any(feature = "fs", feature = "net", feature = "display"))]
+    #[cfg(any(
+        feature = "fs",
+        feature = "net",
+        feature = "display",
+        feature = "rng",
+        feature = "console"
+    ))]
     {
-        #[allow(unused_variables)]
-        let all_devices = axdriver::init_drivers();
+        #[allow(unused_variables, unused_mut)]
+        let mut all_devices = axdriver::init_drivers();
+
+        #[cfg(feature = "console")]
+        self::console::init_console(&mut all_devices.char);
+
+        // before the network stack, which is seeded by random numbers
+        #[cfg(feature = "rng")]
+        self::rng::init_rng(all_devices.rng);
 
         #[cfg(feature = "fs")]
-        axfs::init_filesystems(all_devices.block);
+        {
+            axfs::init_filesystems(all_devices.block);
+            axfs::init_char_devices(all_devices.char);
+            axfs::init_bus_devices(all_devices.gpio, all_devices.i2c, all_devices.spi);
+        }
 
         #[cfg(feature = "net")]
         axnet::init_network(all_devices.net);
 
         #[cfg(feature = "display")]
-        axdisplay::init_display(all_devices.display);
+        {
+            axdisplay::init_display(all_devices.display);
+            axdisplay::init_input(all_devices.input);
+        }
     }
 
+    #[cfg(feature = "hotplug")]
+    axtask::spawn(|| loop {
+        axdriver::hotplug::poll();
+        axtask::sleep(core::time::Duration::from_millis(100));
+    });
+
     #[cfg(feature = "smp")]
     self::mp::start_secondary_cpus(cpu_id);
 
@@ -191,6 +259,9 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
 
     unsafe { main() };
 
+    #[cfg(feature = "alloc")]
+    axalloc::dump_allocations();
+
     #[cfg(feature = "multitask")]
     axtask::exit(0);
     #[cfg(not(feature = "multitask"))]
@@ -202,31 +273,52 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
 
 #[cfg(feature = "alloc")]
 fn init_allocator() {
-    use axhal::mem::{memory_regions, phys_to_virt, MemRegionFlags};
+    use axhal::mem::{memory_regions, phys_to_virt, MemRegion, MemRegionFlags};
 
     info!("Initialize global memory allocator...");
     info!("  use {} allocator.", axalloc::global_allocator().name());
 
+    // only allocate from normal memory that is free and not reserved
+    let is_free = |r: &MemRegion| {
+        r.flags.contains(MemRegionFlags::FREE)
+            && !r.flags.intersects(
+                MemRegionFlags::RESERVED | MemRegionFlags::DEVICE | MemRegionFlags::UNCACHED,
+            )
+    };
+
     let mut max_region_size = 0;
     let mut max_region_paddr = 0.into();
     for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.size > max_region_size {
+        if is_free(&r) && r.size > max_region_size {
             max_region_size = r.size;
             max_region_paddr = r.paddr;
         }
     }
     for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.paddr == max_region_paddr {
+        if is_free(&r) && r.paddr == max_region_paddr {
             axalloc::global_init(phys_to_virt(r.paddr).as_usize(), r.size);
             break;
         }
     }
     for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.paddr != max_region_paddr {
-            axalloc::global_add_memory(phys_to_virt(r.paddr).as_usize(), r.size)
-                .expect("add heap memory region failed");
+        if is_free(&r) && r.paddr != max_region_paddr {
+            let vaddr = phys_to_virt(r.paddr).as_usize();
+            if let Err(e) = axalloc::global_add_memory(vaddr, r.size) {
+                warn!(
+                    "  skip memory region [{:#x}, {:#x}) {}: {:?}",
+                    r.paddr,
+                    r.paddr + r.size,
+                    r.name,
+                    e
+                );
+            }
         }
     }
+    info!(
+        "  {} page regions, {} pages available.",
+        axalloc::global_allocator().num_page_regions(),
+        axalloc::global_allocator().available_pages()
+    );
 }
 
 #[cfg(feature = "paging")]
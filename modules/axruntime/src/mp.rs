@@ -9,7 +9,7 @@ static ENTERED_CPUS: AtomicUsize = AtomicUsize::new(1);
 
 pub fn start_secondary_cpus(primary_cpu_id: usize) {
     let mut logic_cpu_id = 0;
-    for i in 0..SMP {
+    for i in 0..super::cpu_count() {
         if i != primary_cpu_id {
             let stack_top = virt_to_phys(VirtAddr::from(unsafe {
                 SECONDARY_BOOT_STACK[logic_cpu_id].as_ptr_range().end as usize
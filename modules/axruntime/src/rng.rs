@@ -0,0 +1,19 @@
+use axdriver::{prelude::*, AxDeviceContainer};
+use lazy_init::LazyInit;
+use spinlock::SpinNoIrq;
+
+static RNG: LazyInit<SpinNoIrq<AxRngDevice>> = LazyInit::new();
+
+fn request_entropy(buf: &mut [u8]) -> usize {
+    RNG.lock().request_entropy(buf).unwrap_or(0)
+}
+
+/// Uses the first random number generator as the entropy source of
+/// [`axhal::random`].
+pub fn init_rng(mut rng_devs: AxDeviceContainer<AxRngDevice>) {
+    if let Some(dev) = rng_devs.take_one() {
+        info!("Use entropy source: {:?}", dev.device_name());
+        RNG.init_by(SpinNoIrq::new(dev));
+        axhal::random::set_entropy_source(request_entropy);
+    }
+}
@@ -1,5 +1,5 @@
 use alloc::{boxed::Box, string::String, sync::Arc};
-use core::ops::Deref;
+use core::ops::{Deref, Range};
 use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, Ordering};
 use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull};
 
@@ -109,6 +109,14 @@ impl TaskInner {
             .wait_until(|| self.state() == TaskState::Exited);
         Some(self.exit_code.load(Ordering::Acquire))
     }
+
+    /// Gets the range of the kernel stack of the task, or [`None`] if the task
+    /// runs on the boot stack (i.e., the `main` task).
+    pub fn kernel_stack_range(&self) -> Option<Range<usize>> {
+        self.kstack
+            .as_ref()
+            .map(|kstack| kstack.bottom()..kstack.top().as_usize())
+    }
 }
 
 // private methods
@@ -313,6 +321,10 @@ impl TaskStack {
         }
     }
 
+    pub fn bottom(&self) -> usize {
+        self.ptr.as_ptr() as usize
+    }
+
     pub const fn top(&self) -> VirtAddr {
         unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
     }
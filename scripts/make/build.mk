@@ -25,10 +25,9 @@ else ifeq ($(filter $(MAKECMDGOALS),clippy unittest unittest_no_fail_fast),) # n
   endif
   ifeq ($(APP_TYPE), c)
     $(if $(V), $(info CFLAGS: "$(CFLAGS)") $(info LDFLAGS: "$(LDFLAGS)"))
-  else
-    $(if $(V), $(info RUSTFLAGS: "$(RUSTFLAGS)"))
-    export RUSTFLAGS
   endif
+  $(if $(V), $(info RUSTFLAGS: "$(RUSTFLAGS)"))
+  export RUSTFLAGS
 endif
 
 _cargo_build:
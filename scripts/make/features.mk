@@ -35,6 +35,12 @@ endif
 
 override FEATURES := $(strip $(FEATURES))
 
+ifneq ($(filter alloc-debug,$(FEATURES)),)
+  # to record the callers of allocations
+  RUSTFLAGS += -C force-frame-pointers=yes
+  CFLAGS += -fno-omit-frame-pointer
+endif
+
 ax_feat :=
 lib_feat :=
 
@@ -27,11 +27,25 @@ qemu_args-aarch64 := \
 qemu_args-y := -m 128M -smp $(SMP) $(qemu_args-$(ARCH))
 
 qemu_args-$(BLK) += \
-  -device virtio-blk-$(vdev-suffix),drive=disk0 \
   -drive id=disk0,if=none,format=raw,file=$(DISK_IMG)
 
-qemu_args-$(NET) += \
-  -device virtio-net-$(vdev-suffix),netdev=net0
+ifeq ($(BLK_DEV), virtio)
+  qemu_args-$(BLK) += -device virtio-blk-$(vdev-suffix),drive=disk0
+else ifeq ($(BLK_DEV), nvme)
+  qemu_args-$(BLK) += -device nvme,serial=arceos,drive=disk0
+else
+  $(error "BLK_DEV" must be one of "virtio" or "nvme")
+endif
+
+ifeq ($(NIC), virtio)
+  qemu_args-$(NET) += -device virtio-net-$(vdev-suffix),netdev=net0
+else ifeq ($(NIC), e1000)
+  qemu_args-$(NET) += -device e1000,netdev=net0
+else ifeq ($(NIC), e1000e)
+  qemu_args-$(NET) += -device e1000e,netdev=net0
+else
+  $(error "NIC" must be one of "virtio", "e1000" or "e1000e")
+endif
 
 ifeq ($(NET_DEV), user)
   qemu_args-$(NET) += -netdev user,id=net0,hostfwd=tcp::5555-:5555,hostfwd=udp::5555-:5555
@@ -47,8 +61,19 @@ endif
 
 qemu_args-$(GRAPHIC) += \
   -device virtio-gpu-$(vdev-suffix) -vga none \
+  -device virtio-keyboard-$(vdev-suffix) \
+  -device virtio-mouse-$(vdev-suffix) \
   -serial mon:stdio
 
+qemu_args-$(RNG) += \
+  -device virtio-rng-$(vdev-suffix)
+
+# Native PCI Express hot-plug, the ACPI hot-plug of q35 is disabled
+qemu_args-$(HOTPLUG) += \
+  -global ICH9-LPC.acpi-pci-hotplug-with-bridge-support=off \
+  -device pcie-root-port,id=hp0,chassis=1,slot=1 \
+  -device pcie-root-port,id=hp1,chassis=2,slot=2
+
 ifeq ($(GRAPHIC), n)
   qemu_args-y += -nographic
 endif
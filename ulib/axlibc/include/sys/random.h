@@ -0,0 +1,12 @@
+#ifndef __SYS_RANDOM_H__
+#define __SYS_RANDOM_H__
+
+#include <stddef.h>
+#include <sys/types.h>
+
+#define GRND_NONBLOCK 0x0001
+#define GRND_RANDOM   0x0002
+
+ssize_t getrandom(void *buf, size_t buflen, unsigned int flags);
+
+#endif // __SYS_RANDOM_H__
@@ -87,7 +87,7 @@ pub use self::mktime::mktime;
 pub use self::rand::{rand, random, srand};
 pub use self::resource::{getrlimit, setrlimit};
 pub use self::setjmp::{longjmp, setjmp};
-pub use self::sys::sysconf;
+pub use self::sys::{getrandom, sysconf};
 pub use self::time::{clock_gettime, nanosleep};
 pub use self::unistd::{abort, exit, getpid};
 
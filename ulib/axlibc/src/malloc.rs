@@ -39,7 +39,8 @@ pub unsafe extern "C" fn malloc(size: ctypes::size_t) -> *mut c_void {
 ///
 /// (WARNING) If the address to be released does not match the allocated address, an error should
 /// occur, but it will NOT be checked out. This is due to the global allocator `Buddy_system`
-/// (currently used) does not check the validity of address to be released.
+/// (currently used) does not check the validity of address to be released. Enable the
+/// `alloc-debug` feature to detect such errors.
 #[no_mangle]
 pub unsafe extern "C" fn free(ptr: *mut c_void) {
     if ptr.is_null() {
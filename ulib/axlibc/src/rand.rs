@@ -1,12 +1,25 @@
 //! Random number generator.
+//!
+//! `rand` is a linear congruential generator seeded by `srand`, so its
+//! sequence is reproducible. `random` returns numbers from the system entropy
+//! source (see `getrandom`), and falls back to the same generator if it fails.
 
 use core::{
     ffi::{c_int, c_long, c_uint},
     sync::atomic::{AtomicU64, Ordering::SeqCst},
 };
 
+use arceos_posix_api::sys_getrandom;
+
 static SEED: AtomicU64 = AtomicU64::new(0xa2ce_a2ce);
 
+/// Returns the next 64-bit number of the linear congruential generator.
+fn next_u64() -> u64 {
+    let new_seed = SEED.load(SeqCst).wrapping_mul(6364136223846793005) + 1;
+    SEED.store(new_seed, SeqCst);
+    new_seed
+}
+
 /// Sets the seed for the random number generator.
 #[no_mangle]
 pub unsafe extern "C" fn srand(seed: c_uint) {
@@ -16,15 +29,15 @@ pub unsafe extern "C" fn srand(seed: c_uint) {
 /// Returns a 32-bit unsigned pseudo random interger.
 #[no_mangle]
 pub unsafe extern "C" fn rand() -> c_int {
-    let new_seed = SEED.load(SeqCst).wrapping_mul(6364136223846793005) + 1;
-    SEED.store(new_seed, SeqCst);
-    (new_seed >> 33) as c_int
+    (next_u64() >> 33) as c_int
 }
 
-/// Returns a 64-bit unsigned pseudo random number.
+/// Returns a 64-bit unsigned random number.
 #[no_mangle]
 pub unsafe extern "C" fn random() -> c_long {
-    let new_seed = SEED.load(SeqCst).wrapping_mul(6364136223846793005) + 1;
-    SEED.store(new_seed, SeqCst);
-    new_seed as c_long
+    let mut bytes = [0u8; 8];
+    if sys_getrandom(bytes.as_mut_ptr() as _, bytes.len(), 0) == bytes.len() as _ {
+        return u64::from_ne_bytes(bytes) as c_long;
+    }
+    next_u64() as c_long
 }
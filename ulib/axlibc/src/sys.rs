@@ -1,5 +1,7 @@
-use arceos_posix_api::sys_sysconf;
-use core::ffi::{c_int, c_long};
+use arceos_posix_api::{sys_getrandom, sys_sysconf};
+use core::ffi::{c_int, c_long, c_uint, c_void};
+
+use crate::{ctypes, utils::e};
 
 /// Return system configuration infomation
 ///
@@ -8,3 +10,15 @@ use core::ffi::{c_int, c_long};
 pub unsafe extern "C" fn sysconf(name: c_int) -> c_long {
     sys_sysconf(name)
 }
+
+/// Fill the buffer with random bytes.
+///
+/// Return the number of bytes filled if success.
+#[no_mangle]
+pub unsafe extern "C" fn getrandom(
+    buf: *mut c_void,
+    buflen: usize,
+    flags: c_uint,
+) -> ctypes::ssize_t {
+    e(sys_getrandom(buf, buflen, flags) as _) as _
+}
@@ -20,6 +20,7 @@
 //!     - `alloc-tlsf`: Use the TLSF allocator.
 //!     - `alloc-slab`: Use the slab allocator.
 //!     - `alloc-buddy`: Use the buddy system allocator.
+//!     - `alloc-debug`: Enable heap debugging (redzones, poisoning, double-free and leak detection).
 //!     - `paging`: Enable page table manipulation.
 //!     - `tls`: Enable thread-local storage.
 //! - Task management
@@ -31,14 +32,28 @@
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
 //!     - `net`: Enable networking support.
+//!     - `dhcp`: Configure the network interface by DHCP.
+//!     - `net-tls`: Enable TLS streams over TCP (`net::tls`), it also enables
+//!       `rng` as the source of the keys.
+//!     - `pcap`: Enable packet capture in the pcap format (`axnet::pcap`).
 //!     - `dns`: Enable DNS lookup support.
-//!     - `display`: Enable graphics support.
+//!     - `display`: Enable graphics support and the input devices.
+//!     - `rng`: Use the VirtIO entropy device as the source of random numbers.
+//!     - `console`: Use the VirtIO console as the console.
 //! - Device drivers
 //!     - `bus-mmio`: Use device tree to probe all MMIO devices.
 //!     - `bus-pci`: Use PCI bus to probe all PCI devices.
 //!     - `driver-ramdisk`: Use the RAM disk to emulate the block device.
 //!     - `driver-ixgbe`: Enable the Intel 82599 10Gbit NIC driver.
 //!     - `driver-bcm2835-sdhci`: Enable the BCM2835 SDHCI driver (Raspberry Pi SD card).
+//!     - `driver-nvme`: Enable the NVMe SSD driver (PCI only).
+//!     - `driver-uart16550`: Enable the 16550 UART driver for serial ports other than the console.
+//!     - `driver-pl011`: Enable the ARM PL011 UART driver for serial ports other than the console.
+//!     - `driver-virtio-console`: Enable the VirtIO console driver (`/dev/hvc0`).
+//!     - `driver-bcm2711-gpio`: Enable the BCM2711 GPIO driver (Raspberry Pi 4, `/dev/gpiochip0`).
+//!     - `driver-bcm2711-i2c`: Enable the BCM2711 I2C driver (Raspberry Pi 4, `/dev/i2c-*`).
+//!     - `driver-bcm2711-spi`: Enable the BCM2711 SPI driver (Raspberry Pi 4, `/dev/spidev*`).
+//!     - `hotplug`: Add and remove the devices in PCI Express hot-plug slots at runtime.
 //! - Logging
 //!     - `log-level-off`: Disable all logging.
 //!     - `log-level-error`, `log-level-warn`, `log-level-info`, `log-level-debug`,
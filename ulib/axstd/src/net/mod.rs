@@ -7,6 +7,7 @@
 //!
 //! * [`TcpListener`] and [`TcpStream`] provide functionality for communication over TCP
 //! * [`UdpSocket`] provides functionality for communication over UDP
+//! * [`tls::TlsStream`] provides TLS over a [`TcpStream`] (the `net-tls` feature)
 //! * [`IpAddr`] represents IP addresses of either IPv4 or IPv6; [`Ipv4Addr`] and
 //!   [`Ipv6Addr`] are respectively IPv4 and IPv6 addresses
 //! * [`SocketAddr`] represents socket addresses of either IPv4 or IPv6; [`SocketAddrV4`]
@@ -18,6 +19,9 @@ mod socket_addr;
 mod tcp;
 mod udp;
 
+#[cfg(feature = "net-tls")]
+pub mod tls;
+
 pub use self::socket_addr::{IpAddr, Ipv4Addr, Ipv6Addr};
 pub use self::socket_addr::{SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs};
 pub use self::tcp::{TcpListener, TcpStream};
@@ -44,3 +48,7 @@ where
         axerrno::ax_err_type!(InvalidInput, "could not resolve to any addresses")
     }))
 }
+
+fn ttl_from_u32(ttl: u32) -> io::Result<u8> {
+    u8::try_from(ttl).map_err(|_| axerrno::ax_err_type!(InvalidInput, "invalid TTL"))
+}
@@ -1,5 +1,6 @@
 use super::{SocketAddr, ToSocketAddrs};
 use crate::io::{self, prelude::*};
+use core::time::Duration;
 
 use arceos_api::net::{self as api, AxTcpSocketHandle};
 
@@ -43,6 +44,81 @@ impl TcpStream {
     pub fn shutdown(&self) -> io::Result<()> {
         api::ax_tcp_shutdown(&self.0)
     }
+
+    /// Sets the value of the `TCP_NODELAY` option on this socket.
+    ///
+    /// If set, this option disables the Nagle algorithm. This means that
+    /// segments are always sent as soon as possible, even if there is only a
+    /// small amount of data.
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        api::ax_tcp_set_nodelay(&self.0, nodelay)
+    }
+
+    /// Gets the value of the `TCP_NODELAY` option on this socket.
+    pub fn nodelay(&self) -> io::Result<bool> {
+        api::ax_tcp_nodelay(&self.0)
+    }
+
+    /// Sets the read timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`read`] calls will block
+    /// indefinitely. An [`Err`] is returned if the zero [`Duration`] is
+    /// passed to this method. A read that times out fails with
+    /// [`WouldBlock`](io::Error::WouldBlock).
+    ///
+    /// [`read`]: Read::read
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_read_timeout(&self.0, dur)
+    }
+
+    /// Returns the read timeout of this socket.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        api::ax_tcp_read_timeout(&self.0)
+    }
+
+    /// Sets the write timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`write`] calls will block
+    /// indefinitely. An [`Err`] is returned if the zero [`Duration`] is
+    /// passed to this method. A write that times out fails with
+    /// [`WouldBlock`](io::Error::WouldBlock).
+    ///
+    /// [`write`]: Write::write
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_write_timeout(&self.0, dur)
+    }
+
+    /// Returns the write timeout of this socket.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        api::ax_tcp_write_timeout(&self.0)
+    }
+
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This value sets the time-to-live field that is used in every packet
+    /// sent from this socket.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        api::ax_tcp_set_ttl(&self.0, super::ttl_from_u32(ttl)?)
+    }
+
+    /// Gets the value of the `IP_TTL` option for this socket.
+    pub fn ttl(&self) -> io::Result<u32> {
+        api::ax_tcp_ttl(&self.0).map(u32::from)
+    }
+
+    /// Sets the value of the `SO_LINGER` option on this socket.
+    ///
+    /// If set, [`shutdown`](Self::shutdown) or dropping the stream waits until
+    /// the sent data is acknowledged, or the timeout expires. A zero timeout
+    /// resets the connection at once.
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_linger(&self.0, linger)
+    }
+
+    /// Gets the value of the `SO_LINGER` option on this socket.
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        api::ax_tcp_linger(&self.0)
+    }
 }
 
 impl Read for TcpStream {
@@ -94,6 +170,18 @@ impl TcpListener {
         api::ax_tcp_socket_addr(&self.0)
     }
 
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// It's also used by the connections accepted afterwards.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        api::ax_tcp_set_ttl(&self.0, super::ttl_from_u32(ttl)?)
+    }
+
+    /// Gets the value of the `IP_TTL` option for this socket.
+    pub fn ttl(&self) -> io::Result<u32> {
+        api::ax_tcp_ttl(&self.0).map(u32::from)
+    }
+
     /// Accept a new incoming connection from this listener.
     ///
     /// This function will block the calling thread until a new TCP connection
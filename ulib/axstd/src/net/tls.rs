@@ -0,0 +1,426 @@
+//! TLS (Transport Layer Security) over TCP.
+//!
+//! A [`TlsStream`] wraps a [`TcpStream`] and protects the data with TLS 1.2
+//! or 1.3, using [rustls] with the [*ring*] crypto provider. It acts as a
+//! client with [`TlsStream::connect`], or as a server with
+//! [`TlsStream::accept`].
+//!
+//! Certificates and private keys are given in PEM, either embedded by
+//! `include_bytes!` or read from a file by `fs::read`. A client trusts the
+//! Mozilla root certificates by default ([`client_config`]).
+//!
+//! Random numbers come from the platform RNG (an entropy source device or the
+//! CPU RNG). Without one, the handshakes fail, as the clock-seeded fallback
+//! generator is predictable. As there is no real-time clock,
+//! the current time used to check certificates is the `AX_EPOCH` environment
+//! variable at compile time (the build time, set by the Makefile) plus the
+//! uptime.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use std::io::prelude::*;
+//! use std::net::tls::{self, TlsStream};
+//! use std::net::TcpStream;
+//!
+//! let stream = TcpStream::connect("example.com:443")?;
+//! let mut tls = TlsStream::connect(stream, "example.com", tls::client_config()?)?;
+//! tls.write_all(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n")?;
+//! ```
+//!
+//! [rustls]: https://docs.rs/rustls
+//! [*ring*]: https://docs.rs/ring
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use axerrno::{ax_err, ax_err_type};
+use rustls::client::UnbufferedClientConnection;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::server::UnbufferedServerConnection;
+use rustls::time_provider::TimeProvider;
+use rustls::unbuffered::{AppDataRecord, ConnectionState, EncodeError, EncryptError};
+use rustls::unbuffered::{InsufficientSizeError, UnbufferedStatus};
+use rustls::RootCertStore;
+
+use super::TcpStream;
+use crate::io::{self, prelude::*};
+
+pub use rustls::{ClientConfig, ServerConfig};
+
+/// Initial size of the buffer of received TLS records.
+const INCOMING_BUF_LEN: usize = 4096;
+/// Maximum size of the buffer of received TLS records, enough for the largest
+/// record (16 KiB of plaintext with the overhead).
+const MAX_INCOMING_BUF_LEN: usize = 32 * 1024;
+
+/// Provides the current time as `AX_EPOCH` plus the uptime.
+#[derive(Debug)]
+struct AxTimeProvider;
+
+impl TimeProvider for AxTimeProvider {
+    fn current_time(&self) -> Option<UnixTime> {
+        let epoch = option_env!("AX_EPOCH")?.parse().ok()?;
+        let now = Duration::from_secs(epoch) + arceos_api::time::ax_current_time();
+        Some(UnixTime::since_unix_epoch(now))
+    }
+}
+
+fn ax_getrandom(buf: &mut [u8]) -> Result<(), getrandom::Error> {
+    if arceos_api::sys::ax_fill_random(buf) {
+        Ok(())
+    } else {
+        Err(getrandom::Error::UNSUPPORTED)
+    }
+}
+
+getrandom::register_custom_getrandom!(ax_getrandom);
+
+fn tls_err(e: rustls::Error) -> io::Error {
+    ax_err_type!(InvalidData, e)
+}
+
+fn pem_err(e: rustls::pki_types::pem::Error) -> io::Error {
+    ax_err_type!(InvalidInput, alloc::format!("invalid PEM: {:?}", e))
+}
+
+fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn client_config_with_store(roots: RootCertStore) -> io::Result<Arc<ClientConfig>> {
+    let config = ClientConfig::builder_with_details(crypto_provider(), Arc::new(AxTimeProvider))
+        .with_safe_default_protocol_versions()
+        .map_err(tls_err)?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// Creates a client configuration that trusts the Mozilla root certificates.
+pub fn client_config() -> io::Result<Arc<ClientConfig>> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    client_config_with_store(roots)
+}
+
+/// Creates a client configuration that only trusts the root certificates in
+/// `roots_pem`.
+pub fn client_config_with_roots(roots_pem: &[u8]) -> io::Result<Arc<ClientConfig>> {
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_slice_iter(roots_pem) {
+        roots.add(cert.map_err(pem_err)?).map_err(tls_err)?;
+    }
+    if roots.is_empty() {
+        return ax_err!(InvalidInput, "no certificate in PEM");
+    }
+    client_config_with_store(roots)
+}
+
+/// Creates a server configuration with the certificate chain in
+/// `cert_chain_pem` (the server certificate first) and the private key in
+/// `key_pem`.
+pub fn server_config(cert_chain_pem: &[u8], key_pem: &[u8]) -> io::Result<Arc<ServerConfig>> {
+    let cert_chain = CertificateDer::pem_slice_iter(cert_chain_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(pem_err)?;
+    let key = PrivateKeyDer::from_pem_slice(key_pem).map_err(pem_err)?;
+    let config = ServerConfig::builder_with_details(crypto_provider(), Arc::new(AxTimeProvider))
+        .with_safe_default_protocol_versions()
+        .map_err(tls_err)?
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)
+        .map_err(tls_err)?;
+    Ok(Arc::new(config))
+}
+
+enum Connection {
+    Client(UnbufferedClientConnection),
+    Server(UnbufferedServerConnection),
+}
+
+/// What to do when the connection is able to send application data.
+#[derive(Clone, Copy)]
+enum Action<'a> {
+    None,
+    Write(&'a [u8]),
+    Close,
+}
+
+/// The result of processing the connection once.
+enum Step {
+    /// Made some progress, process again.
+    Continue,
+    /// More TLS records are needed to make progress.
+    NeedData,
+    /// The handshake is done, and the given number of bytes are written.
+    Writable(usize),
+    /// The connection is closed by the peer or by us.
+    Closed,
+}
+
+/// The underlying stream with the outgoing and the decrypted data.
+struct TlsIo {
+    stream: TcpStream,
+    outgoing: Vec<u8>,
+    outgoing_len: usize,
+    plaintext: VecDeque<u8>,
+}
+
+/// A TLS stream over a TCP connection.
+///
+/// It is created by [`TlsStream::connect`] or [`TlsStream::accept`], which
+/// perform the handshake, and then read from or written to like the
+/// [`TcpStream`].
+pub struct TlsStream {
+    conn: Connection,
+    incoming: Vec<u8>,
+    incoming_len: usize,
+    io: TlsIo,
+    closed: bool,
+}
+
+impl TlsIo {
+    /// Makes room for `required` more bytes of outgoing records.
+    fn reserve_outgoing(&mut self, required: usize) {
+        self.outgoing.resize(self.outgoing_len + required, 0);
+    }
+
+    /// Sends all the outgoing records.
+    fn send_outgoing(&mut self) -> io::Result<()> {
+        self.stream.write_all(&self.outgoing[..self.outgoing_len])?;
+        self.outgoing_len = 0;
+        Ok(())
+    }
+
+    /// Handles the state of the connection once, returns the number of bytes
+    /// of the processed incoming records.
+    fn handle<Data>(
+        &mut self,
+        status: UnbufferedStatus<'_, '_, Data>,
+        action: Action,
+    ) -> io::Result<(usize, Step)> {
+        let UnbufferedStatus {
+            mut discard, state, ..
+        } = status;
+        let step = match state.map_err(tls_err)? {
+            ConnectionState::ReadTraffic(mut traffic) => {
+                while let Some(record) = traffic.next_record() {
+                    let AppDataRecord {
+                        discard: record_len,
+                        payload,
+                        ..
+                    } = record.map_err(tls_err)?;
+                    discard += record_len;
+                    self.plaintext.extend(payload.iter());
+                }
+                Step::Continue
+            }
+            ConnectionState::EncodeTlsData(mut state) => {
+                let len = loop {
+                    match state.encode(&mut self.outgoing[self.outgoing_len..]) {
+                        Err(EncodeError::InsufficientSize(InsufficientSizeError {
+                            required_size,
+                        })) => self.reserve_outgoing(required_size),
+                        res => break res.map_err(|e| ax_err_type!(InvalidData, e))?,
+                    }
+                };
+                self.outgoing_len += len;
+                Step::Continue
+            }
+            ConnectionState::TransmitTlsData(state) => {
+                self.send_outgoing()?;
+                state.done();
+                Step::Continue
+            }
+            ConnectionState::BlockedHandshake => Step::NeedData,
+            ConnectionState::WriteTraffic(mut traffic) => {
+                let (len, res) = match action {
+                    Action::None => return Ok((discard, Step::Writable(0))),
+                    Action::Write(data) => loop {
+                        let outgoing = &mut self.outgoing[self.outgoing_len..];
+                        match traffic.encrypt(data, outgoing) {
+                            Err(EncryptError::InsufficientSize(InsufficientSizeError {
+                                required_size,
+                            })) => self.reserve_outgoing(required_size),
+                            res => break (data.len(), res),
+                        }
+                    },
+                    Action::Close => loop {
+                        let outgoing = &mut self.outgoing[self.outgoing_len..];
+                        match traffic.queue_close_notify(outgoing) {
+                            Err(EncryptError::InsufficientSize(InsufficientSizeError {
+                                required_size,
+                            })) => self.reserve_outgoing(required_size),
+                            res => break (0, res),
+                        }
+                    },
+                };
+                self.outgoing_len += res.map_err(|e| ax_err_type!(InvalidData, e))?;
+                self.send_outgoing()?;
+                Step::Writable(len)
+            }
+            ConnectionState::PeerClosed | ConnectionState::Closed => Step::Closed,
+            _ => return ax_err!(Unsupported, "unexpected TLS connection state"),
+        };
+        Ok((discard, step))
+    }
+}
+
+impl TlsStream {
+    fn new(conn: Connection, stream: TcpStream) -> Self {
+        Self {
+            conn,
+            incoming: vec![0; INCOMING_BUF_LEN],
+            incoming_len: 0,
+            io: TlsIo {
+                stream,
+                outgoing: Vec::new(),
+                outgoing_len: 0,
+                plaintext: VecDeque::new(),
+            },
+            closed: false,
+        }
+    }
+
+    /// Performs the client handshake over `stream` with the server
+    /// `server_name`, whose certificate is checked against the name.
+    pub fn connect(
+        stream: TcpStream,
+        server_name: &str,
+        config: Arc<ClientConfig>,
+    ) -> io::Result<Self> {
+        let server_name = ServerName::try_from(server_name)
+            .map_err(|_| ax_err_type!(InvalidInput, "invalid server name"))?
+            .to_owned();
+        let conn = UnbufferedClientConnection::new(config, server_name).map_err(tls_err)?;
+        let mut tls = Self::new(Connection::Client(conn), stream);
+        tls.handshake()?;
+        Ok(tls)
+    }
+
+    /// Performs the server handshake over `stream`, which is usually accepted
+    /// by a [`TcpListener`](super::TcpListener).
+    pub fn accept(stream: TcpStream, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let conn = UnbufferedServerConnection::new(config).map_err(tls_err)?;
+        let mut tls = Self::new(Connection::Server(conn), stream);
+        tls.handshake()?;
+        Ok(tls)
+    }
+
+    /// Returns a reference to the underlying TCP stream.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.io.stream
+    }
+
+    /// Sends the TLS `close_notify` alert and shuts down the TCP connection.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        if !self.closed {
+            loop {
+                match self.step(Action::Close)? {
+                    Step::Continue => {}
+                    Step::NeedData => self.recv()?,
+                    Step::Writable(_) | Step::Closed => break,
+                }
+            }
+            self.closed = true;
+        }
+        self.io.stream.shutdown()
+    }
+
+    fn handshake(&mut self) -> io::Result<()> {
+        loop {
+            match self.step(Action::None)? {
+                Step::Continue => {}
+                Step::NeedData => self.recv()?,
+                Step::Writable(_) => return Ok(()),
+                Step::Closed => return ax_err!(ConnectionReset, "TLS handshake failed"),
+            }
+        }
+    }
+
+    /// Processes the received records once, and does `action` if the
+    /// connection is writable.
+    fn step(&mut self, action: Action) -> io::Result<Step> {
+        let incoming = &mut self.incoming[..self.incoming_len];
+        let (discard, step) = match &mut self.conn {
+            Connection::Client(conn) => self.io.handle(conn.process_tls_records(incoming), action),
+            Connection::Server(conn) => self.io.handle(conn.process_tls_records(incoming), action),
+        }?;
+        if discard > 0 {
+            self.incoming.copy_within(discard..self.incoming_len, 0);
+            self.incoming_len -= discard;
+        }
+        Ok(step)
+    }
+
+    /// Receives more TLS records from the TCP stream.
+    fn recv(&mut self) -> io::Result<()> {
+        if self.incoming_len == self.incoming.len() {
+            if self.incoming.len() >= MAX_INCOMING_BUF_LEN {
+                return ax_err!(InvalidData, "TLS record too large");
+            }
+            self.incoming.resize(self.incoming.len() * 2, 0);
+        }
+        match self
+            .io
+            .stream
+            .read(&mut self.incoming[self.incoming_len..])?
+        {
+            0 => ax_err!(UnexpectedEof, "TLS connection closed without close_notify"),
+            len => {
+                self.incoming_len += len;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if !self.io.plaintext.is_empty() {
+                let len = buf.len().min(self.io.plaintext.len());
+                for (dst, src) in buf.iter_mut().zip(self.io.plaintext.drain(..len)) {
+                    *dst = src;
+                }
+                return Ok(len);
+            }
+            if self.closed {
+                return Ok(0);
+            }
+            match self.step(Action::None)? {
+                Step::Continue => {}
+                // no complete records to read
+                Step::NeedData | Step::Writable(_) => self.recv()?,
+                Step::Closed => self.closed = true,
+            }
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            match self.step(Action::Write(buf))? {
+                Step::Continue => {}
+                Step::NeedData => self.recv()?,
+                Step::Writable(len) => return Ok(len),
+                Step::Closed => return ax_err!(NotConnected, "TLS connection closed"),
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // records are sent once encrypted
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use super::{SocketAddr, ToSocketAddrs};
 use crate::io;
+use core::time::Duration;
 
 use arceos_api::net::{self as api, AxUdpSocketHandle};
 
@@ -93,4 +94,44 @@ impl UdpSocket {
     pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
         api::ax_udp_recv(&self.0, buf)
     }
+    /// Sets the read timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`recv`](Self::recv) and
+    /// [`recv_from`](Self::recv_from) calls will block indefinitely. An
+    /// [`Err`] is returned if the zero [`Duration`] is passed to this method.
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_udp_set_read_timeout(&self.0, dur)
+    }
+
+    /// Returns the read timeout of this socket.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        api::ax_udp_read_timeout(&self.0)
+    }
+
+    /// Sets the write timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`send`](Self::send) and
+    /// [`send_to`](Self::send_to) calls will block indefinitely. An [`Err`]
+    /// is returned if the zero [`Duration`] is passed to this method.
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_udp_set_write_timeout(&self.0, dur)
+    }
+
+    /// Returns the write timeout of this socket.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        api::ax_udp_write_timeout(&self.0)
+    }
+
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This value sets the time-to-live field that is used in every packet
+    /// sent from this socket.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        api::ax_udp_set_ttl(&self.0, super::ttl_from_u32(ttl)?)
+    }
+
+    /// Gets the value of the `IP_TTL` option for this socket.
+    pub fn ttl(&self) -> io::Result<u32> {
+        api::ax_udp_ttl(&self.0).map(u32::from)
+    }
 }